@@ -0,0 +1,74 @@
+#ifndef PNGSECRET_H
+#define PNGSECRET_H
+
+/* generated by cbindgen from src/ffi.rs, don't edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * the call succeeded
+ */
+#define PNGSECRET_OK 0
+
+/**
+ * writes the png in `in_buf` with `data` in a new chunk of `chunk_type` before its
+ * IEND to a new buffer, which `pngsecret_free` frees
+ *
+ * # Safety
+ * `in_buf` and `data` point to `in_len` and `data_len` readable bytes, `chunk_type` to
+ * a NUL terminated string and `out_buf` and `out_len` to writable pointers
+ */
+int pngsecret_encode(const uint8_t *in_buf,
+                     size_t in_len,
+                     const char *chunk_type,
+                     const uint8_t *data,
+                     size_t data_len,
+                     uint8_t **out_buf,
+                     size_t *out_len);
+
+/**
+ * copies the data of the first chunk of `chunk_type` in the png in `in_buf` to a new
+ * buffer, which `pngsecret_free` frees
+ *
+ * # Safety
+ * as for `pngsecret_encode`
+ */
+int pngsecret_decode(const uint8_t *in_buf,
+                     size_t in_len,
+                     const char *chunk_type,
+                     uint8_t **out_buf,
+                     size_t *out_len);
+
+/**
+ * writes the png in `in_buf` without its first chunk of `chunk_type` to a new buffer,
+ * which `pngsecret_free` frees
+ *
+ * # Safety
+ * as for `pngsecret_encode`
+ */
+int pngsecret_remove(const uint8_t *in_buf,
+                     size_t in_len,
+                     const char *chunk_type,
+                     uint8_t **out_buf,
+                     size_t *out_len);
+
+/**
+ * frees a buffer one of the functions above returned, null does nothing
+ *
+ * # Safety
+ * `buf` and `len` are what a call returned in `out_buf` and `out_len`, and `buf`
+ * isn't freed twice
+ */
+void pngsecret_free(uint8_t *buf, size_t len);
+
+/**
+ * what the last failed call on this thread failed with, null after a call that
+ * succeeded. the string stays valid until the next call on the thread
+ */
+const char *pngsecret_last_error_message(void);
+
+#endif  /* PNGSECRET_H */
@@ -1,42 +1,895 @@
-use std::path::PathBuf;
+use std::{ffi::OsString, path::PathBuf, str::FromStr};
 
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, FromArgMatches, Parser, Subcommand, ValueEnum};
+use regex::Regex;
+
+use crate::{
+    chunk_type::ChunkType,
+    color::ColorChoice,
+    completions::Shell,
+    crypto::{Argon2Params, Recipient},
+    encoding::Encoding,
+    hex::HexBytes,
+    output::Format,
+    progress,
+    scan::Severity,
+    text, time,
+};
+
+/// payloads bigger than this are split across several chunks
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// the arguments as clap makes them of `positionals`, so the constructors have the
+/// same defaults as the command line. a `--` first keeps a message starting with - from
+/// being taken for an option
+fn parsed<T: Args + FromArgMatches>(positionals: &[OsString]) -> T {
+    let command = T::augment_args(clap::Command::new("pngsecret"));
+    let args = ["pngsecret".into(), "--".into()]
+        .into_iter()
+        .chain(positionals.iter().cloned());
+    let matches = command
+        .try_get_matches_from(args)
+        .expect("the positionals alone are valid arguments");
+    T::from_arg_matches(&matches).expect("the matches are of these arguments")
+}
+
+/// what the exit code of a run means, at the end of --help
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Any other failure, like bad arguments or problems verify found
+  2  The input is not a valid png, or a file couldn't be read or written
+  3  The requested chunk was not found
+  4  A crc, checksum or signature didn't match, or --verify-render saw other pixels
+  5  Wrong password or key, the payload couldn't be decrypted
+  6  Refused to overwrite a file or change a critical chunk, see --force and --allow-critical
+  7  There are fewer chunks of the type than remove --nth asked for";
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES)]
 pub struct Cli {
     #[command(subcommand)]
     pub commands: Commands,
+    /// Log what is going on to stderr, -vv for even more
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Print only errors and the data asked for
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// When to color the output
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+    /// Show a progress bar for files of at least BYTES, on a terminal
+    #[arg(long, value_name = "BYTES", global = true, default_value_t = progress::DEFAULT_THRESHOLD)]
+    pub progress_threshold: u64,
+    /// How to write results, json prints one JSON document per run
+    #[arg(long, value_enum, global = true, default_value_t = Format::Human)]
+    pub format: Format,
+    /// Give up on a png with more than BYTES of chunk data or more than 10000 chunks,
+    /// for files from untrusted sources. without it only the chunk count is limited,
+    /// unlimited takes that limit away too
+    #[arg(long, value_name = "BYTES", global = true, value_parser = max_memory)]
+    pub max_memory: Option<u64>,
 }
 
+/// bytes, or unlimited as u64::MAX
+fn max_memory(value: &str) -> Result<u64, String> {
+    match value {
+        "unlimited" => Ok(u64::MAX),
+        bytes => match bytes.parse() {
+            Ok(0) | Err(_) => Err(format!(
+                "{} is neither a number of bytes nor unlimited",
+                value
+            )),
+            Ok(bytes) => Ok(bytes),
+        },
+    }
+}
+
+// parsed once, the size of the biggest arguments doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
-    /// does testing things
+    /// Hide a message in a new chunk of the given type, before IEND unless --position says otherwise
     Encode(EncodeArgs),
+    /// Remove the first chunk of the given type, or the ones picked by --all, --nth or --matching
     Remove(RemoveArgs),
+    /// Print the message hidden in the first chunk of the given type, or in every one with --all
     Decode(DecodeArgs),
+    /// Print every chunk with its type, length, crc and data
     Print(PrintArgs),
+    /// List chunks as a compact table without reading their data, verify checks the crcs
+    List(ListArgs),
+    /// Report chunks that look like hidden data, in a file or a directory
+    Scan(ScanArgs),
+    /// Check the file structure and every chunk crc
+    Verify(VerifyArgs),
+    /// Fix wrong chunk crcs and a missing IEND chunk
+    Repair(RepairArgs),
+    /// Remove every ancillary chunk, metadata and hidden messages included
+    Strip(StripArgs),
+    /// Write the raw data of every chunk to its own file
+    ExtractChunks(ExtractChunksArgs),
+    /// Insert a chunk holding the raw contents of a file
+    Inject(InjectArgs),
+    /// Copy the chunks of a type from one file into another, replacing the ones there
+    CopyChunk(CopyChunkArgs),
+    /// Show which chunks were added, removed or changed between two files
+    Diff(DiffArgs),
+    /// Show how much a payload would grow the file
+    Capacity(CapacityArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+    /// Generate an identity to decrypt messages encoded with --recipient, or a keypair to sign them
+    Keygen(KeygenArgs),
+    /// Set the tIME chunk to the given time or now, replacing the one there
+    Touch(TouchArgs),
+    /// Show the eXIf chunk, write its EXIF data to a file or remove it
+    Exif(ExifArgs),
+    /// Show what the standard ancillary chunks say: gamma, colour space, pixel size and more
+    Metadata(MetadataArgs),
+    /// Show the image size and pixel format from IHDR, the file size and whether it is an APNG
+    Info(InfoArgs),
+    /// Exit with 0 when the file has chunks of the given types and 3 when it doesn't, for scripts
+    Has(HasArgs),
+    /// Print the man page, or write one for every subcommand too, for packaging
+    #[command(hide = true)]
+    Man(ManArgs),
 }
 #[derive(Args)]
+#[command(group(ArgGroup::new("encryption").args(["password", "encrypt", "sign_hmac"]).multiple(true)))]
 pub struct EncodeArgs {
-    file_path: PathBuf,
-    chunk_type: String,
-    message: String,
-    output_file: Option<PathBuf>,
+    /// A png file, or a directory to encode every png file in
+    pub file_path: PathBuf,
+    #[arg(value_parser = ChunkType::from_str)]
+    pub chunk_type: ChunkType,
+    pub message: Option<String>,
+    #[arg(conflicts_with = "out_dir")]
+    pub output_file: Option<PathBuf>,
+    /// Write the encoded png here instead of in place, like OUTPUT_FILE, which only comes
+    /// after a MESSAGE and so can't follow --message-file, --edit, --input-file or
+    /// --message-hex
+    #[arg(short, long, value_name = "PATH", conflicts_with_all = ["out_dir", "output_file"])]
+    pub output: Option<PathBuf>,
+    /// Read the message from a UTF-8 text file instead
+    #[arg(long, value_name = "PATH", conflicts_with = "message")]
+    pub message_file: Option<PathBuf>,
+    /// Write the message in $EDITOR instead
+    #[arg(long, conflicts_with_all = ["message", "message_file"])]
+    pub edit: bool,
+    /// Embed this file, any bytes, along with its name, size, mode and modification time
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["message", "message_file", "edit", "base64"])]
+    pub input_file: Option<PathBuf>,
+    /// Embed the bytes HEX spells out instead of a message, like deadbeef or 0xde 0xad
+    #[arg(long, value_name = "HEX", value_parser = HexBytes::from_str, conflicts_with_all = ["message", "message_file", "edit", "input_file", "base64", "text_keyword", "ztxt_keyword", "itxt_keyword"])]
+    pub message_hex: Option<HexBytes>,
+    /// Leave the name, size, mode and time of --input-file out of the payload
+    #[arg(long, requires = "input_file")]
+    pub no_metadata: bool,
+    /// Drop the trailing newline of the message
+    #[arg(long)]
+    pub trim: bool,
+    /// Only encode the files in the directory whose name matches PATTERN, with * and ?
+    #[arg(long, value_name = "PATTERN")]
+    pub glob: Option<String>,
+    /// Write the encoded files of a directory here instead of in place
+    #[arg(long)]
+    pub out_dir: Option<PathBuf>,
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Copy the original aside, to its name plus SUFFIX, before changing it in place
+    #[arg(long, value_name = "SUFFIX", num_args = 0..=1, require_equals = true, default_missing_value = ".bak")]
+    pub backup: Option<String>,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub force: bool,
+    /// Keep the modification time of the original file
+    #[arg(long)]
+    pub preserve_times: bool,
+    /// Copy the file chunk by chunk instead of reading it whole, which files over 256 MiB
+    /// always are. only works with --position before-iend
+    #[arg(long)]
+    pub stream: bool,
+    /// Decode the written file and compare its pixels to the original's, undoing the write
+    /// if they differ. needs the image feature
+    #[arg(long, conflicts_with = "stream")]
+    pub verify_render: bool,
+    /// Where to insert the chunk: before-iend, after-ihdr, before-idat or index:N
+    #[arg(long, default_value = "before-iend", value_parser = Position::from_str)]
+    pub position: Position,
+    /// Put the payload in place of the chunk of its type already there, or of the text
+    /// chunk with its keyword, instead of adding another one that decode wouldn't read
+    #[arg(long, conflicts_with_all = ["stream", "stealth", "position", "replace_or_append"])]
+    pub replace: bool,
+    /// Like --replace, but add the chunk as usual when there is none to replace
+    #[arg(long, conflicts_with_all = ["stream", "stealth"])]
+    pub replace_or_append: bool,
+    /// Hide the payload in a chunk, or with lsb in the lowest bits of the pixels, which
+    /// rewrites them as 8 bit RGBA. lsb needs the image feature
+    #[arg(long, value_enum, default_value_t = Method::Chunk)]
+    pub method: Method,
+    /// The color channels --method lsb writes bits to
+    #[arg(long, value_enum, default_value_t = Channels::Rgb)]
+    pub channels: Channels,
+    /// Spread the payload over a few small chunks of types other programs write, each
+    /// encrypted on its own and at a random place, instead of one chunk of the type
+    /// given. encrypts with a password unless --key-file or --recipient is given
+    #[arg(long, conflicts_with_all = ["stream", "position", "method", "decoy_message", "sign_hmac", "text_keyword", "ztxt_keyword", "itxt_keyword"])]
+    pub stealth: bool,
+    /// Also add N chunks of random bytes, of the types and at the kind of places --stealth
+    /// picks, so a listing doesn't show which chunks hold the payload
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "stream")]
+    pub noise: usize,
+    /// Pick the sizes, types and places of --noise from SEED, the same every time, and with
+    /// --deterministic every other random byte too
+    #[arg(long)]
+    pub seed: Option<String>,
+    /// Draw the salts, nonces, keys and everything else random from --seed, so the same
+    /// inputs give a byte for byte identical file. this weakens the encryption: whoever has
+    /// the seed knows all of them but the password, and the same seed, password and
+    /// message always encrypt alike, which shows a message repeats
+    #[arg(long, requires = "seed")]
+    pub deterministic: bool,
+    /// Allow a critical chunk type, which can corrupt the image
+    #[arg(long)]
+    pub allow_critical: bool,
+    /// Don't ask before touching a critical chunk, for scripts
+    #[arg(short, long, requires = "allow_critical")]
+    pub yes: bool,
+    /// Encrypt the message with AES-256-GCM, keyed by PASSWORD, or with --sign-hmac key its tag
+    #[arg(long, value_name = "PASSWORD")]
+    pub password: Option<String>,
+    /// Encrypt the message with a password from $PNGSECRET_PASSWORD, or asked for on the terminal
+    #[arg(long)]
+    pub encrypt: bool,
+    /// Argon2id cost as memory in KiB, passes and lanes, stored with the message
+    #[arg(long, value_name = "M,T,P", requires = "encryption", value_parser = Argon2Params::from_str)]
+    pub kdf_params: Option<Argon2Params>,
+    /// Encrypt the message with the raw 32 byte key in this file instead of a password
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["password", "encrypt"])]
+    pub key_file: Option<PathBuf>,
+    /// Encrypt the message to this age1... public key, can be repeated
+    #[arg(long, value_name = "RECIPIENT", value_parser = Recipient::from_str, conflicts_with_all = ["encryption", "key_file"])]
+    pub recipient: Vec<Recipient>,
+    /// The message is base64, embed the bytes it decodes to
+    #[arg(long)]
+    pub base64: bool,
+    /// Deflate the message before encrypting and embedding it, LEVEL 0 to 9 defaults to 6
+    #[arg(long, value_name = "LEVEL", num_args = 0..=1, default_missing_value = "6", value_parser = clap::value_parser!(u8).range(0..=9))]
+    pub compress: Option<u8>,
+    /// Pad the message before it is encrypted, so the chunk doesn't give its length away:
+    /// none, block:N up to a multiple of N bytes, or random:MAX by 0 to MAX bytes
+    #[arg(long, value_name = "STRATEGY", default_value = "none", value_parser = Pad::from_str, conflicts_with_all = ["decoy_message", "sign_hmac"])]
+    pub pad: Pad,
+    /// Largest chunk a payload is split into, not counting the part headers
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_CHUNK_SIZE)]
+    pub max_chunk_size: usize,
+    /// Also store DECOY, readable with --decoy-password, so the chunk can be opened without giving the message away
+    #[arg(long, value_name = "DECOY", requires = "decoy_password", conflicts_with_all = ["sign_hmac", "key_file", "recipient"])]
+    pub decoy_message: Option<String>,
+    /// The password of --decoy-message, it has to differ from the real one
+    #[arg(long, value_name = "PASSWORD", requires = "decoy_message")]
+    pub decoy_password: Option<String>,
+    /// Keep the message readable but add an HMAC-SHA-256 tag, keyed like encryption, to detect changes
+    #[arg(long)]
+    pub sign_hmac: bool,
+    /// Store a SHA-256 of the message so decode can tell whether it changed. it is kept in the clear, next to the encrypted message
+    #[arg(long, conflicts_with = "decoy_message")]
+    pub checksum: bool,
+    /// Sign the message with the Ed25519 keypair in this file, before it is encrypted. the
+    /// signature and the signer's public key are kept in the clear
+    #[arg(long, value_name = "KEYPAIR", conflicts_with = "decoy_message")]
+    pub sign: Option<PathBuf>,
+    /// Write a standard tEXt chunk with the message as Latin-1 text under KEYWORD, for other
+    /// tools to read. the chunk type has to be tEXt
+    #[arg(long, value_name = "KEYWORD", value_parser = text::keyword, conflicts_with_all = ["encryption", "key_file", "recipient", "compress", "base64", "input_file", "checksum", "sign", "decoy_message"])]
+    pub text_keyword: Option<String>,
+    /// Write a standard zTXt chunk with the message as compressed Latin-1 text under KEYWORD.
+    /// the chunk type has to be zTXt
+    #[arg(long, value_name = "KEYWORD", value_parser = text::keyword, conflicts_with_all = ["encryption", "key_file", "recipient", "compress", "base64", "input_file", "checksum", "sign", "decoy_message", "text_keyword"])]
+    pub ztxt_keyword: Option<String>,
+    /// Write a standard iTXt chunk with the message as UTF-8 text under KEYWORD, deflated
+    /// with --compress. the chunk type has to be iTXt
+    #[arg(long, value_name = "KEYWORD", value_parser = text::keyword, conflicts_with_all = ["encryption", "key_file", "recipient", "base64", "input_file", "checksum", "sign", "decoy_message", "text_keyword", "ztxt_keyword"])]
+    pub itxt_keyword: Option<String>,
+    /// The language tag of the iTXt text, like en or en-US
+    #[arg(long, value_name = "TAG", value_parser = text::language, requires = "itxt_keyword", default_value = "")]
+    pub language: String,
+    /// The keyword of the iTXt chunk translated into its language
+    #[arg(
+        long,
+        value_name = "TEXT",
+        requires = "itxt_keyword",
+        default_value = ""
+    )]
+    pub translated_keyword: String,
+}
+impl EncodeArgs {
+    /// where the encoded png goes, none for in place
+    pub fn output(&self) -> Option<&PathBuf> {
+        self.output.as_ref().or(self.output_file.as_ref())
+    }
+    /// encoding `message` into `file_path` in place, with the defaults of every option
+    pub fn new(
+        file_path: impl Into<PathBuf>,
+        chunk_type: ChunkType,
+        message: impl Into<String>,
+    ) -> Self {
+        let message: OsString = message.into().into();
+        parsed(&[
+            file_path.into().into(),
+            chunk_type.to_string().into(),
+            message,
+        ])
+    }
 }
 #[derive(Args)]
 pub struct RemoveArgs {
-    file_path: PathBuf,
-    chunk_type: String,
+    pub file_path: PathBuf,
+    #[arg(value_parser = ChunkType::from_str)]
+    pub chunk_type: ChunkType,
+    /// Remove every chunk of the given type
+    #[arg(long, conflicts_with = "nth")]
+    pub all: bool,
+    /// Remove only the nth chunk of the given type, counting from 0
+    #[arg(long)]
+    pub nth: Option<usize>,
+    /// Remove every chunk of the given type whose data, read as UTF-8 with anything else
+    /// replaced, matches REGEX, and no other
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new, conflicts_with_all = ["all", "nth"])]
+    pub matching: Option<Regex>,
+    /// Remove chunks of the type in any case, ruSt as well as RuSt
+    #[arg(long)]
+    pub ignore_case: bool,
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Copy the original aside, to its name plus SUFFIX, before changing it in place
+    #[arg(long, value_name = "SUFFIX", num_args = 0..=1, require_equals = true, default_missing_value = ".bak")]
+    pub backup: Option<String>,
+    /// Keep the modification time of the original file
+    #[arg(long)]
+    pub preserve_times: bool,
+    /// Print the type, length and data of every removed chunk
+    #[arg(long)]
+    pub print_removed: bool,
+    /// With --print-removed, only show the first BYTES bytes of each chunk's data
+    #[arg(long, value_name = "BYTES", requires = "print_removed")]
+    pub limit: Option<usize>,
+    /// Write the data of every removed chunk to its own file in DIR, named by the
+    /// chunk's index in the file like extract-chunks does
+    #[arg(long, value_name = "DIR")]
+    pub save_removed: Option<PathBuf>,
+    /// Overwrite files that already exist in the --save-removed directory
+    #[arg(short, long, requires = "save_removed")]
+    pub force: bool,
+    /// Decode the written file and compare its pixels to the original's, undoing the write
+    /// if they differ. needs the image feature
+    #[arg(long)]
+    pub verify_render: bool,
+    /// Allow a critical chunk type, which can corrupt the image
+    #[arg(long)]
+    pub allow_critical: bool,
+    /// Don't ask before touching a critical chunk, for scripts
+    #[arg(short, long, requires = "allow_critical")]
+    pub yes: bool,
+}
+impl RemoveArgs {
+    /// removing the first chunk of `chunk_type` from `file_path`, with the defaults of every option
+    pub fn new(file_path: impl Into<PathBuf>, chunk_type: ChunkType) -> Self {
+        parsed(&[file_path.into().into(), chunk_type.to_string().into()])
+    }
 }
 
 #[derive(Args)]
 pub struct DecodeArgs {
-    file_path: PathBuf,
-    chunk_type: String,
+    pub file_path: PathBuf,
+    #[arg(value_parser = ChunkType::from_str)]
+    pub chunk_type: ChunkType,
+    /// Write the raw chunk data to this file instead of printing it
+    #[arg(short, long, conflicts_with = "all")]
+    pub output: Option<PathBuf>,
+    /// Decode every chunk of the given type, in file order
+    #[arg(long)]
+    pub all: bool,
+    /// Find chunks of the type in any case, ruSt as well as RuSt. the case of each letter
+    /// is a property bit, so it is kept apart otherwise
+    #[arg(long)]
+    pub ignore_case: bool,
+    /// Write the raw data to a file in this directory, under its original name and mode for
+    /// an --input-file payload. with --all a file for each chunk
+    #[arg(long, conflicts_with = "output")]
+    pub output_dir: Option<PathBuf>,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub force: bool,
+    /// Password of an encrypted payload, otherwise $PNGSECRET_PASSWORD or a prompt is used
+    #[arg(long, value_name = "PASSWORD")]
+    pub password: Option<String>,
+    /// Decrypt with the raw 32 byte key in this file, for payloads encoded with --key-file
+    #[arg(long, value_name = "PATH", conflicts_with = "password")]
+    pub key_file: Option<PathBuf>,
+    /// Print the payload as base64 whatever it holds, or write it to --output as base64
+    #[arg(long)]
+    pub base64: bool,
+    /// Print the payload as hex whatever it holds, or write it to --output as hex
+    #[arg(long, conflicts_with = "base64")]
+    pub hex: bool,
+    /// Print the payload as text of this encoding, whatever it holds. without it a payload
+    /// that isn't UTF-8 isn't printed, and tEXt and zTXt text is Latin-1 as the spec says
+    #[arg(long, value_enum, conflicts_with_all = ["base64", "hex"])]
+    pub encoding: Option<Encoding>,
+    /// Print the payload header first, what it says about compression, encryption and parts
+    #[arg(long)]
+    pub show_header: bool,
+    /// Decode a payload whose checksum doesn't match anyway, with a warning
+    #[arg(long)]
+    pub ignore_checksum: bool,
+    /// Fail unless the payload is signed by this Ed25519 public key, given in hex or as a
+    /// keypair or public key file
+    #[arg(long, value_name = "KEY")]
+    pub verify_with: Option<String>,
+    /// Print the text of the tEXt, zTXt or iTXt chunk with this keyword, the chunk type has
+    /// to be one of them. zTXt and iTXt text is decoded even without a keyword
+    #[arg(long, value_name = "KEYWORD", value_parser = text::keyword, conflicts_with = "all")]
+    pub text_keyword: Option<String>,
+    /// Print the text of the iTXt chunk with this keyword, the chunk type has to be iTXt
+    #[arg(long, value_name = "KEYWORD", value_parser = text::keyword, conflicts_with_all = ["all", "text_keyword"])]
+    pub itxt_keyword: Option<String>,
+    /// Decrypt with an identity from this file, for payloads encoded with --recipient
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["password", "key_file"])]
+    pub identity: Option<PathBuf>,
+    /// Map the file into memory and only copy out the chunks of the given type, for huge
+    /// files. the file is read as usual where it can't be mapped
+    #[arg(long)]
+    pub mmap: bool,
+    /// Read the payload from a chunk, or with lsb from the lowest bits of the pixels as
+    /// encode --method lsb wrote it. lsb needs the image feature
+    #[arg(long, value_enum, default_value_t = Method::Chunk)]
+    pub method: Method,
+    /// The color channels encode --method lsb wrote bits to
+    #[arg(long, value_enum, default_value_t = Channels::Rgb)]
+    pub channels: Channels,
+    /// Read the payload encode --stealth spread over chunks of other types, trying every
+    /// unknown ancillary chunk with the password or key. the chunk type given only names
+    /// the output file
+    #[arg(long, conflicts_with_all = ["all", "mmap", "method", "text_keyword", "itxt_keyword"])]
+    pub stealth: bool,
+    /// The Argon2id cost encode --stealth was given as --kdf-params, which the parts
+    /// don't record
+    #[arg(long, value_name = "M,T,P", requires = "stealth", value_parser = Argon2Params::from_str)]
+    pub kdf_params: Option<Argon2Params>,
+    /// Read a file with chunks whose CRC doesn't match anyway, with a warning for each.
+    /// the payload is decoded from what the chunks hold
+    #[arg(long, conflicts_with = "mmap")]
+    pub ignore_crc: bool,
+    /// Read what is left of a damaged or cut short file, the chunks up to the first broken
+    /// one, with a warning for what was dropped
+    #[arg(long, conflicts_with_all = ["mmap", "ignore_crc"])]
+    pub salvage: bool,
+    /// Print or write the bytes after IEND that aren't a chunk, as they are. the chunk
+    /// type given only names the output file
+    #[arg(long, conflicts_with_all = ["all", "stealth", "method", "text_keyword", "itxt_keyword"])]
+    pub trailing: bool,
+}
+impl DecodeArgs {
+    /// decoding the first chunk of `chunk_type` in `file_path` to stdout, with the defaults
+    /// of every option
+    pub fn new(file_path: impl Into<PathBuf>, chunk_type: ChunkType) -> Self {
+        parsed(&[file_path.into().into(), chunk_type.to_string().into()])
+    }
 }
 
 #[derive(Args)]
 pub struct PrintArgs {
-    file_path: PathBuf,
+    pub file_path: PathBuf,
+    /// Only print chunks of this type, can be repeated
+    #[arg(long = "type", value_name = "CHUNKTYPE", value_parser = ChunkType::from_str)]
+    pub types: Vec<ChunkType>,
+    /// Skip chunks of this type, can be repeated
+    #[arg(long = "exclude-type", value_name = "CHUNKTYPE", value_parser = ChunkType::from_str)]
+    pub exclude_types: Vec<ChunkType>,
+    /// Match --type and --exclude-type in any case, ruSt as well as RuSt
+    #[arg(long)]
+    pub ignore_case: bool,
+    /// Only print critical chunks, the ones the image needs
+    #[arg(long, conflicts_with = "ancillary_only")]
+    pub critical_only: bool,
+    /// Only print ancillary chunks, each marked as public or private
+    #[arg(long)]
+    pub ancillary_only: bool,
+    /// Print the chunks as a JSON array
+    ///
+    /// Every chunk is an object with the fields `index`, `type`, `length`,
+    /// `crc` (8 hex digits), `offset` (byte offset in the file), `critical`,
+    /// `public`, `safe_to_copy` and `data` (base64, left out with --no-data).
+    /// With --ignore-crc a chunk whose CRC doesn't match has `computed_crc` too.
+    #[arg(long, verbatim_doc_comment)]
+    pub json: bool,
+    /// Leave the data field out of the JSON output
+    #[arg(long, requires = "json")]
+    pub no_data: bool,
+    /// Show chunk data as a hex dump
+    #[arg(long, conflicts_with = "json")]
+    pub hex: bool,
+    /// Read chunk data and the text of text chunks as this encoding. data is UTF-8 with
+    /// anything else replaced otherwise, and tEXt and zTXt text Latin-1 as the spec says
+    #[arg(long, value_enum, conflicts_with_all = ["json", "hex"])]
+    pub encoding: Option<Encoding>,
+    /// Only show the first BYTES bytes of each chunk's data
+    ///
+    /// Defaults to 512 on a terminal and no limit when the output is piped.
+    #[arg(long, value_name = "BYTES", conflicts_with = "json")]
+    pub limit: Option<usize>,
+    /// Print a file with chunks whose CRC doesn't match anyway, each marked with the
+    /// stored and the computed CRC
+    #[arg(long)]
+    pub ignore_crc: bool,
+    /// Print what is left of a damaged or cut short file, the chunks up to the first
+    /// broken one, with a warning for what was dropped
+    #[arg(long, conflicts_with = "ignore_crc")]
+    pub salvage: bool,
+}
+impl PrintArgs {
+    /// printing every chunk of `file_path`, with the defaults of every option
+    pub fn new(file_path: impl Into<PathBuf>) -> Self {
+        parsed(&[file_path.into().into()])
+    }
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    pub(crate) file_path: PathBuf,
+    /// Order of the rows, sizes and types sort ascending
+    #[arg(long, value_enum, default_value_t = SortKey::Offset)]
+    pub(crate) sort: SortKey,
+    /// List what is left of a damaged or cut short file, the chunks up to the first
+    /// broken one, with a warning for what was dropped
+    #[arg(long)]
+    pub(crate) salvage: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SortKey {
+    Size,
+    Type,
+    Offset,
+}
+
+#[derive(Args)]
+pub struct ScanArgs {
+    /// A png file, or a directory that is searched recursively for png files
+    pub(crate) path: PathBuf,
+    /// Exit with an error when a finding of at least this severity shows up
+    #[arg(long, value_enum, default_value_t = Severity::Medium)]
+    pub(crate) fail_on: Severity,
+    /// Files to read at once in a directory, one per core by default
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub(crate) threads: Option<u16>,
+}
+
+#[derive(Args)]
+/// with the global --quiet nothing is printed, only the exit code is set
+pub struct VerifyArgs {
+    pub(crate) file_path: PathBuf,
+    /// Also check where the spec wants each ancillary chunk, like gAMA before PLTE and
+    /// IDAT. a misplaced chunk is a hint it was added by a tool
+    #[arg(long)]
+    pub(crate) strict: bool,
+}
+
+#[derive(Args)]
+pub struct RepairArgs {
+    pub(crate) file_path: PathBuf,
+    /// Write the repaired file here instead of overwriting the input
+    #[arg(short, long)]
+    pub(crate) output: Option<PathBuf>,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Args)]
+pub struct StripArgs {
+    pub(crate) file_path: PathBuf,
+    /// Write the stripped file here instead of overwriting the input
+    #[arg(short, long)]
+    pub(crate) output: Option<PathBuf>,
+    /// Keep ancillary chunks of this type, can be repeated
+    #[arg(long, value_name = "CHUNKTYPE", value_parser = ChunkType::from_str)]
+    pub(crate) keep: Vec<ChunkType>,
+    /// Drop the bytes after IEND that aren't a chunk too, which are kept otherwise
+    #[arg(long)]
+    pub(crate) strip_trailing: bool,
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+    /// Decode the written file and compare its pixels to the original's, undoing the write
+    /// if they differ. needs the image feature
+    #[arg(long)]
+    pub(crate) verify_render: bool,
+}
+
+#[derive(Args)]
+pub struct ExtractChunksArgs {
+    pub(crate) file_path: PathBuf,
+    /// Directory for the chunk files, created if missing
+    #[arg(long)]
+    pub(crate) out_dir: PathBuf,
+    /// Only extract chunks of this type, can be repeated
+    #[arg(long = "type", value_name = "CHUNKTYPE", value_parser = ChunkType::from_str)]
+    pub(crate) types: Vec<ChunkType>,
+    /// Overwrite chunk files that already exist
+    #[arg(short, long)]
+    pub(crate) force: bool,
+    /// Extract what is left of a damaged or cut short file, the chunks up to the first
+    /// broken one, with a warning for what was dropped
+    #[arg(long)]
+    pub(crate) salvage: bool,
+}
+
+#[derive(Args)]
+pub struct InjectArgs {
+    pub(crate) file_path: PathBuf,
+    #[arg(value_parser = ChunkType::from_str)]
+    pub(crate) chunk_type: ChunkType,
+    /// File whose bytes become the chunk data
+    #[arg(long)]
+    pub(crate) data_file: PathBuf,
+    /// Where to insert the chunk: before-iend, after-ihdr, before-idat or index:N
+    #[arg(long, default_value = "before-iend", value_parser = Position::from_str)]
+    pub(crate) position: Position,
+    /// Allow a critical chunk type, which can corrupt the image
+    #[arg(long)]
+    pub(crate) allow_critical: bool,
+    /// Don't ask before touching a critical chunk, for scripts
+    #[arg(short, long, requires = "allow_critical")]
+    pub(crate) yes: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub(crate) output: Option<PathBuf>,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Args)]
+pub struct CopyChunkArgs {
+    /// The png file to copy the chunks from
+    pub(crate) source: PathBuf,
+    /// The png file to copy them into
+    pub(crate) destination: PathBuf,
+    #[arg(value_parser = ChunkType::from_str)]
+    pub(crate) chunk_type: ChunkType,
+    /// Write the result here instead of overwriting the destination
+    #[arg(short, long)]
+    pub(crate) output: Option<PathBuf>,
+    /// Keep the chunks of the type already in the destination and add the copies
+    #[arg(long)]
+    pub(crate) append: bool,
+    /// Copy a chunk that isn't safe to copy into a file with other image data, and
+    /// overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+    /// Allow a critical chunk type, which can corrupt the image
+    #[arg(long)]
+    pub(crate) allow_critical: bool,
+    /// Don't ask before touching a critical chunk, for scripts
+    #[arg(short, long, requires = "allow_critical")]
+    pub(crate) yes: bool,
+}
+
+/// where encode hides the payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Method {
+    Chunk,
+    Lsb,
+}
+
+/// the channels of a pixel that carry bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Channels {
+    Rgb,
+    Rgba,
+}
+
+/// where a new chunk is inserted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    BeforeIend,
+    AfterIhdr,
+    BeforeIdat,
+    Index(usize),
+}
+impl FromStr for Position {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "before-iend" => Ok(Position::BeforeIend),
+            "after-ihdr" => Ok(Position::AfterIhdr),
+            "before-idat" => Ok(Position::BeforeIdat),
+            _ => match s.strip_prefix("index:") {
+                Some(index) => index
+                    .parse()
+                    .map(Position::Index)
+                    .map_err(|_| format!("invalid chunk index {}", index)),
+                None => Err(format!(
+                    "unknown position {}, expected before-iend, after-ihdr, before-idat or index:N",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// how encode --pad hides the length of the message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pad {
+    None,
+    /// up to a multiple of this many bytes
+    Block(usize),
+    /// by between 0 and this many bytes
+    Random(usize),
+}
+impl FromStr for Pad {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let size = |size: &str| {
+            size.parse()
+                .map_err(|_| format!("invalid padding size {}", size))
+        };
+        if s == "none" {
+            Ok(Pad::None)
+        } else if let Some(block) = s.strip_prefix("block:") {
+            match size(block)? {
+                0 => Err("the block size has to be at least 1".to_string()),
+                block => Ok(Pad::Block(block)),
+            }
+        } else if let Some(max) = s.strip_prefix("random:") {
+            size(max).map(Pad::Random)
+        } else {
+            Err(format!(
+                "unknown padding {}, expected none, block:N or random:MAX",
+                s
+            ))
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    pub(crate) old_file: PathBuf,
+    pub(crate) new_file: PathBuf,
+    /// Also report chunks that only moved
+    #[arg(long)]
+    pub(crate) strict_order: bool,
+    /// Show a hex diff of changed chunk data
+    #[arg(long)]
+    pub(crate) data: bool,
+    /// Only compare the first BYTES bytes of changed data
+    #[arg(long, value_name = "BYTES", default_value_t = 256, requires = "data")]
+    pub(crate) data_limit: usize,
+}
+
+#[derive(Args)]
+pub struct CapacityArgs {
+    pub(crate) file_path: PathBuf,
+    /// Size of the payload to embed
+    #[arg(long, value_name = "BYTES")]
+    pub(crate) payload_size: Option<usize>,
+    /// Largest chunk a payload is split into
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_CHUNK_SIZE)]
+    pub(crate) max_chunk_size: usize,
+    /// Count the room in chunks, or with lsb in the lowest bits of the pixels
+    #[arg(long, value_enum, default_value_t = Method::Chunk)]
+    pub(crate) method: Method,
+    /// The color channels --method lsb would write bits to
+    #[arg(long, value_enum, default_value_t = Channels::Rgb)]
+    pub(crate) channels: Channels,
+    /// Bits of each channel --method lsb would use, encode writes 1
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=4))]
+    pub(crate) bits: u8,
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    #[arg(value_enum)]
+    pub(crate) shell: Shell,
+}
+
+#[derive(Args)]
+pub struct ManArgs {
+    /// Write pngsecret.1 and a pngsecret-COMMAND.1 for every subcommand to this
+    /// directory, created if missing
+    #[arg(long, value_name = "DIR")]
+    pub(crate) out_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct KeygenArgs {
+    /// Write the key to this file instead of stdout
+    #[arg(short, long, visible_alias = "out")]
+    pub(crate) output: Option<PathBuf>,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+    /// An age identity for --recipient, or an Ed25519 keypair in JSON for --sign
+    #[arg(long = "type", value_enum, default_value_t = KeyType::Age)]
+    pub(crate) key_type: KeyType,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyType {
+    Age,
+    Ed25519,
+}
+
+#[derive(Args)]
+pub struct TouchArgs {
+    pub(crate) file_path: PathBuf,
+    /// The time to set, in RFC 3339 like 2024-03-05T14:30:00Z, instead of now or
+    /// $SOURCE_DATE_EPOCH
+    #[arg(long, value_parser = time::rfc3339)]
+    pub(crate) time: Option<time::Time>,
+    /// Write the touched file here instead of overwriting the input
+    #[arg(short, long)]
+    pub(crate) output: Option<PathBuf>,
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Args)]
+pub struct ExifArgs {
+    pub(crate) file_path: PathBuf,
+    /// Write the raw EXIF data here, for other tools to read
+    #[arg(short, long, conflicts_with = "remove_exif")]
+    pub(crate) output: Option<PathBuf>,
+    /// Remove every eXIf chunk from the file, camera, location and all
+    #[arg(long)]
+    pub(crate) remove_exif: bool,
+    /// Show what would change without writing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+    /// Overwrite an existing output file
+    #[arg(short, long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Args)]
+pub struct MetadataArgs {
+    pub(crate) file_path: PathBuf,
+}
+
+#[derive(Args)]
+/// prints nothing unless --count is given, the exit code is the answer
+pub struct HasArgs {
+    pub(crate) file_path: PathBuf,
+    /// The types to look for, the file needs a chunk of each of them
+    #[arg(required = true, num_args = 1.., value_parser = ChunkType::from_str)]
+    pub(crate) chunk_types: Vec<ChunkType>,
+    /// A chunk of any of the types is enough
+    #[arg(long)]
+    pub(crate) any: bool,
+    /// Look for the types in any case, ruSt as well as RuSt
+    #[arg(long)]
+    pub(crate) ignore_case: bool,
+    /// Print how many chunks there are of each type, one line each
+    #[arg(long)]
+    pub(crate) count: bool,
+}
+
+#[derive(Args)]
+pub struct InfoArgs {
+    pub(crate) file_path: PathBuf,
+    /// Describe a file with chunks whose CRC doesn't match anyway, with a warning for each
+    #[arg(long)]
+    pub(crate) ignore_crc: bool,
 }
@@ -0,0 +1,84 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// standard base64 with padding
+pub fn encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bytes = [
+            group[0],
+            group.get(1).copied().unwrap_or(0),
+            group.get(2).copied().unwrap_or(0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= group.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// standard base64, whitespace is skipped and the padding can be left out
+pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut values = Vec::with_capacity(text.len());
+    let mut padding = 0;
+    for (i, c) in text
+        .char_indices()
+        .filter(|(_, c)| !c.is_ascii_whitespace())
+    {
+        match (c, ALPHABET.iter().position(|&a| a as char == c)) {
+            ('=', _) => padding += 1,
+            (_, Some(value)) if padding == 0 => values.push(value as u32),
+            _ => return Err(format!("Invalid base64 character {:?} at {}", c, i)),
+        }
+    }
+    if values.len() % 4 == 1 || padding > 2 || (padding > 0 && (values.len() + padding) % 4 != 0) {
+        return Err("Invalid base64 length".to_string());
+    }
+    let mut data = Vec::with_capacity(values.len() * 3 / 4);
+    for group in values.chunks(4) {
+        let bits = group
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, value)| bits | value << (18 - 6 * i));
+        data.extend_from_slice(&bits.to_be_bytes()[1..group.len()]);
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode(&[0xff, 0xfe, 0x00]), "//4A");
+    }
+
+    #[test]
+    fn test_decode() {
+        for data in [
+            &b""[..],
+            b"f",
+            b"fo",
+            b"foo",
+            b"foobar",
+            &[0xff, 0xfe, 0x00],
+        ] {
+            assert_eq!(decode(&encode(data)).unwrap(), data);
+        }
+        assert_eq!(decode("Zm9v\nYmE").unwrap(), b"fooba");
+        assert!(decode("Zm9v!").is_err());
+        assert!(decode("Z").is_err());
+        assert!(decode("Zg==Zg==").is_err());
+        assert!(decode("Zg===").is_err());
+    }
+}
@@ -1,27 +1,43 @@
-use crc::Crc;
+//! a single chunk: reading and writing it and its crc
+
 use std::{
-    fmt::Display,
-    io::{BufReader, Read},
-    process::{ExitCode, Termination},
+    fmt::{Display, Write},
+    io::{self, BufReader, Read},
+};
+
+use crate::{
+    chunk_type::ChunkType,
+    error::{Location, PngSecretError},
 };
 
-use crate::{chunk_type::ChunkType, Error};
-#[derive(Debug, Clone)]
+#[cfg(all(feature = "crc", not(feature = "no-crc-dep")))]
+type Digest = crc::Digest<'static, u32>;
+#[cfg(all(feature = "crc", not(feature = "no-crc-dep")))]
+fn digest() -> Digest {
+    static CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+    CRC.digest()
+}
+#[cfg(not(all(feature = "crc", not(feature = "no-crc-dep"))))]
+use crate::crc32::{digest, Digest};
+
+/// a chunk of a png: its length, type, data and crc
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Chunk {
     data_length: [u8; 4],
     chunk_type: ChunkType,
     data: Vec<u8>,
     crc: [u8; 4],
 }
-impl Termination for Chunk {
-    fn report(self) -> std::process::ExitCode {
-        ExitCode::SUCCESS
-    }
-}
 impl Chunk {
+    /// bytes every chunk takes besides its data: length, type and crc
+    pub const OVERHEAD: usize = 12;
+    /// the longest data the png spec allows, 2^31 - 1 bytes
+    pub const MAX_LENGTH: usize = (1 << 31) - 1;
+
+    /// a chunk of `data` with the crc computed from it
     pub fn new(chunk_type: ChunkType, data: &[u8]) -> Self {
         let data_length = data.len() as u32;
-        let crc = Chunk::calculate_crc(&chunk_type.bytes(), &data);
+        let crc = Chunk::calculate_crc(&chunk_type.bytes(), data);
         Chunk {
             data_length: data_length.to_be_bytes(),
             chunk_type,
@@ -29,32 +45,91 @@ impl Chunk {
             crc,
         }
     }
+    /// builds a chunk keeping `crc` as is, even if it doesn't match the data
+    pub fn new_unchecked(chunk_type: ChunkType, data: &[u8], crc: u32) -> Self {
+        Chunk {
+            data_length: (data.len() as u32).to_be_bytes(),
+            chunk_type,
+            data: data.to_vec(),
+            crc: crc.to_be_bytes(),
+        }
+    }
+    /// replaces the stored crc with the one computed from type and data, returns whether it changed
+    pub fn recompute_crc(&mut self) -> bool {
+        let crc = Chunk::calculate_crc(&self.chunk_type.bytes(), &self.data);
+        let changed = crc != self.crc;
+        self.crc = crc;
+        changed
+    }
+    /// the stored crc
     pub fn crc(&self) -> u32 {
         u32::from_be_bytes(self.crc)
     }
+    /// the crc of the chunk type and data, what should be stored
+    pub fn computed_crc(&self) -> u32 {
+        u32::from_be_bytes(Chunk::calculate_crc(&self.chunk_type.bytes(), &self.data))
+    }
+    /// whether the stored crc matches the chunk type and data
+    pub fn is_crc_valid(&self) -> bool {
+        self.crc() == self.computed_crc()
+    }
+    /// the length of the data
     pub fn length(&self) -> u32 {
         self.data.len() as u32
     }
+    /// the type of the chunk
     pub fn chunk_type(&self) -> &ChunkType {
         &self.chunk_type
     }
-    pub fn data_as_string(&self) -> Result<String, Error> {
-        let string = String::from_utf8(self.data.iter().cloned().collect())?;
-        Ok(string)
+    /// the data of the chunk
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// the data as text, an error if it isn't utf-8
+    pub fn data_as_string(&self) -> Result<String, PngSecretError> {
+        Ok(std::str::from_utf8(&self.data)?.to_owned())
     }
+    /// the crc of a chunk of type `chunk` holding `data`, big endian
     pub fn calculate_crc(chunk: &[u8], data: &[u8]) -> [u8; 4] {
-        let data_check: Vec<u8> = chunk.iter().chain(data.iter()).copied().collect();
-        let crc = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-        crc.checksum(&data_check).to_be_bytes()
+        let mut digest = digest();
+        digest.update(chunk);
+        digest.update(data);
+        digest.finalize().to_be_bytes()
     }
+    /// canonical hex dump of the data: offset, 16 hex bytes and an ascii gutter per line
+    pub fn hex_dump(&self, limit: Option<usize>) -> String {
+        let end = limit.map_or(self.data.len(), |limit| limit.min(self.data.len()));
+        let mut dump = String::new();
+        for (line, bytes) in self.data[..end].chunks(16).enumerate() {
+            let mut hex = String::new();
+            for (i, byte) in bytes.iter().enumerate() {
+                if i == 8 {
+                    hex.push(' ');
+                }
+                let _ = write!(hex, "{:02x} ", byte);
+            }
+            let ascii: String = bytes
+                .iter()
+                .map(|&b| {
+                    if b == b' ' || b.is_ascii_graphic() {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            let _ = writeln!(dump, "{:08x}  {:<49} |{}|", line * 16, hex, ascii);
+        }
+        dump
+    }
+    /// the chunk as it is in a file
     pub fn as_bytes(&self) -> Vec<u8> {
-        self.data_length
-            .iter()
-            .chain(self.chunk_type.bytes().iter())
-            .chain(self.data.iter())
-            .chain(self.crc.iter())
-            .cloned()
-            .collect()
+        let mut bytes = Vec::with_capacity(Chunk::OVERHEAD + self.data.len());
+        bytes.extend_from_slice(&self.data_length);
+        bytes.extend_from_slice(&self.chunk_type.bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes.extend_from_slice(&self.crc);
+        bytes
     }
 }
 impl Display for Chunk {
@@ -63,42 +138,70 @@ impl Display for Chunk {
             f,
             "Data length: {}\nChunk:{}\nData:{}\nCrc:{}",
             u32::from_be_bytes(self.data_length),
-            self.chunk_type().to_string(),
-            self.data_as_string().unwrap(),
+            self.chunk_type(),
+            String::from_utf8_lossy(&self.data),
             self.crc()
         )
     }
 }
 
 impl TryFrom<&[u8]> for Chunk {
-    type Error = Error;
+    type Error = PngSecretError;
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Chunk::read(value, true)
+    }
+}
+impl Chunk {
+    /// like `try_from` but keeps a chunk whose stored crc doesn't match its contents
+    pub fn try_from_lenient(value: &[u8]) -> Result<Self, PngSecretError> {
+        Chunk::read(value, false)
+    }
+    fn read(value: &[u8], check_crc: bool) -> Result<Self, PngSecretError> {
         // preparing buffer and reader to read &[u8]
         let mut reader = BufReader::new(value);
         let mut buffer: [u8; 4] = [0, 0, 0, 0];
+        let truncated = |chunk_type: Option<&ChunkType>| PngSecretError::Truncated {
+            at: Location {
+                chunk_type: chunk_type.map(ChunkType::to_string),
+                ..Location::default()
+            },
+        };
 
         // reads the data length
-        reader.read_exact(&mut buffer)?;
+        reader
+            .read_exact(&mut buffer)
+            .map_err(|_| truncated(None))?;
         let data_length = u32::from_be_bytes(buffer);
 
         // reads the chunk type
-        reader.read_exact(&mut buffer)?;
-        let chunk_type = ChunkType::new(buffer[0], buffer[1], buffer[2], buffer[3]);
-        if !chunk_type.is_valid() {
-            return Err(Error::from("Not a valid chunk"));
-        }
+        reader
+            .read_exact(&mut buffer)
+            .map_err(|_| truncated(None))?;
+        let chunk_type = ChunkType::try_from(buffer)?;
 
         // reads the data
         let mut buffer = vec![0; data_length as usize];
-        reader.read_exact(&mut buffer)?;
+        reader
+            .read_exact(&mut buffer)
+            .map_err(|_| truncated(Some(&chunk_type)))?;
         let data = buffer;
 
         //reads the crc
         let mut buffer: [u8; 4] = [0, 0, 0, 0];
-        reader.read_exact(&mut buffer)?;
+        reader
+            .read_exact(&mut buffer)
+            .map_err(|_| truncated(Some(&chunk_type)))?;
         let crc = buffer;
-        if crc != Chunk::calculate_crc(&chunk_type.bytes(), &data) {
-            return Err(Error::from("Not a valid crc"));
+        let expected = Chunk::calculate_crc(&chunk_type.bytes(), &data);
+        if check_crc && crc != expected {
+            return Err(PngSecretError::CrcMismatch {
+                expected: u32::from_be_bytes(expected),
+                found: u32::from_be_bytes(crc),
+                at: Location {
+                    chunk_type: Some(chunk_type.to_string()),
+                    ..Location::default()
+                },
+            });
         }
 
         Ok(Chunk {
@@ -110,13 +213,47 @@ impl TryFrom<&[u8]> for Chunk {
     }
 }
 
+/// passes writes through to `W` and keeps the crc of everything written so far,
+/// so a chunk's crc comes out of copying its type and data without holding them
+pub struct CrcWriter<W: io::Write> {
+    inner: W,
+    digest: Digest,
+}
+impl<W: io::Write> CrcWriter<W> {
+    /// a writer to `inner` with the crc of nothing written yet
+    pub fn new(inner: W) -> Self {
+        CrcWriter {
+            inner,
+            digest: digest(),
+        }
+    }
+    /// the crc of what was written, writing can go on after it
+    pub fn crc(&self) -> u32 {
+        self.digest.clone().finalize()
+    }
+    /// the writer everything went to
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+impl<W: io::Write> io::Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        // only what the inner writer took, the rest comes again in the next write
+        self.digest.update(&buf[..written]);
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::chunk_type::ChunkType;
+    use crate::chunk_type::{ChunkType, ChunkTypeError};
     use std::str::FromStr;
 
-    #[test]
     fn testing_chunk() -> Chunk {
         let data_length: u32 = 42;
         let chunk_type = "RuSt".as_bytes();
@@ -166,6 +303,28 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_data_as_string_not_utf8() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), &[b'a', 0xff]);
+        let error = chunk.data_as_string().unwrap_err();
+        assert!(matches!(error, PngSecretError::NotUtf8(ref e) if e.valid_up_to() == 1));
+    }
+
+    #[test]
+    fn test_as_bytes_big_chunk() {
+        for size in [0, 1, 10 * 1024 * 1024] {
+            let data: Vec<u8> = (0..size).map(|i| (i % 253) as u8).collect();
+            let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), &data);
+            let bytes = chunk.as_bytes();
+            assert_eq!(bytes.len(), Chunk::OVERHEAD + size);
+            assert_eq!(bytes[..4], (size as u32).to_be_bytes());
+            assert_eq!(&bytes[4..8], b"RuSt");
+            assert!(bytes[8..8 + size] == data[..]);
+            assert_eq!(bytes[8 + size..], chunk.crc().to_be_bytes());
+            assert!(Chunk::try_from(bytes.as_ref()).unwrap() == chunk);
+        }
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
@@ -217,7 +376,128 @@ mod tests {
 
         let chunk = Chunk::try_from(chunk_data.as_ref());
 
-        assert!(chunk.is_err());
+        assert!(matches!(
+            chunk,
+            Err(PngSecretError::CrcMismatch {
+                expected: 2882656334,
+                found: 2882656333,
+                ..
+            })
+        ));
+        assert_eq!(
+            chunk.unwrap_err().to_string(),
+            "invalid CRC in chunk (RuSt): expected 0xABD1D84E, found 0xABD1D84D"
+        );
+        assert!(matches!(
+            Chunk::try_from(&chunk_data[..20]),
+            Err(PngSecretError::Truncated { at }) if at.chunk_type.as_deref() == Some("RuSt")
+        ));
+        let mut bad_type = chunk_data.clone();
+        bad_type[4] = b'1';
+        assert!(matches!(
+            Chunk::try_from(bad_type.as_ref()),
+            Err(PngSecretError::InvalidChunkType {
+                error: ChunkTypeError::NotLetters,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"Hello, hex dump!\x00\x01\xff tail",
+        );
+        let expected = "\
+00000000  48 65 6c 6c 6f 2c 20 68  65 78 20 64 75 6d 70 21  |Hello, hex dump!|
+00000010  00 01 ff 20 74 61 69 6c                           |... tail|
+";
+        assert_eq!(chunk.hex_dump(None), expected);
+        assert_eq!(
+            chunk.hex_dump(Some(4)),
+            "00000000  48 65 6c 6c                                       |Hell|\n"
+        );
+    }
+
+    #[test]
+    fn test_hex_dump_empty() {
+        let chunk = Chunk::new(ChunkType::from_str("IEND").unwrap(), &[]);
+        assert_eq!(chunk.hex_dump(None), "");
+    }
+
+    #[test]
+    fn test_lenient_chunk_from_bytes() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656333;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from_lenient(chunk_data.as_ref()).unwrap();
+        assert_eq!(chunk.crc(), 2882656333);
+        assert_eq!(chunk.computed_crc(), testing_chunk().crc());
+        assert!(!chunk.is_crc_valid());
+        assert!(testing_chunk().is_crc_valid());
+    }
+
+    #[test]
+    fn test_recompute_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!".as_bytes();
+        let mut chunk = Chunk::new_unchecked(chunk_type, data, 1);
+        assert!(!chunk.is_crc_valid());
+        assert!(chunk.recompute_crc());
+        assert_eq!(chunk.crc(), 2882656334);
+        assert!(!chunk.recompute_crc());
+    }
+
+    #[test]
+    fn test_crc_writer() {
+        use std::io::Write;
+        for size in [0, 1, 7, 4096, 100_003] {
+            let data: Vec<u8> = (0..size).map(|i| (i * 31 % 251) as u8).collect();
+            let whole: Vec<u8> = b"RuSt".iter().chain(&data).copied().collect();
+            let mut writer = CrcWriter::new(Vec::new());
+            writer.write_all(b"RuSt").unwrap();
+            for part in data.chunks(1000) {
+                writer.write_all(part).unwrap();
+            }
+            let mut digest = digest();
+            digest.update(&whole);
+            assert_eq!(writer.crc(), digest.finalize());
+            assert_eq!(
+                writer.crc().to_be_bytes(),
+                Chunk::calculate_crc(b"RuSt", &data)
+            );
+            assert!(writer.into_inner() == whole);
+        }
+        let mut writer = CrcWriter::new(io::sink());
+        writer.write_all(b"RuSt").unwrap();
+        writer
+            .write_all(b"This is where your secret message will be!")
+            .unwrap();
+        assert_eq!(writer.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_chunk_eq() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type.clone(), b"data");
+        assert_eq!(chunk, Chunk::new(chunk_type.clone(), b"data"));
+        assert_ne!(chunk, Chunk::new(chunk_type, b"other"));
+        assert_ne!(
+            chunk,
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"data")
+        );
     }
 
     #[test]
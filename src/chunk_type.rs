@@ -1,9 +1,12 @@
+//! the 4 letter chunk types and what their letters say about a chunk
+
 use std::{
     convert::TryFrom,
     fmt::{Debug, Display},
     str::FromStr,
 };
-#[derive(Clone)]
+/// the 4 letter type of a chunk, each letter's case is one of its properties
+#[derive(Clone, PartialEq, Eq)]
 pub struct ChunkType {
     ancilliary: u8,
     private: u8,
@@ -11,7 +14,15 @@ pub struct ChunkType {
     safe_to_copy: u8,
 }
 
+/// chunk types defined by the png spec and its registered extensions
+pub const REGISTERED_TYPES: [&str; 32] = [
+    "IHDR", "PLTE", "IDAT", "IEND", "tRNS", "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "cICP", "mDCV",
+    "cLLI", "tEXt", "zTXt", "iTXt", "bKGD", "hIST", "pHYs", "sPLT", "eXIf", "tIME", "acTL", "fcTL",
+    "fdAT", "oFFs", "pCAL", "sCAL", "gIFg", "gIFx", "sTER", "dSIG",
+];
+
 impl ChunkType {
+    /// the type of these 4 bytes as they are, `try_from` checks them
     pub fn new(ancilliary: u8, private: u8, reserved: u8, safe_to_copy: u8) -> Self {
         ChunkType {
             ancilliary,
@@ -20,6 +31,7 @@ impl ChunkType {
             safe_to_copy,
         }
     }
+    /// the 4 bytes of the type, as they are in the file
     pub fn bytes(&self) -> [u8; 4] {
         [
             self.ancilliary,
@@ -28,35 +40,34 @@ impl ChunkType {
             self.safe_to_copy,
         ]
     }
+    /// whether the image needs the chunk, an uppercase first letter
     pub fn is_critical(&self) -> bool {
         self.ancilliary.is_ascii_uppercase()
     }
+    /// whether the type is in the spec or registered, an uppercase second letter
     pub fn is_public(&self) -> bool {
         self.private.is_ascii_uppercase()
     }
+    /// whether the third letter is uppercase, as the spec asks for now
     pub fn is_reserved_bit_valid(&self) -> bool {
         self.reserved.is_ascii_uppercase()
     }
+    /// letters only with a valid reserved bit
     pub fn is_valid(&self) -> bool {
-        if ![
-            self.ancilliary,
-            self.private,
-            self.reserved,
-            self.safe_to_copy,
-        ]
-        .iter()
-        .all(|i| i.is_ascii_alphabetic())
-        {
-            false
-        } else if self.reserved.is_ascii_lowercase() {
-            false
-        } else {
-            true
-        }
+        self.bytes().iter().all(|i| i.is_ascii_alphabetic()) && self.is_reserved_bit_valid()
     }
+    /// whether editors may keep the chunk after changing critical ones, a lowercase last letter
     pub fn is_safe_to_copy(&self) -> bool {
         self.safe_to_copy.is_ascii_lowercase()
     }
+    /// whether the type is one of `REGISTERED_TYPES`
+    pub fn is_registered(&self) -> bool {
+        REGISTERED_TYPES.contains(&self.to_string().as_str())
+    }
+    /// whether the letters are the same, whatever the property bits their case holds
+    pub fn eq_ignore_case(&self, other: &ChunkType) -> bool {
+        self.bytes().eq_ignore_ascii_case(&other.bytes())
+    }
 }
 impl Debug for ChunkType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -81,26 +92,30 @@ impl Display for ChunkType {
     }
 }
 
-impl PartialEq for ChunkType {
-    fn eq(&self, other: &Self) -> bool {
-        if self.bytes().len() == other.bytes().len() {
-            let lenght = self.bytes().len();
-            for i in 0..lenght {
-                if !self.bytes()[i] == other.bytes()[i] {
-                    return false;
-                }
-            }
-        }
-        true
-    }
+/// why bytes or a string are not a chunk type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ChunkTypeError {
+    /// the string is this many bytes long, not 4
+    #[error("String should be exactly 4 bytes long, not {0}")]
+    Length(usize),
+    /// a byte isn't ascii
+    #[error("String should contain ascii chars only")]
+    NotAscii,
+    /// a byte isn't a letter
+    #[error("String should contain letters only")]
+    NotLetters,
+    /// the third letter is lowercase, which the png spec reserves
+    #[error("Byte 3 must be uppercase")]
+    ReservedBit,
 }
+
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = &'static str;
+    type Error = ChunkTypeError;
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
         if !value.iter().all(|i| i.is_ascii_alphabetic()) {
-            Err("All bytes of chunk should be a valid ascii")
+            Err(ChunkTypeError::NotLetters)
         } else if value[2].is_ascii_lowercase() {
-            Err("Byte 3 must be lowercase")
+            Err(ChunkTypeError::ReservedBit)
         } else {
             Ok(ChunkType {
                 ancilliary: value[0],
@@ -112,17 +127,17 @@ impl TryFrom<[u8; 4]> for ChunkType {
     }
 }
 impl FromStr for ChunkType {
-    type Err = &'static str;
+    type Err = ChunkTypeError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() != 4 {
-            Err("String should be exactly 4 lenght")
+            Err(ChunkTypeError::Length(s.len()))
         } else if !s.is_ascii() {
-            Err("String should contain ascii chars only")
+            Err(ChunkTypeError::NotAscii)
         } else {
             let bytes = s.as_bytes();
             for b in bytes {
                 if !b.is_ascii_alphabetic() {
-                    return Err("String should contain letters only");
+                    return Err(ChunkTypeError::NotLetters);
                 }
             }
             Ok(ChunkType {
@@ -154,6 +169,29 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    pub fn test_chunk_type_errors() {
+        assert_eq!(ChunkType::from_str("RuS"), Err(ChunkTypeError::Length(3)));
+        assert_eq!(
+            ChunkType::from_str("Ru\u{e9}"),
+            Err(ChunkTypeError::NotAscii)
+        );
+        assert_eq!(ChunkType::from_str("Ru1t"), Err(ChunkTypeError::NotLetters));
+        assert_eq!(
+            ChunkType::try_from(*b"Rust"),
+            Err(ChunkTypeError::ReservedBit)
+        );
+    }
+
+    #[test]
+    pub fn test_chunk_type_not_equal() {
+        let chunk_type_1 = ChunkType::from_str("RuSt").unwrap();
+        let chunk_type_2 = ChunkType::from_str("ruSt").unwrap();
+        assert_ne!(chunk_type_1, chunk_type_2);
+        assert!(chunk_type_1.eq_ignore_case(&chunk_type_2));
+        assert!(!chunk_type_1.eq_ignore_case(&ChunkType::from_str("RuSk").unwrap()));
+    }
+
     #[test]
     pub fn test_chunk_type_is_critical() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -217,6 +255,14 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_chunk_type_is_registered() {
+        assert!(ChunkType::from_str("tEXt").unwrap().is_registered());
+        assert!(ChunkType::from_str("IDAT").unwrap().is_registered());
+        assert!(!ChunkType::from_str("ruSt").unwrap().is_registered());
+        assert!(!ChunkType::from_str("TEXT").unwrap().is_registered());
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -0,0 +1,107 @@
+use std::{
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use clap::ValueEnum;
+
+use crate::chunk_type::ChunkType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+impl ColorChoice {
+    pub fn resolve(self, terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => terminal && !no_color,
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// decides once for the whole run whether stdout gets colors
+pub fn init(choice: ColorChoice) {
+    // https://no-color.org, an empty value doesn't count
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let enabled = choice.resolve(std::io::stdout().is_terminal(), no_color);
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Red,
+    Yellow,
+    Green,
+    Dim,
+}
+impl Style {
+    /// critical chunks are red, private ancillary ones yellow and registered ancillary ones green
+    pub fn for_chunk_type(chunk_type: &ChunkType) -> Option<Style> {
+        if chunk_type.is_critical() {
+            Some(Style::Red)
+        } else if !chunk_type.is_public() {
+            Some(Style::Yellow)
+        } else if chunk_type.is_registered() {
+            Some(Style::Green)
+        } else {
+            None
+        }
+    }
+    fn code(self) -> &'static str {
+        match self {
+            Style::Red => "31",
+            Style::Yellow => "33",
+            Style::Green => "32",
+            Style::Dim => "2",
+        }
+    }
+}
+
+/// wraps `text` in the escape codes for `style`, or leaves it alone without color
+pub fn paint(text: &str, style: Option<Style>, color: bool) -> String {
+    match style {
+        Some(style) if color => format!("\x1b[{}m{}\x1b[0m", style.code(), text),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_resolve() {
+        assert!(ColorChoice::Always.resolve(false, true));
+        assert!(!ColorChoice::Never.resolve(true, false));
+        assert!(ColorChoice::Auto.resolve(true, false));
+        assert!(!ColorChoice::Auto.resolve(true, true));
+        assert!(!ColorChoice::Auto.resolve(false, false));
+    }
+
+    #[test]
+    fn test_chunk_type_style() {
+        let style = |chunk_type| Style::for_chunk_type(&ChunkType::from_str(chunk_type).unwrap());
+        assert_eq!(style("IHDR"), Some(Style::Red));
+        assert_eq!(style("ruSt"), Some(Style::Yellow));
+        assert_eq!(style("tEXt"), Some(Style::Green));
+        assert_eq!(style("zZZz"), None);
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint("IHDR", Some(Style::Red), true), "\x1b[31mIHDR\x1b[0m");
+        assert_eq!(paint("IHDR", Some(Style::Red), false), "IHDR");
+        assert_eq!(paint("zZZz", None, true), "zZZz");
+    }
+}
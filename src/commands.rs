@@ -1,6 +1,7393 @@
-use crate::args::{DecodeArgs, EncodeArgs, PrintArgs, RemoveArgs};
+use std::{
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-pub fn encode(args: &EncodeArgs) {}
-pub fn remove(args: &RemoveArgs) {}
-pub fn print(args: &PrintArgs) {}
-pub fn decode(args: &DecodeArgs) {}
+use clap::{CommandFactory, ValueEnum};
+
+use crate::{
+    args::{
+        CapacityArgs, Channels, Cli, Commands, CopyChunkArgs, DecodeArgs, DiffArgs, EncodeArgs,
+        ExifArgs, ExtractChunksArgs, HasArgs, InfoArgs, InjectArgs, KeyType, KeygenArgs, ListArgs,
+        ManArgs, MetadataArgs, Method, Pad, Position, PrintArgs, RemoveArgs, RepairArgs, ScanArgs,
+        SortKey, StripArgs, TouchArgs, VerifyArgs,
+    },
+    base64,
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    color::{self, paint, Style},
+    completions,
+    crypto::{self, Argon2Params, Identity, Kdf, Secret, SigningKey},
+    deflate,
+    diff::{self, ChunkDiff},
+    encoding::Encoding,
+    error::PngSecretError,
+    exif::{self, Exif},
+    hex, http,
+    index::{ChunkInfo, PngIndex},
+    json::Value,
+    log::{self, Level},
+    lsb, metadata, mmap,
+    output::{self, report},
+    parallel, password, payload,
+    png::{Limits, Png, PngStats, ValidationIssue},
+    progress::{Bar, ProgressReader, ProgressWriter},
+    render, scan, stealth, stream, text,
+    time::{self, Time},
+    Result,
+};
+
+/// println for messages about what was done, left out with --quiet and
+/// sent to stderr when `output` is stdout and the line would end up in the file
+macro_rules! status {
+    ($output:expr, $($arg:tt)*) => {
+        if log::enabled(Level::Info) && !output::json() {
+            if is_stdio($output) {
+                eprintln!($($arg)*)
+            } else {
+                println!($($arg)*)
+            }
+        }
+    };
+}
+/// print for the human output of a command, which --format json replaces with the report
+macro_rules! out {
+    ($($arg:tt)*) => {
+        if !output::json() {
+            print!($($arg)*)
+        }
+    };
+}
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if !output::json() {
+            println!($($arg)*)
+        }
+    };
+}
+/// the chunk data read_png takes from a file at most, DEFAULT_LIMITS without --max-memory
+/// and u64::MAX for --max-memory unlimited
+static MAX_MEMORY: AtomicU64 = AtomicU64::new(DEFAULT_LIMITS);
+const DEFAULT_LIMITS: u64 = 0;
+
+/// --max-memory, for pngs from untrusted sources
+pub fn init(max_memory: Option<u64>) {
+    MAX_MEMORY.store(max_memory.unwrap_or(DEFAULT_LIMITS), Ordering::Relaxed);
+    crypto::limit_memory(max_memory.unwrap_or(u64::MAX));
+}
+fn limits() -> Limits {
+    match MAX_MEMORY.load(Ordering::Relaxed) {
+        DEFAULT_LIMITS => Limits::default(),
+        u64::MAX => Limits::NONE,
+        bytes => Limits::memory(usize::try_from(bytes).unwrap_or(usize::MAX)),
+    }
+}
+/// how much of a response read_png takes from a url, --max-memory when it is given
+fn max_response() -> u64 {
+    match MAX_MEMORY.load(Ordering::Relaxed) {
+        DEFAULT_LIMITS => http::MAX_SIZE,
+        bytes => bytes,
+    }
+}
+/// runs the command and returns its report, which main prints with --format json
+pub fn run(commands: &Commands) -> Result<Value> {
+    let _span = command_span(commands).entered();
+    if let Some(path) = changed_file(commands).filter(|path| http::is_url(path)) {
+        return Err(PngSecretError::Usage(format!(
+            "{} is a URL, which is only read, download it to change it",
+            path.display()
+        )));
+    }
+    match commands {
+        Commands::Encode(args) => encode(args),
+        Commands::Decode(args) => decode(args),
+        Commands::Remove(args) => remove(args),
+        Commands::Print(args) => print(args),
+        Commands::List(args) => list(args),
+        Commands::Scan(args) => scan(args),
+        Commands::Verify(args) => verify(args),
+        Commands::Repair(args) => repair(args),
+        Commands::Strip(args) => strip(args),
+        Commands::ExtractChunks(args) => extract_chunks(args),
+        Commands::Inject(args) => inject(args),
+        Commands::CopyChunk(args) => copy_chunk(args),
+        Commands::Diff(args) => diff(args),
+        Commands::Capacity(args) => capacity(args),
+        Commands::Keygen(args) => keygen(args),
+        Commands::Touch(args) => touch(args),
+        Commands::Exif(args) => exif(args),
+        Commands::Metadata(args) => metadata(args),
+        Commands::Info(args) => info(args),
+        Commands::Has(args) => has(args),
+        Commands::Man(args) => man(args),
+        Commands::Completions(args) => {
+            let script = completions::generate(args.shell, Cli::command());
+            out!("{}", script);
+            let shell = args.shell.to_possible_value().unwrap();
+            Ok(report(
+                "completions",
+                [
+                    ("shell", shell.get_name().into()),
+                    ("script", script.into()),
+                ],
+            ))
+        }
+    }
+}
+/// a span for the whole run of a command, with the file it changes or reads
+fn command_span(commands: &Commands) -> tracing::Span {
+    let (name, path) = match commands {
+        Commands::Encode(args) => ("encode", Some(&args.file_path)),
+        Commands::Decode(args) => ("decode", Some(&args.file_path)),
+        Commands::Remove(args) => ("remove", Some(&args.file_path)),
+        Commands::Print(args) => ("print", Some(&args.file_path)),
+        Commands::List(args) => ("list", Some(&args.file_path)),
+        Commands::Scan(args) => ("scan", Some(&args.path)),
+        Commands::Verify(args) => ("verify", Some(&args.file_path)),
+        Commands::Repair(args) => ("repair", Some(&args.file_path)),
+        Commands::Strip(args) => ("strip", Some(&args.file_path)),
+        Commands::ExtractChunks(args) => ("extract-chunks", Some(&args.file_path)),
+        Commands::Inject(args) => ("inject", Some(&args.file_path)),
+        Commands::CopyChunk(args) => ("copy-chunk", Some(&args.destination)),
+        Commands::Diff(args) => ("diff", Some(&args.new_file)),
+        Commands::Capacity(args) => ("capacity", Some(&args.file_path)),
+        Commands::Keygen(args) => ("keygen", args.output.as_ref()),
+        Commands::Touch(args) => ("touch", Some(&args.file_path)),
+        Commands::Exif(args) => ("exif", Some(&args.file_path)),
+        Commands::Metadata(args) => ("metadata", Some(&args.file_path)),
+        Commands::Info(args) => ("info", Some(&args.file_path)),
+        Commands::Has(args) => ("has", Some(&args.file_path)),
+        Commands::Man(args) => ("man", args.out_dir.as_ref()),
+        Commands::Completions(_) => ("completions", None),
+    };
+    let path = path.map(|path| path.display().to_string());
+    tracing::info_span!("command", name, path)
+}
+
+/// the file a command writes back unless it is given another output
+fn changed_file(commands: &Commands) -> Option<&PathBuf> {
+    match commands {
+        Commands::Encode(args) => Some(&args.file_path),
+        Commands::Remove(args) => Some(&args.file_path),
+        Commands::Repair(args) => Some(&args.file_path),
+        Commands::Strip(args) => Some(&args.file_path),
+        Commands::Inject(args) => Some(&args.file_path),
+        Commands::CopyChunk(args) => Some(&args.destination),
+        Commands::Touch(args) => Some(&args.file_path),
+        Commands::Exif(args) if args.remove_exif => Some(&args.file_path),
+        _ => None,
+    }
+}
+
+/// the man pages come from the same definitions as --help, exit codes included
+pub fn man(args: &ManArgs) -> Result<Value> {
+    let mut command = Cli::command().disable_help_subcommand(true);
+    // building gives every subcommand the global options, like their --help shows them
+    command.build();
+    let Some(dir) = &args.out_dir else {
+        let mut page = Vec::new();
+        clap_mangen::Man::new(command).render(&mut page)?;
+        let page = String::from_utf8(page).map_err(|e| PngSecretError::Failed(e.to_string()))?;
+        out!("{}", page);
+        return Ok(report("man", [("page", page.into())]));
+    };
+    fs::create_dir_all(dir)?;
+    let mut pages = Vec::new();
+    write_man_pages(&command, dir, &mut pages)?;
+    status!(dir, "wrote {} man pages to {}", pages.len(), dir.display());
+    Ok(report(
+        "man",
+        [("out_dir", path_json(dir)), ("pages", pages.into())],
+    ))
+}
+/// a page for `command` and each of its visible subcommands, named like pngsecret-encode.1
+fn write_man_pages(command: &clap::Command, dir: &Path, pages: &mut Vec<Value>) -> Result<()> {
+    let page = clap_mangen::Man::new(command.clone()).generate_to(dir)?;
+    pages.push(path_json(&page));
+    for subcommand in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        write_man_pages(subcommand, dir, pages)?;
+    }
+    Ok(())
+}
+
+pub fn keygen(args: &KeygenArgs) -> Result<Value> {
+    if args.key_type == KeyType::Ed25519 {
+        return keygen_ed25519(args);
+    }
+    let identity = Identity::generate()?;
+    let recipient = identity.recipient();
+    let file = format!("# public key: {}\n{}\n", recipient, identity);
+    write_key(args, &file, &recipient.to_string())?;
+    // printed, the report is the only place the identity ends up in
+    let printed = match &args.output {
+        None => identity.to_string().into(),
+        Some(_) => Value::Null,
+    };
+    Ok(report(
+        "keygen",
+        [
+            ("public_key", recipient.to_string().into()),
+            (
+                "output",
+                args.output.as_deref().map_or(Value::Null, path_json),
+            ),
+            ("identity", printed),
+        ],
+    ))
+}
+fn keygen_ed25519(args: &KeygenArgs) -> Result<Value> {
+    let key = SigningKey::generate()?;
+    let public_key = crypto::hex(&key.public_key());
+    let keypair = key.to_json();
+    write_key(args, &format!("{}\n", keypair), &public_key)?;
+    let printed = match &args.output {
+        None => keypair,
+        Some(_) => Value::Null,
+    };
+    Ok(report(
+        "keygen",
+        [
+            ("public_key", public_key.into()),
+            (
+                "output",
+                args.output.as_deref().map_or(Value::Null, path_json),
+            ),
+            ("keypair", printed),
+        ],
+    ))
+}
+/// writes the secret key `file` to --output, readable by its owner only, or prints it
+fn write_key(args: &KeygenArgs, file: &str, public_key: &str) -> Result<()> {
+    match &args.output {
+        Some(output) => {
+            check_overwrite(output, args.force)?;
+            write_output(output, file.as_bytes())?;
+            #[cfg(unix)]
+            if !is_stdio(output) {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(output, fs::Permissions::from_mode(0o600))?;
+            }
+            status!(output, "Public key: {}", public_key);
+        }
+        None => out!("{}", file),
+    }
+    Ok(())
+}
+
+/// every file with a png extension below `dir`, sorted by path
+fn png_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// the chunk at `index` with where it ended up
+fn inserted_json(png: &Png, index: usize) -> Value {
+    chunk_json(
+        &png.chunks()[index],
+        [
+            ("index", index.into()),
+            ("offset", png.chunk_offset(index).into()),
+        ],
+    )
+}
+fn insertion_index(png: &Png, position: Position) -> Result<usize> {
+    let index = match position {
+        // files without IEND get the chunk at the end
+        Position::BeforeIend => png.iend_index().unwrap_or(png.chunks().len()),
+        Position::AfterIhdr => {
+            png.ihdr_index()
+                .ok_or_else(|| PngSecretError::InvalidData("No IHDR chunk found".into()))?
+                + 1
+        }
+        Position::BeforeIdat => png
+            .first_idat_index()
+            .ok_or_else(|| PngSecretError::InvalidData("No IDAT chunk found".into()))?,
+        Position::Index(index) => index,
+    };
+    // browsers drop an APNG frame with a chunk between its fcTL and its data
+    match png.is_animated().then(|| png.frame_at(index)).flatten() {
+        Some(frame) => {
+            log::warning!(
+                "index {} is inside the animation frame at #{}, inserting before it",
+                index,
+                frame.start()
+            );
+            Ok(*frame.start())
+        }
+        None => Ok(index),
+    }
+}
+
+/// a path of `-` stands for stdin or stdout
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+/// the file at `path`, stdin for - or the response for a url, with a progress bar for
+/// big files
+fn open_input(path: &Path) -> std::result::Result<Box<dyn Read>, PngSecretError> {
+    if is_stdio(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    if http::is_url(path) {
+        let url = path.to_str().unwrap();
+        return http::open(url, max_response()).map_err(|source| PngSecretError::io(path, source));
+    }
+    let failed = |source| PngSecretError::io(path, source);
+    let file = fs::File::open(path).map_err(failed)?;
+    let bar = Bar::bytes(
+        file.metadata().map_err(failed)?.len(),
+        &path.display().to_string(),
+    );
+    Ok(Box::new(ProgressReader::new(file, bar)))
+}
+fn read_input(path: &Path) -> std::result::Result<Vec<u8>, PngSecretError> {
+    let mut bytes = Vec::new();
+    open_input(path)?
+        .read_to_end(&mut bytes)
+        .map_err(|source| match is_stdio(path) {
+            true => source.into(),
+            false => PngSecretError::io(path, source),
+        })?;
+    log::debug!(path = %path.display(), length = bytes.len(), "read file");
+    Ok(bytes)
+}
+fn write_output(path: &Path, bytes: &[u8]) -> Result<()> {
+    if http::is_url(path) {
+        return Err(PngSecretError::Usage(format!(
+            "Can't write to {}, a URL is only read",
+            path.display()
+        )));
+    }
+    if is_stdio(path) && output::json() {
+        return Err(PngSecretError::Usage(
+            "Can't write to stdout with --format json, it holds the report".into(),
+        ));
+    }
+    if is_stdio(path) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()?;
+    } else {
+        let bar = Bar::bytes(bytes.len() as u64, &path.display().to_string());
+        write_atomically(path, |file| ProgressWriter::new(file, bar).write_all(bytes))?;
+    }
+    log::debug!(path = %path.display(), length = bytes.len(), "wrote file");
+    Ok(())
+}
+fn read_png(path: &Path) -> std::result::Result<Png, PngSecretError> {
+    let limits = limits();
+    // with a size limit the file is parsed as it is read, so a huge one stops at the limit
+    let png = match limits.max_total == usize::MAX {
+        true => Png::from_reader_with_limits(&read_input(path)?[..], limits),
+        false => Png::from_reader_with_limits(open_input(path)?, limits),
+    };
+    png.map_err(|error| error.in_file(path))
+}
+/// like `read_png`, but with `ignore_crc` a chunk whose crc doesn't match is kept, with a
+/// warning. only for commands that don't write the file back
+fn read_png_lenient(path: &Path, ignore_crc: bool) -> std::result::Result<Png, PngSecretError> {
+    if !ignore_crc {
+        return read_png(path);
+    }
+    let limits = limits();
+    let png = match limits.max_total == usize::MAX {
+        true => Png::from_reader_lenient(&read_input(path)?[..], limits),
+        false => Png::from_reader_lenient(open_input(path)?, limits),
+    }
+    .map_err(|error| error.in_file(path))?;
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        if let Some(mismatch) = crc_mismatch(chunk) {
+            log::warning!("chunk #{} ({}) {}", index, chunk.chunk_type(), mismatch);
+        }
+    }
+    Ok(png)
+}
+/// every chunk `Png::parse_salvage` keeps of a damaged file, with a warning for what it
+/// drops. like `read_png_lenient`, only for commands that don't write the file back
+fn read_png_salvage(path: &Path) -> std::result::Result<Png, PngSecretError> {
+    let bytes = read_input(path)?;
+    let (png, issues) = Png::parse_salvage(&bytes).map_err(|error| error.in_file(path))?;
+    for issue in &issues {
+        log::warning!("{}: {}", path.display(), issue);
+    }
+    Ok(png)
+}
+/// how the crc of a kept chunk is off, none when it matches
+fn crc_mismatch(chunk: &Chunk) -> Option<String> {
+    (!chunk.is_crc_valid()).then(|| {
+        format!(
+            "crc MISMATCH: stored 0x{:08x}, computed 0x{:08x}",
+            chunk.crc(),
+            chunk.computed_crc()
+        )
+    })
+}
+fn write_png(path: &Path, png: &Png) -> Result<()> {
+    write_output(path, &png.as_bytes())
+}
+/// writes to a temp file next to `path` and renames it over `path`, so a failed
+/// write never leaves a half written file behind. an existing file keeps its permissions
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| PngSecretError::Usage("Output path is not a file".into()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    // taken before the rename replaces the original
+    let original = fs::metadata(path).ok();
+    let written = fs::File::create(&temp)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|()| match &original {
+            Some(original) => copy_permissions(original, &temp),
+            None => Ok(()),
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(written?)
+}
+fn copy_permissions(original: &fs::Metadata, path: &Path) -> io::Result<()> {
+    fs::set_permissions(path, original.permissions())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // only root can hand a file to someone else, everyone else keeps their own
+        let _ = std::os::unix::fs::chown(path, Some(original.uid()), Some(original.gid()));
+    }
+    Ok(())
+}
+/// gives `path` the modification time `from` had before it was rewritten
+fn restore_modified(path: &Path, modified: Option<std::time::SystemTime>) -> Result<()> {
+    if let Some(modified) = modified {
+        fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(modified)?;
+    }
+    Ok(())
+}
+/// the modification time to restore with --preserve-times
+fn modified_time(path: &Path, preserve: bool) -> Result<Option<std::time::SystemTime>> {
+    if !preserve || is_stdio(path) {
+        return Ok(None);
+    }
+    Ok(Some(fs::metadata(path)?.modified()?))
+}
+/// explicit output paths are only overwritten with --force
+fn check_overwrite(path: &Path, force: bool) -> Result<()> {
+    if !force && !is_stdio(path) && path.exists() {
+        return Err(PngSecretError::Refused {
+            reason: format!(
+                "{} already exists, use --force to overwrite",
+                path.display()
+            ),
+        });
+    }
+    Ok(())
+}
+/// the input as --verify-render compares against it, none without the flag
+fn render_original(verify: bool, input: &Path, output: &Path) -> Result<Option<Vec<u8>>> {
+    if !verify {
+        return Ok(None);
+    }
+    if !render::ENABLED {
+        return Err(PngSecretError::Usage(render::UNAVAILABLE.into()));
+    }
+    if is_stdio(input) || is_stdio(output) {
+        return Err(PngSecretError::Usage(
+            "--verify-render reads the written file back, it can't use stdin or stdout".into(),
+        ));
+    }
+    Ok(Some(
+        fs::read(input).map_err(|source| PngSecretError::io(input, source))?,
+    ))
+}
+/// decodes what was written to `output` and compares its pixels to those of
+/// `original`. when they differ the write is undone: an input changed in place gets
+/// `original` back, any other output is removed
+fn verify_render(original: &[u8], input: &Path, output: &Path) -> Result<Value> {
+    let rendered = fs::read(output)
+        .map_err(|source| PngSecretError::io(output, source))
+        .and_then(|written| render::verify(original, &written));
+    let rendered = match rendered {
+        Ok(rendered) => rendered,
+        Err(error) => {
+            match input == output {
+                true => write_output(output, original)?,
+                false => fs::remove_file(output)?,
+            }
+            log::warning!(path = %output.display(), "undid the write, {}", error);
+            return Err(error);
+        }
+    };
+    status!(
+        output,
+        "render verified: {}x{}, {} pixels identical",
+        rendered.width,
+        rendered.height,
+        rendered.pixels()
+    );
+    Ok(Value::object([
+        ("width", rendered.width.into()),
+        ("height", rendered.height.into()),
+        ("pixels", rendered.pixels().into()),
+    ]))
+}
+/// copies `path` to `path` + `suffix` before it gets changed in place
+fn backup(path: &Path, suffix: &str) -> Result<()> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(suffix);
+    fs::copy(path, &backup)?;
+    Ok(())
+}
+fn path_json(path: &Path) -> Value {
+    path.display().to_string().into()
+}
+/// how a chunk shows up in a report, after `fields`
+fn chunk_json<const N: usize>(chunk: &Chunk, fields: [(&str, Value); N]) -> Value {
+    let mut value = Value::object(fields);
+    if let Value::Object(fields) = &mut value {
+        fields.extend([
+            ("type".to_string(), chunk.chunk_type().to_string().into()),
+            ("length".to_string(), chunk.length().into()),
+            ("crc".to_string(), format!("{:08x}", chunk.crc()).into()),
+        ]);
+    }
+    value
+}
+
+pub fn encode(args: &EncodeArgs) -> Result<Value> {
+    let chunk_type = args.chunk_type.clone();
+    if !chunk_type.is_valid() {
+        return Err(PngSecretError::Usage("Not a valid chunk type".into()));
+    }
+    guard_critical(&chunk_type, "add", args.allow_critical, args.yes)?;
+    if args.seed.is_some() && args.noise == 0 && !args.deterministic {
+        return Err(PngSecretError::Usage(
+            "--seed is for --noise and --deterministic".into(),
+        ));
+    }
+    // for as long as the encode takes, whichever way it goes
+    let _deterministic = match (&args.seed, args.deterministic) {
+        (Some(seed), true) => Some(crypto::deterministic(seed.as_bytes())),
+        _ => None,
+    };
+    if args.method == Method::Lsb {
+        return encode_lsb(args);
+    }
+    if args.channels != Channels::Rgb {
+        return Err(PngSecretError::Usage(
+            "--channels is for --method lsb".into(),
+        ));
+    }
+    if args.stealth {
+        return encode_stealth(args);
+    }
+    let chunks = match text_chunk(args)? {
+        Some(chunk) => vec![chunk],
+        None => payload_chunks(args)?,
+    };
+    if args.file_path.is_dir() {
+        return encode_dir(args, &chunks);
+    }
+    if args.glob.is_some() || args.out_dir.is_some() {
+        return Err(PngSecretError::Usage(
+            "--glob and --out-dir need a directory".into(),
+        ));
+    }
+    let output = args.output().unwrap_or(&args.file_path);
+    let encoded = encode_file(&args.file_path, output, &chunks, args)?;
+    Ok(report(
+        "encode",
+        [("dry_run", args.dry_run.into()), ("file", encoded)],
+    ))
+}
+/// a tEXt or zTXt chunk with the message under the keyword given, none without one
+fn text_chunk(args: &EncodeArgs) -> Result<Option<Chunk>> {
+    let keywords = [
+        ("--text-keyword", text::TEXT, &args.text_keyword),
+        ("--ztxt-keyword", text::ZTXT, &args.ztxt_keyword),
+        ("--itxt-keyword", text::ITXT, &args.itxt_keyword),
+    ];
+    let Some((flag, chunk_type, Some(keyword))) = keywords
+        .into_iter()
+        .find(|(_, _, keyword)| keyword.is_some())
+    else {
+        return Ok(None);
+    };
+    if args.chunk_type.to_string() != chunk_type {
+        return Err(PngSecretError::Usage(format!(
+            "{} writes a {} chunk, give {} as the chunk type",
+            flag, chunk_type, chunk_type
+        )));
+    }
+    let message = message(args)?;
+    let data = match chunk_type {
+        text::TEXT => text::text_data(keyword, &message)?,
+        text::ZTXT => text::ztxt_data(keyword, &message)?,
+        _ => text::itxt_data(
+            keyword,
+            &args.language,
+            &args.translated_keyword,
+            &message,
+            args.compress,
+        )?,
+    };
+    Ok(Some(Chunk::new(args.chunk_type.clone(), &data)))
+}
+/// the chunks that hold the message with its header, as many as it needs
+fn payload_chunks(args: &EncodeArgs) -> Result<Vec<Chunk>> {
+    let payload = packed_payload(args)?;
+    let chunks = payload::split(&payload, args.max_chunk_size, crypto::random_bytes()?)?
+        .iter()
+        .map(|part| Chunk::new(args.chunk_type.clone(), part))
+        .collect();
+    Ok(chunks)
+}
+/// the message with its header, before it is split into chunks
+fn packed_payload(args: &EncodeArgs) -> Result<Vec<u8>> {
+    let chunk_type = &args.chunk_type;
+    let encrypted = args.encrypt
+        || args.password.is_some()
+        || args.key_file.is_some()
+        || !args.recipient.is_empty();
+    if args.pad != Pad::None && !encrypted && !args.stealth {
+        return Err(PngSecretError::Usage(
+            "--pad hides the length under encryption, give --password, --encrypt, --key-file or --recipient"
+                .into(),
+        ));
+    }
+    let (message, metadata) = match &args.input_file {
+        Some(path) => {
+            let message = fs::read(path).map_err(|e| {
+                PngSecretError::Failed(format!("Could not read {}: {}", path.display(), e))
+            })?;
+            let metadata = match args.no_metadata {
+                true => None,
+                false => Some(payload::Metadata::of(path)?),
+            };
+            (message, metadata)
+        }
+        None => match &args.message_hex {
+            Some(hex) => (hex.0.clone(), None),
+            None => (message_bytes(args, message(args)?)?, None),
+        },
+    };
+    // two messages under one --deterministic seed get different nonces
+    crypto::mix(&message);
+    // of the message as it was given, so it holds with or without --compress
+    let checksum = args.checksum.then(|| payload::checksum(&message));
+    let message = compressed(args, message);
+    // under the encryption and the signature, so the padding looks like the message
+    let message = match args.pad {
+        Pad::None => message,
+        pad => payload::pad(&message, padding(pad, message.len())?)?,
+    };
+    let signature = match &args.sign {
+        Some(keypair) => {
+            let key = SigningKey::read(keypair)?;
+            Some(payload::Signature {
+                public_key: key.public_key(),
+                signature: key.sign(&message),
+            })
+        }
+        None => None,
+    };
+    let payload = if let Some(decoy) = &args.decoy_message {
+        let decoy = compressed(args, message_bytes(args, decoy.clone())?);
+        crypto::mix(&decoy);
+        let password = password::resolve(args.password.as_deref(), true)?;
+        let decoy_password = args.decoy_password.as_deref().unwrap_or_default();
+        let params = args.kdf_params.unwrap_or(Argon2Params::DEFAULT);
+        crypto::encrypt_with_password(&message, &password, Some((&decoy, decoy_password)), params)?
+    } else if args.sign_hmac {
+        let secret = match &args.key_file {
+            Some(key_file) => Secret::Key(crypto::read_key_file(key_file)?),
+            None => Secret::Password(password::resolve(args.password.as_deref(), true)?),
+        };
+        let params = args.kdf_params.unwrap_or(Argon2Params::DEFAULT);
+        crypto::sign(chunk_type.bytes(), &message, &secret, params)?
+    } else if args.stealth {
+        // every part is encrypted on its own instead
+        message
+    } else if let Some(key_file) = &args.key_file {
+        crypto::encrypt_with_key(&message, &crypto::read_key_file(key_file)?)?
+    } else if !args.recipient.is_empty() {
+        crypto::encrypt_to(&message, &args.recipient)?
+    } else if args.encrypt || args.password.is_some() {
+        let password = password::resolve(args.password.as_deref(), true)?;
+        let params = args.kdf_params.unwrap_or(Argon2Params::DEFAULT);
+        crypto::encrypt_with_password(&message, &password, None, params)?
+    } else {
+        message
+    };
+    let header = payload::Header {
+        compressed: args.compress.is_some(),
+        encrypted: !args.sign_hmac && !args.stealth && encrypted,
+        signed: args.sign_hmac,
+        padded: args.pad != Pad::None,
+        checksum,
+        metadata,
+        signature,
+        ..payload::Header::new()
+    };
+    if args.max_chunk_size == 0
+        || args.max_chunk_size > Chunk::MAX_LENGTH - payload::PART_HEADER_SIZE
+    {
+        return Err(PngSecretError::Usage(format!(
+            "--max-chunk-size must be between 1 and {} bytes",
+            Chunk::MAX_LENGTH - payload::PART_HEADER_SIZE
+        )));
+    }
+    // a plain message stays raw, so other tools can read it
+    Ok(match header == payload::Header::new() {
+        true => payload,
+        false => payload::pack_for(header, &payload, args.max_chunk_size),
+    })
+}
+/// how many bytes --pad adds to a message of `length` bytes, behind its length
+fn padding(pad: Pad, length: usize) -> Result<usize> {
+    Ok(match pad {
+        Pad::None => 0,
+        Pad::Block(size) => (4 + length).next_multiple_of(size) - 4 - length,
+        Pad::Random(max) => {
+            let random = u64::from_be_bytes(crypto::random_bytes()?);
+            (random % (max as u64 + 1)) as usize
+        }
+    })
+}
+/// encode --method lsb, the payload goes into the pixels of one file and no chunk is added
+fn encode_lsb(args: &EncodeArgs) -> Result<Value> {
+    if !lsb::ENABLED {
+        return Err(PngSecretError::Usage(lsb::UNAVAILABLE.into()));
+    }
+    if args.text_keyword.is_some() || args.ztxt_keyword.is_some() || args.itxt_keyword.is_some() {
+        return Err(PngSecretError::Usage(
+            "Text chunks can't be hidden in pixels, leave out --method lsb".into(),
+        ));
+    }
+    if args.stream
+        || args.position != Position::BeforeIend
+        || args.replace
+        || args.replace_or_append
+    {
+        return Err(PngSecretError::Usage(
+            "--stream, --position and --replace place a chunk, --method lsb adds none".into(),
+        ));
+    }
+    if args.verify_render {
+        return Err(PngSecretError::Usage(
+            "--verify-render wants the same pixels, --method lsb changes them".into(),
+        ));
+    }
+    if args.file_path.is_dir() || args.glob.is_some() || args.out_dir.is_some() {
+        return Err(PngSecretError::Usage(
+            "--method lsb encodes one file at a time".into(),
+        ));
+    }
+    let input = &args.file_path;
+    let output = args.output().unwrap_or(input);
+    if input != output {
+        check_overwrite(output, args.force)?;
+    }
+    let png = read_png(input)?;
+    if png.is_animated() {
+        return Err(PngSecretError::Usage(
+            "--method lsb only writes the default image of an APNG, not its frames".into(),
+        ));
+    }
+    let ihdr = png.ihdr()?;
+    if ihdr.bit_depth == 16 {
+        log::warning!(
+            "{} has 16 bit samples, the pixels are written back with 8",
+            input.display()
+        );
+    }
+    let payload = packed_payload(args)?;
+    let alpha = args.channels == Channels::Rgba;
+    let capacity = lsb::capacity(ihdr.width as usize * ihdr.height as usize, alpha, lsb::BITS);
+    let encoded = Value::object([
+        ("path", path_json(input)),
+        ("output", path_json(output)),
+        ("method", "lsb".into()),
+        ("length", payload.len().into()),
+        ("capacity", capacity.into()),
+    ]);
+    let report = |encoded| {
+        report(
+            "encode",
+            [("dry_run", args.dry_run.into()), ("file", encoded)],
+        )
+    };
+    if payload.len() > capacity {
+        return Err(PngSecretError::LsbTooSmall {
+            capacity,
+            needed: payload.len(),
+        });
+    }
+    if args.dry_run {
+        outln!(
+            "would hide {} bytes in the pixels of {}, which hold {}",
+            payload.len(),
+            output.display(),
+            capacity
+        );
+        return Ok(report(encoded));
+    }
+    let png = lsb::hide(&png, &payload, alpha)?;
+    if let Some(suffix) = &args.backup {
+        if input == output && !is_stdio(input) {
+            backup(input, suffix)?;
+        }
+    }
+    let modified = modified_time(input, args.preserve_times)?;
+    write_png(output, &png)?;
+    if !is_stdio(output) {
+        restore_modified(output, modified)?;
+    }
+    status!(
+        output,
+        "hid {} bytes in the pixels of {}, which hold {}",
+        payload.len(),
+        output.display(),
+        capacity
+    );
+    Ok(report(encoded))
+}
+/// encode --stealth, the payload in parts encrypted one by one, in chunks of
+/// stealth::TYPES at random places of one file
+fn encode_stealth(args: &EncodeArgs) -> Result<Value> {
+    if args.file_path.is_dir() || args.glob.is_some() || args.out_dir.is_some() {
+        return Err(PngSecretError::Usage(
+            "--stealth encodes one file at a time".into(),
+        ));
+    }
+    let input = &args.file_path;
+    let output = args.output().unwrap_or(input);
+    if input != output {
+        check_overwrite(output, args.force)?;
+    }
+    let original = render_original(args.verify_render && !args.dry_run, input, output)?;
+    let mut png = read_png(input)?;
+    let seal = stealth_seal(args)?;
+    let payload = packed_payload(args)?;
+    let free = stealth::free_types(&png).len();
+    // the noise needs types of its own
+    let count = stealth::part_count(payload.len(), free.saturating_sub(args.noise))?;
+    let sealed = stealth::parts(&payload, count)?
+        .iter()
+        .map(|part| seal(part))
+        .collect::<Result<Vec<_>>>()?;
+    let mut random = stealth::Random::new(args.seed.as_deref())?;
+    let mut indexes = Vec::new();
+    stealth::spread(&mut png, sealed, &mut random, &mut indexes)?;
+    // a part to recipients starts with how many there are, the rest of it is random
+    let header = match args.recipient.len() {
+        0 => Vec::new(),
+        count => vec![count as u8],
+    };
+    let noise = stealth::noise(args.noise, &mut random, &header);
+    stealth::spread(&mut png, noise, &mut random, &mut indexes)?;
+    indexes.sort_unstable();
+    let spread = match args.noise {
+        0 => format!("{} chunks", count),
+        noise => format!("{} chunks and {} of noise", count, noise),
+    };
+    let types: Vec<String> = indexes
+        .iter()
+        .map(|&index| png.chunks()[index].chunk_type().to_string())
+        .collect();
+    let mut encoded = Value::object([
+        ("path", path_json(input)),
+        ("output", path_json(output)),
+        ("stealth", true.into()),
+        ("length", payload.len().into()),
+        (
+            "chunks",
+            indexes
+                .iter()
+                .map(|&index| inserted_json(&png, index))
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+    ]);
+    let report = |encoded| {
+        report(
+            "encode",
+            [("dry_run", args.dry_run.into()), ("file", encoded)],
+        )
+    };
+    if args.dry_run {
+        outln!(
+            "would spread {} bytes over {} ({}) in {}",
+            payload.len(),
+            spread,
+            types.join(", "),
+            output.display()
+        );
+        return Ok(report(encoded));
+    }
+    if let Some(suffix) = &args.backup {
+        if input == output && !is_stdio(input) {
+            backup(input, suffix)?;
+        }
+    }
+    let modified = modified_time(input, args.preserve_times)?;
+    write_png(output, &png)?;
+    if let Some(original) = original {
+        let rendered = verify_render(&original, input, output)?;
+        if let Value::Object(fields) = &mut encoded {
+            fields.push(("render".to_string(), rendered));
+        }
+    }
+    if !is_stdio(output) {
+        restore_modified(output, modified)?;
+    }
+    status!(
+        output,
+        "spread {} bytes over {} ({}) in {}",
+        payload.len(),
+        spread,
+        types.join(", "),
+        output.display()
+    );
+    Ok(report(encoded))
+}
+/// encrypts one part of encode --stealth
+type Seal = Box<dyn Fn(&[u8]) -> Result<Vec<u8>>>;
+/// how each part of encode --stealth is encrypted: with the key file, to the
+/// recipients, or with the password given or asked for
+fn stealth_seal(args: &EncodeArgs) -> Result<Seal> {
+    // without the header, which would be the same in every part
+    let strip = |sealed: Result<Vec<u8>>| Ok(crypto::strip_header(&sealed?).to_vec());
+    if let Some(key_file) = &args.key_file {
+        let key = crypto::read_key_file(key_file)?;
+        return Ok(Box::new(move |part| {
+            strip(crypto::encrypt_with_key(part, &key))
+        }));
+    }
+    if !args.recipient.is_empty() {
+        let recipients = args.recipient.clone();
+        return Ok(Box::new(move |part| {
+            strip(crypto::encrypt_to(part, &recipients))
+        }));
+    }
+    let password = password::resolve(args.password.as_deref(), true)?;
+    let kdf = Kdf::Argon2id(args.kdf_params.unwrap_or(Argon2Params::DEFAULT));
+    Ok(Box::new(move |part| {
+        strip(crypto::encrypt_with(part, &password, kdf))
+    }))
+}
+/// critical chunks need --allow-critical, and on a terminal a yes as well unless --yes is given
+fn guard_critical(
+    chunk_type: &ChunkType,
+    verb: &'static str,
+    allow: bool,
+    yes: bool,
+) -> Result<()> {
+    if !chunk_type.is_critical() {
+        return Ok(());
+    }
+    if !allow {
+        return Err(PngSecretError::CriticalChunk {
+            chunk_type: chunk_type.to_string(),
+            verb,
+        });
+    }
+    if yes || !io::stdin().is_terminal() {
+        return Ok(());
+    }
+    eprint!(
+        "really {} critical chunk {} (this will corrupt the image)? [y/N] ",
+        verb, chunk_type
+    );
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(PngSecretError::Refused {
+            reason: "Aborted, nothing changed".to_string(),
+        }),
+    }
+}
+/// the bytes `message` stands for with --base64
+fn message_bytes(args: &EncodeArgs, message: String) -> Result<Vec<u8>> {
+    // before anything is written
+    Ok(match args.base64 {
+        true => base64::decode(&message)
+            .map_err(|e| PngSecretError::Usage(format!("Invalid base64 message: {}", e)))?,
+        false => message.into_bytes(),
+    })
+}
+/// `message` deflated with --compress
+fn compressed(args: &EncodeArgs, message: Vec<u8>) -> Vec<u8> {
+    match args.compress {
+        Some(level) => deflate::compress(&message, level),
+        None => message,
+    }
+}
+/// the message argument, the contents of --message-file, or what the user types in
+fn message(args: &EncodeArgs) -> Result<String> {
+    let mut message = match (&args.message, &args.message_file) {
+        (Some(message), None) => message.clone(),
+        (None, Some(path)) => fs::read_to_string(path).map_err(|e| {
+            PngSecretError::Failed(format!("Could not read {}: {}", path.display(), e))
+        })?,
+        (Some(_), Some(_)) => {
+            return Err(PngSecretError::Usage(
+                "Give either a message or --message-file, not both".into(),
+            ))
+        }
+        (None, None) if args.edit => {
+            edit_message(&std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string()))?
+        }
+        // stdin can't hold both the file and the message
+        (None, None) if io::stdin().is_terminal() && !is_stdio(&args.file_path) => {
+            eprintln!("Enter message (end with Ctrl-D):");
+            let mut message = String::new();
+            io::stdin().lock().read_to_string(&mut message)?;
+            message
+        }
+        (None, None) => {
+            return Err(PngSecretError::Usage(
+                "No message given, pass one or use --message-file".into(),
+            ))
+        }
+    };
+    if args.trim {
+        // only the one newline editors add at the end
+        if let Some(trimmed) = message.strip_suffix('\n') {
+            message = trimmed.strip_suffix('\r').unwrap_or(trimmed).to_string();
+        }
+    }
+    Ok(message)
+}
+/// opens `editor` on an empty temp file and returns what was saved in it. the file
+/// is only readable by its owner, in a directory of its own that is removed again
+/// however this returns
+fn edit_message(editor: &str) -> Result<String> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("pngsecret-");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        builder.permissions(fs::Permissions::from_mode(0o700));
+        options.mode(0o600);
+    }
+    let dir = builder.tempdir()?;
+    let path = dir.path().join("message.txt");
+    options.open(&path)?;
+    // $EDITOR may carry arguments, like "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| PngSecretError::Failed("$EDITOR is empty".into()))?;
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status();
+    let message = fs::read_to_string(&path);
+    let status =
+        status.map_err(|e| PngSecretError::Failed(format!("Could not run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(PngSecretError::Failed(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+    let message = message?;
+    if message.is_empty() {
+        return Err(PngSecretError::Usage(
+            "Empty message, nothing encoded".into(),
+        ));
+    }
+    Ok(message)
+}
+/// files from this size on are streamed by encode even without --stream
+const STREAM_THRESHOLD: u64 = 256 * 1024 * 1024;
+/// encodes one file and returns what was added where, the parts of a split
+/// payload go next to each other
+fn encode_file(input: &Path, output: &Path, chunks: &[Chunk], args: &EncodeArgs) -> Result<Value> {
+    if input != output {
+        check_overwrite(output, args.force)?;
+    }
+    let big = || !is_stdio(input) && fs::metadata(input).is_ok_and(|m| m.len() >= STREAM_THRESHOLD);
+    if args.stream && args.position != Position::BeforeIend {
+        return Err(PngSecretError::Usage(
+            "--stream only inserts before the IEND, leave out --position".into(),
+        ));
+    }
+    // --verify-render needs the original once the output replaced it
+    let whole = args.verify_render || args.noise > 0 || args.replace || args.replace_or_append;
+    if args.stream || (args.position == Position::BeforeIend && !whole && big()) {
+        return encode_streamed(input, output, chunks, args);
+    }
+    let original = render_original(args.verify_render && !args.dry_run, input, output)?;
+    let mut png = read_png(input)?;
+    let replaced = replace_chunks(&mut png, input, chunks, args)?;
+    let index = match replaced {
+        Some(index) => index,
+        None => {
+            let index = insertion_index(&png, args.position)?;
+            for (offset, chunk) in chunks.iter().enumerate() {
+                png.insert_chunk_at(index + offset, chunk.clone())?;
+            }
+            index
+        }
+    };
+    let mut added: Vec<usize> = (index..index + chunks.len()).collect();
+    if args.noise > 0 {
+        let mut random = stealth::Random::new(args.seed.as_deref())?;
+        let noise = stealth::noise(args.noise, &mut random, &[]);
+        stealth::spread(&mut png, noise, &mut random, &mut added)?;
+        added.sort_unstable();
+    }
+    // with noise the chunks are listed together, in file order
+    let chunk = match args.noise {
+        0 => ("chunk", inserted_json(&png, index)),
+        _ => (
+            "chunks",
+            added
+                .iter()
+                .map(|&index| inserted_json(&png, index))
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+    };
+    let mut encoded = Value::object([
+        ("path", path_json(input)),
+        ("output", path_json(output)),
+        chunk,
+        ("parts", chunks.len().into()),
+        ("replaced", replaced.is_some().into()),
+    ]);
+    if args.dry_run {
+        match args.noise {
+            0 => print_would_add(chunks, output),
+            noise => outln!(
+                "would add {} chunks, {} of them noise, to {}: {:+} bytes",
+                added.len(),
+                noise,
+                output.display(),
+                added
+                    .iter()
+                    .map(|&index| png.chunks()[index].as_bytes().len())
+                    .sum::<usize>()
+            ),
+        }
+        return Ok(encoded);
+    }
+    if let Some(suffix) = &args.backup {
+        if input == output && !is_stdio(input) {
+            backup(input, suffix)?;
+        }
+    }
+    let modified = modified_time(input, args.preserve_times)?;
+    write_png(output, &png)?;
+    if let Some(original) = original {
+        let rendered = verify_render(&original, input, output)?;
+        if let Value::Object(fields) = &mut encoded {
+            fields.push(("render".to_string(), rendered));
+        }
+    }
+    if !is_stdio(output) {
+        restore_modified(output, modified)?;
+    }
+    Ok(encoded)
+}
+/// with --replace, puts `chunks` where the chunks like them are: the first one swapped for
+/// the first of them, the others dropped. returns where they went, none when encode adds
+/// them as usual. text chunks are only like them with the same keyword
+fn replace_chunks(
+    png: &mut Png,
+    input: &Path,
+    chunks: &[Chunk],
+    args: &EncodeArgs,
+) -> Result<Option<usize>> {
+    let chunk_type = chunks[0].chunk_type().to_string();
+    let keyword = text::is_text(&chunk_type).then(|| text::keyword_of(chunks[0].data()));
+    // the index of each in the file, and among the chunks of its type
+    let existing: Vec<(usize, usize)> = png
+        .chunks_by_type(&chunk_type)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (_, chunk))| {
+            let keyword = keyword.as_ref();
+            keyword.is_none_or(|keyword| text::keyword_of(chunk.data()) == *keyword)
+        })
+        .map(|(nth, (index, _))| (index, nth))
+        .collect();
+    let Some(&(first, _)) = existing.first() else {
+        if args.replace {
+            return Err(PngSecretError::ChunkNotFound {
+                chunk_type,
+                keyword: keyword.flatten(),
+                matching: None,
+                path: Some(input.to_path_buf()),
+            });
+        }
+        return Ok(None);
+    };
+    if !args.replace && !args.replace_or_append {
+        log::warning!(
+            "{} has a {} chunk already, decode reads the first one, --replace updates it",
+            input.display(),
+            chunk_type
+        );
+        return Ok(None);
+    }
+    for &(_, nth) in existing[1..].iter().rev() {
+        png.remove_nth_chunk_by_type(&chunk_type, nth);
+    }
+    png.replace_chunk(first, chunks[0].clone())?;
+    for (offset, chunk) in chunks[1..].iter().enumerate() {
+        png.insert_chunk_at(first + 1 + offset, chunk.clone())?;
+    }
+    Ok(Some(first))
+}
+fn print_would_add(chunks: &[Chunk], output: &Path) {
+    let length: usize = chunks.iter().map(|chunk| chunk.data().len()).sum();
+    let growth: usize = chunks.iter().map(|chunk| chunk.as_bytes().len()).sum();
+    let parts = match chunks.len() {
+        1 => String::new(),
+        parts => format!(" in {} chunks", parts),
+    };
+    outln!(
+        "would add {} ({} bytes{}) to {}: {:+} bytes",
+        chunks[0].chunk_type(),
+        length,
+        parts,
+        output.display(),
+        growth
+    );
+}
+/// like encode_file, copying the input to the output chunk by chunk before its IEND
+fn encode_streamed(
+    input: &Path,
+    output: &Path,
+    chunks: &[Chunk],
+    args: &EncodeArgs,
+) -> Result<Value> {
+    log::debug!("streaming {} to {}", input.display(), output.display());
+    let reader = || -> std::result::Result<Box<dyn Read>, PngSecretError> {
+        if is_stdio(input) {
+            return Ok(Box::new(io::stdin().lock()));
+        }
+        let file = fs::File::open(input).map_err(|source| PngSecretError::io(input, source))?;
+        let length = file.metadata().map_or(0, |metadata| metadata.len());
+        let bar = Bar::bytes(length, &input.display().to_string());
+        Ok(Box::new(ProgressReader::new(file, bar)))
+    };
+    let stream = |writer: &mut dyn Write| {
+        stream::insert_before_iend(reader()?, writer, chunks).map_err(|error| error.in_file(input))
+    };
+    let encoded = |inserted: stream::Inserted| {
+        Value::object([
+            ("path", path_json(input)),
+            ("output", path_json(output)),
+            (
+                "chunk",
+                chunk_json(
+                    &chunks[0],
+                    [
+                        ("index", inserted.index.into()),
+                        ("offset", inserted.offset.into()),
+                    ],
+                ),
+            ),
+            ("parts", chunks.len().into()),
+        ])
+    };
+    if args.dry_run {
+        let inserted = stream(&mut io::sink())?;
+        print_would_add(chunks, output);
+        return Ok(encoded(inserted));
+    }
+    if let Some(suffix) = &args.backup {
+        if input == output && !is_stdio(input) {
+            backup(input, suffix)?;
+        }
+    }
+    let modified = modified_time(input, args.preserve_times)?;
+    let inserted = if is_stdio(output) {
+        if output::json() {
+            return Err(PngSecretError::Usage(
+                "Can't write to stdout with --format json, it holds the report".into(),
+            ));
+        }
+        stream(&mut io::stdout().lock())?
+    } else {
+        // the temp file is only renamed over the output once the whole input made it through
+        let (mut inserted, mut failed) = (None, None);
+        let written = write_atomically(output, |file| match stream(file) {
+            Ok(done) => {
+                inserted = Some(done);
+                Ok(())
+            }
+            Err(error) => {
+                let message = error.to_string();
+                failed = Some(error);
+                Err(io::Error::other(message))
+            }
+        });
+        if let Some(error) = failed {
+            return Err(error);
+        }
+        written?;
+        restore_modified(output, modified)?;
+        inserted.unwrap()
+    };
+    Ok(encoded(inserted))
+}
+/// encodes every file directly in the directory, going on after errors
+fn encode_dir(args: &EncodeArgs, chunks: &[Chunk]) -> Result<Value> {
+    let mut files = fs::read_dir(&args.file_path)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<PathBuf>>>()?;
+    files.sort();
+    if let Some(out_dir) = args.out_dir.as_ref().filter(|_| !args.dry_run) {
+        fs::create_dir_all(out_dir)?;
+    }
+    let (mut succeeded, mut failed, mut skipped) = (0_usize, 0_usize, 0_usize);
+    let mut reports = Vec::new();
+    let files: Vec<&PathBuf> = files.iter().filter(|file| file.is_file()).collect();
+    let bar = Bar::files(files.len() as u64);
+    for file in files {
+        bar.advance(1);
+        let name = file.file_name().unwrap_or_default();
+        bar.set_label(&name.to_string_lossy());
+        if let Some(pattern) = &args.glob {
+            if !glob_match(pattern, &name.to_string_lossy()) {
+                continue;
+            }
+        }
+        if !file
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        {
+            log::warning!("skipping {}, not a png file", file.display());
+            reports.push(Value::object([
+                ("path", path_json(file)),
+                ("skipped", true.into()),
+            ]));
+            skipped += 1;
+            continue;
+        }
+        let output = match &args.out_dir {
+            Some(out_dir) => out_dir.join(name),
+            None => file.clone(),
+        };
+        match encode_file(file, &output, chunks, args) {
+            Ok(encoded) => {
+                reports.push(encoded);
+                succeeded += 1
+            }
+            Err(e) => {
+                if !output::json() {
+                    eprintln!("error: {}: {}", file.display(), e);
+                }
+                reports.push(Value::object([
+                    ("path", path_json(file)),
+                    ("error", e.to_string().into()),
+                ]));
+                failed += 1;
+            }
+        }
+    }
+    status!(
+        &args.file_path,
+        "{} {} file(s), {} failed, {} skipped",
+        if args.dry_run {
+            "would encode"
+        } else {
+            "encoded"
+        },
+        succeeded,
+        failed,
+        skipped
+    );
+    let report = report(
+        "encode",
+        [
+            ("dry_run", args.dry_run.into()),
+            ("directory", path_json(&args.file_path)),
+            ("files", reports.into()),
+            ("encoded", succeeded.into()),
+            ("failed", failed.into()),
+            ("skipped", skipped.into()),
+        ],
+    );
+    if failed > 0 {
+        // the report still tells which files made it
+        output::emit(&report);
+        return Err(PngSecretError::Failed(format!(
+            "{} file(s) could not be encoded",
+            failed
+        )));
+    }
+    Ok(report)
+}
+/// shell style matching where * is any run of characters and ? is one character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // where to resume after the last *, as (pattern index, name index)
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+pub fn remove(args: &RemoveArgs) -> Result<Value> {
+    // with --ignore-case the case in the file says whether a chunk is critical
+    if !args.ignore_case {
+        guard_critical(&args.chunk_type, "remove", args.allow_critical, args.yes)?;
+    }
+    let original = render_original(
+        args.verify_render && !args.dry_run,
+        &args.file_path,
+        &args.file_path,
+    )?;
+    let mut png = read_png(&args.file_path)?;
+    let chunk_type = args.chunk_type.to_string();
+    let candidates: Vec<usize> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| same_type(chunk.chunk_type(), &args.chunk_type, args.ignore_case))
+        .map(|(index, _)| index)
+        .collect();
+    let not_found = |matching: Option<String>| PngSecretError::ChunkNotFound {
+        chunk_type: chunk_type.clone(),
+        keyword: None,
+        matching,
+        path: Some(args.file_path.clone()),
+    };
+    if candidates.is_empty() && args.nth.is_none() {
+        return Err(not_found(None));
+    }
+    let indexes = if let Some(regex) = &args.matching {
+        let chunks = png.chunks();
+        let matched: Vec<usize> = candidates
+            .into_iter()
+            .filter(|&index| regex.is_match(&String::from_utf8_lossy(chunks[index].data())))
+            .collect();
+        if matched.is_empty() {
+            return Err(not_found(Some(regex.to_string())));
+        }
+        matched
+    } else if args.all {
+        candidates
+    } else if let Some(nth) = args.nth {
+        let index = candidates
+            .get(nth)
+            .ok_or_else(|| PngSecretError::NthOutOfRange {
+                chunk_type: chunk_type.clone(),
+                nth,
+                count: candidates.len(),
+            })?;
+        vec![*index]
+    } else {
+        vec![candidates[0]]
+    };
+    let critical = indexes
+        .iter()
+        .map(|&index| png.chunks()[index].chunk_type())
+        .find(|chunk_type| chunk_type.is_critical());
+    if let Some(chunk_type) = critical.filter(|_| args.ignore_case) {
+        guard_critical(chunk_type, "remove", args.allow_critical, args.yes)?;
+    }
+    // from the back, so the indexes before stay where they are
+    let mut removed = indexes
+        .iter()
+        .rev()
+        .map(|&index| png.remove_chunk_at(index))
+        .collect::<std::result::Result<Vec<Chunk>, PngSecretError>>()?;
+    removed.reverse();
+    if args.print_removed {
+        for chunk in &removed {
+            let text = removed_text(chunk, args.limit, color::enabled());
+            // keeps a png written to stdout clean
+            match is_stdio(&args.file_path) {
+                true => eprint!("{}", text),
+                false => out!("{}", text),
+            }
+        }
+    }
+    let saved: usize = removed.iter().map(|chunk| chunk.as_bytes().len()).sum();
+    let mut report = report(
+        "remove",
+        [
+            ("dry_run", args.dry_run.into()),
+            ("file", path_json(&args.file_path)),
+            (
+                "removed",
+                removed
+                    .iter()
+                    .map(|chunk| chunk_json(chunk, []))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ("saved", saved.into()),
+        ],
+    );
+    if let (Some(regex), Value::Object(fields)) = (&args.matching, &mut report) {
+        fields.push(("matching".to_string(), regex.as_str().into()));
+        fields.push(("matched".to_string(), removed.len().into()));
+    }
+    if args.dry_run {
+        if let Some(regex) = &args.matching {
+            outln!(
+                "{} chunk(s) of type {} match {}",
+                removed.len(),
+                removed[0].chunk_type(),
+                regex
+            );
+        }
+        for chunk in &removed {
+            outln!(
+                "would remove {} ({} bytes)",
+                chunk.chunk_type(),
+                chunk.length()
+            );
+        }
+        outln!("{}: -{} bytes", args.file_path.display(), saved);
+        return Ok(report);
+    }
+    if let Some(dir) = &args.save_removed {
+        save_removed(dir, &indexes, &removed, args.force)?;
+    }
+    if let Some(suffix) = &args.backup {
+        if !is_stdio(&args.file_path) {
+            backup(&args.file_path, suffix)?;
+        }
+    }
+    let modified = modified_time(&args.file_path, args.preserve_times)?;
+    write_png(&args.file_path, &png)?;
+    if let Some(original) = original {
+        let rendered = verify_render(&original, &args.file_path, &args.file_path)?;
+        if let Value::Object(fields) = &mut report {
+            fields.push(("render".to_string(), rendered));
+        }
+    }
+    restore_modified(&args.file_path, modified)?;
+    match &args.matching {
+        Some(regex) => status!(
+            &args.file_path,
+            "removed {} chunk(s) of type {} matching {}",
+            removed.len(),
+            removed[0].chunk_type(),
+            regex
+        ),
+        None => status!(
+            &args.file_path,
+            "removed {} chunk(s) of type {}",
+            removed.len(),
+            removed[0].chunk_type()
+        ),
+    }
+    Ok(report)
+}
+/// like print, with a hex dump for data that isn't UTF-8
+fn removed_text(chunk: &Chunk, limit: Option<usize>, color: bool) -> String {
+    let hex = std::str::from_utf8(chunk.data()).is_err();
+    chunk_text(chunk, limit, hex, None, color)
+}
+/// written before the file is changed, so the data is safe if that fails. named like
+/// extract-chunks names them, by the index each chunk had in the file
+fn save_removed(dir: &Path, indexes: &[usize], removed: &[Chunk], force: bool) -> Result<()> {
+    let files: Vec<(PathBuf, &Chunk)> = indexes
+        .iter()
+        .zip(removed)
+        .map(|(index, chunk)| {
+            let name = format!("{:03}_{}.bin", index, chunk.chunk_type());
+            (dir.join(name), chunk)
+        })
+        .collect();
+    for (path, _) in &files {
+        check_overwrite(path, force)?;
+    }
+    fs::create_dir_all(dir)?;
+    for (path, chunk) in files {
+        fs::write(&path, chunk.data())?;
+        log::debug!("saved removed chunk to {}", path.display());
+    }
+    Ok(())
+}
+pub fn print(args: &PrintArgs) -> Result<Value> {
+    // the index doesn't read the data, so it can't tell a bad crc, and stops at damage
+    let whole = args.ignore_crc || args.salvage;
+    let seekable = !is_stdio(&args.file_path) && !http::is_url(&args.file_path);
+    if args.json && args.no_data && !whole && seekable {
+        return print_index(args);
+    }
+    let png = match args.salvage {
+        true => read_png_salvage(&args.file_path)?,
+        false => read_png_lenient(&args.file_path, args.ignore_crc)?,
+    };
+    let chunks = filter_chunks(&png, args);
+    if chunks.is_empty() && !png.chunks().is_empty() {
+        return Err(PngSecretError::InvalidData(
+            "No chunk matched the type filter".into(),
+        ));
+    }
+    let json = chunks_json(&png, &chunks, !args.no_data);
+    let report = report(
+        "print",
+        [
+            ("file", path_json(&args.file_path)),
+            ("chunks", json.clone()),
+        ],
+    );
+    if args.json {
+        outln!("{}", json);
+        return Ok(report);
+    }
+    let limit = args
+        .limit
+        .or_else(|| io::stdout().is_terminal().then_some(DEFAULT_PRINT_LIMIT));
+    for (_, chunk) in chunks {
+        let mut text = chunk_text(chunk, limit, args.hex, args.encoding, color::enabled());
+        if args.ancillary_only {
+            text.push_str(&visibility_line(chunk, color::enabled()));
+        }
+        outln!("{}", text);
+    }
+    Ok(report)
+}
+/// print --json --no-data, which needs no chunk data past the times of tIME chunks
+fn print_index(args: &PrintArgs) -> Result<Value> {
+    let (rows, _) = read_index(&args.file_path)?;
+    let chunks: Vec<&Indexed> = rows
+        .iter()
+        .filter(|row| shown_by(args, &row.info.chunk_type))
+        .collect();
+    if chunks.is_empty() && !rows.is_empty() {
+        return Err(PngSecretError::InvalidData(
+            "No chunk matched the type filter".into(),
+        ));
+    }
+    let json: Value = chunks
+        .iter()
+        .map(|row| info_json(&row.info, row.time()))
+        .collect::<Vec<_>>()
+        .into();
+    outln!("{}", json);
+    Ok(report(
+        "print",
+        [("file", path_json(&args.file_path)), ("chunks", json)],
+    ))
+}
+/// private chunks are where hidden data usually lives, so they get the private style
+fn visibility_line(chunk: &Chunk, color: bool) -> String {
+    let visibility = match chunk.chunk_type().is_public() {
+        true => paint("public", None, color),
+        false => paint("private", Some(Style::Yellow), color),
+    };
+    format!("Visibility:  {}\n", visibility)
+}
+/// how much data `print` shows per chunk on a terminal
+const DEFAULT_PRINT_LIMIT: usize = 512;
+/// one chunk as `print` shows it, with at most `limit` bytes of its data
+fn chunk_text(
+    chunk: &Chunk,
+    limit: Option<usize>,
+    hex: bool,
+    encoding: Option<Encoding>,
+    color: bool,
+) -> String {
+    let shown = limit.map_or(chunk.data().len(), |limit| limit.min(chunk.data().len()));
+    let hidden = chunk.data().len() - shown;
+    let marker = match hidden {
+        0 => String::new(),
+        hidden => format!("… ({} more bytes)\n", hidden),
+    };
+    let chunk_type = chunk.chunk_type();
+    let mut text = format!(
+        "Chunk:       {}\nData length: {}\nCrc:         {}\n",
+        paint(
+            &chunk_type.to_string(),
+            Style::for_chunk_type(chunk_type),
+            color
+        ),
+        chunk.length(),
+        paint(&format!("{:08x}", chunk.crc()), Some(Style::Dim), color)
+    );
+    if let Some(mismatch) = crc_mismatch(chunk) {
+        text.insert_str(
+            text.len() - 1,
+            &format!("  {}", paint(&mismatch, Some(Style::Red), color)),
+        );
+    }
+    if hex {
+        text.push_str(&chunk.hex_dump(Some(shown)));
+    } else if chunk_type.to_string() == time::TIME {
+        match Time::parse(chunk.data()) {
+            Ok(time) => text.push_str(&format!("Time:        {}\n", time)),
+            Err(e) => text.push_str(&format!("Error:       {}\n", e)),
+        }
+        return text;
+    } else if chunk_type.to_string() == exif::EXIF {
+        match Exif::parse(chunk.data()) {
+            Ok(exif) => text.push_str(&format!(
+                "Exif:        {}, {} entries in IFD0\n",
+                exif.byte_order(),
+                exif.entries
+            )),
+            Err(e) => text.push_str(&format!("Error:       {}\n", e)),
+        }
+        return text;
+    } else if let Some(mut parsed) = text_of(chunk) {
+        if let Some(encoding) = encoding {
+            parsed.text = encoding.text(&text::text_bytes(&chunk_type.to_string(), &parsed.text));
+        }
+        // the limit counts characters of the text here, not bytes of the chunk
+        let characters = parsed.text.chars().count();
+        let shown = limit.map_or(characters, |limit| limit.min(characters));
+        let hidden = characters - shown;
+        text.push_str(&format!("Keyword:     {}\n", parsed.keyword));
+        if let (Some(language), Some(translated)) = (&parsed.language, &parsed.translated_keyword) {
+            text.push_str(&format!(
+                "Language:    {}\nTranslated:  {}\n",
+                if language.is_empty() {
+                    "none"
+                } else {
+                    language
+                },
+                translated
+            ));
+        }
+        text.push_str(&format!(
+            "Text:        {}\n",
+            parsed.text.chars().take(shown).collect::<String>()
+        ));
+        if hidden > 0 {
+            text.push_str(&format!("… ({} more characters)\n", hidden));
+        }
+        if let Some(compressed_size) = parsed.compressed_size {
+            text.push_str(&format!(
+                "Compressed:  {} bytes, {} uncompressed\n",
+                compressed_size,
+                parsed.text.len()
+            ));
+        }
+        return text;
+    } else {
+        // a text chunk other tools can't read either, shown as it is
+        if let Some(Err(e)) = text::is_text(&chunk_type.to_string())
+            .then(|| text::parse(&chunk_type.to_string(), chunk.data()))
+        {
+            text.push_str(&format!("Error:       {}\n", e));
+        }
+        let data = &chunk.data()[..shown];
+        text.push_str(&format!(
+            "Data:        {}\n",
+            encoding.map_or_else(
+                || String::from_utf8_lossy(data).into_owned(),
+                |encoding| { encoding.text(data) }
+            )
+        ));
+    }
+    text.push_str(&marker);
+    text
+}
+/// the keyword and text of a text chunk, none for other chunks or a malformed one
+fn text_of(chunk: &Chunk) -> Option<text::Text> {
+    let chunk_type = chunk.chunk_type().to_string();
+    match text::is_text(&chunk_type) {
+        true => text::parse(&chunk_type, chunk.data()).ok(),
+        false => None,
+    }
+}
+/// the time in a tIME chunk, an error for other chunks or a malformed one
+fn time_of(chunk: &Chunk) -> Result<Time> {
+    match chunk.chunk_type().to_string() == time::TIME {
+        true => Time::parse(chunk.data()),
+        false => Err(PngSecretError::InvalidData("Not a tIME chunk".into())),
+    }
+}
+/// whether a chunk of `chunk_type` is one of `wanted`, with `ignore_case` in any case
+fn same_type(chunk_type: &ChunkType, wanted: &ChunkType, ignore_case: bool) -> bool {
+    match ignore_case {
+        true => chunk_type.eq_ignore_case(wanted),
+        false => chunk_type == wanted,
+    }
+}
+/// whether the type filters of print let a chunk of `chunk_type` through
+fn shown_by(args: &PrintArgs, chunk_type: &ChunkType) -> bool {
+    let listed = |types: &[ChunkType]| {
+        types
+            .iter()
+            .any(|wanted| same_type(chunk_type, wanted, args.ignore_case))
+    };
+    (args.types.is_empty() || listed(&args.types))
+        && !listed(&args.exclude_types)
+        && (!args.critical_only || chunk_type.is_critical())
+        && (!args.ancillary_only || !chunk_type.is_critical())
+}
+fn filter_chunks<'a>(png: &'a Png, args: &PrintArgs) -> Vec<(usize, &'a Chunk)> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| shown_by(args, chunk.chunk_type()))
+        .collect()
+}
+/// the fields print and list have for every chunk, whether its data was read or not
+fn info_json(info: &ChunkInfo, time: Option<&Time>) -> Value {
+    let chunk_type = &info.chunk_type;
+    let mut value = Value::object([
+        ("index", info.index.into()),
+        ("type", chunk_type.to_string().into()),
+        ("length", info.length.into()),
+        ("crc", format!("{:08x}", info.crc).into()),
+        ("offset", info.offset.into()),
+        ("critical", chunk_type.is_critical().into()),
+        ("public", chunk_type.is_public().into()),
+        ("safe_to_copy", chunk_type.is_safe_to_copy().into()),
+    ]);
+    if let (Some(time), Value::Object(fields)) = (time, &mut value) {
+        fields.push(("time".to_string(), time.to_string().into()));
+    }
+    value
+}
+fn chunks_json(png: &Png, chunks: &[(usize, &Chunk)], with_data: bool) -> Value {
+    let values = chunks
+        .iter()
+        .map(|&(index, chunk)| {
+            let info = ChunkInfo::new(index, png.chunk_offset(index), chunk);
+            let mut value = info_json(&info, time_of(chunk).ok().as_ref());
+            if let (false, Value::Object(fields)) = (chunk.is_crc_valid(), &mut value) {
+                let computed = format!("{:08x}", chunk.computed_crc());
+                fields.push(("computed_crc".to_string(), computed.into()));
+            }
+            if let (true, Value::Object(fields)) = (with_data, &mut value) {
+                fields.push(("data".to_string(), base64::encode(chunk.data()).into()));
+                if let Some(parsed) = text_of(chunk) {
+                    fields.push(("keyword".to_string(), parsed.keyword.into()));
+                    fields.push(("text".to_string(), parsed.text.into()));
+                    if let Some(language) = parsed.language {
+                        fields.push(("language".to_string(), language.into()));
+                    }
+                    if let Some(translated) = parsed.translated_keyword {
+                        fields.push(("translated_keyword".to_string(), translated.into()));
+                    }
+                }
+            }
+            value
+        })
+        .collect::<Vec<_>>();
+    values.into()
+}
+/// a chunk as list shows it, without its data but with the time of a tIME chunk
+struct Indexed {
+    info: ChunkInfo,
+    time: Option<Result<Time>>,
+}
+impl Indexed {
+    fn time(&self) -> Option<&Time> {
+        self.time.as_ref()?.as_ref().ok()
+    }
+}
+/// the chunks of a file found by seeking over their data, so their crcs aren't
+/// checked, and how many bytes trail them after IEND. stdin and urls can't seek, a png
+/// from them is read whole
+fn read_index(path: &Path) -> std::result::Result<(Vec<Indexed>, usize), PngSecretError> {
+    if is_stdio(path) || http::is_url(path) {
+        let png = read_png(path)?;
+        return Ok((indexed(&png), png.trailing_data().len()));
+    }
+    let failed = |source| PngSecretError::io(path, source);
+    let mut file = fs::File::open(path).map_err(failed)?;
+    let chunks = PngIndex::scan(&mut file).map_err(|error| error.in_file(path))?;
+    log::debug!(path = %path.display(), chunks = chunks.len(), "indexed file");
+    let end = chunks
+        .last()
+        .map_or(Png::STANDARD_HEADER.len(), ChunkInfo::end);
+    let trailing = file.metadata().map_err(failed)?.len() as usize - end;
+    let rows = chunks
+        .into_iter()
+        .map(|info| {
+            let time = match info.chunk_type.to_string() == time::TIME {
+                true => Some(Time::parse(
+                    &PngIndex::read_data(&mut file, &info).map_err(failed)?,
+                )),
+                false => None,
+            };
+            Ok(Indexed { info, time })
+        })
+        .collect::<std::result::Result<_, PngSecretError>>()?;
+    Ok((rows, trailing))
+}
+fn indexed(png: &Png) -> Vec<Indexed> {
+    let rows = PngIndex::of(png).into_iter().zip(png.chunks());
+    rows.map(|(info, chunk)| Indexed {
+        time: (info.chunk_type.to_string() == time::TIME).then(|| time_of(chunk)),
+        info,
+    })
+    .collect()
+}
+pub fn list(args: &ListArgs) -> Result<Value> {
+    let (rows, trailing) = match args.salvage {
+        true => {
+            let png = read_png_salvage(&args.file_path)?;
+            (indexed(&png), png.trailing_data().len())
+        }
+        false => read_index(&args.file_path)?,
+    };
+    out!("{}", list_table(&rows, args.sort, color::enabled()));
+    if trailing > 0 {
+        outln!("{} trailing bytes after IEND", trailing);
+    }
+    let chunks: Vec<Value> = rows
+        .iter()
+        .map(|row| info_json(&row.info, row.time()))
+        .collect();
+    Ok(report(
+        "list",
+        [
+            ("file", path_json(&args.file_path)),
+            ("chunks", chunks.into()),
+            ("trailing_bytes", trailing.into()),
+        ],
+    ))
+}
+fn list_table(rows: &[Indexed], sort: SortKey, color: bool) -> String {
+    let mut rows: Vec<&Indexed> = rows.iter().collect();
+    match sort {
+        SortKey::Size => rows.sort_by_key(|row| row.info.length),
+        SortKey::Type => rows.sort_by_key(|row| row.info.chunk_type.to_string()),
+        SortKey::Offset => {}
+    }
+    let mut table = format!(
+        "{:>5}  {:<4}  {:>10}  {:<8}  {:>10}  {}\n",
+        "index", "type", "length", "crc", "offset", "flags"
+    );
+    for row in rows {
+        let (info, chunk_type) = (&row.info, &row.info.chunk_type);
+        // ancillary, private, safe to copy
+        let flags: String = [
+            (!chunk_type.is_critical(), 'A'),
+            (!chunk_type.is_public(), 'P'),
+            (chunk_type.is_safe_to_copy(), 'S'),
+        ]
+        .iter()
+        .map(|&(set, flag)| if set { flag } else { '.' })
+        .collect();
+        // padded before painting, the escape codes have no width
+        table.push_str(&format!(
+            "{:>5}  {}  {:>10}  {}  {:>10}  {}\n",
+            info.index,
+            paint(
+                &format!("{:<4}", chunk_type),
+                Style::for_chunk_type(chunk_type),
+                color
+            ),
+            info.length,
+            paint(&format!("{:08x}", info.crc), Some(Style::Dim), color),
+            info.offset,
+            flags
+        ));
+        if let Some(time) = &row.time {
+            // the row ends in the time, or why the chunk holds none
+            let time = time
+                .as_ref()
+                .map_or_else(|e| e.to_string(), |time| time.to_string());
+            table.insert_str(table.len() - 1, &format!("  {}", time));
+        }
+    }
+    table
+}
+pub fn scan(args: &ScanArgs) -> Result<Value> {
+    let files = if args.path.is_dir() {
+        png_files(&args.path)?
+    } else {
+        vec![args.path.clone()]
+    };
+    let threads = args
+        .threads
+        .map_or_else(parallel::default_threads, usize::from);
+    let bar = Bar::files(files.len() as u64);
+    let scanned = parallel::map(
+        &files,
+        threads,
+        |file| read_png(file).map(|png| scan::scan(&png)),
+        |index| {
+            bar.advance(1);
+            bar.set_label(&files[index].display().to_string());
+        },
+    );
+    drop(bar);
+    // printed once everything is in, so the order is the sorted one of the files
+    let mut failed = 0_usize;
+    let mut reports = Vec::new();
+    for (file, findings) in files.iter().zip(scanned) {
+        let findings = match findings {
+            Ok(findings) => findings,
+            Err(e) => {
+                outln!("{}: error: {}", file.display(), e);
+                reports.push(Value::object([
+                    ("path", path_json(file)),
+                    ("error", e.to_string().into()),
+                ]));
+                failed += 1;
+                continue;
+            }
+        };
+        reports.push(Value::object([
+            ("path", path_json(file)),
+            (
+                "findings",
+                findings
+                    .iter()
+                    .map(|finding| {
+                        Value::object([
+                            ("severity", finding.severity.to_string().into()),
+                            ("index", finding.chunk_index.into()),
+                            ("reason", finding.reason.as_str().into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        ]));
+        if findings.is_empty() {
+            outln!("{}: clean", file.display());
+            continue;
+        }
+        outln!("{}: {} finding(s)", file.display(), findings.len());
+        for finding in &findings {
+            outln!("  {}", finding);
+        }
+        if findings
+            .iter()
+            .any(|finding| finding.severity >= args.fail_on)
+        {
+            failed += 1;
+        }
+    }
+    if files.len() > 1 {
+        outln!("scanned {} file(s), {} flagged", files.len(), failed);
+    }
+    let report = report(
+        "scan",
+        [("files", reports.into()), ("flagged", failed.into())],
+    );
+    if failed > 0 {
+        output::emit(&report);
+        return Err(PngSecretError::Failed(format!(
+            "{} file(s) with findings of {} severity or above, or unreadable",
+            failed, args.fail_on
+        )));
+    }
+    Ok(report)
+}
+pub fn verify(args: &VerifyArgs) -> Result<Value> {
+    let quiet = !log::enabled(Level::Info);
+    let png = read_input(&args.file_path).and_then(|bytes| {
+        Png::try_from_lenient(bytes.as_ref()).map_err(|error| error.in_file(&args.file_path))
+    });
+    let png = match png {
+        Ok(png) => png,
+        Err(_) if quiet => return Err(PngSecretError::Silent { code: 2 }),
+        Err(error) => return Err(error),
+    };
+    let mut issues = png.validate();
+    if args.strict {
+        issues.extend(png.validate_ordering());
+    }
+    let located = |issue: &ValidationIssue| issue.chunk_index().filter(|&i| i < png.chunks().len());
+    // not wrong by the spec, but some browsers drop such a frame
+    let in_frames: Vec<usize> = png
+        .frames()
+        .into_iter()
+        .flat_map(|frame| *frame.start() + 1..*frame.end())
+        .filter(|&index| {
+            // fdAT has the private bit set too, it never got registered
+            let chunk_type = png.chunks()[index].chunk_type();
+            !chunk_type.is_public() && chunk_type.to_string() != "fdAT"
+        })
+        .collect();
+    for &index in &in_frames {
+        log::warning!(
+            "chunk #{} ({}) sits inside an animation frame",
+            index,
+            png.chunks()[index].chunk_type()
+        );
+    }
+    let report = report(
+        "verify",
+        [
+            ("file", path_json(&args.file_path)),
+            ("ok", issues.is_empty().into()),
+            (
+                "issues",
+                issues
+                    .iter()
+                    .map(|issue| {
+                        Value::object([
+                            ("index", located(issue).map_or(Value::Null, Value::from)),
+                            ("message", issue.to_string().into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            (
+                "in_frames",
+                in_frames
+                    .iter()
+                    .map(|&index| Value::from(index))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        ],
+    );
+    let problems = PngSecretError::ProblemsFound {
+        count: issues.len(),
+        crc: issues
+            .iter()
+            .any(|issue| matches!(issue, ValidationIssue::CrcMismatch { .. })),
+    };
+    if quiet {
+        return match issues.is_empty() {
+            true => Ok(report),
+            false => Err(PngSecretError::Silent {
+                code: crate::exit::code(&problems),
+            }),
+        };
+    }
+    if issues.is_empty() {
+        outln!("OK");
+        return Ok(report);
+    }
+    for issue in &issues {
+        match located(issue) {
+            Some(index) => outln!(
+                "chunk #{} ({}) at offset {}: {}",
+                index,
+                png.chunks()[index].chunk_type(),
+                png.chunk_offset(index),
+                issue
+            ),
+            None => outln!("{}", issue),
+        }
+    }
+    output::emit(&report);
+    Err(problems)
+}
+pub fn repair(args: &RepairArgs) -> Result<Value> {
+    if let Some(output) = &args.output {
+        check_overwrite(output, args.force)?;
+    }
+    let bytes = read_input(&args.file_path)?;
+    let mut png =
+        Png::try_from_lenient(bytes.as_ref()).map_err(|error| error.in_file(&args.file_path))?;
+    let output = args.output.as_ref().unwrap_or(&args.file_path);
+    let issues = png.validate();
+    // without a header and image data there is nothing worth saving
+    if let Some(issue) = issues.iter().find(|issue| {
+        matches!(
+            issue,
+            ValidationIssue::IhdrNotFirst { .. }
+                | ValidationIssue::ChunkBeforeIhdr { .. }
+                | ValidationIssue::MissingIdat
+        )
+    }) {
+        return Err(PngSecretError::InvalidData(format!(
+            "Refusing to repair {}: {}",
+            args.file_path.display(),
+            issue
+        )));
+    }
+    let fixed = png.recompute_crcs();
+    for &index in &fixed {
+        let chunk = &png.chunks()[index];
+        status!(
+            output,
+            "chunk #{} ({}) at offset {}: crc set to {:#010x}",
+            index,
+            chunk.chunk_type(),
+            png.chunk_offset(index),
+            chunk.crc()
+        );
+    }
+    let missing_iend = issues.contains(&ValidationIssue::MissingIend);
+    if missing_iend {
+        png.append_chunk(Chunk::new(ChunkType::from_str("IEND")?, &[]));
+        status!(output, "appended missing IEND chunk");
+    }
+    let repaired = png.as_bytes();
+    if repaired == bytes {
+        status!(output, "nothing to repair");
+    }
+    write_output(output, &repaired)?;
+    Ok(report(
+        "repair",
+        [
+            ("file", path_json(&args.file_path)),
+            ("output", path_json(output)),
+            (
+                "fixed_crcs",
+                fixed
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ("appended_iend", missing_iend.into()),
+            ("changed", (repaired != bytes).into()),
+        ],
+    ))
+}
+pub fn strip(args: &StripArgs) -> Result<Value> {
+    if let Some(output) = &args.output {
+        check_overwrite(output, args.force)?;
+    }
+    let output = args.output.as_ref().unwrap_or(&args.file_path);
+    let original = render_original(args.verify_render && !args.dry_run, &args.file_path, output)?;
+    let mut png = read_png(&args.file_path)?;
+    let removed = png.strip_ancillary(&args.keep);
+    let (dropped, summary) = match args.dry_run {
+        true => ("would drop", "would remove"),
+        false => ("dropped", "removed"),
+    };
+    for chunk in &removed {
+        status!(
+            output,
+            "{} {} ({} bytes)",
+            dropped,
+            chunk.chunk_type(),
+            chunk.length()
+        );
+    }
+    let trailing = match args.strip_trailing {
+        true => png.strip_trailing_data().len(),
+        false => 0,
+    };
+    if trailing > 0 {
+        status!(output, "{} {} trailing bytes after IEND", dropped, trailing);
+    }
+    let saved = removed
+        .iter()
+        .map(|chunk| chunk.as_bytes().len())
+        .sum::<usize>()
+        + trailing;
+    status!(
+        output,
+        "{} {} chunk(s), saved {} bytes",
+        summary,
+        removed.len(),
+        saved
+    );
+    let mut report = report(
+        "strip",
+        [
+            ("dry_run", args.dry_run.into()),
+            ("file", path_json(&args.file_path)),
+            ("output", path_json(output)),
+            (
+                "removed",
+                removed
+                    .iter()
+                    .map(|chunk| chunk_json(chunk, []))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ("trailing_bytes", trailing.into()),
+            ("saved", saved.into()),
+        ],
+    );
+    if !args.dry_run {
+        write_png(output, &png)?;
+    }
+    if let Some(original) = original {
+        let rendered = verify_render(&original, &args.file_path, output)?;
+        if let Value::Object(fields) = &mut report {
+            fields.push(("render".to_string(), rendered));
+        }
+    }
+    Ok(report)
+}
+pub fn touch(args: &TouchArgs) -> Result<Value> {
+    if let Some(output) = &args.output {
+        check_overwrite(output, args.force)?;
+    }
+    let mut png = read_png(&args.file_path)?;
+    let time = match args.time {
+        Some(time) => time,
+        None => Time::now()?,
+    };
+    let chunk = Chunk::new(ChunkType::from_str(time::TIME)?, &time.to_data());
+    let output = args.output.as_ref().unwrap_or(&args.file_path);
+    let previous = match png.chunks_by_type(time::TIME).first() {
+        Some(&(index, _)) => {
+            let previous = png.remove_nth_chunk_by_type(time::TIME, 0);
+            png.insert_chunk_at(index, chunk)?;
+            previous
+        }
+        None => {
+            png.append_chunk(chunk);
+            None
+        }
+    };
+    // an out of range time is replaced like any other
+    let previous = previous.map(|chunk| time_of(&chunk).map_err(|e| e.to_string()));
+    match (&previous, args.dry_run) {
+        (Some(Ok(old)), true) => status!(output, "would change tIME from {} to {}", old, time),
+        (Some(Ok(old)), false) => status!(output, "changed tIME from {} to {}", old, time),
+        (Some(Err(e)), _) => {
+            log::warning!("replacing a bad tIME chunk: {}", e);
+            status!(output, "set tIME to {}", time)
+        }
+        (None, true) => status!(output, "would set tIME to {}", time),
+        (None, false) => status!(output, "set tIME to {}", time),
+    }
+    if !args.dry_run {
+        write_png(output, &png)?;
+    }
+    let previous = match previous {
+        Some(Ok(old)) => old.to_string().into(),
+        _ => Value::Null,
+    };
+    Ok(report(
+        "touch",
+        [
+            ("dry_run", args.dry_run.into()),
+            ("file", path_json(&args.file_path)),
+            ("output", path_json(output)),
+            ("time", time.to_string().into()),
+            ("previous", previous),
+        ],
+    ))
+}
+pub fn exif(args: &ExifArgs) -> Result<Value> {
+    let mut png = read_png(&args.file_path)?;
+    let not_found = || PngSecretError::ChunkNotFound {
+        chunk_type: exif::EXIF.to_string(),
+        keyword: None,
+        matching: None,
+        path: Some(args.file_path.clone()),
+    };
+    if args.remove_exif {
+        let removed = png.remove_all_chunks_by_type(exif::EXIF);
+        if removed.is_empty() {
+            return Err(not_found());
+        }
+        let verb = if args.dry_run {
+            "would remove"
+        } else {
+            "removed"
+        };
+        status!(&args.file_path, "{} {} eXIf chunk(s)", verb, removed.len());
+        if !args.dry_run {
+            write_png(&args.file_path, &png)?;
+        }
+        return Ok(report(
+            "exif",
+            [
+                ("dry_run", args.dry_run.into()),
+                ("file", path_json(&args.file_path)),
+                (
+                    "removed",
+                    removed
+                        .iter()
+                        .map(|chunk| chunk_json(chunk, []))
+                        .collect::<Vec<_>>()
+                        .into(),
+                ),
+            ],
+        ));
+    }
+    let chunk = png.chunk_by_type(exif::EXIF).ok_or_else(not_found)?;
+    // other tools would choke on what isn't EXIF
+    let parsed = Exif::parse(chunk.data())?;
+    match &args.output {
+        Some(output) => {
+            check_overwrite(output, args.force)?;
+            if !args.dry_run {
+                write_output(output, chunk.data())?;
+            }
+            status!(output, "wrote {} bytes of EXIF data", chunk.length());
+        }
+        None => outln!(
+            "{} bytes of EXIF data, {}, {} entries in IFD0",
+            chunk.length(),
+            parsed.byte_order(),
+            parsed.entries
+        ),
+    }
+    Ok(report(
+        "exif",
+        [
+            ("dry_run", args.dry_run.into()),
+            ("file", path_json(&args.file_path)),
+            ("length", chunk.length().into()),
+            ("big_endian", parsed.big_endian.into()),
+            ("entries", u32::from(parsed.entries).into()),
+            (
+                "output",
+                args.output.as_deref().map_or(Value::Null, path_json),
+            ),
+        ],
+    ))
+}
+pub fn info(args: &InfoArgs) -> Result<Value> {
+    let png = read_png_lenient(&args.file_path, args.ignore_crc)?;
+    let stats = png.stats();
+    let animated = png.is_animated();
+    let mismatches: Vec<Value> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| !chunk.is_crc_valid())
+        .map(|(index, _)| index.into())
+        .collect();
+    outln!("file size    {} bytes", stats.file_size);
+    outln!("chunks       {}", stats.chunk_count);
+    if !mismatches.is_empty() {
+        outln!("bad crcs     {}", mismatches.len());
+    }
+    outln!("animated     {}", if animated { "yes, APNG" } else { "no" });
+    // a bad IHDR is shown as such, the rest of the file still is
+    let ihdr = match png.ihdr() {
+        Ok(ihdr) => {
+            outln!("dimensions   {} x {}", ihdr.width, ihdr.height);
+            outln!("bit depth    {}", ihdr.bit_depth);
+            outln!(
+                "color type   {} ({})",
+                ihdr.color_type.name(),
+                ihdr.color_type.byte()
+            );
+            outln!("compression  {}", ihdr.compression);
+            outln!("filter       {}", ihdr.filter);
+            outln!(
+                "interlace    {} ({})",
+                ihdr.interlace_name(),
+                ihdr.interlace
+            );
+            Value::object([
+                ("width", ihdr.width.into()),
+                ("height", ihdr.height.into()),
+                ("bit_depth", u32::from(ihdr.bit_depth).into()),
+                ("color_type", ihdr.color_type.name().into()),
+                ("color_type_code", u32::from(ihdr.color_type.byte()).into()),
+                ("compression", u32::from(ihdr.compression).into()),
+                ("filter", u32::from(ihdr.filter).into()),
+                ("interlace", ihdr.interlace_name().into()),
+            ])
+        }
+        Err(e) => {
+            outln!("IHDR         error: {}", e);
+            Value::object([("error", e.to_string().into())])
+        }
+    };
+    Ok(report(
+        "info",
+        [
+            ("file", path_json(&args.file_path)),
+            ("file_size", stats.file_size.into()),
+            ("chunk_count", stats.chunk_count.into()),
+            ("animated", animated.into()),
+            ("crc_mismatches", mismatches.into()),
+            ("ihdr", ihdr),
+        ],
+    ))
+}
+
+/// the exit code says whether the chunks are there, 3 when they aren't. only the index
+/// of the file is read, so neither chunk data nor crcs are looked at
+pub fn has(args: &HasArgs) -> Result<Value> {
+    let (rows, _) = read_index(&args.file_path)?;
+    let counts: Vec<usize> = args
+        .chunk_types
+        .iter()
+        .map(|chunk_type| {
+            rows.iter()
+                .filter(|row| same_type(&row.info.chunk_type, chunk_type, args.ignore_case))
+                .count()
+        })
+        .collect();
+    if args.count {
+        for count in &counts {
+            outln!("{}", count);
+        }
+    }
+    let found = match args.any {
+        true => counts.iter().any(|&count| count > 0),
+        false => counts.iter().all(|&count| count > 0),
+    };
+    if !found {
+        return Err(PngSecretError::Silent { code: 3 });
+    }
+    Ok(report(
+        "has",
+        [
+            ("file", path_json(&args.file_path)),
+            (
+                "counts",
+                Value::Object(
+                    args.chunk_types
+                        .iter()
+                        .zip(&counts)
+                        .map(|(chunk_type, &count)| (chunk_type.to_string(), count.into()))
+                        .collect(),
+                ),
+            ),
+        ],
+    ))
+}
+
+pub fn metadata(args: &MetadataArgs) -> Result<Value> {
+    let png = read_png(&args.file_path)?;
+    let mut known = Vec::new();
+    for chunk_type in metadata::KNOWN {
+        for (index, chunk) in png.chunks_by_type(chunk_type) {
+            let mut fields = vec![
+                ("index".to_string(), index.into()),
+                ("type".to_string(), chunk_type.into()),
+            ];
+            // a malformed chunk is shown as such, the others still are
+            let text = match metadata::describe(chunk_type, chunk.data()) {
+                Some(Ok((text, Value::Object(described)))) => {
+                    fields.extend(described);
+                    text
+                }
+                Some(Err(e)) => {
+                    fields.push(("error".to_string(), e.to_string().into()));
+                    format!("error: {}", e)
+                }
+                _ => continue,
+            };
+            outln!("{}  {}", chunk_type, text);
+            known.push(Value::Object(fields));
+        }
+    }
+    let counts: Vec<(&str, usize)> = [text::TEXT, text::ZTXT, text::ITXT]
+        .into_iter()
+        .map(|chunk_type| (chunk_type, png.chunks_by_type(chunk_type).len()))
+        .collect();
+    outln!(
+        "text  {}",
+        counts
+            .iter()
+            .map(|(chunk_type, count)| format!("{} {}", count, chunk_type))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    // the critical chunks are the image itself, not metadata
+    let other: Vec<(usize, &Chunk)> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            let chunk_type = chunk.chunk_type().to_string();
+            !chunk.chunk_type().is_critical()
+                && !metadata::KNOWN.contains(&chunk_type.as_str())
+                && !text::is_text(&chunk_type)
+        })
+        .collect();
+    if !other.is_empty() {
+        outln!(
+            "other {}",
+            other
+                .iter()
+                .map(|(_, chunk)| format!("{} ({} bytes)", chunk.chunk_type(), chunk.length()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(report(
+        "metadata",
+        [
+            ("file", path_json(&args.file_path)),
+            ("chunks", known.into()),
+            (
+                "text",
+                Value::Object(
+                    counts
+                        .iter()
+                        .map(|&(chunk_type, count)| (chunk_type.to_string(), count.into()))
+                        .collect(),
+                ),
+            ),
+            (
+                "other",
+                other
+                    .iter()
+                    .map(|&(index, chunk)| {
+                        Value::object([
+                            ("index", index.into()),
+                            ("type", chunk.chunk_type().to_string().into()),
+                            ("length", chunk.length().into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        ],
+    ))
+}
+pub fn extract_chunks(args: &ExtractChunksArgs) -> Result<Value> {
+    let png = match args.salvage {
+        true => read_png_salvage(&args.file_path)?,
+        false => read_png(&args.file_path)?,
+    };
+    // the index prefix keeps duplicate types apart
+    let files: Vec<(PathBuf, &Chunk)> = png
+        .chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| args.types.is_empty() || args.types.contains(chunk.chunk_type()))
+        .map(|(index, chunk)| {
+            let name = format!("{:03}_{}.bin", index, chunk.chunk_type());
+            (args.out_dir.join(name), chunk)
+        })
+        .collect();
+    for (path, _) in &files {
+        check_overwrite(path, args.force)?;
+    }
+    fs::create_dir_all(&args.out_dir)?;
+    let mut extracted = Vec::new();
+    for (path, chunk) in files {
+        fs::write(&path, chunk.data())?;
+        status!(&path, "{} ({} bytes)", path.display(), chunk.length());
+        extracted.push(chunk_json(chunk, [("path", path_json(&path))]));
+    }
+    Ok(report(
+        "extract-chunks",
+        [
+            ("file", path_json(&args.file_path)),
+            ("files", extracted.into()),
+        ],
+    ))
+}
+pub fn inject(args: &InjectArgs) -> Result<Value> {
+    if !args.chunk_type.is_valid() {
+        return Err(PngSecretError::Usage("Not a valid chunk type".into()));
+    }
+    guard_critical(&args.chunk_type, "inject", args.allow_critical, args.yes)?;
+    if let Some(output) = &args.output {
+        check_overwrite(output, args.force)?;
+    }
+    let mut png = read_png(&args.file_path)?;
+    let data = read_input(&args.data_file)?;
+    let chunk = Chunk::new(args.chunk_type.clone(), &data);
+    let (length, crc) = (chunk.length(), chunk.crc());
+    let index = insertion_index(&png, args.position)?;
+    png.insert_chunk_at(index, chunk)?;
+    let output = args.output.as_ref().unwrap_or(&args.file_path);
+    write_png(output, &png)?;
+    status!(
+        output,
+        "injected {} at index {}: {} bytes, crc {:#010x}",
+        args.chunk_type,
+        index,
+        length,
+        crc
+    );
+    Ok(report(
+        "inject",
+        [
+            ("file", path_json(&args.file_path)),
+            ("output", path_json(output)),
+            ("chunk", inserted_json(&png, index)),
+        ],
+    ))
+}
+pub fn copy_chunk(args: &CopyChunkArgs) -> Result<Value> {
+    guard_critical(&args.chunk_type, "copy", args.allow_critical, args.yes)?;
+    if let Some(output) = &args.output {
+        check_overwrite(output, args.force)?;
+    }
+    let source = read_png(&args.source)?;
+    let mut destination = read_png(&args.destination)?;
+    let chunk_type = args.chunk_type.to_string();
+    let copies: Vec<Chunk> = source
+        .chunks_by_type(&chunk_type)
+        .into_iter()
+        .map(|(_, chunk)| chunk.clone())
+        .collect();
+    if copies.is_empty() {
+        return Err(PngSecretError::ChunkNotFound {
+            chunk_type,
+            keyword: None,
+            matching: None,
+            path: Some(args.source.clone()),
+        });
+    }
+    // the spec only allows it while the critical chunks stay the same
+    if !args.chunk_type.is_safe_to_copy() && image_data(&source) != image_data(&destination) {
+        let unsafe_copy = format!(
+            "{} is not safe to copy and the image data of {} and {} differs",
+            chunk_type,
+            args.source.display(),
+            args.destination.display()
+        );
+        if !args.force {
+            return Err(PngSecretError::Refused {
+                reason: format!("{}, use --force to copy it anyway", unsafe_copy),
+            });
+        }
+        log::warning!("{}, copying it anyway", unsafe_copy);
+    }
+    let replaced = match args.append {
+        true => Vec::new(),
+        false => destination
+            .chunks_by_type(&chunk_type)
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect(),
+    };
+    // the copies take the place of the first chunk they replace
+    match replaced.first() {
+        Some(&index) => {
+            destination.remove_all_chunks_by_type(&chunk_type);
+            for (offset, chunk) in copies.iter().enumerate() {
+                destination.insert_chunk_at(index + offset, chunk.clone())?;
+            }
+        }
+        None => copies
+            .iter()
+            .for_each(|chunk| destination.append_chunk(chunk.clone())),
+    }
+    let output = args.output.as_ref().unwrap_or(&args.destination);
+    write_png(output, &destination)?;
+    status!(
+        output,
+        "copied {} {} chunk(s), replacing {}",
+        copies.len(),
+        chunk_type,
+        replaced.len()
+    );
+    Ok(report(
+        "copy-chunk",
+        [
+            ("source", path_json(&args.source)),
+            ("destination", path_json(&args.destination)),
+            ("output", path_json(output)),
+            (
+                "copied",
+                copies
+                    .iter()
+                    .map(|chunk| chunk_json(chunk, []))
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ("replaced", replaced.len().into()),
+        ],
+    ))
+}
+/// the critical chunks but IEND, what chunks that aren't safe to copy depend on
+fn image_data(png: &Png) -> Vec<Vec<u8>> {
+    png.chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().is_critical())
+        .filter(|chunk| chunk.chunk_type().to_string() != "IEND")
+        .map(|chunk| chunk.as_bytes())
+        .collect()
+}
+pub fn diff(args: &DiffArgs) -> Result<Value> {
+    let old = read_png(&args.old_file)?;
+    let new = read_png(&args.new_file)?;
+    let diffs = diff::diff(&old, &new, args.strict_order);
+    let report = report(
+        "diff",
+        [
+            ("old", path_json(&args.old_file)),
+            ("new", path_json(&args.new_file)),
+            ("identical", diffs.is_empty().into()),
+            (
+                "changes",
+                diffs.iter().map(diff_json).collect::<Vec<_>>().into(),
+            ),
+        ],
+    );
+    if diffs.is_empty() {
+        outln!("identical");
+        return Ok(report);
+    }
+    let describe = |chunk: &Chunk| format!("{} bytes, crc {:#010x}", chunk.length(), chunk.crc());
+    for diff in &diffs {
+        match diff {
+            ChunkDiff::Added { key, chunk } => outln!("+ added {}: {}", key, describe(chunk)),
+            ChunkDiff::Removed { key, chunk } => outln!("- removed {}: {}", key, describe(chunk)),
+            ChunkDiff::Changed { key, old, new } => {
+                outln!("~ changed {}: {} -> {}", key, describe(old), describe(new));
+                if args.data {
+                    out!("{}", hex_diff(old, new, args.data_limit));
+                }
+            }
+            ChunkDiff::Moved {
+                key,
+                old_index,
+                new_index,
+            } => outln!("> moved {}: index {} -> {}", key, old_index, new_index),
+        }
+    }
+    output::emit(&report);
+    Err(PngSecretError::Silent { code: 1 })
+}
+fn diff_json(diff: &ChunkDiff) -> Value {
+    match diff {
+        ChunkDiff::Added { key, chunk } => chunk_json(
+            chunk,
+            [("change", "added".into()), ("key", key.to_string().into())],
+        ),
+        ChunkDiff::Removed { key, chunk } => chunk_json(
+            chunk,
+            [
+                ("change", "removed".into()),
+                ("key", key.to_string().into()),
+            ],
+        ),
+        ChunkDiff::Changed { key, old, new } => Value::object([
+            ("change", "changed".into()),
+            ("key", key.to_string().into()),
+            ("old", chunk_json(old, [])),
+            ("new", chunk_json(new, [])),
+        ]),
+        ChunkDiff::Moved {
+            key,
+            old_index,
+            new_index,
+        } => Value::object([
+            ("change", "moved".into()),
+            ("key", key.to_string().into()),
+            ("old_index", (*old_index).into()),
+            ("new_index", (*new_index).into()),
+        ]),
+    }
+}
+/// hex dumps of both chunks side by side, line by line
+fn hex_diff(old: &Chunk, new: &Chunk, limit: usize) -> String {
+    let old_dump = old.hex_dump(Some(limit));
+    let new_dump = new.hex_dump(Some(limit));
+    let mut old_lines = old_dump.lines();
+    let mut new_lines = new_dump.lines();
+    let mut diff = String::new();
+    loop {
+        match (old_lines.next(), new_lines.next()) {
+            (None, None) => break,
+            (Some(old), Some(new)) if old == new => diff.push_str(&format!("    {}\n", old)),
+            (old, new) => {
+                if let Some(old) = old {
+                    diff.push_str(&format!("  - {}\n", old));
+                }
+                if let Some(new) = new {
+                    diff.push_str(&format!("  + {}\n", new));
+                }
+            }
+        }
+    }
+    diff
+}
+pub fn capacity(args: &CapacityArgs) -> Result<Value> {
+    if args.max_chunk_size == 0 {
+        return Err(PngSecretError::Usage(
+            "--max-chunk-size must be at least 1".into(),
+        ));
+    }
+    let png = read_png(&args.file_path)?;
+    if args.method == Method::Lsb {
+        return lsb_capacity(args, &png);
+    }
+    if args.channels != Channels::Rgb || args.bits != 1 {
+        return Err(PngSecretError::Usage(
+            "--channels and --bits are for --method lsb".into(),
+        ));
+    }
+    let stats = png.stats();
+    out!(
+        "{}",
+        capacity_report(stats, args.payload_size, args.max_chunk_size)
+    );
+    let payload = args.payload_size.map_or(Value::Null, |payload_size| {
+        let (chunks, growth) = payload_growth(payload_size, args.max_chunk_size);
+        Value::object([
+            ("size", payload_size.into()),
+            ("chunks", chunks.into()),
+            ("max_chunk_size", args.max_chunk_size.into()),
+            ("new_file_size", (stats.file_size + growth).into()),
+        ])
+    });
+    Ok(report(
+        "capacity",
+        [
+            ("file", path_json(&args.file_path)),
+            ("file_size", stats.file_size.into()),
+            ("chunk_count", stats.chunk_count.into()),
+            ("data_size", stats.data_size.into()),
+            ("overhead", Chunk::OVERHEAD.into()),
+            ("payload", payload),
+        ],
+    ))
+}
+/// capacity --method lsb, what the low bits of the pixels hold once they are RGBA
+fn lsb_capacity(args: &CapacityArgs, png: &Png) -> Result<Value> {
+    let ihdr = png.ihdr()?;
+    let alpha = args.channels == Channels::Rgba;
+    let capacity = lsb::capacity(ihdr.width as usize * ihdr.height as usize, alpha, args.bits);
+    let channels = if alpha { "RGBA" } else { "RGB" };
+    outln!(
+        "image: {} x {}, {} bit {}",
+        ihdr.width,
+        ihdr.height,
+        ihdr.bit_depth,
+        ihdr.color_type.name()
+    );
+    outln!(
+        "lsb capacity: {} bytes in the {} channels at {} bit(s) each, after {} bytes of header",
+        capacity,
+        channels,
+        args.bits,
+        lsb::PREFIX_SIZE
+    );
+    let conversion = lsb::conversion(&ihdr);
+    if let Some(conversion) = conversion {
+        outln!(
+            "conversion: lsb needs RGBA pixels, for this {} image {}",
+            ihdr.color_type.name(),
+            conversion
+        );
+    }
+    let payload = args.payload_size.map_or(Value::Null, |payload_size| {
+        let fits = payload_size <= capacity;
+        match fits {
+            true => outln!("payload: {} bytes fit", payload_size),
+            false => log::warning!(
+                "a payload of {} bytes doesn't fit in {}, {} bytes short",
+                payload_size,
+                args.file_path.display(),
+                payload_size - capacity
+            ),
+        }
+        Value::object([("size", payload_size.into()), ("fits", fits.into())])
+    });
+    Ok(report(
+        "capacity",
+        [
+            ("file", path_json(&args.file_path)),
+            ("method", "lsb".into()),
+            ("width", ihdr.width.into()),
+            ("height", ihdr.height.into()),
+            ("color_type", ihdr.color_type.name().into()),
+            ("channels", channels.into()),
+            ("bits", u32::from(args.bits).into()),
+            ("header", lsb::PREFIX_SIZE.into()),
+            ("capacity", capacity.into()),
+            ("conversion", conversion.map_or(Value::Null, Value::from)),
+            ("payload", payload),
+        ],
+    ))
+}
+/// the chunks a payload is split into and how many bytes the file grows by
+fn payload_growth(payload_size: usize, max_chunk_size: usize) -> (usize, usize) {
+    // an empty payload still takes a chunk
+    let chunks = payload_size.div_ceil(max_chunk_size).max(1);
+    let part_headers = if chunks > 1 {
+        chunks * payload::PART_HEADER_SIZE
+    } else {
+        0
+    };
+    (
+        chunks,
+        payload_size + chunks * Chunk::OVERHEAD + part_headers,
+    )
+}
+fn capacity_report(stats: PngStats, payload_size: Option<usize>, max_chunk_size: usize) -> String {
+    let mut report = format!(
+        "file size: {} bytes\nchunks: {} ({} bytes of data)\nper chunk overhead: {} bytes\n",
+        stats.file_size,
+        stats.chunk_count,
+        stats.data_size,
+        Chunk::OVERHEAD
+    );
+    if let Some(payload_size) = payload_size {
+        let (chunks, growth) = payload_growth(payload_size, max_chunk_size);
+        report.push_str(&format!(
+            "payload: {} bytes in {} chunk(s) of at most {} bytes\nnew file size: {} bytes (+{:.2}%)\n",
+            payload_size,
+            chunks,
+            max_chunk_size,
+            stats.file_size + growth,
+            growth as f64 * 100.0 / stats.file_size as f64
+        ));
+    }
+    report
+}
+/// the chunks of the type decode looks at, each with its index and offset in the file
+struct Found {
+    chunks: Vec<(usize, usize, Chunk)>,
+}
+impl Found {
+    fn of(png: &Png, chunk_type: &ChunkType, ignore_case: bool) -> Found {
+        let chunks = png.chunks().iter().enumerate();
+        Found {
+            chunks: chunks
+                .filter(|(_, chunk)| same_type(chunk.chunk_type(), chunk_type, ignore_case))
+                .map(|(index, chunk)| (index, png.chunk_offset(index), chunk.clone()))
+                .collect(),
+        }
+    }
+    /// reads the file in place, only the chunks of `chunk_type` are copied out of it
+    fn mapped(
+        path: &Path,
+        chunk_type: &ChunkType,
+        ignore_case: bool,
+    ) -> std::result::Result<Found, PngSecretError> {
+        let map = mmap::open(path).map_err(|source| PngSecretError::io(path, source))?;
+        let refs = Png::chunk_refs(&map).map_err(|error| error.in_file(path))?;
+        let chunks = refs
+            .iter()
+            .filter(|chunk| same_type(chunk.chunk_type(), chunk_type, ignore_case))
+            .map(|chunk| Ok((chunk.index, chunk.offset, chunk.to_chunk()?)))
+            .collect::<std::result::Result<_, PngSecretError>>()
+            .map_err(|error| error.in_file(path))?;
+        Ok(Found { chunks })
+    }
+    fn by_type(&self) -> Vec<(usize, &Chunk)> {
+        self.chunks
+            .iter()
+            .map(|(index, _, chunk)| (*index, chunk))
+            .collect()
+    }
+    fn get(&self, index: usize) -> (&Chunk, usize) {
+        let (_, offset, chunk) = self.chunks.iter().find(|(i, _, _)| *i == index).unwrap();
+        (chunk, *offset)
+    }
+}
+/// the chunks decode needs, from a map of the file with --mmap unless it can't be mapped
+fn decode_input(args: &DecodeArgs) -> std::result::Result<Found, PngSecretError> {
+    let chunk_type = &args.chunk_type;
+    // a map of a file that gets replaced underneath it is not worth the trouble
+    let writes_input = args.output.as_ref() == Some(&args.file_path);
+    let local = !is_stdio(&args.file_path) && !http::is_url(&args.file_path);
+    if args.mmap && local && !writes_input {
+        match Found::mapped(&args.file_path, chunk_type, args.ignore_case) {
+            Err(PngSecretError::Io { source, .. }) => {
+                log::debug!("could not map {}: {}", args.file_path.display(), source)
+            }
+            found => return found,
+        }
+    }
+    Ok(Found::of(&decode_png(args)?, chunk_type, args.ignore_case))
+}
+/// the file as decode reads it, salvaged or with bad crcs kept when asked to
+fn decode_png(args: &DecodeArgs) -> std::result::Result<Png, PngSecretError> {
+    match args.salvage {
+        true => read_png_salvage(&args.file_path),
+        false => read_png_lenient(&args.file_path, args.ignore_crc),
+    }
+}
+pub fn decode(args: &DecodeArgs) -> Result<Value> {
+    if args.method == Method::Lsb {
+        return decode_lsb(args);
+    }
+    if args.stealth {
+        return decode_stealth(args);
+    }
+    if args.trailing {
+        return decode_trailing(args);
+    }
+    if args.channels != Channels::Rgb {
+        return Err(PngSecretError::Usage(
+            "--channels is for --method lsb".into(),
+        ));
+    }
+    let png = decode_input(args).map_err(|error| error.in_file(&args.file_path))?;
+    if args.all {
+        return decode_all(&png, args);
+    }
+    if args.itxt_keyword.is_some() && args.chunk_type.to_string() != text::ITXT {
+        return Err(PngSecretError::Usage(
+            "--itxt-keyword reads an iTXt chunk, give iTXt as the chunk type".into(),
+        ));
+    }
+    let keyword = args.text_keyword.as_ref().or(args.itxt_keyword.as_ref());
+    if keyword.is_some() || [text::ZTXT, text::ITXT].contains(&&*args.chunk_type.to_string()) {
+        return decode_text(&png, args, keyword.map(String::as_str));
+    }
+    let chunks = png.by_type();
+    let (index, chunk) = *chunks.first().ok_or_else(|| chunk_not_found(args))?;
+    let data = payload_data(&chunks, 0)?;
+    let (header, payload) = payload(chunk.chunk_type(), &data, &mut secret(args)?, args)?;
+    if args.show_header {
+        outln!("{}", header_text(header.as_ref()));
+    }
+    let metadata = header.as_ref().and_then(|header| header.metadata.as_ref());
+    let name = format!("{}_{}.bin", index, chunk.chunk_type());
+    let source = format!("chunk {}", chunk.chunk_type());
+    let output = write_decoded(args, &name, &source, metadata, &payload)?;
+    let decoded = decoded_json(&png, index, header.as_ref(), &payload, output.as_deref());
+    Ok(decode_report(args, vec![decoded]))
+}
+/// decode --method lsb, the payload encode --method lsb wrote into the pixels
+fn decode_lsb(args: &DecodeArgs) -> Result<Value> {
+    if !lsb::ENABLED {
+        return Err(PngSecretError::Usage(lsb::UNAVAILABLE.into()));
+    }
+    if args.all || args.mmap || args.text_keyword.is_some() || args.itxt_keyword.is_some() {
+        return Err(PngSecretError::Usage(
+            "--all, --mmap and the text keywords read chunks, --method lsb the pixels".into(),
+        ));
+    }
+    let png = decode_png(args)?;
+    let data = lsb::reveal(&png, args.channels == Channels::Rgba)?;
+    let (header, payload) = payload(&args.chunk_type, &data, &mut secret(args)?, args)?;
+    if args.show_header {
+        outln!("{}", header_text(header.as_ref()));
+    }
+    let metadata = header.as_ref().and_then(|header| header.metadata.as_ref());
+    let name = format!("lsb_{}.bin", args.chunk_type);
+    let output = write_decoded(args, &name, "the pixels", metadata, &payload)?;
+    Ok(report(
+        "decode",
+        [
+            ("file", path_json(&args.file_path)),
+            ("method", "lsb".into()),
+            (
+                "header",
+                header
+                    .as_ref()
+                    .map_or(Value::Null, payload::Header::to_json),
+            ),
+            ("payload", base64::encode(&payload).into()),
+            ("output", output.as_deref().map_or(Value::Null, path_json)),
+        ],
+    ))
+}
+/// decode --stealth, the parts encode --stealth spread over the file joined again. where
+/// the password opens the parts of several payloads, the one with a part first in the file
+fn decode_stealth(args: &DecodeArgs) -> Result<Value> {
+    let png = decode_png(args)?;
+    let secret = match secret(args)? {
+        Some(secret) => secret,
+        None => Secret::Password(password::resolve(None, false)?),
+    };
+    let params = args.kdf_params.unwrap_or(Argon2Params::DEFAULT);
+    let mut opened = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        if chunk_type.is_critical() || chunk_type.is_registered() {
+            continue;
+        }
+        match crypto::decrypt_stripped(chunk.data(), &secret, params) {
+            Ok(plaintext) => opened.push((index, plaintext)),
+            Err(e) => log::debug!("chunk #{} {} doesn't open: {}", index, chunk_type, e),
+        }
+    }
+    let parts: Vec<(usize, payload::Part)> = opened
+        .iter()
+        .filter_map(|(index, plaintext)| stealth::part(plaintext).map(|part| (*index, part)))
+        .collect();
+    let (_, first) = parts.first().ok_or(PngSecretError::StealthNotFound)?;
+    let (indexes, parts): (Vec<usize>, Vec<payload::Part>) = parts
+        .iter()
+        .filter(|(_, part)| part.id == first.id)
+        .copied()
+        .unzip();
+    let data = payload::join(&parts)?;
+    // the parts were decrypted already, the payload inside isn't encrypted again
+    let (header, payload) = payload(&args.chunk_type, &data, &mut None, args)?;
+    if args.show_header {
+        outln!("{}", header_text(header.as_ref()));
+    }
+    let metadata = header.as_ref().and_then(|header| header.metadata.as_ref());
+    let name = format!("stealth_{}.bin", args.chunk_type);
+    let source = format!("{} stealth chunks", indexes.len());
+    let output = write_decoded(args, &name, &source, metadata, &payload)?;
+    Ok(report(
+        "decode",
+        [
+            ("file", path_json(&args.file_path)),
+            ("stealth", true.into()),
+            (
+                "indexes",
+                indexes
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            (
+                "header",
+                header
+                    .as_ref()
+                    .map_or(Value::Null, payload::Header::to_json),
+            ),
+            ("payload", base64::encode(&payload).into()),
+            ("output", output.as_deref().map_or(Value::Null, path_json)),
+        ],
+    ))
+}
+/// decode --trailing, the bytes after IEND that aren't a chunk, as they are
+fn decode_trailing(args: &DecodeArgs) -> Result<Value> {
+    let png = decode_png(args)?;
+    let trailing = png.trailing_data();
+    if trailing.is_empty() {
+        return Err(PngSecretError::InvalidData(format!(
+            "{} has no trailing bytes after IEND",
+            args.file_path.display()
+        )));
+    }
+    let name = format!("trailing_{}.bin", args.chunk_type);
+    let source = format!("{} trailing bytes", trailing.len());
+    let output = write_decoded(args, &name, &source, None, trailing)?;
+    Ok(report(
+        "decode",
+        [
+            ("file", path_json(&args.file_path)),
+            ("trailing", true.into()),
+            ("offset", png.chunk_offset(png.chunks().len()).into()),
+            ("length", trailing.len().into()),
+            ("payload", base64::encode(trailing).into()),
+            ("output", output.as_deref().map_or(Value::Null, path_json)),
+        ],
+    ))
+}
+/// writes the payload to --output or --output-dir, under `name` without a name of its
+/// own, or prints it, and returns the file written
+fn write_decoded(
+    args: &DecodeArgs,
+    name: &str,
+    source: &str,
+    metadata: Option<&payload::Metadata>,
+    payload: &[u8],
+) -> Result<Option<PathBuf>> {
+    let restored = match &args.output_dir {
+        Some(dir) => Some(restore(dir, metadata, name, args, payload)?),
+        None => None,
+    };
+    match &args.output {
+        // raw bytes go to the file untouched, whatever they are
+        Some(output) => {
+            check_overwrite(output, args.force)?;
+            match (args.base64, args.hex) {
+                (true, _) => write_output(output, base64::encode(payload).as_bytes())?,
+                (_, true) => write_output(output, hex::encode(payload).as_bytes())?,
+                _ => write_output(output, payload)?,
+            }
+        }
+        None if restored.is_some() => {}
+        None if args.base64 => outln!("{}", base64::encode(payload)),
+        None if args.hex => outln!("{}", hex::encode(payload)),
+        None => print_data(source, metadata, payload, args.encoding),
+    }
+    Ok(args.output.clone().or(restored))
+}
+/// prints the text of the text chunk with `keyword`, or of the first one without a
+/// keyword, or writes it to --output
+fn decode_text(png: &Found, args: &DecodeArgs, keyword: Option<&str>) -> Result<Value> {
+    let chunk_type = args.chunk_type.to_string();
+    if !text::is_text(&chunk_type) {
+        return Err(PngSecretError::Usage(
+            "--text-keyword reads a tEXt, zTXt or iTXt chunk, give one of them as the chunk type"
+                .into(),
+        ));
+    }
+    let (index, chunk) = png
+        .by_type()
+        .into_iter()
+        .find(|(_, chunk)| {
+            keyword.is_none_or(|keyword| text::keyword_of(chunk.data()).as_deref() == Some(keyword))
+        })
+        .ok_or_else(|| chunk_not_found(args))?;
+    let mut found = text::parse(&chunk_type, chunk.data())?;
+    if let Some(encoding) = args.encoding {
+        found.text = encoding.text(&text::text_bytes(&chunk_type, &found.text));
+    }
+    match &args.output {
+        Some(output) => {
+            check_overwrite(output, args.force)?;
+            write_output(output, found.text.as_bytes())?;
+        }
+        None => outln!("{}", found.text),
+    }
+    let mut decoded = decoded_json(
+        png,
+        index,
+        None,
+        found.text.as_bytes(),
+        args.output.as_deref(),
+    );
+    if let Value::Object(fields) = &mut decoded {
+        fields.push(("keyword".to_string(), found.keyword.into()));
+    }
+    Ok(decode_report(args, vec![decoded]))
+}
+fn decode_report(args: &DecodeArgs, decoded: Vec<Value>) -> Value {
+    report(
+        "decode",
+        [
+            ("file", path_json(&args.file_path)),
+            ("chunks", decoded.into()),
+        ],
+    )
+}
+/// the key from --key-file, the identities from --identity or the password
+/// from --password, if any
+fn secret(args: &DecodeArgs) -> Result<Option<Secret>> {
+    if let Some(key_file) = &args.key_file {
+        return Ok(Some(Secret::Key(crypto::read_key_file(key_file)?)));
+    }
+    if let Some(identity) = &args.identity {
+        return Ok(Some(Secret::Identities(crypto::read_identity_file(
+            identity,
+        )?)));
+    }
+    Ok(args.password.clone().map(Secret::Password))
+}
+/// the header of `data` and its message, decrypted, checked and decompressed as
+/// the header says. data without a header is decrypted or checked if it looks
+/// like it needs to be, as payloads were before the header
+fn payload(
+    chunk_type: &ChunkType,
+    data: &[u8],
+    secret: &mut Option<Secret>,
+    args: &DecodeArgs,
+) -> Result<(Option<payload::Header>, Vec<u8>)> {
+    let verify_with = match &args.verify_with {
+        Some(key) => Some(crypto::read_verifying_key(key)?),
+        None => None,
+    };
+    let Some((header, body)) = payload::unpack(data)? else {
+        if verify_with.is_some() {
+            return Err(PngSecretError::BadSignature(
+                "The payload is not signed".to_string(),
+            ));
+        }
+        let message = payload::open_legacy(unsealed(chunk_type, data, secret)?)?;
+        return Ok((None, message));
+    };
+    let body = match header.encrypted || header.signed {
+        true => unsealed(chunk_type, body, secret)?,
+        false => body.to_vec(),
+    };
+    check_signature(header.signature.as_ref(), verify_with, &body)?;
+    let body = match header.padded {
+        true => payload::unpad(&body)?,
+        false => body,
+    };
+    let message = payload::decompress(&header, body)?;
+    match payload::verify_checksum(&header, &message) {
+        // stdout may hold the message itself
+        Some(true) => status!(Path::new("-"), "checksum: OK"),
+        Some(false) if args.ignore_checksum => {
+            log::warning!("checksum mismatch, decoding anyway as --ignore-checksum was given")
+        }
+        Some(false) => return Err(PngSecretError::ChecksumMismatch),
+        None => {}
+    }
+    Ok((Some(header), message))
+}
+/// checks `signature` of `body` against the --verify-with key, without one it
+/// is only reported
+fn check_signature(
+    signature: Option<&payload::Signature>,
+    verify_with: Option<[u8; 32]>,
+    body: &[u8],
+) -> Result<()> {
+    // stdout may hold the message itself
+    let stderr = Path::new("-");
+    match (signature, verify_with) {
+        (None, None) => {}
+        (None, Some(_)) => {
+            return Err(PngSecretError::BadSignature(
+                "The payload is not signed".to_string(),
+            ))
+        }
+        (Some(signature), None) => status!(
+            stderr,
+            "signature: not checked, signed by {}, use --verify-with to check it",
+            crypto::hex(&signature.public_key)
+        ),
+        (Some(signature), Some(key)) if signature.public_key != key => {
+            return Err(PngSecretError::BadSignature(format!(
+                "The payload is signed by {}, not by {}",
+                crypto::hex(&signature.public_key),
+                crypto::hex(&key)
+            )))
+        }
+        (Some(signature), Some(key)) => {
+            if !crypto::verify_signature(&key, body, &signature.signature) {
+                return Err(PngSecretError::BadSignature(
+                    "Bad signature, the payload was changed after it was signed".to_string(),
+                ));
+            }
+            status!(stderr, "signature: valid, signed by {}", crypto::hex(&key));
+        }
+    }
+    Ok(())
+}
+/// the data of the payload starting at `chunks[at]`, its parts stitched
+/// together when it was split. parts of other payloads are left out
+fn payload_data(chunks: &[(usize, &Chunk)], at: usize) -> Result<Vec<u8>> {
+    let Some(first) = payload::part(chunks[at].1.data())? else {
+        return Ok(chunks[at].1.data().to_vec());
+    };
+    let mut parts = Vec::new();
+    for (_, chunk) in chunks {
+        if let Some(part) = payload::part(chunk.data())? {
+            if part.id == first.id {
+                parts.push(part);
+            }
+        }
+    }
+    payload::join(&parts)
+}
+/// where every payload in `chunks` starts, a split payload at its first part in file order
+fn payload_starts(chunks: &[(usize, &Chunk)]) -> Result<Vec<usize>> {
+    let mut seen = Vec::new();
+    let mut starts = Vec::new();
+    for (at, (_, chunk)) in chunks.iter().enumerate() {
+        match payload::part(chunk.data())? {
+            Some(part) if seen.contains(&part.id) => continue,
+            Some(part) => seen.push(part.id),
+            None => {}
+        }
+        starts.push(at);
+    }
+    Ok(starts)
+}
+/// the chunk data, decrypted when it is encrypted or a secret is given and
+/// checked when it is signed. the password is only looked for once, then kept
+/// in `secret`
+fn unsealed(chunk_type: &ChunkType, data: &[u8], secret: &mut Option<Secret>) -> Result<Vec<u8>> {
+    let signed = crypto::is_signed(data);
+    if secret.is_none() && !signed && !crypto::is_encrypted(data) {
+        return Ok(data.to_vec());
+    }
+    let secret = match secret {
+        Some(secret) => secret,
+        None if crypto::uses_key_file(data) => {
+            return Err(PngSecretError::Usage(format!(
+                "chunk {} needs a key, use --key-file to read it",
+                chunk_type
+            )))
+        }
+        None if crypto::uses_recipients(data) => {
+            return Err(PngSecretError::Usage(format!(
+                "chunk {} is encrypted to a public key, use --identity to decrypt it",
+                chunk_type
+            )))
+        }
+        None => secret.insert(Secret::Password(password::resolve(None, false)?)),
+    };
+    if !signed {
+        return crypto::decrypt(data, secret);
+    }
+    let message = crypto::verify(chunk_type.bytes(), data, secret)?;
+    // stdout may hold the message itself
+    status!(Path::new("-"), "integrity: verified");
+    Ok(message)
+}
+/// a decoded chunk with its payload header, its payload as base64, and the
+/// file it went to if any
+fn decoded_json(
+    png: &Found,
+    index: usize,
+    header: Option<&payload::Header>,
+    payload: &[u8],
+    output: Option<&Path>,
+) -> Value {
+    let (chunk, offset) = png.get(index);
+    let mut value = chunk_json(chunk, [("index", index.into()), ("offset", offset.into())]);
+    if let Value::Object(fields) = &mut value {
+        let header = header.map_or(Value::Null, payload::Header::to_json);
+        fields.push(("header".to_string(), header));
+        fields.push(("payload".to_string(), base64::encode(payload).into()));
+        fields.push(("output".to_string(), output.map_or(Value::Null, path_json)));
+        if !chunk.is_crc_valid() {
+            let computed = format!("{:08x}", chunk.computed_crc());
+            fields.push(("computed_crc".to_string(), computed.into()));
+        }
+    }
+    value
+}
+fn decode_all(png: &Found, args: &DecodeArgs) -> Result<Value> {
+    let chunks = png.by_type();
+    if chunks.is_empty() {
+        return Err(chunk_not_found(args));
+    }
+    let mut decoded = Vec::new();
+    let mut secret = secret(args)?;
+    for at in payload_starts(&chunks)? {
+        let (index, chunk) = chunks[at];
+        let (_, offset) = png.get(index);
+        let data = payload_data(&chunks, at)?;
+        let (header, payload) = payload(chunk.chunk_type(), &data, &mut secret, args)?;
+        let metadata = header.as_ref().and_then(|header| header.metadata.as_ref());
+        match &args.output_dir {
+            Some(dir) => {
+                let name = format!("{}_{}.bin", index, args.chunk_type);
+                let path = restore(dir, metadata, &name, args, &payload)?;
+                status!(
+                    &path,
+                    "#{} at offset {}: wrote {} bytes to {}",
+                    index,
+                    offset,
+                    payload.len(),
+                    path.display()
+                );
+                decoded.push(decoded_json(
+                    png,
+                    index,
+                    header.as_ref(),
+                    &payload,
+                    Some(&path),
+                ));
+            }
+            None => {
+                outln!("#{} at offset {}:", index, offset);
+                if args.show_header {
+                    outln!("{}", header_text(header.as_ref()));
+                }
+                match (args.base64, args.hex) {
+                    (true, _) => outln!("{}", base64::encode(&payload)),
+                    (_, true) => outln!("{}", hex::encode(&payload)),
+                    _ => print_data(
+                        &format!("chunk {}", chunk.chunk_type()),
+                        metadata,
+                        &payload,
+                        args.encoding,
+                    ),
+                }
+                decoded.push(decoded_json(png, index, header.as_ref(), &payload, None));
+            }
+        }
+    }
+    Ok(decode_report(args, decoded))
+}
+fn chunk_not_found(args: &DecodeArgs) -> PngSecretError {
+    PngSecretError::ChunkNotFound {
+        chunk_type: args.chunk_type.to_string(),
+        keyword: args.text_keyword.clone().or(args.itxt_keyword.clone()),
+        matching: None,
+        path: Some(args.file_path.clone()),
+    }
+}
+/// the payload header as decode --show-header prints it
+fn header_text(header: Option<&payload::Header>) -> String {
+    let Some(header) = header else {
+        return "Header:      none, a raw payload".to_string();
+    };
+    let yes_no = |set: bool| if set { "yes" } else { "no" };
+    format!(
+        "Header:      pSCv version {}\nCompressed:  {}\nEncrypted:   {}\nSigned:      {}\nParts:       {}\nChecksum:    {}\nSigned by:   {}",
+        header.version,
+        yes_no(header.compressed),
+        yes_no(header.encrypted),
+        yes_no(header.signed),
+        header.parts.unwrap_or(1),
+        header
+            .to_json()
+            .get("checksum")
+            .and_then(Value::as_str)
+            .unwrap_or("none"),
+        header
+            .signature
+            .as_ref()
+            .map_or("none".to_string(), |signature| crypto::hex(
+                &signature.public_key
+            ))
+    )
+}
+/// writes `payload` to `dir` under the name in `metadata`, with its mode and
+/// modification time, or as `name` without metadata
+fn restore(
+    dir: &Path,
+    metadata: Option<&payload::Metadata>,
+    name: &str,
+    args: &DecodeArgs,
+    payload: &[u8],
+) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let Some(metadata) = metadata else {
+        let path = dir.join(name);
+        check_overwrite(&path, args.force)?;
+        fs::write(&path, payload)?;
+        return Ok(path);
+    };
+    let path = dir.join(&metadata.name);
+    check_overwrite(&path, args.force)?;
+    fs::write(&path, payload)?;
+    // before the mode, which may not let the file be opened for writing
+    restore_modified(&path, Some(metadata.modified()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(metadata.mode))?;
+    }
+    Ok(path)
+}
+/// `seconds` since the epoch as a UTC date and time
+fn utc(seconds: i64) -> String {
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = time::civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+/// prints `payload` if it is text, `source` is where it came from
+fn print_data(
+    source: &str,
+    metadata: Option<&payload::Metadata>,
+    payload: &[u8],
+    encoding: Option<Encoding>,
+) {
+    if output::json() {
+        return;
+    }
+    if let Some(metadata) = metadata {
+        eprintln!(
+            "file {}, {} bytes, mode {:o}, modified {}",
+            metadata.name,
+            metadata.size,
+            metadata.mode,
+            utc(metadata.mtime)
+        );
+    }
+    if let Some(encoding) = encoding {
+        println!("{}", encoding.text(payload));
+        return;
+    }
+    match std::str::from_utf8(payload) {
+        Ok(message) => println!("{}", message),
+        Err(_) => eprintln!(
+            "{} holds {} bytes of binary data, use --output <PATH> or --output-dir <DIR> to write it to a file",
+            source,
+            payload.len()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Cli;
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pngsecret-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn testing_png() -> Png {
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk(
+                "IDAT",
+                &[120, 1, 1, 5, 0, 250, 255, 0, 0, 0, 0, 0, 0, 5, 0, 1],
+            ),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    fn write_testing_png(name: &str, png: &Png) -> PathBuf {
+        let path = temp_path(name);
+        fs::write(&path, png.as_bytes()).unwrap();
+        path
+    }
+
+    fn run_cli(args: &[&str]) -> Result<Value> {
+        let cli = Cli::try_parse_from(std::iter::once("pngsecret").chain(args.iter().copied()))
+            .map_err(|e| PngSecretError::Usage(e.to_string()))?;
+        let level = Level::from_flags(cli.verbose, cli.quiet);
+        log::capture::capture(level, || run(&cli.commands)).0
+    }
+
+    #[test]
+    fn test_decode_output_text_round_trip() {
+        let file = write_testing_png("decode_text.png", &testing_png());
+        let output = temp_path("decode_text.out");
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "hidden message"]).unwrap();
+        run_cli(&["decode", file, "ruSt", "--output", output.to_str().unwrap()]).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"hidden message");
+    }
+
+    #[test]
+    fn test_decode_output_binary_round_trip() {
+        let payload: Vec<u8> = (0..=255).rev().collect();
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("ruSt").unwrap(), &payload));
+        let file = write_testing_png("decode_binary.png", &png);
+        let output = temp_path("decode_binary.out");
+        run_cli(&[
+            "decode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_decode_binary_without_output() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            &[0xff, 0xfe],
+        ));
+        let file = write_testing_png("decode_binary_hint.png", &png);
+        assert!(run_cli(&["decode", file.to_str().unwrap(), "ruSt"]).is_ok());
+    }
+
+    fn png_with_duplicates() -> Png {
+        let mut png = testing_png();
+        for message in ["first", "second", "third"] {
+            png.append_chunk(Chunk::new(
+                ChunkType::from_str("ruSt").unwrap(),
+                message.as_bytes(),
+            ));
+        }
+        png
+    }
+
+    #[test]
+    fn test_decode_all_output_dir() {
+        let file = write_testing_png("decode_all.png", &png_with_duplicates());
+        let dir = temp_path("decode_all");
+        let _ = fs::remove_dir_all(&dir);
+        run_cli(&[
+            "decode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "--all",
+            "--output-dir",
+            dir.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        assert_eq!(fs::read(dir.join("2_ruSt.bin")).unwrap(), b"first");
+        assert_eq!(fs::read(dir.join("3_ruSt.bin")).unwrap(), b"second");
+        assert_eq!(fs::read(dir.join("4_ruSt.bin")).unwrap(), b"third");
+    }
+
+    #[test]
+    fn test_decode_all_not_found() {
+        let file = write_testing_png("decode_all_none.png", &testing_png());
+        let result = run_cli(&["decode", file.to_str().unwrap(), "ruSt", "--all"]);
+        assert!(matches!(
+            result.unwrap_err(),
+            PngSecretError::ChunkNotFound { .. }
+        ));
+    }
+
+    #[test]
+    fn test_decode_exit_codes() {
+        let file = write_testing_png("decode_exit.png", &png_with_duplicates());
+        let file_arg = file.to_str().unwrap();
+        run_cli(&["decode", file_arg, "ruSt"]).unwrap();
+
+        let error = run_cli(&["decode", file_arg, "nOpE"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("no chunk of type 'nOpE' found in {}", file.display())
+        );
+        assert_eq!(crate::exit::code(&error), 3);
+
+        let corrupt = temp_path("decode_corrupt.png");
+        fs::write(&corrupt, b"\x89PNG but not really").unwrap();
+        let error = run_cli(&["decode", corrupt.to_str().unwrap(), "ruSt"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 2);
+    }
+
+    #[test]
+    fn test_decode_mmap() {
+        let file = write_testing_png("decode_mmap.png", &png_with_duplicates());
+        let file_arg = file.to_str().unwrap();
+        for args in [&["ruSt"][..], &["ruSt", "--base64"], &["ruSt", "--all"]] {
+            let read = run_cli(&[&["decode", file_arg], args].concat()).unwrap();
+            let mapped = run_cli(&[&["decode", file_arg, "--mmap"], args].concat()).unwrap();
+            assert_eq!(read, mapped);
+        }
+        let error = run_cli(&["decode", file_arg, "nOpE", "--mmap"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 3);
+
+        // only the chunks decode looks at have their crc checked
+        let mut bytes = png_with_duplicates().as_bytes();
+        let offset = png_with_duplicates().chunk_offset(3);
+        bytes[offset + 8] ^= 0xff;
+        let corrupt = temp_path("decode_mmap_corrupt.png");
+        fs::write(&corrupt, &bytes).unwrap();
+        let corrupt = corrupt.to_str().unwrap();
+        let error = run_cli(&["decode", corrupt, "ruSt", "--all", "--mmap"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 4);
+        assert!(error.to_string().contains("chunk #3 (ruSt)"));
+        let value = run_cli(&["decode", corrupt, "IHDR", "--mmap"]);
+        assert!(value.is_ok());
+
+        // stdin, or an empty file, is read as usual
+        let empty = temp_path("decode_mmap_empty.png");
+        fs::write(&empty, b"").unwrap();
+        let error = run_cli(&["decode", empty.to_str().unwrap(), "ruSt", "--mmap"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 2);
+    }
+
+    fn remaining_messages(file: &Path) -> Vec<String> {
+        read_png(file)
+            .unwrap()
+            .chunks_by_type("ruSt")
+            .into_iter()
+            .map(|(_, chunk)| chunk.data_as_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_remove_first_match() {
+        let file = write_testing_png("remove_first.png", &png_with_duplicates());
+        run_cli(&["remove", file.to_str().unwrap(), "ruSt"]).unwrap();
+        assert_eq!(remaining_messages(&file), ["second", "third"]);
+    }
+
+    #[test]
+    fn test_remove_nth() {
+        let file = write_testing_png("remove_nth.png", &png_with_duplicates());
+        run_cli(&["remove", file.to_str().unwrap(), "ruSt", "--nth", "1"]).unwrap();
+        assert_eq!(remaining_messages(&file), ["first", "third"]);
+    }
+
+    #[test]
+    fn test_remove_nth_out_of_range() {
+        let file = write_testing_png("remove_nth_range.png", &png_with_duplicates());
+        let error = run_cli(&["remove", file.to_str().unwrap(), "ruSt", "--nth", "3"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::NthOutOfRange { .. }));
+        assert_eq!(crate::exit::code(&error), 7);
+        assert_eq!(remaining_messages(&file).len(), 3);
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let file = write_testing_png("remove_all.png", &png_with_duplicates());
+        run_cli(&["remove", file.to_str().unwrap(), "ruSt", "--all"]).unwrap();
+        assert!(remaining_messages(&file).is_empty());
+    }
+
+    #[test]
+    fn test_remove_matching() {
+        let file = write_testing_png("remove_matching.png", &png_with_duplicates());
+        let path = file.to_str().unwrap();
+        let report = run_cli(&[
+            "--format",
+            "json",
+            "remove",
+            path,
+            "ruSt",
+            "--matching",
+            "^sec",
+            "--dry-run",
+            "--print-removed",
+        ])
+        .unwrap();
+        assert_eq!(report.get("matched").unwrap().as_f64(), Some(1.0));
+        assert_eq!(remaining_messages(&file).len(), 3);
+
+        run_cli(&["remove", path, "ruSt", "--matching", "^sec"]).unwrap();
+        assert_eq!(remaining_messages(&file), ["first", "third"]);
+        let error = run_cli(&["remove", path, "ruSt", "--matching", "^sec"]).unwrap_err();
+        assert!(error.to_string().contains("matches ^sec"));
+
+        // the regex is checked before the file is looked for
+        let error = run_cli(&["remove", "missing.png", "ruSt", "--matching", "(unclosed"]);
+        assert!(error.unwrap_err().to_string().contains("regex parse error"));
+    }
+
+    #[test]
+    fn test_remove_all_conflicts_with_nth() {
+        let result = Cli::try_parse_from([
+            "pngsecret",
+            "remove",
+            "f.png",
+            "ruSt",
+            "--all",
+            "--nth",
+            "1",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_print_type_filters() {
+        let file = write_testing_png("print_filters.png", &png_with_duplicates());
+        let file = file.to_str().unwrap();
+        run_cli(&["print", file, "--type", "ruSt", "--type", "IHDR"]).unwrap();
+        run_cli(&["print", file, "--exclude-type", "IDAT"]).unwrap();
+        run_cli(&["print", file, "--type", "ruSt", "--exclude-type", "IHDR"]).unwrap();
+        // every requested type is excluded again
+        let result = run_cli(&["print", file, "--type", "ruSt", "--exclude-type", "ruSt"]);
+        assert!(result.is_err());
+        assert!(run_cli(&["print", file, "--type", "tEXt"]).is_err());
+    }
+
+    #[test]
+    fn test_print_invalid_type() {
+        assert!(Cli::try_parse_from(["pngsecret", "print", "f.png", "--type", "Ru5t"]).is_err());
+        assert!(
+            Cli::try_parse_from(["pngsecret", "print", "f.png", "--exclude-type", "RuStY"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_print_json() {
+        let png = png_with_duplicates();
+        let chunks: Vec<_> = png.chunks().iter().enumerate().collect();
+        let text = chunks_json(&png, &chunks, true).to_string();
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let entries = value.as_array().unwrap();
+        assert_eq!(entries.len(), 6);
+        let first = &entries[0];
+        assert_eq!(first["type"], "IHDR");
+        assert_eq!(first["offset"], 8);
+        assert_eq!(first["length"], 13);
+        assert_eq!(first["critical"], true);
+        // the appended chunks come before IEND
+        assert_eq!(entries[5]["type"], "IEND");
+        let last = &entries[4];
+        assert_eq!(last["index"], 4);
+        assert_eq!(last["public"], false);
+        assert_eq!(last["safe_to_copy"], true);
+        assert_eq!(last["data"], "dGhpcmQ=");
+        assert_eq!(last["crc"], format!("{:08x}", png.chunks()[4].crc()));
+    }
+
+    #[test]
+    fn test_print_json_no_data() {
+        let png = png_with_duplicates();
+        let chunks: Vec<_> = png.chunks().iter().enumerate().collect();
+        let text = chunks_json(&png, &chunks, false).to_string();
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert!(value[0].get("data").is_none());
+    }
+
+    #[test]
+    fn test_print_hex_with_type() {
+        let file = write_testing_png("print_hex.png", &png_with_duplicates());
+        let file = file.to_str().unwrap();
+        run_cli(&["print", file, "--type", "ruSt", "--hex", "--limit", "2"]).unwrap();
+        run_cli(&["print", file, "--limit", "2"]).unwrap();
+        assert!(
+            Cli::try_parse_from(["pngsecret", "print", file, "--json", "--limit", "2"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_list_table() {
+        let png = png_with_duplicates();
+        let crc = |index: usize| format!("{:08x}", png.chunks()[index].crc());
+        let expected = format!(
+            "\
+index  type      length  crc           offset  flags
+    0  IHDR          13  {}           8  ...
+    1  IDAT          16  {}          33  ...
+    2  ruSt           5  {}          61  APS
+    3  ruSt           6  {}          78  APS
+    4  ruSt           5  {}          96  APS
+    5  IEND           0  {}         113  ...
+",
+            crc(0),
+            crc(1),
+            crc(2),
+            crc(3),
+            crc(4),
+            crc(5)
+        );
+        assert_eq!(list_table(&indexed(&png), SortKey::Offset, false), expected);
+    }
+
+    #[test]
+    fn test_list_index() {
+        let mut png = png_with_duplicates();
+        png.insert_chunk_at(
+            1,
+            Chunk::new(
+                ChunkType::from_str("tIME").unwrap(),
+                &[7, 232, 1, 2, 3, 4, 5],
+            ),
+        )
+        .unwrap();
+        let file = write_testing_png("list_index.png", &png);
+        let file_arg = file.to_str().unwrap();
+        // the report is the same as from reading every chunk
+        let all: Vec<_> = png.chunks().iter().enumerate().collect();
+        let listed = run_cli(&["list", file_arg]).unwrap();
+        assert_eq!(listed.get("chunks"), Some(&chunks_json(&png, &all, false)));
+        assert!(listed
+            .to_string()
+            .contains("\"time\":\"2024-01-02T03:04:05Z"));
+
+        let printed = run_cli(&["print", file_arg, "--json", "--no-data", "--type", "ruSt"]);
+        let matching = png.chunks_by_type("ruSt");
+        assert_eq!(
+            printed.unwrap().get("chunks"),
+            Some(&chunks_json(&png, &matching, false))
+        );
+        let error = run_cli(&["print", file_arg, "--json", "--no-data", "--type", "nOpE"]);
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_list_sort() {
+        let png = png_with_duplicates();
+        let indices = |sort| -> Vec<String> {
+            list_table(&indexed(&png), sort, false)
+                .lines()
+                .skip(1)
+                .map(|line| line.split_whitespace().next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(indices(SortKey::Offset), ["0", "1", "2", "3", "4", "5"]);
+        assert_eq!(indices(SortKey::Size), ["5", "2", "4", "3", "0", "1"]);
+        assert_eq!(indices(SortKey::Type), ["1", "5", "0", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let dir = temp_path("scan_dir");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("clean.png"), testing_png().as_bytes()).unwrap();
+        fs::write(dir.join("notes.txt"), "not a png").unwrap();
+        let dir = dir.to_str().unwrap();
+        run_cli(&["scan", dir]).unwrap();
+
+        fs::write(
+            Path::new(dir).join("nested/hidden.PNG"),
+            png_with_duplicates().as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(png_files(Path::new(dir)).unwrap().len(), 2);
+        assert!(run_cli(&["scan", dir]).is_err());
+    }
+
+    #[test]
+    fn test_scan_threads() {
+        let dir = temp_path("scan_threads");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let mut low = testing_png().chunks().clone();
+        low.insert(2, Chunk::new(ChunkType::from_str("zZZz").unwrap(), b"low"));
+        let low = Png::from_chunks(low);
+        for i in 0..40 {
+            let png = if i % 3 == 0 { &low } else { &testing_png() };
+            let name = format!("{}/{:02}.png", ["", "nested"][i % 2], i);
+            fs::write(dir.join(name.trim_start_matches('/')), png.as_bytes()).unwrap();
+        }
+        let dir_arg = dir.to_str().unwrap();
+        let serial = run_cli(&["scan", dir_arg, "--threads", "1"]).unwrap();
+        for threads in ["2", "8"] {
+            let parallel = run_cli(&["scan", dir_arg, "--threads", threads]).unwrap();
+            assert_eq!(parallel, serial);
+        }
+        let files = serial.get("files").unwrap().as_array().unwrap();
+        assert_eq!(files.len(), 40);
+        let paths: Vec<String> = files
+            .iter()
+            .map(|file| file.get("path").unwrap().as_str().unwrap().to_string())
+            .collect();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths, sorted);
+
+        // an unreadable file is reported with the rest, not the end of the run
+        fs::write(dir.join("05_broken.png"), b"\x89PNG but not really").unwrap();
+        fs::write(dir.join("nested/31_broken.png"), b"").unwrap();
+        let serial = run_cli(&["scan", dir_arg, "--threads", "1"]).unwrap_err();
+        let parallel = run_cli(&["scan", dir_arg, "--threads", "8"]).unwrap_err();
+        assert_eq!(parallel.to_string(), serial.to_string());
+        assert!(serial.to_string().starts_with("2 file(s)"));
+        assert!(run_cli(&["scan", dir_arg, "--threads", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_scan_fail_on() {
+        let mut chunks = testing_png().chunks().clone();
+        chunks.insert(2, Chunk::new(ChunkType::from_str("zZZz").unwrap(), b"low"));
+        let file = write_testing_png("scan_low.png", &Png::from_chunks(chunks));
+        let file = file.to_str().unwrap();
+        run_cli(&["scan", file]).unwrap();
+        assert!(run_cli(&["scan", file, "--fail-on", "low"]).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let file = write_testing_png("verify_clean.png", &testing_png());
+        run_cli(&["verify", file.to_str().unwrap()]).unwrap();
+        run_cli(&["verify", file.to_str().unwrap(), "--quiet"]).unwrap();
+    }
+
+    #[test]
+    fn test_verify_bad_crc() {
+        let mut bytes = testing_png().as_bytes();
+        // last byte of the IHDR crc
+        bytes[32] ^= 0xff;
+        let file = temp_path("verify_crc.png");
+        fs::write(&file, bytes).unwrap();
+        let error = run_cli(&["verify", file.to_str().unwrap()]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 4);
+        let error = run_cli(&["verify", file.to_str().unwrap(), "-q"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::Silent { .. }));
+        assert_eq!(crate::exit::code(&error), 4);
+    }
+
+    #[test]
+    fn test_ignore_crc() {
+        let mut png = testing_png();
+        let bad = Chunk::new_unchecked("ruSt".parse().unwrap(), b"hello", 0x1234_5678);
+        let computed = format!("{:08x}", bad.computed_crc());
+        png.insert_chunk_at(2, bad).unwrap();
+        let file = write_testing_png("ignore_crc.png", &png);
+        let path = file.to_str().unwrap();
+
+        // refused without the flag, and always by commands that write the file
+        let error = run_cli(&["decode", path, "ruSt"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 4);
+        assert!(run_cli(&["remove", path, "ruSt"]).is_err());
+        assert!(run_cli(&["encode", path, "teSt", "message"]).is_err());
+        assert!(run_cli(&["decode", path, "ruSt", "--ignore-crc", "--mmap"]).is_err());
+
+        let cli = Cli::try_parse_from(["pngsecret", "decode", path, "ruSt", "--ignore-crc"]);
+        let (result, lines) = log::capture::capture(Level::Info, || run(&cli.unwrap().commands));
+        let report = result.unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(decoded.get("payload").unwrap().as_str(), Some("aGVsbG8="));
+        assert_eq!(
+            decoded.get("computed_crc").unwrap().as_str(),
+            Some(&*computed)
+        );
+        let warning = format!(
+            "warning: chunk #2 (ruSt) crc MISMATCH: stored 0x12345678, computed 0x{}",
+            computed
+        );
+        assert!(lines.contains(&warning), "{:?}", lines);
+
+        let report = run_cli(&["print", path, "--json", "--no-data", "--ignore-crc"]).unwrap();
+        let chunks = report.get("chunks").unwrap().as_array().unwrap();
+        assert_eq!(chunks[2].get("crc").unwrap().as_str(), Some("12345678"));
+        assert_eq!(
+            chunks[2].get("computed_crc").unwrap().as_str(),
+            Some(&*computed)
+        );
+        assert!(chunks[1].get("computed_crc").is_none());
+        let text = chunk_text(&png.chunks()[2], None, false, None, false);
+        assert!(text.contains(&format!(
+            "Crc:         12345678  crc MISMATCH: stored 0x12345678, computed 0x{}\n",
+            computed
+        )));
+        assert!(!chunk_text(&png.chunks()[1], None, false, None, false).contains("MISMATCH"));
+
+        let report = run_cli(&["--format", "json", "info", path, "--ignore-crc"]).unwrap();
+        let mismatches = report.get("crc_mismatches").unwrap().as_array().unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].as_f64(), Some(2.0));
+        assert!(run_cli(&["info", path]).is_err());
+    }
+
+    #[test]
+    fn test_salvage() {
+        let file = write_testing_png("salvage.png", &testing_png());
+        let path = file.to_str().unwrap();
+        run_cli(&["encode", path, "ruSt", "message"]).unwrap();
+        // cut inside the crc of IEND, what a download that stopped short leaves
+        let bytes = fs::read(&file).unwrap();
+        fs::write(&file, &bytes[..bytes.len() - 2]).unwrap();
+
+        assert!(run_cli(&["decode", path, "ruSt"]).is_err());
+        assert!(run_cli(&["list", path]).is_err());
+        let cli = Cli::try_parse_from(["pngsecret", "decode", path, "ruSt", "--salvage"]);
+        let (result, lines) = log::capture::capture(Level::Info, || run(&cli.unwrap().commands));
+        let report = result.unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            decoded.get("payload").unwrap().as_str(),
+            Some("bWVzc2FnZQ==")
+        );
+        assert!(lines.contains(&format!(
+            "warning: {}: no IEND chunk, the file was cut short",
+            path
+        )));
+        assert!(lines.iter().any(|line| line.starts_with(&format!(
+            "warning: {}: dropped 10 bytes from offset 0x50: the file ends inside chunk #3",
+            path
+        ))));
+
+        let listed = run_cli(&["list", path, "--salvage"]).unwrap();
+        assert_eq!(listed.get("chunks").unwrap().as_array().unwrap().len(), 3);
+        let printed = run_cli(&["print", path, "--json", "--no-data", "--salvage"]).unwrap();
+        assert_eq!(printed.get("chunks").unwrap().as_array().unwrap().len(), 3);
+        let dir = temp_path("salvage_chunks");
+        let _ = fs::remove_dir_all(&dir);
+        let dir = dir.to_str().unwrap();
+        assert!(run_cli(&["extract-chunks", path, "--out-dir", dir]).is_err());
+        run_cli(&["extract-chunks", path, "--out-dir", dir, "--salvage"]).unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 3);
+        assert!(run_cli(&["decode", path, "ruSt", "--salvage", "--ignore-crc"]).is_err());
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut bytes = testing_png().as_bytes();
+        let hidden: Vec<u8> = (0..=255).cycle().take(1024).collect();
+        bytes.extend_from_slice(&hidden);
+        let file = temp_path("trailing.png");
+        fs::write(&file, &bytes).unwrap();
+        let path = file.to_str().unwrap();
+
+        let listed = run_cli(&["list", path]).unwrap();
+        assert_eq!(listed.get("chunks").unwrap().as_array().unwrap().len(), 3);
+        assert_eq!(listed.get("trailing_bytes").unwrap().as_f64(), Some(1024.0));
+        let decoded = run_cli(&["decode", path, "ruSt", "--trailing"]).unwrap();
+        assert_eq!(
+            decoded.get("payload").unwrap().as_str(),
+            Some(&*base64::encode(&hidden))
+        );
+        assert_eq!(decoded.get("offset").unwrap().as_f64(), Some(73.0));
+
+        // a re-save keeps them, after the new chunk
+        run_cli(&["encode", path, "ruSt", "message"]).unwrap();
+        let encoded = fs::read(&file).unwrap();
+        assert!(encoded.ends_with(&hidden));
+        assert_eq!(encoded.len(), bytes.len() + 19);
+        let report = run_cli(&["decode", path, "ruSt"]).unwrap();
+        assert_eq!(
+            report.get("chunks").unwrap().as_array().unwrap()[0]
+                .get("payload")
+                .unwrap()
+                .as_str(),
+            Some("bWVzc2FnZQ==")
+        );
+        let report = run_cli(&["strip", path]).unwrap();
+        assert_eq!(report.get("trailing_bytes").unwrap().as_f64(), Some(0.0));
+        assert_eq!(fs::read(&file).unwrap(), bytes);
+
+        let report = run_cli(&["strip", path, "--strip-trailing"]).unwrap();
+        assert_eq!(report.get("trailing_bytes").unwrap().as_f64(), Some(1024.0));
+        assert_eq!(fs::read(&file).unwrap(), testing_png().as_bytes());
+        let listed = run_cli(&["list", path]).unwrap();
+        assert_eq!(listed.get("trailing_bytes").unwrap().as_f64(), Some(0.0));
+        assert!(run_cli(&["decode", path, "ruSt", "--trailing"]).is_err());
+    }
+
+    /// a server on localhost giving one of `responses` to each request, in order,
+    /// and its url
+    #[cfg(feature = "http")]
+    fn serve(responses: Vec<(&'static str, &'static str, Vec<u8>)>) -> String {
+        use std::io::BufRead;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (status, content_type, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                // a GET ends at the empty line after its headers
+                let mut reader = io::BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    content_type,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        url
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_url_input() {
+        let file = write_testing_png("url.png", &testing_png());
+        run_cli(&["encode", file.to_str().unwrap(), "ruSt", "message"]).unwrap();
+        let png = fs::read(&file).unwrap();
+        let ok = |content_type| ("200 OK", content_type, png.clone());
+        let url = serve(vec![
+            ok("image/png"),
+            ok("application/octet-stream"),
+            ok("image/png"),
+            ("404 Not Found", "text/html", b"<p>not here</p>".to_vec()),
+            ok("text/html"),
+            ok("image/png"),
+        ]);
+        let url = format!("{}/image.png", url);
+
+        let info = run_cli(&["--format", "json", "info", &url]).unwrap();
+        assert_eq!(info.get("chunk_count").unwrap().as_f64(), Some(4.0));
+        let listed = run_cli(&["list", &url]).unwrap();
+        assert_eq!(listed.get("chunks").unwrap().as_array().unwrap().len(), 4);
+        let decoded = run_cli(&["decode", &url, "ruSt"]).unwrap();
+        let decoded = &decoded.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            decoded.get("payload").unwrap().as_str(),
+            Some("bWVzc2FnZQ==")
+        );
+
+        let error = run_cli(&["info", &url]).unwrap_err().to_string();
+        assert_eq!(error, format!("{}: the server answered 404 Not Found", url));
+        let error = run_cli(&["info", &url]).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            format!("{}: the server sent text/html, not a png", url)
+        );
+        // a response stops at the limit, --max-memory when it is given
+        let mut body = http::open(&url, 50).unwrap();
+        assert!(body.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[cfg(not(feature = "http"))]
+    #[test]
+    fn test_url_input_unavailable() {
+        let error = run_cli(&["info", "https://example.com/image.png"]).unwrap_err();
+        assert!(error.to_string().ends_with(http::UNAVAILABLE), "{}", error);
+    }
+
+    #[test]
+    fn test_url_not_written() {
+        let url = "https://example.com/image.png";
+        for args in [
+            &["encode", url, "ruSt", "message"][..],
+            &["remove", url, "ruSt"],
+            &["strip", url],
+            &["touch", url],
+        ] {
+            let error = run_cli(args).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "{} is a URL, which is only read, download it to change it",
+                    url
+                )
+            );
+        }
+        let file = write_testing_png("url_output.png", &testing_png());
+        let path = file.to_str().unwrap();
+        run_cli(&["encode", path, "ruSt", "message"]).unwrap();
+        let error = run_cli(&["decode", path, "ruSt", "--output", url]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Can't write to {}, a URL is only read", url)
+        );
+    }
+
+    #[test]
+    fn test_error_names_file_and_offset() {
+        let mut bytes = testing_png().as_bytes();
+        // the idat starts after the signature and the 25 bytes of the IHDR
+        let offset = 8 + 25;
+        // the first byte of its data
+        bytes[offset + 8] ^= 0xff;
+        let file = temp_path("error_offset.png");
+        fs::write(&file, bytes).unwrap();
+        let error = run_cli(&["decode", file.to_str().unwrap(), "ruSt"]).unwrap_err();
+        let message = error.to_string();
+        assert!(
+            message.starts_with(&format!(
+                "invalid CRC in chunk #1 (IDAT) at offset 0x21 in {}: expected 0x",
+                file.display()
+            )),
+            "{}",
+            message
+        );
+        assert_eq!(crate::exit::code(&error), 4);
+    }
+
+    #[test]
+    fn test_verify_truncated() {
+        let bytes = testing_png().as_bytes();
+        let file = temp_path("verify_truncated.png");
+        fs::write(&file, &bytes[..bytes.len() - 6]).unwrap();
+        let error = run_cli(&["verify", file.to_str().unwrap()]).unwrap_err();
+        let offset = bytes.len() - Chunk::OVERHEAD;
+        assert!(
+            matches!(&error, PngSecretError::Truncated { at } if at.offset == Some(offset)),
+            "{:?}",
+            error
+        );
+        assert_eq!(crate::exit::code(&error), 2);
+        let error = run_cli(&["verify", file.to_str().unwrap(), "--quiet"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 2);
+    }
+
+    #[test]
+    fn test_repair() {
+        let png = testing_png();
+        let mut bytes = png.as_bytes();
+        // corrupt the IHDR and IEND crcs
+        bytes[32] ^= 0xff;
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        let file = temp_path("repair.png");
+        let output = temp_path("repair_out.png");
+        fs::write(&file, &bytes).unwrap();
+        assert!(run_cli(&["verify", file.to_str().unwrap()]).is_err());
+        run_cli(&[
+            "repair",
+            file.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .unwrap();
+        run_cli(&["verify", output.to_str().unwrap()]).unwrap();
+        let repaired = read_png(&output).unwrap();
+        assert_eq!(
+            repaired.chunk_by_type("IDAT").unwrap().data(),
+            png.chunk_by_type("IDAT").unwrap().data()
+        );
+        assert_eq!(repaired.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_repair_missing_iend() {
+        let mut chunks = testing_png().chunks().clone();
+        chunks.pop();
+        let file = write_testing_png("repair_iend.png", &Png::from_chunks(chunks));
+        run_cli(&["repair", file.to_str().unwrap()]).unwrap();
+        run_cli(&["verify", file.to_str().unwrap()]).unwrap();
+    }
+
+    #[test]
+    fn test_repair_refuses_unsalvageable() {
+        let mut chunks = testing_png().chunks().clone();
+        chunks.remove(1);
+        let png = Png::from_chunks(chunks);
+        let file = write_testing_png("repair_refuse.png", &png);
+        assert!(run_cli(&["repair", file.to_str().unwrap()]).is_err());
+        assert_eq!(fs::read(&file).unwrap(), png.as_bytes());
+    }
+
+    fn png_with_metadata() -> Png {
+        let mut chunks = testing_png().chunks().clone();
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        chunks.insert(1, chunk("iCCP", b"profile\0\0\x78\x01"));
+        chunks.insert(2, chunk("tEXt", b"Comment\0hello"));
+        chunks.insert(3, chunk("tIME", &[7, 232, 1, 2, 3, 4, 5]));
+        chunks.insert(5, chunk("ruSt", b"secret"));
+        Png::from_chunks(chunks)
+    }
+
+    fn chunk_types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_strip() {
+        let file = write_testing_png("strip.png", &png_with_metadata());
+        run_cli(&["strip", file.to_str().unwrap()]).unwrap();
+        let png = read_png(&file).unwrap();
+        assert_eq!(chunk_types(&png), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(png.as_bytes(), testing_png().as_bytes());
+    }
+
+    #[test]
+    fn test_strip_keep() {
+        let file = write_testing_png("strip_keep.png", &png_with_metadata());
+        let output = temp_path("strip_keep_out.png");
+        run_cli(&[
+            "strip",
+            file.to_str().unwrap(),
+            "--keep",
+            "iCCP",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .unwrap();
+        let png = read_png(&output).unwrap();
+        assert_eq!(chunk_types(&png), ["IHDR", "iCCP", "IDAT", "IEND"]);
+        // the input is left alone when writing elsewhere
+        assert_eq!(read_png(&file).unwrap().chunks().len(), 7);
+    }
+
+    #[test]
+    fn test_extract_chunks() {
+        let png = png_with_duplicates();
+        let file = write_testing_png("extract.png", &png);
+        let dir = temp_path("extract");
+        let _ = fs::remove_dir_all(&dir);
+        let args = [
+            "extract-chunks",
+            file.to_str().unwrap(),
+            "--out-dir",
+            dir.to_str().unwrap(),
+        ];
+        run_cli(&args).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), png.chunks().len());
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            let path = dir.join(format!("{:03}_{}.bin", index, chunk.chunk_type()));
+            assert_eq!(fs::read(path).unwrap(), chunk.data());
+        }
+        // existing files are only replaced with --force
+        assert!(run_cli(&args).is_err());
+        run_cli(&[&args[..], &["--force"]].concat()).unwrap();
+    }
+
+    #[test]
+    fn test_extract_chunks_type_filter() {
+        let file = write_testing_png("extract_type.png", &png_with_duplicates());
+        let dir = temp_path("extract_type");
+        let _ = fs::remove_dir_all(&dir);
+        run_cli(&[
+            "extract-chunks",
+            file.to_str().unwrap(),
+            "--out-dir",
+            dir.to_str().unwrap(),
+            "--type",
+            "ruSt",
+        ])
+        .unwrap();
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["002_ruSt.bin", "003_ruSt.bin", "004_ruSt.bin"]);
+    }
+
+    fn list_row(png: &Png, index: usize) -> String {
+        list_table(&indexed(png), SortKey::Offset, false)
+            .lines()
+            .nth(index + 1)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_inject() {
+        let file = write_testing_png("inject.png", &testing_png());
+        let data_file = temp_path("inject.bin");
+        fs::write(&data_file, b"profile\0\0\x78\x01").unwrap();
+        let file = file.to_str().unwrap();
+        let data_file = data_file.to_str().unwrap();
+        run_cli(&["inject", file, "iCCP", "--data-file", data_file]).unwrap();
+        run_cli(&[
+            "inject",
+            file,
+            "tEXt",
+            "--data-file",
+            data_file,
+            "--position",
+            "after-ihdr",
+        ])
+        .unwrap();
+        run_cli(&[
+            "inject",
+            file,
+            "ruSt",
+            "--data-file",
+            data_file,
+            "--position",
+            "index:2",
+        ])
+        .unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        assert_eq!(
+            chunk_types(&png),
+            ["IHDR", "tEXt", "ruSt", "IDAT", "iCCP", "IEND"]
+        );
+        assert!(list_row(&png, 4).contains("iCCP"));
+        assert_eq!(png.chunks()[4].data(), b"profile\0\0\x78\x01");
+        run_cli(&["verify", file]).unwrap();
+    }
+
+    #[test]
+    fn test_inject_critical() {
+        let png = testing_png();
+        let file = write_testing_png("inject_critical.png", &png);
+        let data_file = temp_path("inject_critical.bin");
+        fs::write(&data_file, b"data").unwrap();
+        let args = [
+            "inject",
+            file.to_str().unwrap(),
+            "RuSt",
+            "--data-file",
+            data_file.to_str().unwrap(),
+        ];
+        assert!(run_cli(&args).is_err());
+        assert_eq!(fs::read(&file).unwrap(), png.as_bytes());
+        run_cli(&[&args[..], &["--allow-critical"]].concat()).unwrap();
+        assert!(
+            run_cli(&[&args[..], &["--allow-critical", "--position", "index:9"]].concat()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_position_from_str() {
+        assert_eq!(Position::from_str("before-iend"), Ok(Position::BeforeIend));
+        assert_eq!(Position::from_str("after-ihdr"), Ok(Position::AfterIhdr));
+        assert_eq!(Position::from_str("before-idat"), Ok(Position::BeforeIdat));
+        assert_eq!(Position::from_str("index:3"), Ok(Position::Index(3)));
+        assert!(Position::from_str("index:x").is_err());
+        assert!(Position::from_str("middle").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let file = write_testing_png("diff_a.png", &png_with_duplicates());
+        let file = file.to_str().unwrap();
+        run_cli(&["diff", file, file, "--strict-order"]).unwrap();
+
+        let mut chunks = png_with_duplicates().chunks().clone();
+        chunks[4] = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"changed");
+        let other = write_testing_png("diff_b.png", &Png::from_chunks(chunks));
+        let error = run_cli(&["diff", file, other.to_str().unwrap(), "--data"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 1);
+
+        let missing = temp_path("diff_missing.png");
+        let error = run_cli(&["diff", file, missing.to_str().unwrap()]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 2);
+        assert!(
+            matches!(&error, PngSecretError::Io { path: Some(path), source }
+                if *path == missing && source.kind() == io::ErrorKind::NotFound),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_hex_diff() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let old = Chunk::new(chunk_type.clone(), b"0123456789abcdefSAME");
+        let new = Chunk::new(chunk_type, b"0123456789abcdexSAME");
+        let diff = hex_diff(&old, &new, 256);
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("  - 00000000"));
+        assert!(lines[1].starts_with("  + 00000000"));
+        assert!(lines[2].starts_with("    00000010"));
+    }
+
+    #[test]
+    fn test_capacity_report() {
+        // 8 byte header, 13 + 16 bytes of data and 3 * 12 bytes of overhead
+        let stats = testing_png().stats();
+        assert_eq!(
+            capacity_report(stats, None, 1024),
+            "file size: 73 bytes\nchunks: 3 (29 bytes of data)\nper chunk overhead: 12 bytes\n"
+        );
+        let report = capacity_report(stats, Some(100), 1024);
+        assert!(report.ends_with(
+            "payload: 100 bytes in 1 chunk(s) of at most 1024 bytes\nnew file size: 185 bytes (+153.42%)\n"
+        ));
+        let report = capacity_report(stats, Some(2500), 1024);
+        assert!(report.contains("in 3 chunk(s)"));
+        // with a part header in each
+        assert!(report.contains("new file size: 2672 bytes"));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let file = write_testing_png("capacity.png", &testing_png());
+        run_cli(&["capacity", file.to_str().unwrap(), "--payload-size", "10"]).unwrap();
+        assert!(run_cli(&["capacity", file.to_str().unwrap(), "--max-chunk-size", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_lsb_capacity() {
+        let capacity = |name: &str, ihdr: &[u8], args: &[&str]| {
+            let mut png = testing_png();
+            png.remove_chunk("IHDR").unwrap();
+            png.insert_chunk_at(0, Chunk::new(ChunkType::from_str("IHDR").unwrap(), ihdr))
+                .unwrap();
+            let file = write_testing_png(name, &png);
+            let file = file.to_str().unwrap();
+            run_cli(&[&["capacity", file, "--method", "lsb"][..], args].concat()).unwrap()
+        };
+        let rgb = [0, 0, 0, 100, 0, 0, 0, 100, 8, 2, 0, 0, 0];
+        let report = capacity("capacity_rgb.png", &rgb, &["--payload-size", "5000"]);
+        assert_eq!(report.get("capacity").unwrap().as_f64(), Some(3742.0));
+        assert_eq!(report.get("conversion"), Some(&Value::Null));
+        let payload = report.get("payload").unwrap();
+        assert_eq!(payload.get("fits").unwrap().as_bool(), Some(false));
+        let report = capacity(
+            "capacity_rgb.png",
+            &rgb,
+            &["--bits", "2", "--payload-size", "5000"],
+        );
+        assert_eq!(report.get("capacity").unwrap().as_f64(), Some(7492.0));
+        let payload = report.get("payload").unwrap();
+        assert_eq!(payload.get("fits").unwrap().as_bool(), Some(true));
+        let report = capacity("capacity_rgb.png", &rgb, &["--channels", "rgba"]);
+        assert_eq!(report.get("capacity").unwrap().as_f64(), Some(4992.0));
+
+        let gray = [0, 0, 0, 100, 0, 0, 0, 100, 8, 0, 0, 0, 0];
+        let report = capacity("capacity_gray.png", &gray, &[]);
+        assert!(report
+            .get("conversion")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .contains("gray"));
+        let file = write_testing_png("capacity_bits.png", &testing_png());
+        let file = file.to_str().unwrap();
+        assert!(run_cli(&["capacity", file, "--method", "lsb", "--bits", "5"]).is_err());
+        assert!(run_cli(&["capacity", file, "--bits", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.png", "dice.png"));
+        assert!(glob_match("*.png", ".png"));
+        assert!(!glob_match("*.png", "dice.jpg"));
+        assert!(glob_match("d?ce*", "dice.png"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn test_encode_dir() {
+        let dir = temp_path("encode_dir");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "b.png", "c.PNG"] {
+            fs::write(dir.join(name), testing_png().as_bytes()).unwrap();
+        }
+        fs::write(dir.join("d.jpg"), [0xff, 0xd8, 0xff, 0xe0]).unwrap();
+        let out_dir = dir.join("out");
+        let dir_arg = dir.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            dir_arg,
+            "ruSt",
+            "tracked",
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+        ])
+        .unwrap();
+        for name in ["a.png", "b.png", "c.PNG"] {
+            let png = read_png(&out_dir.join(name)).unwrap();
+            assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"tracked");
+            // the originals are left alone
+            assert!(read_png(&dir.join(name))
+                .unwrap()
+                .chunk_by_type("ruSt")
+                .is_none());
+        }
+        assert!(!out_dir.join("d.jpg").exists());
+
+        run_cli(&["encode", dir_arg, "ruSt", "in place", "--glob", "a*"]).unwrap();
+        assert!(read_png(&dir.join("a.png"))
+            .unwrap()
+            .chunk_by_type("ruSt")
+            .is_some());
+        assert!(read_png(&dir.join("b.png"))
+            .unwrap()
+            .chunk_by_type("ruSt")
+            .is_none());
+
+        fs::write(dir.join("broken.png"), b"not a png").unwrap();
+        assert!(run_cli(&["encode", dir_arg, "ruSt", "again"]).is_err());
+        assert_eq!(
+            read_png(&dir.join("b.png"))
+                .unwrap()
+                .chunk_by_type("ruSt")
+                .unwrap()
+                .data(),
+            b"again"
+        );
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let file = write_testing_png("atomic.png", &testing_png());
+        let original = fs::read(&file).unwrap();
+        // a writer that dies halfway through
+        let error = write_atomically(&file, |writer| {
+            writer.write_all(&original[..10])?;
+            Err(io::Error::other("disk on fire"))
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "disk on fire");
+        assert_eq!(fs::read(&file).unwrap(), original);
+        let leftovers = fs::read_dir(file.parent().unwrap())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(".atomic.png")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        write_atomically(&file, |writer| writer.write_all(b"replaced")).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"replaced");
+    }
+
+    #[test]
+    fn test_backup() {
+        let file = write_testing_png("backup.png", &testing_png());
+        let original = fs::read(&file).unwrap();
+        let file_arg = file.to_str().unwrap();
+        run_cli(&["encode", file_arg, "ruSt", "message", "--backup"]).unwrap();
+        assert_eq!(fs::read(temp_path("backup.png.bak")).unwrap(), original);
+
+        let encoded = fs::read(&file).unwrap();
+        run_cli(&["remove", file_arg, "ruSt", "--backup=.orig"]).unwrap();
+        assert_eq!(fs::read(temp_path("backup.png.orig")).unwrap(), encoded);
+        assert_eq!(fs::read(&file).unwrap(), original);
+
+        // no backup without the flag, or when writing elsewhere
+        let other = write_testing_png("backup_other.png", &testing_png());
+        let output = temp_path("backup_output.png");
+        run_cli(&[
+            "encode",
+            other.to_str().unwrap(),
+            "ruSt",
+            "m",
+            output.to_str().unwrap(),
+            "--backup",
+        ])
+        .unwrap();
+        run_cli(&["encode", other.to_str().unwrap(), "ruSt", "m"]).unwrap();
+        assert!(!temp_path("backup_other.png.bak").exists());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_verify_render() {
+        let file = write_testing_png("render.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let report = run_cli(&["encode", file, "ruSt", "hidden", "--verify-render"]).unwrap();
+        let render = report.get("file").unwrap().get("render").unwrap();
+        assert_eq!(render.get("width").unwrap().as_f64(), Some(1.0));
+        assert_eq!(render.get("pixels").unwrap().as_f64(), Some(1.0));
+        let report = run_cli(&["remove", file, "ruSt", "--verify-render"]).unwrap();
+        assert!(report.get("render").is_some());
+        let stripped = temp_path("render_stripped.png");
+        let report = run_cli(&[
+            "strip",
+            file,
+            "-o",
+            stripped.to_str().unwrap(),
+            "--verify-render",
+        ])
+        .unwrap();
+        assert_eq!(
+            report
+                .get("render")
+                .unwrap()
+                .get("height")
+                .unwrap()
+                .as_f64(),
+            Some(1.0)
+        );
+
+        // an IDAT that is no zlib stream, with a good crc
+        let mut png = testing_png();
+        png.remove_chunk("IDAT").unwrap();
+        png.insert_chunk_at(
+            1,
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), b"not deflate"),
+        )
+        .unwrap();
+        let broken = write_testing_png("render_broken.png", &png);
+        let original = fs::read(&broken).unwrap();
+        let output = temp_path("render_broken_out.png");
+        let error = run_cli(&[
+            "encode",
+            broken.to_str().unwrap(),
+            "ruSt",
+            "hidden",
+            output.to_str().unwrap(),
+            "--verify-render",
+        ])
+        .unwrap_err();
+        assert!(matches!(error, PngSecretError::RenderChanged { .. }));
+        assert_eq!(crate::exit::code(&error), 4);
+        assert!(!output.exists());
+        let error = run_cli(&[
+            "encode",
+            broken.to_str().unwrap(),
+            "ruSt",
+            "hidden",
+            "--verify-render",
+        ])
+        .unwrap_err();
+        assert!(error.to_string().contains("the original doesn't decode"));
+        assert_eq!(fs::read(&broken).unwrap(), original);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_encode_lsb() {
+        let file = write_testing_png("lsb.png", &rgba_png(8, 8));
+        let output = temp_path("lsb_out.png");
+        let report = run_cli(&[
+            "encode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "hidden in pixels",
+            output.to_str().unwrap(),
+            "--method",
+            "lsb",
+        ])
+        .unwrap();
+        let encoded = report.get("file").unwrap();
+        assert_eq!(encoded.get("length").unwrap().as_f64(), Some(16.0));
+        assert_eq!(encoded.get("capacity").unwrap().as_f64(), Some(16.0));
+        let written = read_png(&output).unwrap();
+        assert!(written.chunk_by_type("ruSt").is_none());
+        let hidden = image::load_from_memory(&written.as_bytes())
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(lsb::extract(&hidden, false).unwrap(), b"hidden in pixels");
+
+        let error = run_cli(&[
+            "encode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "too long for rgb pixels",
+            "--method",
+            "lsb",
+        ])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The pixels hold 16 bytes, the payload needs 23: 7 bytes short"
+        );
+        // with alpha there is room
+        run_cli(&[
+            "encode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "too long for rgb pixels",
+            "--method",
+            "lsb",
+            "--channels",
+            "rgba",
+        ])
+        .unwrap();
+    }
+
+    /// a png of `width` by `height` RGBA pixels the image crate wrote
+    #[cfg(feature = "image")]
+    fn rgba_png(width: u32, height: u32) -> Png {
+        let pixels: Vec<u8> = (0..width * height * 4).map(|i| (i * 13) as u8).collect();
+        let mut encoded = Vec::new();
+        image::ImageEncoder::write_image(
+            image::codecs::png::PngEncoder::new(&mut encoded),
+            &pixels,
+            width,
+            height,
+            image::ExtendedColorType::Rgba8,
+        )
+        .unwrap();
+        Png::try_from(&encoded[..]).unwrap()
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_decode_lsb() {
+        let file = write_testing_png("lsb_decode.png", &rgba_png(32, 32));
+        let file = file.to_str().unwrap();
+        let lsb = ["--method", "lsb", "--channels", "rgba"];
+        let error = run_cli(&[&["decode", file, "ruSt"][..], &lsb].concat()).unwrap_err();
+        assert!(matches!(error, PngSecretError::LsbNotFound));
+        assert_eq!(crate::exit::code(&error), 3);
+
+        let encode = ["encode", file, "ruSt", "in the pixels", "--compress"];
+        run_cli(&[&encode[..], &lsb, &["--password", "hunter2"]].concat()).unwrap();
+        let decode = |file: &str| {
+            let args = [
+                &["decode", file, "ruSt"][..],
+                &lsb,
+                &["--password", "hunter2"],
+            ];
+            let report = run_cli(&args.concat()).unwrap();
+            let header = report.get("header").unwrap();
+            assert_eq!(header.get("encrypted").unwrap().as_bool(), Some(true));
+            let payload = report.get("payload").unwrap().as_str().unwrap();
+            assert_eq!(base64::decode(payload).unwrap(), b"in the pixels");
+        };
+        decode(file);
+        let error = run_cli(&["decode", file, "ruSt", "--method", "lsb"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::LsbNotFound));
+
+        // the pixels survive a lossless re-save with other compression and filters
+        use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+        let pixels = image::open(file).unwrap().to_rgba8();
+        let mut resaved = Vec::new();
+        let encoder =
+            PngEncoder::new_with_quality(&mut resaved, CompressionType::Best, FilterType::Paeth);
+        pixels.write_with_encoder(encoder).unwrap();
+        assert_ne!(resaved, fs::read(file).unwrap());
+        let path = temp_path("lsb_resaved.png");
+        fs::write(&path, resaved).unwrap();
+        decode(path.to_str().unwrap());
+    }
+
+    #[test]
+    fn test_stealth() {
+        let file = write_testing_png("stealth.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let cheap = ["--kdf-params", "8,1,1"];
+        let message = "spread thin ".repeat(1000);
+        let encode = |message: &str, password: &str| {
+            let args = [
+                &["encode", file, "ruSt", message, "--stealth", "--compress=0"][..],
+                &["--password", password],
+                &cheap,
+            ];
+            run_cli(&args.concat()).unwrap()
+        };
+        let report = encode(&message, "hunter2");
+        let chunks = report.get("file").unwrap().get("chunks").unwrap();
+        assert_eq!(chunks.as_array().unwrap().len(), 3);
+        encode("another one", "swordfish");
+        let png = read_png(Path::new(file)).unwrap();
+        assert!(png.chunk_by_type("ruSt").is_none());
+        let stealthy: Vec<usize> = (0..png.chunks().len())
+            .filter(|&index| {
+                stealth::TYPES.contains(&&*png.chunks()[index].chunk_type().to_string())
+            })
+            .collect();
+        assert_eq!(stealthy.len(), 6);
+
+        let decode = |password: &str| {
+            run_cli(
+                &[
+                    &["decode", file, "ruSt", "--stealth", "--password", password][..],
+                    &cheap,
+                ]
+                .concat(),
+            )
+        };
+        let report = decode("hunter2").unwrap();
+        let payload = report.get("payload").unwrap().as_str().unwrap();
+        assert_eq!(base64::decode(payload).unwrap(), message.as_bytes());
+        let header = report.get("header").unwrap();
+        assert_eq!(header.get("encrypted").unwrap().as_bool(), Some(false));
+        let report = decode("swordfish").unwrap();
+        let payload = report.get("payload").unwrap().as_str().unwrap();
+        assert_eq!(base64::decode(payload).unwrap(), b"another one");
+        let error = decode("wrong").unwrap_err();
+        assert!(matches!(error, PngSecretError::StealthNotFound));
+        assert_eq!(crate::exit::code(&error), 3);
+
+        // without a password every stealth chunk looks the same on its own, and none of
+        // them are tied to another
+        let findings = scan::scan(&png);
+        assert!(findings
+            .iter()
+            .all(|finding| stealthy.contains(&finding.chunk_index)));
+        assert!(findings
+            .iter()
+            .all(|finding| !finding.reason.contains("share")));
+        for &index in &stealthy {
+            let name = png.chunks()[index].chunk_type().to_string();
+            let severities: Vec<scan::Severity> = findings
+                .iter()
+                .filter(|finding| finding.chunk_index == index)
+                .map(|finding| finding.severity)
+                .collect();
+            // random bytes that happen to start like a zlib stream pass for one
+            let expected: &[scan::Severity] =
+                match scan::is_known_binary(png.chunks()[index].data()) {
+                    true => &[scan::Severity::Medium],
+                    false => &[scan::Severity::Medium, scan::Severity::High],
+                };
+            assert_eq!(severities, expected, "{}", name);
+        }
+        let lengths: Vec<u32> = stealthy
+            .iter()
+            .map(|&index| png.chunks()[index].length())
+            .collect();
+        assert!(lengths[1..].iter().any(|&length| length != lengths[0]));
+        // no header in the clear, the same in every part
+        assert!(stealthy
+            .iter()
+            .all(|&index| !crypto::is_encrypted(png.chunks()[index].data())));
+        let starts: Vec<&[u8]> = stealthy
+            .iter()
+            .map(|&index| &png.chunks()[index].data()[..8])
+            .collect();
+        assert!(starts[1..].iter().all(|&start| start != starts[0]));
+        // the parameters aren't in the parts, without them the key is another
+        let error =
+            run_cli(&["decode", file, "ruSt", "--stealth", "--password", "hunter2"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::StealthNotFound));
+
+        assert!(run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "x",
+            "--stealth",
+            "--position",
+            "after-ihdr"
+        ])
+        .is_err());
+        assert!(run_cli(&["decode", file, "ruSt", "--stealth", "--all"]).is_err());
+    }
+
+    #[test]
+    fn test_pad() {
+        let message = "a secret of some length";
+        let encode = |name: &str, message: &str, pad: &str| {
+            let file = write_testing_png(name, &testing_png());
+            let file = file.to_str().unwrap().to_string();
+            let args = [
+                &["encode", &file, "ruSt", message, "--pad", pad][..],
+                &["--password", "pw", "--kdf-params", "8,1,1", "--compress"],
+            ];
+            run_cli(&args.concat()).unwrap();
+            let png = read_png(Path::new(&file)).unwrap();
+            let length = png.chunk_by_type("ruSt").unwrap().length();
+            let report = run_cli(&["decode", &file, "ruSt", "--password", "pw"]).unwrap();
+            let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+            let header = decoded.get("header").unwrap();
+            assert_eq!(header.get("padded").unwrap().as_bool(), Some(pad != "none"));
+            let payload = decoded.get("payload").unwrap().as_str().unwrap();
+            assert_eq!(base64::decode(payload).unwrap(), message.as_bytes());
+            length
+        };
+        let unpadded = encode("pad_none.png", message, "none");
+        let block = encode("pad_block.png", message, "block:256");
+        assert!(block > unpadded);
+        // messages of other lengths fill the same block
+        assert_eq!(encode("pad_block_short.png", "short", "block:256"), block);
+        // the slots of a password payload are padded to 16 bytes, the length --pad puts
+        // in front of this message fits in what they had spare
+        assert_eq!(encode("pad_block_one.png", message, "block:1"), unpadded);
+        let random: Vec<u32> = (0..3)
+            .map(|_| encode("pad_random.png", message, "random:4096"))
+            .collect();
+        assert!(random.iter().all(|&length| length >= unpadded));
+        assert!(random[1..].iter().any(|&length| length != random[0]));
+        assert_eq!(encode("pad_random_zero.png", message, "random:0"), unpadded);
+
+        let file = write_testing_png("pad_plain.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let error = run_cli(&["encode", file, "ruSt", "x", "--pad", "block:16"]).unwrap_err();
+        assert!(error.to_string().starts_with("--pad hides the length"));
+        assert!(run_cli(&["encode", file, "ruSt", "x", "--pad", "block:0"]).is_err());
+        assert!(run_cli(&["encode", file, "ruSt", "x", "--pad", "more"]).is_err());
+        let stealth = ["--stealth", "--password", "pw", "--kdf-params", "8,1,1"];
+        run_cli(
+            &[
+                &["encode", file, "ruSt", message, "--pad", "random:64"][..],
+                &stealth,
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let report = run_cli(&[&["decode", file, "ruSt"][..], &stealth].concat()).unwrap();
+        let payload = report.get("payload").unwrap().as_str().unwrap();
+        assert_eq!(base64::decode(payload).unwrap(), message.as_bytes());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let encode = |name: &str, args: &[&str]| {
+            let file = write_testing_png(name, &testing_png());
+            let encode = ["encode", file.to_str().unwrap(), "ruSt", "reproducible"];
+            let password = ["--password", "pw", "--kdf-params", "8,1,1"];
+            run_cli(&[&encode[..], &password, args].concat()).unwrap();
+            fs::read(file).unwrap()
+        };
+        for args in [
+            &["--pad", "random:512"][..],
+            &["--stealth", "--noise", "2", "--pad", "random:512"],
+        ] {
+            let seeded = |name: &str, seed: &str| {
+                encode(
+                    name,
+                    &[&["--deterministic", "--seed", seed][..], args].concat(),
+                )
+            };
+            let first = seeded("deterministic.png", "build 1");
+            assert_eq!(seeded("deterministic_again.png", "build 1"), first);
+            assert_ne!(seeded("deterministic_other.png", "build 2"), first);
+            assert_ne!(encode("deterministic_not.png", args), first);
+        }
+
+        let file = write_testing_png("deterministic_seedless.png", &testing_png());
+        let file = file.to_str().unwrap();
+        assert!(run_cli(&["encode", file, "ruSt", "x", "--deterministic"]).is_err());
+    }
+
+    #[test]
+    fn test_noise() {
+        let original = testing_png();
+        let encode = |name: &str, args: &[&str]| {
+            let file = write_testing_png(name, &original);
+            let file = file.to_str().unwrap().to_string();
+            let encode = ["encode", &file, "ruSt", "the real one", "--noise", "4"];
+            let report = run_cli(&[&encode[..], args].concat()).unwrap();
+            (file, report)
+        };
+        let (file, report) = encode("noise.png", &["--seed", "7"]);
+        let chunks = report.get("file").unwrap().get("chunks").unwrap();
+        assert_eq!(chunks.as_array().unwrap().len(), 5);
+        assert!(report.get("file").unwrap().get("chunk").is_none());
+        let png = read_png(Path::new(&file)).unwrap();
+        assert_eq!(png.chunks().len(), original.chunks().len() + 5);
+        run_cli(&["verify", &file]).unwrap();
+        let report = run_cli(&["decode", &file, "ruSt"]).unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        let payload = decoded.get("payload").unwrap().as_str().unwrap();
+        assert_eq!(base64::decode(payload).unwrap(), b"the real one");
+
+        // the same seed makes the same noise, without one it differs
+        let (again, _) = encode("noise_again.png", &["--seed", "7"]);
+        assert_eq!(fs::read(&file).unwrap(), fs::read(&again).unwrap());
+        let (unseeded, _) = encode("noise_unseeded.png", &[]);
+        assert_ne!(fs::read(&file).unwrap(), fs::read(&unseeded).unwrap());
+
+        let stealth = ["--stealth", "--password", "pw", "--kdf-params", "8,1,1"];
+        let (file, report) = encode("noise_stealth.png", &stealth);
+        let chunks = report.get("file").unwrap().get("chunks").unwrap();
+        assert_eq!(chunks.as_array().unwrap().len(), 3 + 4);
+        run_cli(&["verify", &file]).unwrap();
+        let report = run_cli(&[&["decode", &file, "ruSt"][..], &stealth].concat()).unwrap();
+        let payload = report.get("payload").unwrap().as_str().unwrap();
+        assert_eq!(base64::decode(payload).unwrap(), b"the real one");
+        // parts and noise alike start with the number of recipients
+        let recipient = Identity::generate().unwrap().recipient().to_string();
+        let (file, _) = encode(
+            "noise_recipients.png",
+            &["--stealth", "--recipient", &recipient],
+        );
+        let png = read_png(Path::new(&file)).unwrap();
+        let stealthy: Vec<&Chunk> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| stealth::TYPES.contains(&&*chunk.chunk_type().to_string()))
+            .collect();
+        assert_eq!(stealthy.len(), 3 + 4);
+        assert!(stealthy.iter().all(|chunk| chunk.data()[0] == 1));
+
+        let file = write_testing_png("noise_seed.png", &original);
+        let file = file.to_str().unwrap();
+        assert!(run_cli(&["encode", file, "ruSt", "x", "--seed", "7"]).is_err());
+        assert!(run_cli(&["encode", file, "ruSt", "x", "--noise", "10"]).is_err());
+    }
+
+    #[cfg(not(feature = "image"))]
+    #[test]
+    fn test_image_feature_unavailable() {
+        let file = write_testing_png("render_unavailable.png", &testing_png());
+        let original = fs::read(&file).unwrap();
+        let file = file.to_str().unwrap();
+        let error = run_cli(&["encode", file, "ruSt", "hidden", "--verify-render"]).unwrap_err();
+        assert_eq!(error.to_string(), render::UNAVAILABLE);
+        let error = run_cli(&["encode", file, "ruSt", "hidden", "--method", "lsb"]).unwrap_err();
+        assert_eq!(error.to_string(), lsb::UNAVAILABLE);
+        let error = run_cli(&["decode", file, "ruSt", "--method", "lsb"]).unwrap_err();
+        assert_eq!(error.to_string(), lsb::UNAVAILABLE);
+        assert_eq!(fs::read(file).unwrap(), original);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let file = write_testing_png("dry_run.png", &png_with_duplicates());
+        let original = fs::read(&file).unwrap();
+        let file_arg = file.to_str().unwrap();
+        run_cli(&["remove", file_arg, "ruSt", "--all", "--dry-run"]).unwrap();
+        run_cli(&["encode", file_arg, "ruSt", "more", "--dry-run"]).unwrap();
+        run_cli(&["strip", file_arg, "--dry-run"]).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), original);
+        // still fails the way the real run would
+        let error = run_cli(&["remove", file_arg, "ruSt", "--nth", "5", "--dry-run"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 7);
+        assert!(run_cli(&["remove", file_arg, "nOpE", "--dry-run"]).is_err());
+        assert_eq!(fs::read(&file).unwrap(), original);
+    }
+
+    #[test]
+    fn test_overwrite_protection() {
+        let file = write_testing_png("overwrite.png", &testing_png());
+        let file_arg = file.to_str().unwrap();
+        let output = temp_path("overwrite_out.png");
+        let _ = fs::remove_file(&output);
+        let output_arg = output.to_str().unwrap();
+        run_cli(&["encode", file_arg, "ruSt", "first", output_arg]).unwrap();
+        let first = fs::read(&output).unwrap();
+
+        let error = run_cli(&["encode", file_arg, "ruSt", "second", output_arg]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{} already exists, use --force to overwrite",
+                output.display()
+            )
+        );
+        assert_eq!(fs::read(&output).unwrap(), first);
+        assert!(run_cli(&["strip", file_arg, "-o", output_arg]).is_err());
+        assert!(run_cli(&["decode", file_arg, "IHDR", "-o", output_arg]).is_err());
+        assert_eq!(fs::read(&output).unwrap(), first);
+
+        run_cli(&["encode", file_arg, "ruSt", "second", output_arg, "-f"]).unwrap();
+        assert_eq!(
+            read_png(&output)
+                .unwrap()
+                .chunk_by_type("ruSt")
+                .unwrap()
+                .data(),
+            b"second"
+        );
+        // changing the input in place needs no --force
+        run_cli(&["encode", file_arg, "ruSt", "in place"]).unwrap();
+    }
+
+    #[test]
+    fn test_message_file() {
+        let file = write_testing_png("message_file.png", &testing_png());
+        let file_arg = file.to_str().unwrap();
+        let message_file = temp_path("message.txt");
+        let text = "first \"quoted\" line\nsecond line, ünïcødé 🦀\n";
+        fs::write(&message_file, text).unwrap();
+        let message_arg = message_file.to_str().unwrap();
+
+        run_cli(&["encode", file_arg, "ruSt", "--message-file", message_arg]).unwrap();
+        let png = read_png(&file).unwrap();
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), text.as_bytes());
+
+        run_cli(&[
+            "encode",
+            file_arg,
+            "ruSt",
+            "--message-file",
+            message_arg,
+            "--trim",
+        ])
+        .unwrap();
+        let png = read_png(&file).unwrap();
+        assert_eq!(
+            png.chunks_by_type("ruSt")[1].1.data(),
+            text.trim_end_matches('\n').as_bytes()
+        );
+
+        assert!(run_cli(&[
+            "encode",
+            file_arg,
+            "ruSt",
+            "message",
+            "--message-file",
+            message_arg
+        ])
+        .is_err());
+        assert!(run_cli(&["encode", file_arg, "ruSt"]).is_err());
+        fs::write(&message_file, [0xff, 0xfe]).unwrap();
+        assert!(run_cli(&["encode", file_arg, "ruSt", "--message-file", message_arg]).is_err());
+    }
+
+    #[test]
+    fn test_edit_message() {
+        let script = temp_path("editor.sh");
+        fs::write(
+            &script,
+            "#!/bin/sh\nprintf 'from the editor\\nline two\\n' > \"$1\"\n",
+        )
+        .unwrap();
+        let editor = format!("sh {}", script.display());
+        assert_eq!(
+            edit_message(&editor).unwrap(),
+            "from the editor\nline two\n"
+        );
+        assert!(edit_message("true").is_err());
+        assert!(edit_message("false").is_err());
+        // the file and its directory are the owner's only, and gone afterwards
+        #[cfg(unix)]
+        {
+            fs::write(
+                &script,
+                "#!/bin/sh\n{ ls -ld \"$1\"; ls -ld \"$(dirname \"$1\")\"; } > \"$1.ls\"\nmv \"$1.ls\" \"$1\"\n",
+            )
+            .unwrap();
+            let listing = edit_message(&editor).unwrap();
+            let lines: Vec<&str> = listing.lines().collect();
+            assert!(lines[0].starts_with("-rw-------"), "{}", listing);
+            assert!(lines[1].starts_with("drwx------"), "{}", listing);
+            let path = lines[0].split_whitespace().last().unwrap();
+            assert!(!std::path::Path::new(path).parent().unwrap().exists());
+        }
+        assert!(edit_message("pngsecret-no-such-editor").is_err());
+    }
+
+    #[test]
+    fn test_missing_message_without_terminal() {
+        // cargo test doesn't give the tests a terminal, so there is nobody to prompt
+        if io::stdin().is_terminal() {
+            return;
+        }
+        let file = write_testing_png("no_message.png", &testing_png());
+        let error = run_cli(&["encode", file.to_str().unwrap(), "ruSt"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No message given, pass one or use --message-file"
+        );
+    }
+
+    #[test]
+    fn test_chunk_text_limit() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"a fairly long message",
+        );
+        let header = format!(
+            "Chunk:       ruSt\nData length: 21\nCrc:         {:08x}\n",
+            chunk.crc()
+        );
+        assert_eq!(
+            chunk_text(&chunk, None, false, None, false),
+            format!("{}Data:        a fairly long message\n", header)
+        );
+        assert_eq!(
+            chunk_text(&chunk, Some(8), false, None, false),
+            format!("{}Data:        a fairly\n… (13 more bytes)\n", header)
+        );
+        let hex = chunk_text(&chunk, Some(4), true, None, false);
+        assert!(hex.starts_with(&header));
+        assert!(hex.ends_with("|a fa|\n… (17 more bytes)\n"));
+
+        // the metadata stays, the data goes
+        for hex in [false, true] {
+            let text = chunk_text(&chunk, Some(0), hex, None, false);
+            assert!(!text.contains("fairly"));
+            assert!(text.contains("21"));
+            assert!(text.contains("… (21 more bytes)"));
+        }
+    }
+
+    #[test]
+    fn test_color() {
+        let png = png_with_duplicates();
+        for hex in [false, true] {
+            assert!(!chunk_text(&png.chunks()[0], None, hex, None, false).contains('\x1b'));
+            assert!(
+                chunk_text(&png.chunks()[0], None, hex, None, true).contains("\x1b[31mIHDR\x1b[0m")
+            );
+        }
+        assert!(!list_table(&indexed(&png), SortKey::Offset, false).contains('\x1b'));
+        let table = list_table(&indexed(&png), SortKey::Offset, true);
+        assert!(table.contains("\x1b[33mruSt\x1b[0m"));
+        assert!(table.contains(&format!("\x1b[2m{:08x}\x1b[0m", png.chunks()[0].crc())));
+        assert!(Cli::try_parse_from(["pngsecret", "list", "x.png", "--color", "never"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["pngsecret", "--color", "sometimes", "list", "x.png"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_verbose_logging() {
+        let file = write_testing_png("verbose.png", &testing_png());
+        let cli = Cli::try_parse_from([
+            "pngsecret",
+            "encode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "message",
+            "-vv",
+        ])
+        .unwrap();
+        let level = Level::from_flags(cli.verbose, cli.quiet);
+        let (result, lines) = log::capture::capture(level, || run(&cli.commands));
+        result.unwrap();
+        let expected = [
+            format!("debug: read file path={} length=73", file.display()),
+            "debug: png signature ok".to_string(),
+            "debug: parsed chunk chunk_type=IHDR length=13 offset=8".to_string(),
+            "debug: parsed chunk chunk_type=IDAT length=16 offset=33".to_string(),
+            "debug: parsed chunk chunk_type=IEND length=0 offset=61".to_string(),
+            "debug: inserted chunk chunk_type=ruSt index=2 length=7".to_string(),
+            format!("debug: wrote file path={} length=92", file.display()),
+        ];
+        let debug_lines: Vec<&String> = lines
+            .iter()
+            .filter(|line| line.starts_with("debug"))
+            .collect();
+        assert_eq!(debug_lines, expected.iter().collect::<Vec<_>>());
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("trace: chunk crc chunk_type=IHDR crc=")));
+        // the span of the command, with its file
+        assert_eq!(
+            lines[0],
+            format!("span: command name=encode path={}", file.display())
+        );
+    }
+
+    #[test]
+    fn test_quiet_conflicts_with_verbose() {
+        assert!(Cli::try_parse_from(["pngsecret", "list", "x.png", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_type_parsed_with_arguments() {
+        let parse = |command: &str, chunk_type: &str| {
+            let mut args = vec!["pngsecret", command, "missing.png", chunk_type];
+            if command == "encode" {
+                args.push("message");
+            }
+            Cli::try_parse_from(args)
+        };
+        for command in ["encode", "decode", "remove"] {
+            let error = parse(command, "Ru5t").err().unwrap();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+            assert!(error
+                .to_string()
+                .contains("String should contain letters only"));
+            assert!(parse(command, "RuSt").is_ok());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserve_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let file = write_testing_png("permissions.png", &testing_png());
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        let file_arg = file.to_str().unwrap();
+        run_cli(&["encode", file_arg, "ruSt", "message"]).unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&file), 0o640);
+        run_cli(&["remove", file_arg, "ruSt"]).unwrap();
+        assert_eq!(mode(&file), 0o640);
+    }
+
+    #[test]
+    fn test_preserve_times() {
+        let file = write_testing_png("times.png", &testing_png());
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        restore_modified(&file, Some(old)).unwrap();
+        let file_arg = file.to_str().unwrap();
+        let modified = |path: &Path| fs::metadata(path).unwrap().modified().unwrap();
+
+        run_cli(&["encode", file_arg, "ruSt", "message", "--preserve-times"]).unwrap();
+        assert_eq!(modified(&file), old);
+        run_cli(&["remove", file_arg, "ruSt", "--preserve-times"]).unwrap();
+        assert_eq!(modified(&file), old);
+        run_cli(&["encode", file_arg, "ruSt", "message"]).unwrap();
+        assert_ne!(modified(&file), old);
+    }
+
+    #[test]
+    fn test_encode_position() {
+        let png = testing_png();
+        for (position, index) in [
+            ("before-iend", 2),
+            ("after-ihdr", 1),
+            ("before-idat", 1),
+            ("index:0", 0),
+            ("index:3", 3),
+        ] {
+            let file = write_testing_png("encode_position.png", &png);
+            let file = file.to_str().unwrap();
+            run_cli(&["encode", file, "ruSt", "message", "--position", position]).unwrap();
+            let encoded = read_png(Path::new(file)).unwrap();
+            let row = list_row(&encoded, index);
+            assert!(row.contains("ruSt"), "{}: {}", position, row);
+            assert!(row.contains(&format!(" {} ", png.chunk_offset(index))));
+        }
+    }
+
+    #[test]
+    fn test_encode_invalid_position() {
+        let file = write_testing_png("encode_invalid_position.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let error = run_cli(&["encode", file, "ruSt", "message", "--position", "index:4"]);
+        assert!(error.unwrap_err().to_string().contains("past the end"));
+
+        let mut no_idat = testing_png();
+        no_idat.remove_chunk("IDAT").unwrap();
+        let file = write_testing_png("encode_no_idat.png", &no_idat);
+        let args = ["encode", file.to_str().unwrap(), "ruSt", "message"];
+        let error = run_cli(&[&args[..], &["--position", "before-idat"]].concat());
+        assert_eq!(error.unwrap_err().to_string(), "No IDAT chunk found");
+        assert_eq!(fs::read(&file).unwrap(), no_idat.as_bytes());
+    }
+
+    #[test]
+    fn test_encode_message_hex() {
+        let file = write_testing_png("encode_message_hex.png", &testing_png());
+        let path = file.to_str().unwrap();
+        run_cli(&["encode", path, "ruSt", "--message-hex", "0xdead beef00ff"]).unwrap();
+        let png = read_png(&file).unwrap();
+        let report = run_cli(&["--format", "json", "decode", path, "ruSt"]).unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        let payload = decoded.get("payload").unwrap().as_str().unwrap();
+        assert_eq!(
+            base64::decode(payload).unwrap(),
+            [0xde, 0xad, 0xbe, 0xef, 0x00, 0xff]
+        );
+
+        let output = temp_path("encode_message_hex.out");
+        let output = output.to_str().unwrap();
+        run_cli(&["decode", path, "ruSt", "--hex", "-o", output, "-f"]).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), "deadbeef00ff");
+        run_cli(&["decode", path, "ruSt", "--hex"]).unwrap();
+        assert!(run_cli(&["decode", path, "ruSt", "--hex", "--base64"]).is_err());
+
+        for bad in ["abc", "dead bexf"] {
+            let error = run_cli(&["encode", path, "ruSt", "--message-hex", bad]).unwrap_err();
+            assert!(error.to_string().contains("hex"), "{}", error);
+        }
+        assert_eq!(fs::read(&file).unwrap(), png.as_bytes());
+        assert!(run_cli(&["encode", path, "ruSt", "text", "--message-hex", "00"]).is_err());
+    }
+
+    #[test]
+    fn test_encode_replace() {
+        let file = write_testing_png("encode_replace.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let encode = |args: &[&str]| {
+            let args: Vec<&str> = ["pngsecret", "encode", file]
+                .iter()
+                .chain(args)
+                .copied()
+                .collect();
+            let cli = Cli::try_parse_from(args).unwrap();
+            log::capture::capture(Level::Info, || run(&cli.commands))
+        };
+        let rust_chunks = || {
+            read_png(Path::new(file))
+                .unwrap()
+                .chunks_by_type("ruSt")
+                .len()
+        };
+        let decoded = || {
+            let report = run_cli(&["--format", "json", "decode", file, "ruSt"]).unwrap();
+            let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+            chunk.get("payload").unwrap().as_str().unwrap().to_string()
+        };
+
+        assert!(encode(&["ruSt", "older", "--replace"]).0.is_err());
+        encode(&["ruSt", "older", "--replace-or-append"]).0.unwrap();
+        assert_eq!(rust_chunks(), 1);
+        let (result, lines) = encode(&["ruSt", "newer", "--replace"]);
+        assert_eq!(
+            result
+                .unwrap()
+                .get("file")
+                .unwrap()
+                .get("replaced")
+                .unwrap()
+                .as_bool(),
+            Some(true)
+        );
+        assert!(!lines.iter().any(|line| line.starts_with("warning")));
+        assert_eq!(rust_chunks(), 1);
+        assert_eq!(decoded(), "bmV3ZXI=");
+        encode(&["ruSt", "newest", "--replace-or-append"])
+            .0
+            .unwrap();
+        assert_eq!(rust_chunks(), 1);
+        assert_eq!(decoded(), "bmV3ZXN0");
+
+        let (result, lines) = encode(&["ruSt", "another"]);
+        result.unwrap();
+        assert_eq!(rust_chunks(), 2);
+        assert!(lines.contains(&format!(
+            "warning: {} has a ruSt chunk already, decode reads the first one, --replace updates it",
+            file
+        )));
+        // both go, the first one is where the newer payload ends up
+        encode(&["ruSt", "last", "--replace"]).0.unwrap();
+        assert_eq!(rust_chunks(), 1);
+        assert_eq!(decoded(), "bGFzdA==");
+
+        // a text chunk only takes the place of the one with its keyword
+        encode(&["tEXt", "one", "--text-keyword", "Comment"])
+            .0
+            .unwrap();
+        encode(&[
+            "tEXt",
+            "two",
+            "--text-keyword",
+            "Title",
+            "--replace-or-append",
+        ])
+        .0
+        .unwrap();
+        encode(&["tEXt", "three", "--text-keyword", "Title", "--replace"])
+            .0
+            .unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        let texts: Vec<&[u8]> = png
+            .chunks_by_type("tEXt")
+            .iter()
+            .map(|(_, chunk)| chunk.data())
+            .collect();
+        assert_eq!(texts, [&b"Comment\0one"[..], b"Title\0three"]);
+    }
+
+    #[test]
+    fn test_removed_text() {
+        let testing_chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        let text = removed_text(&testing_chunk("ruSt", b"hidden message"), None, false);
+        assert!(text.contains("Chunk:       ruSt\nData length: 14\n"));
+        assert!(text.ends_with("Data:        hidden message\n"));
+        let binary = testing_chunk("ruSt", &[0xff, 0xfe, 0, 1]);
+        assert!(removed_text(&binary, None, false).contains("ff fe 00 01"));
+        let limited = removed_text(&testing_chunk("ruSt", b"hidden message"), Some(6), false);
+        assert!(limited.ends_with("Data:        hidden\n… (8 more bytes)\n"));
+    }
+
+    #[test]
+    fn test_remove_save_removed() {
+        let file = write_testing_png("save_removed.png", &png_with_duplicates());
+        let file = file.to_str().unwrap();
+        let dir = temp_path("save_removed");
+        let dir_arg = dir.to_str().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        let png = png_with_duplicates();
+        let removed = png.chunks_by_type("ruSt");
+        run_cli(&[
+            "remove",
+            file,
+            "ruSt",
+            "--all",
+            "--print-removed",
+            "--save-removed",
+            dir_arg,
+        ])
+        .unwrap();
+        for (index, chunk) in &removed {
+            let saved = fs::read(dir.join(format!("{:03}_ruSt.bin", index))).unwrap();
+            assert_eq!(saved, chunk.data());
+        }
+        // a second run would write the same names, which needs --force
+        let file = write_testing_png("save_removed.png", &png);
+        let file = file.to_str().unwrap();
+        let remove = ["remove", file, "ruSt", "--save-removed", dir_arg];
+        let error = run_cli(&remove).unwrap_err();
+        assert!(matches!(error, PngSecretError::Refused { .. }));
+        assert_eq!(
+            Png::try_from(fs::read(file).unwrap().as_ref())
+                .unwrap()
+                .as_bytes(),
+            png.as_bytes()
+        );
+        run_cli(&[&remove[..], &["--force"]].concat()).unwrap();
+        assert!(run_cli(&["remove", file, "ruSt", "--limit", "4"]).is_err());
+    }
+
+    fn print_args(args: &[&str]) -> PrintArgs {
+        let cli = Cli::try_parse_from([&["pngsecret", "print", "f.png"], args].concat()).unwrap();
+        match cli.commands {
+            Commands::Print(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let file = write_testing_png("ignore_case.png", &png_with_duplicates());
+        let path = file.to_str().unwrap();
+        assert!(run_cli(&["decode", path, "RUST"]).is_err());
+        let report =
+            run_cli(&["--format", "json", "decode", path, "RUST", "--ignore-case"]).unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(decoded.get("type").unwrap().as_str(), Some("ruSt"));
+        assert_eq!(decoded.get("payload").unwrap().as_str(), Some("Zmlyc3Q="));
+
+        assert!(run_cli(&["has", path, "RUST"]).is_err());
+        run_cli(&["has", path, "RUST", "--ignore-case"]).unwrap();
+
+        let png = png_with_duplicates();
+        let types = |args: &[&str]| filter_chunks(&png, &print_args(args)).len();
+        assert_eq!(types(&["--type", "RUST"]), 0);
+        assert_eq!(types(&["--type", "RUST", "--ignore-case"]), 3);
+        assert_eq!(types(&["--exclude-type", "RuSt", "--ignore-case"]), 3);
+
+        assert!(run_cli(&["remove", path, "RUST", "--nth", "1"]).is_err());
+        run_cli(&["remove", path, "RUST", "--nth", "1", "--ignore-case"]).unwrap();
+        assert_eq!(remaining_messages(&file), ["first", "third"]);
+    }
+
+    #[test]
+    fn test_encoding() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"caf\xe9");
+        let data = |encoding| {
+            let text = chunk_text(&chunk, None, false, encoding, false);
+            text.lines().last().unwrap().to_string()
+        };
+        assert_eq!(data(Some(Encoding::Latin1)), "Data:        café");
+        assert_eq!(data(Some(Encoding::Utf8)), "Data:        caf\u{fffd}");
+        assert_eq!(data(None), "Data:        caf\u{fffd}");
+        assert_eq!(data(Some(Encoding::Hex)), "Data:        636166e9");
+
+        let mut png = testing_png();
+        png.append_chunk(chunk.clone());
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("tEXt").unwrap(),
+            b"Title\0caf\xe9",
+        ));
+        let file = write_testing_png("encoding.png", &png);
+        let path = file.to_str().unwrap();
+        run_cli(&["decode", path, "ruSt", "--encoding", "latin1"]).unwrap();
+        let text = |encoding: &[&str]| {
+            let output = temp_path("encoding.txt");
+            let output = output.to_str().unwrap();
+            let args = [
+                "decode",
+                path,
+                "tEXt",
+                "--text-keyword",
+                "Title",
+                "-o",
+                output,
+                "-f",
+            ];
+            run_cli(&[&args[..], encoding].concat()).unwrap();
+            fs::read_to_string(output).unwrap()
+        };
+        // tEXt is Latin-1 unless told otherwise
+        assert_eq!(text(&[]), "café");
+        assert_eq!(text(&["--encoding", "utf8"]), "caf\u{fffd}");
+        assert!(run_cli(&["decode", path, "ruSt", "--encoding", "utf8", "--hex"]).is_err());
+    }
+
+    #[test]
+    fn test_print_category_filters() {
+        let mut png = png_with_duplicates();
+        png.append_chunk(Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"public"));
+        let types = |args: &[&str]| {
+            filter_chunks(&png, &print_args(args))
+                .iter()
+                .map(|(_, chunk)| chunk.chunk_type().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(types(&["--critical-only"]), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(
+            types(&["--ancillary-only"]),
+            ["ruSt", "ruSt", "ruSt", "tEXt"]
+        );
+        assert_eq!(types(&["--ancillary-only", "--type", "tEXt"]), ["tEXt"]);
+        assert!(types(&["--critical-only", "--type", "ruSt"]).is_empty());
+        assert!(Cli::try_parse_from([
+            "pngsecret",
+            "print",
+            "f",
+            "--critical-only",
+            "--ancillary-only"
+        ])
+        .is_err());
+
+        assert_eq!(
+            visibility_line(&png.chunks()[3], false),
+            "Visibility:  private\n"
+        );
+        assert_eq!(
+            visibility_line(&png.chunks()[6], false),
+            "Visibility:  public\n"
+        );
+        let file = write_testing_png("print_categories.png", &png);
+        let file = file.to_str().unwrap();
+        run_cli(&["print", file, "--ancillary-only", "--hex"]).unwrap();
+        run_cli(&["print", file, "--critical-only", "--json"]).unwrap();
+    }
+
+    #[test]
+    fn test_reports() {
+        let file = write_testing_png("reports.png", &png_with_duplicates());
+        let file = file.to_str().unwrap();
+        let report = run_cli(&["remove", file, "ruSt", "--all", "--dry-run"]).unwrap();
+        assert_eq!(report.get("command").unwrap().as_str(), Some("remove"));
+        assert_eq!(report.get("removed").unwrap().as_array().unwrap().len(), 3);
+        assert_eq!(report.get("saved").unwrap().as_f64(), Some(52.0));
+
+        let report = run_cli(&["decode", file, "ruSt", "--all"]).unwrap();
+        let chunks = report.get("chunks").unwrap().as_array().unwrap();
+        let payloads: Vec<_> = chunks
+            .iter()
+            .map(|chunk| chunk.get("payload").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(payloads, ["Zmlyc3Q=", "c2Vjb25k", "dGhpcmQ="]);
+        assert_eq!(chunks[1].get("offset").unwrap().as_f64(), Some(78.0));
+
+        let report = run_cli(&["capacity", file, "--payload-size", "10"]).unwrap();
+        let payload = report.get("payload").unwrap();
+        assert_eq!(payload.get("chunks").unwrap().as_f64(), Some(1.0));
+        let clean = write_testing_png("reports_clean.png", &testing_png());
+        let report = run_cli(&["verify", clean.to_str().unwrap()]).unwrap();
+        assert_eq!(report.get("ok").unwrap().as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_critical_guard() {
+        let png = testing_png();
+        let file = write_testing_png("critical_guard.png", &png);
+        let file = file.to_str().unwrap();
+        for args in [
+            &["remove", file, "IDAT"][..],
+            &["encode", file, "IDAT", "message"],
+        ] {
+            let error = run_cli(args).unwrap_err();
+            assert!(matches!(error, PngSecretError::CriticalChunk { .. }));
+            assert_eq!(crate::exit::code(&error), 6);
+        }
+        assert_eq!(fs::read(file).unwrap(), png.as_bytes());
+        assert!(run_cli(&["remove", file, "IDAT", "--yes"]).is_err());
+
+        run_cli(&["remove", file, "IDAT", "--allow-critical", "--yes"]).unwrap();
+        let removed = read_png(Path::new(file)).unwrap();
+        assert_eq!(chunk_types(&removed), ["IHDR", "IEND"]);
+        run_cli(&["encode", file, "IDAT", "x", "--allow-critical", "-y"]).unwrap();
+    }
+
+    #[test]
+    fn test_encode_password() {
+        let file = write_testing_png("password.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "secret message",
+            "--password",
+            "hunter2",
+        ])
+        .unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        let (header, data) = payload::unpack(png.chunk_by_type("ruSt").unwrap().data())
+            .unwrap()
+            .unwrap();
+        assert!(header.encrypted);
+        assert!(crypto::is_encrypted(data));
+        assert!(!data.windows(6).any(|window| window == b"secret"));
+
+        let report = run_cli(&["decode", file, "ruSt", "--password", "hunter2"]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            chunk.get("payload").unwrap().as_str(),
+            Some("c2VjcmV0IG1lc3NhZ2U=")
+        );
+        let error = run_cli(&["decode", file, "ruSt", "--password", "hunter3"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::DecryptionFailed));
+        // no garbage printed for an encrypted chunk without a password
+        assert!(run_cli(&["decode", file, "ruSt"]).is_err());
+    }
+
+    #[test]
+    fn test_decode_password_flipped_byte() {
+        let kdf = Kdf::Argon2id("8,1,1".parse().unwrap());
+        let payload = crypto::encrypt_with(b"secret", "hunter2", kdf).unwrap();
+        let mut png = testing_png();
+        let mut flipped = payload.clone();
+        *flipped.last_mut().unwrap() ^= 0x80;
+        png.append_chunk(Chunk::new(ChunkType::from_str("ruSt").unwrap(), &flipped));
+        let file = write_testing_png("password_flipped.png", &png);
+        let args = [
+            "decode",
+            file.to_str().unwrap(),
+            "ruSt",
+            "--password",
+            "hunter2",
+        ];
+        assert!(matches!(
+            run_cli(&args).unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+        // plain chunks don't decrypt
+        let file = write_testing_png("password_plain.png", &png_with_duplicates());
+        assert!(run_cli(&["decode", file.to_str().unwrap(), "ruSt", "--password", "pw"]).is_err());
+    }
+
+    #[test]
+    fn test_encode_password_twice() {
+        let file = write_testing_png("password_twice.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let args = ["--password", "pw", "--kdf-params", "8,1,1"];
+        for message in ["same", "same"] {
+            run_cli(&[&["encode", file, "ruSt", message][..], &args].concat()).unwrap();
+        }
+        let png = read_png(Path::new(file)).unwrap();
+        let chunks = png.chunks_by_type("ruSt");
+        assert_ne!(chunks[0].1.data(), chunks[1].1.data());
+        let report = run_cli(&["decode", file, "ruSt", "--all", "--password", "pw"]).unwrap();
+        for chunk in report.get("chunks").unwrap().as_array().unwrap() {
+            assert_eq!(chunk.get("payload").unwrap().as_str(), Some("c2FtZQ=="));
+        }
+        assert!(Cli::try_parse_from([
+            "pngsecret",
+            "encode",
+            file,
+            "ruSt",
+            "m",
+            "--kdf-params",
+            "8,1,1"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_encrypt_needs_a_password() {
+        let file = write_testing_png("encrypt_no_password.png", &testing_png());
+        let file = file.to_str().unwrap();
+        // tests are never on a terminal, so without the env var there is no password
+        if std::env::var_os(password::ENV_VAR).is_none() {
+            let error = run_cli(&["encode", file, "ruSt", "m", "--encrypt"]).unwrap_err();
+            assert!(error.to_string().contains(password::ENV_VAR));
+        }
+        run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "m",
+            "--encrypt",
+            "--password",
+            "pw",
+            "--kdf-params",
+            "8,1,1",
+        ])
+        .unwrap();
+    }
+
+    #[test]
+    fn test_key_file() {
+        let key = temp_path("key_file.key");
+        fs::write(&key, [42; 32]).unwrap();
+        let key = key.to_str().unwrap();
+        let file = write_testing_png("key_file.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "automated", "--key-file", key]).unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        let data = png.chunk_by_type("ruSt").unwrap().data();
+        assert!(crypto::uses_key_file(
+            payload::unpack(data).unwrap().unwrap().1
+        ));
+        let report = run_cli(&["decode", file, "ruSt", "--key-file", key]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(chunk.get("payload").unwrap().as_str(), Some("YXV0b21hdGVk"));
+        assert!(run_cli(&["decode", file, "ruSt"])
+            .unwrap_err()
+            .to_string()
+            .contains("--key-file"));
+
+        let short = temp_path("key_file_short.key");
+        fs::write(&short, [42; 31]).unwrap();
+        let error = run_cli(&[
+            "decode",
+            file,
+            "ruSt",
+            "--key-file",
+            short.to_str().unwrap(),
+        ]);
+        assert!(error.unwrap_err().to_string().contains("has 31 bytes"));
+
+        let file = write_testing_png("key_file_password.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "m",
+            "--password",
+            "pw",
+            "--kdf-params",
+            "8,1,1",
+        ])
+        .unwrap();
+        let error = run_cli(&["decode", file, "ruSt", "--key-file", key]).unwrap_err();
+        assert!(error.to_string().contains("encrypted with a password"));
+        assert!(run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "m",
+            "--password",
+            "pw",
+            "--key-file",
+            key
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_recipients() {
+        let alice = temp_path("recipient_alice.txt");
+        let bob = temp_path("recipient_bob.txt");
+        let mut recipients = Vec::new();
+        for identity in [&alice, &bob] {
+            let report = run_cli(&["keygen", "-o", identity.to_str().unwrap(), "--force"]).unwrap();
+            recipients.push(
+                report
+                    .get("public_key")
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        let file = write_testing_png("recipients.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let mut args = vec!["encode", file, "ruSt", "for both"];
+        for recipient in &recipients {
+            args.extend(["--recipient", recipient]);
+        }
+        run_cli(&args).unwrap();
+        for identity in [&alice, &bob] {
+            let args = [
+                "decode",
+                file,
+                "ruSt",
+                "--identity",
+                identity.to_str().unwrap(),
+            ];
+            let report = run_cli(&args).unwrap();
+            let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+            assert_eq!(chunk.get("payload").unwrap().as_str(), Some("Zm9yIGJvdGg="));
+        }
+        let eve = temp_path("recipient_eve.txt");
+        run_cli(&["keygen", "-o", eve.to_str().unwrap(), "--force"]).unwrap();
+        let error = run_cli(&["decode", file, "ruSt", "--identity", eve.to_str().unwrap()]);
+        assert!(matches!(
+            error.unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+        let error = run_cli(&["decode", file, "ruSt"]).unwrap_err();
+        assert!(error.to_string().contains("--identity"));
+        assert!(run_cli(&["encode", file, "ruSt", "m", "--recipient", "age1nope"]).is_err());
+    }
+
+    #[test]
+    fn test_sign_hmac() {
+        let file = write_testing_png("sign_hmac.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let sign = ["--sign-hmac", "--password", "pw", "--kdf-params", "8,1,1"];
+        run_cli(&[&["encode", file, "ruSt", "build 1234"][..], &sign].concat()).unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        let data = png.chunk_by_type("ruSt").unwrap().data().to_vec();
+        assert!(data.ends_with(b"build 1234"));
+
+        let report = run_cli(&["decode", file, "ruSt", "--password", "pw"]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            chunk.get("payload").unwrap().as_str(),
+            Some("YnVpbGQgMTIzNA==")
+        );
+        let wrong = run_cli(&["decode", file, "ruSt", "--password", "pw2"]).unwrap_err();
+        assert!(matches!(wrong, PngSecretError::IntegrityFailed));
+
+        // swap the message for another one of the same length
+        let mut tampered = data.clone();
+        let end = tampered.len();
+        tampered[end - 4..].copy_from_slice(b"9999");
+        let tampered_file = temp_path("sign_hmac_tampered.bin");
+        fs::write(&tampered_file, &tampered).unwrap();
+        let tampered_file = tampered_file.to_str().unwrap();
+        run_cli(&["remove", file, "ruSt"]).unwrap();
+        run_cli(&["inject", file, "ruSt", "--data-file", tampered_file]).unwrap();
+        let error = run_cli(&["decode", file, "ruSt", "--password", "pw"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::IntegrityFailed));
+    }
+
+    #[test]
+    fn test_encode_compress() {
+        let text = "2026-10-14 12:00:00 INFO request handled\n".repeat(2500);
+        assert!(text.len() >= 100_000);
+        let message_file = temp_path("compress.txt");
+        fs::write(&message_file, &text).unwrap();
+        let file = write_testing_png("compress.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let message_file = message_file.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "--message-file",
+            message_file,
+            "--compress",
+        ])
+        .unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        assert!(png.chunk_by_type("ruSt").unwrap().length() * 20 < text.len() as u32);
+
+        let output = temp_path("compress_out.txt");
+        let output = output.to_str().unwrap();
+        run_cli(&["decode", file, "ruSt", "-o", output, "--force"]).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), text);
+
+        // compressed first, then encrypted
+        let file = write_testing_png("compress_encrypted.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let args = ["--compress=9", "--password", "pw", "--kdf-params", "8,1,1"];
+        run_cli(
+            &[
+                &["encode", file, "ruSt", "--message-file", message_file][..],
+                &args,
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        let (header, data) = payload::unpack(png.chunk_by_type("ruSt").unwrap().data())
+            .unwrap()
+            .unwrap();
+        assert!(header.encrypted);
+        assert!(crypto::is_encrypted(data));
+        assert!(data.len() * 20 < text.len());
+        run_cli(&[
+            "decode",
+            file,
+            "ruSt",
+            "-o",
+            output,
+            "--force",
+            "--password",
+            "pw",
+        ])
+        .unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), text);
+        assert!(run_cli(&["encode", file, "ruSt", "m", "--compress=10"]).is_err());
+    }
+
+    #[test]
+    fn test_base64() {
+        let binary = [0x00, 0xff, 0x89, 0x50, 0x0a, 0x00];
+        let encoded = base64::encode(&binary);
+        let file = write_testing_png("base64.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", &encoded, "--base64"]).unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), binary);
+
+        let raw = temp_path("base64_raw.bin");
+        run_cli(&[
+            "decode",
+            file,
+            "ruSt",
+            "-o",
+            raw.to_str().unwrap(),
+            "--force",
+        ])
+        .unwrap();
+        assert_eq!(fs::read(&raw).unwrap(), binary);
+        let text = temp_path("base64_text.txt");
+        let args = [
+            "decode",
+            file,
+            "ruSt",
+            "-o",
+            text.to_str().unwrap(),
+            "--force",
+            "--base64",
+        ];
+        run_cli(&args).unwrap();
+        assert_eq!(fs::read_to_string(&text).unwrap(), encoded);
+
+        let before = fs::read(file).unwrap();
+        let error = run_cli(&["encode", file, "ruSt", "not base64!", "--base64"]).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid base64 message"));
+        assert_eq!(fs::read(file).unwrap(), before);
+    }
+
+    #[test]
+    fn test_encode_split() {
+        let blob = "0123456789abcdef".repeat(5 * 1024 * 1024 / 16);
+        let message_file = temp_path("split.txt");
+        fs::write(&message_file, &blob).unwrap();
+        let file = write_testing_png("split.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let args = [
+            "--message-file",
+            message_file.to_str().unwrap(),
+            "--max-chunk-size",
+            "1048576",
+        ];
+        let report = run_cli(&[&["encode", file, "ruSt"][..], &args].concat()).unwrap();
+        assert_eq!(
+            report.get("file").unwrap().get("parts").unwrap().as_f64(),
+            Some(5.0)
+        );
+
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        let chunks = png.chunks_by_type("ruSt");
+        assert_eq!(chunks.len(), 5);
+        let parts: Vec<payload::Part> = chunks
+            .iter()
+            .map(|(_, chunk)| payload::part(chunk.data()).unwrap().unwrap())
+            .collect();
+        let mut stitched = Vec::new();
+        for (index, part) in parts.iter().enumerate() {
+            assert_eq!(
+                (part.index, part.total, part.id),
+                (index as u32, 5, parts[0].id)
+            );
+            assert_eq!(part.body.len(), 1024 * 1024);
+            stitched.extend_from_slice(part.body);
+        }
+        assert_eq!(stitched, blob.as_bytes());
+        // the parts come right before IEND
+        assert_eq!(
+            chunk_types(&png)[2..],
+            ["ruSt", "ruSt", "ruSt", "ruSt", "ruSt", "IEND"]
+        );
+        assert!(run_cli(&["encode", file, "ruSt", "m", "--max-chunk-size", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_decode_split() {
+        let message = "split me into parts ".repeat(50);
+        let file = write_testing_png("decode_split.png", &testing_png());
+        let path = file.to_str().unwrap();
+        run_cli(&["encode", path, "ruSt", &message, "--max-chunk-size", "300"]).unwrap();
+        // shuffle the parts and put an unrelated ruSt chunk among them
+        let mut png = Png::try_from(fs::read(&file).unwrap().as_slice()).unwrap();
+        let parts: Vec<Chunk> = (0..4).map(|_| png.remove_chunk("ruSt").unwrap()).collect();
+        for index in [2, 0, 3, 1] {
+            png.insert_chunk_at(1, parts[index].clone()).unwrap();
+        }
+        let unrelated = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"unrelated");
+        png.insert_chunk_at(3, unrelated).unwrap();
+        fs::write(&file, png.as_bytes()).unwrap();
+
+        let report = run_cli(&["decode", path, "ruSt"]).unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        let payload = base64::encode(message.as_bytes());
+        assert_eq!(
+            decoded.get("payload").unwrap().as_str(),
+            Some(payload.as_str())
+        );
+        let report = run_cli(&["decode", path, "ruSt", "--all"]).unwrap();
+        let decoded = report.get("chunks").unwrap().as_array().unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(
+            decoded[1].get("payload").unwrap().as_str(),
+            Some("dW5yZWxhdGVk")
+        );
+
+        // drop part 2
+        let n = png
+            .chunks_by_type("ruSt")
+            .iter()
+            .position(|(_, chunk)| chunk.data() == parts[2].data())
+            .unwrap();
+        png.remove_nth_chunk_by_type("ruSt", n).unwrap();
+        fs::write(&file, png.as_bytes()).unwrap();
+        let error = run_cli(&["decode", path, "ruSt"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The split payload is missing part(s) 2 of 4"
+        );
+    }
+
+    #[test]
+    fn test_decoy() {
+        let file = write_testing_png("decoy.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "the real plans",
+            "--password",
+            "real",
+            "--decoy-message",
+            "grocery list",
+            "--decoy-password",
+            "decoy",
+            "--kdf-params",
+            "8,1,1",
+        ])
+        .unwrap();
+        let decoded = |password: &str| -> Result<String> {
+            let report = run_cli(&["decode", file, "ruSt", "--password", password])?;
+            let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+            let payload = chunk.get("payload").unwrap().as_str().unwrap();
+            Ok(String::from_utf8(base64::decode(payload).unwrap()).unwrap())
+        };
+        assert_eq!(decoded("real").unwrap(), "the real plans");
+        assert_eq!(decoded("decoy").unwrap(), "grocery list");
+        assert!(matches!(
+            decoded("third").unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+        assert!(run_cli(&["encode", file, "ruSt", "m", "--decoy-message", "d"]).is_err());
+    }
+
+    #[test]
+    fn test_payload_header() {
+        let file = write_testing_png("payload_header.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let args = ["--compress", "--password", "pw", "--kdf-params", "8,1,1"];
+        run_cli(&[&["encode", file, "ruSt", "hidden"][..], &args].concat()).unwrap();
+        let report =
+            run_cli(&["decode", file, "ruSt", "--password", "pw", "--show-header"]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        let header = chunk.get("header").unwrap();
+        assert_eq!(header.get("version").unwrap().as_f64(), Some(2.0));
+        assert_eq!(header.get("compressed").unwrap().as_bool(), Some(true));
+        assert_eq!(header.get("encrypted").unwrap().as_bool(), Some(true));
+        assert_eq!(header.get("signed").unwrap().as_bool(), Some(false));
+        assert_eq!(chunk.get("payload").unwrap().as_str(), Some("aGlkZGVu"));
+        let header = payload::unpack(&payload::pack(
+            &payload::Header {
+                signed: true,
+                ..payload::Header::new()
+            },
+            b"",
+        ))
+        .unwrap()
+        .map(|(header, _)| header);
+        assert_eq!(
+            header_text(header.as_ref()),
+            "Header:      pSCv version 2\nCompressed:  no\nEncrypted:   no\nSigned:      yes\nParts:       1\nChecksum:    none\nSigned by:   none"
+        );
+
+        // a plain message has no header, like every payload encoded before it
+        let file = write_testing_png("payload_header_plain.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "plain"]).unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"plain");
+        let report = run_cli(&["decode", file, "ruSt"]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(chunk.get("header"), Some(&Value::Null));
+        assert_eq!(header_text(None), "Header:      none, a raw payload");
+    }
+
+    #[test]
+    fn test_checksum() {
+        let file = write_testing_png("checksum.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "build 1234", "--checksum"]).unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        let data = png.chunk_by_type("ruSt").unwrap().data().to_vec();
+        let (header, _) = payload::unpack(&data).unwrap().unwrap();
+        assert_eq!(header.checksum, Some(payload::checksum(b"build 1234")));
+        run_cli(&["decode", file, "ruSt"]).unwrap();
+
+        // one byte of the message flipped
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let tampered_file = temp_path("checksum_tampered.bin");
+        fs::write(&tampered_file, &tampered).unwrap();
+        let tampered_file = tampered_file.to_str().unwrap();
+        run_cli(&["remove", file, "ruSt"]).unwrap();
+        run_cli(&["inject", file, "ruSt", "--data-file", tampered_file]).unwrap();
+        let error = run_cli(&["decode", file, "ruSt"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::ChecksumMismatch));
+        let report = run_cli(&["decode", file, "ruSt", "--ignore-checksum"]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            chunk.get("payload").unwrap().as_str(),
+            Some(base64::encode(b"build 1235").as_str())
+        );
+
+        // taken before compression
+        let file = write_testing_png("checksum_compressed.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let text = "compressible ".repeat(100);
+        run_cli(&["encode", file, "ruSt", &text, "--checksum", "--compress"]).unwrap();
+        let png = Png::try_from(fs::read(file).unwrap().as_slice()).unwrap();
+        let data = png.chunk_by_type("ruSt").unwrap().data();
+        let (header, _) = payload::unpack(data).unwrap().unwrap();
+        assert!(header.compressed);
+        assert_eq!(header.checksum, Some(payload::checksum(text.as_bytes())));
+        run_cli(&["decode", file, "ruSt"]).unwrap();
+    }
+
+    #[test]
+    fn test_input_file_metadata() {
+        let input = temp_path("report.bin");
+        let contents: Vec<u8> = (0..=255).cycle().take(3000).collect();
+        fs::write(&input, &contents).unwrap();
+        let mtime =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&input)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&input, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let file = write_testing_png("input_file.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let input = input.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "--input-file", input]).unwrap();
+
+        let dir = temp_path("input_file_out");
+        let _ = fs::remove_dir_all(&dir);
+        let dir_arg = dir.to_str().unwrap();
+        let report = run_cli(&["decode", file, "ruSt", "--output-dir", dir_arg]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        let metadata = chunk.get("header").unwrap().get("metadata").unwrap();
+        assert_eq!(metadata.get("name").unwrap().as_str(), Some("report.bin"));
+        assert_eq!(metadata.get("size").unwrap().as_f64(), Some(3000.0));
+        let restored = dir.join("report.bin");
+        assert_eq!(fs::read(&restored).unwrap(), contents);
+        let restored_metadata = fs::metadata(&restored).unwrap();
+        assert_eq!(restored_metadata.modified().unwrap(), mtime);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(restored_metadata.permissions().mode() & 0o7777, 0o640);
+            assert_eq!(metadata.get("mode").unwrap().as_str(), Some("640"));
+        }
+        // it's there now
+        assert!(run_cli(&["decode", file, "ruSt", "--output-dir", dir_arg]).is_err());
+
+        let file = write_testing_png("input_file_private.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "--input-file",
+            input,
+            "--no-metadata",
+        ])
+        .unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), contents);
+        let dir = temp_path("input_file_private_out");
+        let _ = fs::remove_dir_all(&dir);
+        run_cli(&[
+            "decode",
+            file,
+            "ruSt",
+            "--output-dir",
+            dir.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(fs::read(dir.join("2_ruSt.bin")).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_utc() {
+        assert_eq!(utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(utc(1_700_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(utc(951_825_600), "2000-02-29 12:00:00 UTC");
+        assert_eq!(utc(-1), "1969-12-31 23:59:59 UTC");
+    }
+
+    #[test]
+    fn test_ed25519_signature() {
+        let keypair = temp_path("signing_keypair.json");
+        let _ = fs::remove_file(&keypair);
+        let keypair = keypair.to_str().unwrap();
+        let report = run_cli(&["keygen", "--type", "ed25519", "--out", keypair]).unwrap();
+        let public_key = report
+            .get("public_key")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string();
+        let file = write_testing_png("ed25519.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "from me", "--sign", keypair]).unwrap();
+
+        let report = run_cli(&["decode", file, "ruSt", "--verify-with", keypair]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        let signature = chunk.get("header").unwrap().get("signature").unwrap();
+        assert_eq!(
+            signature.get("public_key").unwrap().as_str(),
+            Some(public_key.as_str())
+        );
+        run_cli(&["decode", file, "ruSt", "--verify-with", &public_key]).unwrap();
+        // without --verify-with the signature is only reported
+        run_cli(&["decode", file, "ruSt"]).unwrap();
+
+        let other = crypto::hex(&SigningKey::generate().unwrap().public_key());
+        let error = run_cli(&["decode", file, "ruSt", "--verify-with", &other]).unwrap_err();
+        assert!(matches!(error, PngSecretError::BadSignature(_)));
+
+        let png = read_png(Path::new(file)).unwrap();
+        let mut tampered = png.chunk_by_type("ruSt").unwrap().data().to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        let tampered_file = temp_path("ed25519_tampered.bin");
+        fs::write(&tampered_file, &tampered).unwrap();
+        run_cli(&["remove", file, "ruSt"]).unwrap();
+        let tampered_file = tampered_file.to_str().unwrap();
+        run_cli(&["inject", file, "ruSt", "--data-file", tampered_file]).unwrap();
+        let error = run_cli(&["decode", file, "ruSt", "--verify-with", keypair]).unwrap_err();
+        assert!(matches!(error, PngSecretError::BadSignature(_)));
+
+        // signed, then encrypted
+        let file = write_testing_png("ed25519_encrypted.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let args = [
+            "--sign",
+            keypair,
+            "--password",
+            "pw",
+            "--kdf-params",
+            "8,1,1",
+        ];
+        run_cli(&[&["encode", file, "ruSt", "from me"][..], &args].concat()).unwrap();
+        let report = run_cli(&[
+            "decode",
+            file,
+            "ruSt",
+            "--password",
+            "pw",
+            "--verify-with",
+            keypair,
+        ])
+        .unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(chunk.get("payload").unwrap().as_str(), Some("ZnJvbSBtZQ=="));
+        let plain = write_testing_png("ed25519_unsigned.png", &testing_png());
+        let plain = plain.to_str().unwrap();
+        run_cli(&["encode", plain, "ruSt", "from me"]).unwrap();
+        let error = run_cli(&["decode", plain, "ruSt", "--verify-with", keypair]).unwrap_err();
+        assert_eq!(error.to_string(), "The payload is not signed");
+    }
+
+    #[test]
+    fn test_text_keyword() {
+        let file = write_testing_png("text_keyword.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let encode = [
+            "encode",
+            file,
+            "tEXt",
+            "Hello, world",
+            "--text-keyword",
+            "Comment",
+        ];
+        run_cli(&encode).unwrap();
+        // the layout exiftool writes for -Comment="Hello, world"
+        let fixture = [
+            0, 0, 0, 20, b't', b'E', b'X', b't', b'C', b'o', b'm', b'm', b'e', b'n', b't', 0, b'H',
+            b'e', b'l', b'l', b'o', b',', b' ', b'w', b'o', b'r', b'l', b'd', 87, 45, 0, 127,
+        ];
+        let bytes = fs::read(file).unwrap();
+        assert!(bytes.windows(fixture.len()).any(|window| window == fixture));
+
+        let report = run_cli(&["decode", file, "tEXt", "--text-keyword", "Comment"]).unwrap();
+        let chunk = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(chunk.get("keyword").unwrap().as_str(), Some("Comment"));
+        assert_eq!(
+            chunk.get("payload").unwrap().as_str(),
+            Some(base64::encode(b"Hello, world").as_str())
+        );
+        let error = run_cli(&["decode", file, "tEXt", "--text-keyword", "Author"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("no tEXt chunk with keyword 'Author' found in {}", file)
+        );
+        assert!(run_cli(&["encode", file, "ruSt", "x", "--text-keyword", "Comment"]).is_err());
+        assert!(run_cli(&["encode", file, "tEXt", "x", "--text-keyword", " Comment"]).is_err());
+
+        let png = read_png(Path::new(file)).unwrap();
+        let chunk = png.chunk_by_type("tEXt").unwrap();
+        let text = chunk_text(chunk, None, false, None, false);
+        assert!(text.ends_with("Keyword:     Comment\nText:        Hello, world\n"));
+        let text = chunk_text(chunk, Some(5), false, None, false);
+        assert!(text.ends_with("Text:        Hello\n… (7 more characters)\n"));
+    }
+
+    #[test]
+    fn test_ztxt_keyword() {
+        let file = write_testing_png("ztxt_keyword.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let message = temp_path("ztxt_keyword.txt");
+        let text = "a line of the description\n".repeat(2000);
+        fs::write(&message, &text).unwrap();
+        let message = message.to_str().unwrap();
+        let encode = [
+            "encode",
+            file,
+            "zTXt",
+            "--message-file",
+            message,
+            "--ztxt-keyword",
+            "Description",
+        ];
+        run_cli(&encode).unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        let chunk = png.chunk_by_type("zTXt").unwrap();
+        assert!((chunk.length() as usize) < text.len() / 20);
+
+        // the text comes back decompressed, with or without the keyword
+        for decode in [
+            &["decode", file, "zTXt"][..],
+            &["decode", file, "zTXt", "--text-keyword", "Description"],
+        ] {
+            let report = run_cli(decode).unwrap();
+            let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+            assert_eq!(
+                decoded.get("payload").unwrap().as_str(),
+                Some(base64::encode(text.as_bytes()).as_str())
+            );
+        }
+        let shown = chunk_text(chunk, Some(6), false, None, false);
+        assert!(shown.contains("Keyword:     Description\nText:        a line\n"));
+        assert!(shown.ends_with(&format!(
+            "Compressed:  {} bytes, {} uncompressed\n",
+            chunk.length() - 13,
+            text.len()
+        )));
+        assert!(run_cli(&["encode", file, "tEXt", "x", "--ztxt-keyword", "Comment"]).is_err());
+
+        // a corrupt stream is an error to decode and a note to print
+        let broken = write_testing_png("ztxt_broken.png", &testing_png());
+        let broken = broken.to_str().unwrap();
+        let data = temp_path("ztxt_broken.bin");
+        fs::write(&data, b"Comment\0\0\x78\x9c\xff\xff\xff").unwrap();
+        run_cli(&[
+            "inject",
+            broken,
+            "zTXt",
+            "--data-file",
+            data.to_str().unwrap(),
+        ])
+        .unwrap();
+        let error = run_cli(&["decode", broken, "zTXt"]).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("The text of the zTXt chunk 'Comment' is not a valid zlib stream"));
+        let png = read_png(Path::new(broken)).unwrap();
+        let shown = chunk_text(png.chunk_by_type("zTXt").unwrap(), None, false, None, false);
+        assert!(shown.contains("Error:       The text of the zTXt chunk 'Comment'"));
+    }
+
+    #[test]
+    fn test_itxt_keyword() {
+        let file = write_testing_png("itxt_keyword.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let text = "Привет, мир! ".repeat(300);
+        let encode = [
+            "encode",
+            file,
+            "iTXt",
+            &text,
+            "--itxt-keyword",
+            "Title",
+            "--language",
+            "ru",
+            "--translated-keyword",
+            "Заголовок",
+            "--compress",
+        ];
+        run_cli(&encode).unwrap();
+        run_cli(&["encode", file, "iTXt", "", "--itxt-keyword", "Comment"]).unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        let chunks = png.chunks_by_type("iTXt");
+        assert!((chunks[0].1.length() as usize) < text.len() / 10);
+
+        let report = run_cli(&["decode", file, "iTXt", "--itxt-keyword", "Title"]).unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            decoded.get("payload").unwrap().as_str(),
+            Some(base64::encode(text.as_bytes()).as_str())
+        );
+        // the empty text of the second chunk
+        let report = run_cli(&["decode", file, "iTXt", "--text-keyword", "Comment"]).unwrap();
+        let decoded = &report.get("chunks").unwrap().as_array().unwrap()[0];
+        assert_eq!(decoded.get("payload").unwrap().as_str(), Some(""));
+        assert!(run_cli(&["decode", file, "tEXt", "--itxt-keyword", "Title"]).is_err());
+        assert!(run_cli(&[
+            "encode",
+            file,
+            "iTXt",
+            "x",
+            "--itxt-keyword",
+            "K",
+            "--language",
+            "en_US"
+        ])
+        .is_err());
+
+        let shown = chunk_text(chunks[0].1, Some(6), false, None, false);
+        assert!(shown.contains(
+            "Keyword:     Title\nLanguage:    ru\nTranslated:  Заголовок\nText:        Привет\n"
+        ));
+        assert!(shown.contains("Compressed:  "));
+        let shown = chunk_text(chunks[1].1, None, false, None, false);
+        assert!(shown.ends_with("Language:    none\nTranslated:  \nText:        \n"));
+        let listed = chunks_json(&png, &chunks, true);
+        let listed = &listed.as_array().unwrap()[0];
+        assert_eq!(listed.get("language").unwrap().as_str(), Some("ru"));
+        assert_eq!(
+            listed.get("translated_keyword").unwrap().as_str(),
+            Some("Заголовок")
+        );
+    }
+
+    #[test]
+    fn test_touch() {
+        let file = write_testing_png("touch.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let report = run_cli(&["touch", file, "--time", "2024-03-05T16:30:09+02:00"]).unwrap();
+        assert_eq!(
+            report.get("time").unwrap().as_str(),
+            Some("2024-03-05T14:30:09Z")
+        );
+        assert!(matches!(report.get("previous"), Some(Value::Null)));
+        let png = read_png(Path::new(file)).unwrap();
+        let (index, chunk) = png.chunks_by_type("tIME")[0];
+        assert_eq!(chunk.data(), [0x07, 0xe8, 3, 5, 14, 30, 9]);
+        assert_eq!(index + 1, png.iend_index().unwrap());
+        assert!(chunk_text(chunk, None, false, None, false)
+            .ends_with("Time:        2024-03-05T14:30:09Z\n"));
+        assert!(list_table(&indexed(&png), SortKey::Offset, false)
+            .contains("A..  2024-03-05T14:30:09Z\n"));
+
+        // the chunk is replaced where it is, not added again
+        let report = run_cli(&["touch", file]).unwrap();
+        assert_eq!(
+            report.get("previous").unwrap().as_str(),
+            Some("2024-03-05T14:30:09Z")
+        );
+        let png = read_png(Path::new(file)).unwrap();
+        assert_eq!(png.chunks_by_type("tIME").len(), 1);
+        assert_eq!(png.chunks_by_type("tIME")[0].0, index);
+        assert!(run_cli(&["touch", file, "--time", "2024-03-05"]).is_err());
+
+        // an out of range month is reported, and touch replaces it
+        let broken = write_testing_png("touch_broken.png", &testing_png());
+        let broken = broken.to_str().unwrap();
+        let data = temp_path("touch_broken.bin");
+        fs::write(&data, [0x07, 0xe8, 13, 5, 14, 30, 9]).unwrap();
+        run_cli(&[
+            "inject",
+            broken,
+            "tIME",
+            "--data-file",
+            data.to_str().unwrap(),
+        ])
+        .unwrap();
+        let png = read_png(Path::new(broken)).unwrap();
+        let chunk = png.chunk_by_type("tIME").unwrap();
+        assert!(chunk_text(chunk, None, false, None, false)
+            .ends_with("Error:       The tIME chunk has month 13, not 1 to 12\n"));
+        let report = run_cli(&["touch", broken, "--time", "2000-01-01T00:00:00Z"]).unwrap();
+        assert!(matches!(report.get("previous"), Some(Value::Null)));
+        let png = read_png(Path::new(broken)).unwrap();
+        assert_eq!(png.chunks_by_type("tIME").len(), 1);
+        assert_eq!(
+            time_of(png.chunk_by_type("tIME").unwrap()).unwrap().year,
+            2000
+        );
+    }
+
+    #[test]
+    fn test_exif() {
+        // a big endian eXIf chunk laid out the way camera firmware writes it, IFD0
+        // with the make, model, orientation and date of the shot
+        let fixture: Vec<u8> = [
+            &b"MM\0*\0\0\0\x08\0\x04"[..],
+            b"\x01\x0f\0\x02\0\0\0\x06\0\0\0\x3e",
+            b"\x01\x10\0\x02\0\0\0\x0d\0\0\0\x44",
+            b"\x01\x12\0\x03\0\0\0\x01\0\x01\0\0",
+            b"\x01\x32\0\x02\0\0\0\x14\0\0\0\x51",
+            b"\0\0\0\0Canon\0Canon EOS R5\x002024:03:05 14:30:09\0",
+        ][..]
+            .concat();
+        let file = write_testing_png("exif.png", &testing_png());
+        let file = file.to_str().unwrap();
+        let data = temp_path("exif_fixture.bin");
+        fs::write(&data, &fixture).unwrap();
+        let inject = [
+            "inject",
+            file,
+            "eXIf",
+            "--data-file",
+            data.to_str().unwrap(),
+        ];
+        run_cli(&inject).unwrap();
+
+        let report = run_cli(&["exif", file]).unwrap();
+        assert_eq!(report.get("entries").unwrap().as_f64(), Some(4.0));
+        assert_eq!(report.get("big_endian"), Some(&Value::Bool(true)));
+        let png = read_png(Path::new(file)).unwrap();
+        let shown = chunk_text(png.chunk_by_type("eXIf").unwrap(), None, false, None, false);
+        assert!(shown.ends_with("Exif:        big endian (MM), 4 entries in IFD0\n"));
+
+        let output = temp_path("exif_out.bin");
+        let _ = fs::remove_file(&output);
+        run_cli(&["exif", file, "--output", output.to_str().unwrap()]).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), fixture);
+        assert!(run_cli(&["exif", file, "--output", output.to_str().unwrap()]).is_err());
+
+        run_cli(&["exif", file, "--remove-exif", "--dry-run"]).unwrap();
+        assert!(read_png(Path::new(file))
+            .unwrap()
+            .chunk_by_type("eXIf")
+            .is_some());
+        run_cli(&["exif", file, "--remove-exif"]).unwrap();
+        assert!(read_png(Path::new(file))
+            .unwrap()
+            .chunk_by_type("eXIf")
+            .is_none());
+        let error = run_cli(&["exif", file]).unwrap_err();
+        assert!(matches!(error, PngSecretError::ChunkNotFound { .. }));
+
+        // the Exif\0\0 of a jpeg APP1 segment has no place in a png
+        let mut jpeg = b"Exif\0\0".to_vec();
+        jpeg.extend(&fixture);
+        fs::write(&data, jpeg).unwrap();
+        run_cli(&inject).unwrap();
+        let error = run_cli(&["exif", file]).unwrap_err();
+        assert!(error.to_string().contains("of a jpeg"));
+    }
+
+    #[test]
+    fn test_encode_before_iend() {
+        let file = write_testing_png("encode_before_iend.png", &testing_png());
+        let file = file.to_str().unwrap();
+        run_cli(&["encode", file, "ruSt", "kept by pngcrush"]).unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        let len = png.chunks().len();
+        assert_eq!(png.chunks_by_type("ruSt")[0].0, len - 2);
+        assert_eq!(png.iend_index(), Some(len - 1));
+    }
+
+    #[test]
+    fn test_encode_stream() {
+        let mut png = testing_png();
+        // a few MiB of image data across several IDAT chunks
+        for seed in 0..4_u8 {
+            let data: Vec<u8> = (0..1 << 20).map(|i: u32| (i as u8) ^ seed).collect();
+            png.insert_chunk_at(2, Chunk::new(ChunkType::from_str("IDAT").unwrap(), &data))
+                .unwrap();
+        }
+        let file = write_testing_png("encode_stream.png", &png);
+        let file = file.to_str().unwrap();
+        let in_memory = temp_path("encode_stream_memory.png");
+        let streamed = temp_path("encode_stream_streamed.png");
+        let _ = fs::remove_file(&in_memory);
+        let _ = fs::remove_file(&streamed);
+        let encode = |output: &Path, extra: &[&str]| {
+            let args = [
+                "encode",
+                file,
+                "ruSt",
+                "a short message",
+                output.to_str().unwrap(),
+            ];
+            run_cli(&[&args[..], extra].concat())
+        };
+        let memory_report = encode(&in_memory, &[]).unwrap();
+        let stream_report = encode(&streamed, &["--stream"]).unwrap();
+        assert!(fs::read(&streamed).unwrap() == fs::read(&in_memory).unwrap());
+        assert_eq!(
+            stream_report.get("file").unwrap().get("chunk"),
+            memory_report.get("file").unwrap().get("chunk")
+        );
+
+        // in place, and nothing at all with --dry-run
+        let in_place = write_testing_png("encode_stream_in_place.png", &png);
+        let in_place = in_place.to_str().unwrap();
+        run_cli(&[
+            "encode",
+            in_place,
+            "ruSt",
+            "a short message",
+            "--dry-run",
+            "--stream",
+        ])
+        .unwrap();
+        assert_eq!(fs::read(in_place).unwrap(), png.as_bytes());
+        run_cli(&["encode", in_place, "ruSt", "a short message", "--stream"]).unwrap();
+        let decoded = temp_path("encode_stream_decoded.bin");
+        run_cli(&[
+            "decode",
+            in_place,
+            "ruSt",
+            "-o",
+            decoded.to_str().unwrap(),
+            "-f",
+        ])
+        .unwrap();
+        assert_eq!(fs::read(&decoded).unwrap(), b"a short message");
+
+        let error = run_cli(&[
+            "encode",
+            file,
+            "ruSt",
+            "m",
+            "--stream",
+            "--position",
+            "after-ihdr",
+        ])
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("--stream only inserts before the IEND"));
+        // a broken input leaves the output alone
+        let mut bytes = png.as_bytes();
+        // in the data of the first IDAT
+        bytes[45] ^= 0xff;
+        fs::write(file, &bytes).unwrap();
+        let error = encode(&streamed, &["--stream", "--force"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 4);
+        let error = encode(&streamed, &["--force"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 4);
+        assert!(fs::read(&streamed).unwrap() == fs::read(&in_memory).unwrap());
+    }
+
+    #[test]
+    fn test_copy_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("tEXt").unwrap(),
+            b"Author\0Ana",
+        ));
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruST").unwrap(),
+            b"tied to the pixels",
+        ));
+        let source = write_testing_png("copy_source.png", &png);
+        let source = source.to_str().unwrap();
+        let mut other = testing_png();
+        other.append_chunk(Chunk::new(
+            ChunkType::from_str("tEXt").unwrap(),
+            b"Author\0Old",
+        ));
+        // other pixels than the source
+        other
+            .insert_chunk_at(2, Chunk::new(ChunkType::from_str("IDAT").unwrap(), &[0]))
+            .unwrap();
+        let destination = write_testing_png("copy_destination.png", &other);
+        let destination = destination.to_str().unwrap();
+
+        let report = run_cli(&["copy-chunk", source, destination, "tEXt"]).unwrap();
+        assert_eq!(report.get("replaced").unwrap().as_f64(), Some(1.0));
+        let copied = read_png(Path::new(destination)).unwrap();
+        let texts = copied.chunks_by_type("tEXt");
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].1.data(), b"Author\0Ana");
+        assert_eq!(texts[0].0, 3);
+        run_cli(&["copy-chunk", source, destination, "tEXt", "--append"]).unwrap();
+        let copied = read_png(Path::new(destination)).unwrap();
+        assert_eq!(copied.chunks_by_type("tEXt").len(), 2);
+        assert_eq!(copied.iend_index(), Some(copied.chunks().len() - 1));
+
+        // ruST isn't safe to copy, and the image data differs
+        let error = run_cli(&["copy-chunk", source, destination, "ruST"]).unwrap_err();
+        assert!(error.to_string().ends_with("use --force to copy it anyway"));
+        assert!(read_png(Path::new(destination))
+            .unwrap()
+            .chunk_by_type("ruST")
+            .is_none());
+        run_cli(&["copy-chunk", source, destination, "ruST", "--force"]).unwrap();
+        assert!(read_png(Path::new(destination))
+            .unwrap()
+            .chunk_by_type("ruST")
+            .is_some());
+        // the same image data makes it safe
+        let same = write_testing_png("copy_same.png", &testing_png());
+        run_cli(&["copy-chunk", source, same.to_str().unwrap(), "ruST"]).unwrap();
+
+        let error = run_cli(&["copy-chunk", source, destination, "zTXt"]).unwrap_err();
+        assert!(matches!(error, PngSecretError::ChunkNotFound { .. }));
+        assert!(matches!(
+            run_cli(&["copy-chunk", source, destination, "IDAT"]).unwrap_err(),
+            PngSecretError::CriticalChunk { .. }
+        ));
+    }
+
+    fn testing_apng() -> Png {
+        let mut png = testing_png();
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        // two frames, the first one shown from the IDAT
+        let fctl = |sequence: u8| {
+            let mut data = vec![0, 0, 0, sequence, 0, 0, 0, 1, 0, 0, 0, 1];
+            data.extend([0; 8]);
+            data.extend([0, 1, 0, 10, 0, 0]);
+            data
+        };
+        png.insert_chunk_at(1, chunk("acTL", &[0, 0, 0, 2, 0, 0, 0, 0]))
+            .unwrap();
+        png.insert_chunk_at(2, chunk("fcTL", &fctl(0))).unwrap();
+        png.append_chunk(chunk("fcTL", &fctl(1)));
+        png.append_chunk(chunk("fdAT", &[0, 0, 0, 2, 120, 1]));
+        png.append_chunk(chunk("fdAT", &[0, 0, 0, 3, 1, 0]));
+        png
+    }
+
+    #[test]
+    fn test_encode_apng() {
+        let types = |png: &Png| -> Vec<String> {
+            png.chunks()
+                .iter()
+                .map(|chunk| chunk.chunk_type().to_string())
+                .collect()
+        };
+        let file = write_testing_png("apng.png", &testing_apng());
+        let file = file.to_str().unwrap();
+        // before IDAT would be between the first fcTL and its data
+        run_cli(&["encode", file, "ruSt", "one", "--position", "before-idat"]).unwrap();
+        // and index:7 between the fdAT chunks of the second frame
+        run_cli(&["encode", file, "ruSt", "two", "--position", "index:7"]).unwrap();
+        run_cli(&["encode", file, "ruSt", "three"]).unwrap();
+        let png = read_png(Path::new(file)).unwrap();
+        assert_eq!(
+            types(&png),
+            [
+                "IHDR", "acTL", "ruSt", "fcTL", "IDAT", "ruSt", "fcTL", "fdAT", "fdAT", "ruSt",
+                "IEND"
+            ]
+        );
+        let report = run_cli(&["verify", file]).unwrap();
+        assert!(report
+            .get("in_frames")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .is_empty());
+
+        // a private chunk some other tool put inside a frame
+        let mut png = testing_apng();
+        png.insert_chunk_at(6, Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"x"))
+            .unwrap();
+        let file = write_testing_png("apng_inside.png", &png);
+        let report = run_cli(&["verify", file.to_str().unwrap()]).unwrap();
+        let in_frames = report.get("in_frames").unwrap().as_array().unwrap();
+        assert_eq!(in_frames.len(), 1);
+        assert_eq!(in_frames[0].as_f64(), Some(6.0));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut png = testing_png();
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        let chrm: Vec<u8> = [31270_u32, 32900, 64000, 33000, 30000, 60000, 15000, 6000]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        // the ancillary chunks an image editor export carries, values as GIMP writes them
+        for (index, (chunk_type, data)) in [
+            (
+                "iCCP",
+                &b"GIMP built-in sRGB\0\0\x78\x9c\x63\x60\x00\x00\x00\x02\x00\x01"[..],
+            ),
+            ("gAMA", &[0, 0, 177, 143]),
+            ("cHRM", &chrm),
+            ("bKGD", &[0, 255, 0, 255, 0, 255]),
+            ("pHYs", &[0, 0, 11, 19, 0, 0, 11, 19, 1]),
+            ("tIME", &[0x07, 0xe8, 3, 5, 14, 30, 9]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            png.insert_chunk_at(index + 1, chunk(chunk_type, data))
+                .unwrap();
+        }
+        png.append_chunk(chunk("tEXt", b"Comment\0Created with GIMP"));
+        png.append_chunk(chunk("sRGB", &[9]));
+        png.append_chunk(chunk("ruSt", b"hidden"));
+        let file = write_testing_png("metadata.png", &png);
+
+        let report = run_cli(&["metadata", file.to_str().unwrap()]).unwrap();
+        let chunks = report.get("chunks").unwrap().as_array().unwrap();
+        let types: Vec<_> = chunks
+            .iter()
+            .map(|chunk| chunk.get("type").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            ["gAMA", "cHRM", "sRGB", "iCCP", "pHYs", "bKGD", "tIME"]
+        );
+        let field = |index: usize, name: &str| chunks[index + 1].get(name).unwrap().clone();
+        assert_eq!(chunks[0].get("gamma").unwrap().as_f64(), Some(0.45455));
+        assert_eq!(field(0, "white").get("x").unwrap().as_f64(), Some(0.3127));
+        assert!(field(1, "error")
+            .as_str()
+            .unwrap()
+            .contains("rendering intent 9"));
+        assert_eq!(field(2, "profile").as_str(), Some("GIMP built-in sRGB"));
+        assert_eq!(field(2, "compressed_size").as_f64(), Some(10.0));
+        assert_eq!(field(3, "unit").as_str(), Some("metre"));
+        assert_eq!(field(4, "rgb").as_array().unwrap().len(), 3);
+        assert_eq!(field(5, "time").as_str(), Some("2024-03-05T14:30:09Z"));
+        let text = report.get("text").unwrap();
+        assert_eq!(text.get("tEXt").unwrap().as_f64(), Some(1.0));
+        assert_eq!(text.get("iTXt").unwrap().as_f64(), Some(0.0));
+        let other = report.get("other").unwrap().as_array().unwrap();
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].get("type").unwrap().as_str(), Some("ruSt"));
+        assert_eq!(other[0].get("length").unwrap().as_f64(), Some(6.0));
+    }
+
+    #[test]
+    fn test_info() {
+        let info = |name: &str, ihdr: &[u8]| {
+            let mut png = testing_png();
+            png.remove_chunk("IHDR").unwrap();
+            png.insert_chunk_at(0, Chunk::new(ChunkType::from_str("IHDR").unwrap(), ihdr))
+                .unwrap();
+            let file = write_testing_png(name, &png);
+            let report = run_cli(&["--format", "json", "info", file.to_str().unwrap()]).unwrap();
+            assert_eq!(
+                report.get("file_size").unwrap().as_f64(),
+                Some(png.as_bytes().len() as f64)
+            );
+            assert_eq!(report.get("chunk_count").unwrap().as_f64(), Some(3.0));
+            assert_eq!(report.get("animated").unwrap().as_bool(), Some(false));
+            report.get("ihdr").unwrap().clone()
+        };
+        let field = |ihdr: &Value, name: &str| ihdr.get(name).unwrap().clone();
+
+        let gray = info("info_gray.png", &[0, 0, 1, 0, 0, 0, 0, 64, 16, 0, 0, 0, 0]);
+        assert_eq!(field(&gray, "width").as_f64(), Some(256.0));
+        assert_eq!(field(&gray, "height").as_f64(), Some(64.0));
+        assert_eq!(field(&gray, "bit_depth").as_f64(), Some(16.0));
+        assert_eq!(field(&gray, "color_type").as_str(), Some("grayscale"));
+        assert_eq!(field(&gray, "interlace").as_str(), Some("none"));
+
+        let rgba = info("info_rgba.png", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        assert_eq!(field(&rgba, "color_type").as_str(), Some("RGBA"));
+        assert_eq!(field(&rgba, "color_type_code").as_f64(), Some(6.0));
+        assert_eq!(field(&rgba, "compression").as_f64(), Some(0.0));
+        assert_eq!(field(&rgba, "filter").as_f64(), Some(0.0));
+
+        let interlaced = info("info_adam7.png", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 1]);
+        assert_eq!(field(&interlaced, "color_type").as_str(), Some("RGB"));
+        assert_eq!(field(&interlaced, "interlace").as_str(), Some("Adam7"));
+
+        let short = info("info_short.png", &[0, 0, 0, 1, 0, 0, 0, 1, 8]);
+        assert_eq!(
+            field(&short, "error").as_str(),
+            Some("The IHDR chunk has 9 bytes, not 13")
+        );
+        let unknown = info("info_unknown.png", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 7, 0, 0, 0]);
+        assert!(field(&unknown, "error")
+            .as_str()
+            .unwrap()
+            .contains("color type 7"));
+    }
+
+    #[test]
+    fn test_has() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"a\0b"));
+        png.append_chunk(Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"c\0d"));
+        let file = write_testing_png("has.png", &png);
+        let file = file.to_str().unwrap();
+        let code = |args: &[&str]| {
+            let args: Vec<&str> = ["has", file].iter().chain(args).copied().collect();
+            run_cli(&args).map_or_else(|error| crate::exit::code(&error), |_| 0)
+        };
+        assert_eq!(code(&["IDAT"]), 0);
+        assert_eq!(code(&["ruSt"]), 3);
+        assert_eq!(code(&["IDAT", "tEXt"]), 0);
+        assert_eq!(code(&["IDAT", "ruSt"]), 3);
+        assert_eq!(code(&["IDAT", "ruSt", "--any"]), 0);
+        assert_eq!(code(&["ruSt", "zTXt", "--any"]), 3);
+
+        let report =
+            run_cli(&["--format", "json", "has", file, "tEXt", "IDAT", "--count"]).unwrap();
+        let counts = report.get("counts").unwrap();
+        assert_eq!(counts.get("tEXt").unwrap().as_f64(), Some(2.0));
+        assert_eq!(counts.get("IDAT").unwrap().as_f64(), Some(1.0));
+
+        let broken = temp_path("has_broken.png");
+        fs::write(&broken, b"not a png").unwrap();
+        let error = run_cli(&["has", broken.to_str().unwrap(), "IDAT"]).unwrap_err();
+        assert_eq!(crate::exit::code(&error), 2);
+    }
+
+    #[test]
+    fn test_verify_strict() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("gAMA").unwrap(),
+            &[0, 0, 177, 143],
+        ));
+        let file = write_testing_png("verify_strict.png", &png);
+        let file = file.to_str().unwrap();
+        run_cli(&["verify", file]).unwrap();
+        let error = run_cli(&["verify", file, "--strict"]).unwrap_err();
+        assert_eq!(error.to_string(), "1 problem(s) found");
+    }
+
+    #[test]
+    fn test_man() {
+        let dir = temp_path("man");
+        let report = run_cli(&["man", "--out-dir", dir.to_str().unwrap()]).unwrap();
+        let pages = report.get("pages").unwrap().as_array().unwrap();
+        assert_eq!(
+            pages[0].as_str(),
+            Some(dir.join("pngsecret.1").to_str().unwrap())
+        );
+        let encode = fs::read_to_string(dir.join("pngsecret-encode.1")).unwrap();
+        assert!(encode.contains(".SH SYNOPSIS\n\\fBpngsecret encode\\fR"));
+        assert!(encode.contains("Hide a message in a new chunk of the given type"));
+        assert!(encode.contains("\\fB\\-f\\fR, \\fB\\-\\-force\\fR"));
+        // hidden, like in --help
+        assert!(!dir.join("pngsecret-man.1").exists());
+
+        let page = run_cli(&["man"]).unwrap();
+        let page = page.get("page").unwrap().as_str().unwrap();
+        assert!(page.contains("Exit codes:"));
+        assert!(page.contains("pngsecret\\-encode(1)"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+//! the completion scripts of `pngsecret completions`, written by clap_complete from
+//! the same definitions the arguments are parsed with
+
+use clap::Command;
+
+pub use clap_complete::Shell;
+
+/// completion script for `command` and its subcommands
+pub fn generate(shell: Shell, mut command: Command) -> String {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, "pngsecret", &mut script);
+    String::from_utf8(script).expect("completion scripts are utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_bash() {
+        let script = generate(Shell::Bash, Cli::command());
+        for subcommand in ["encode", "decode", "remove", "print", "extract-chunks"] {
+            assert!(script.contains(subcommand), "{} missing", subcommand);
+        }
+        assert!(script.contains("complete -F _pngsecret"));
+        // value enums complete to their values
+        assert!(script.contains("size type offset"));
+        assert!(script.contains("bash elvish fish powershell zsh"));
+        // only check the syntax where bash is around
+        if let Ok(mut bash) = std::process::Command::new("bash")
+            .arg("-n")
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+        {
+            use std::io::Write;
+            bash.stdin
+                .take()
+                .unwrap()
+                .write_all(script.as_bytes())
+                .unwrap();
+            assert!(bash.wait().unwrap().success());
+        }
+    }
+
+    #[test]
+    fn test_other_shells() {
+        let zsh = generate(Shell::Zsh, Cli::command());
+        assert!(zsh.starts_with("#compdef pngsecret"));
+        assert!(zsh.contains("':chunk_type:_default'"));
+        let fish = generate(Shell::Fish, Cli::command());
+        assert!(fish.contains("-a \"encode\""));
+        assert!(fish.contains("__fish_pngsecret_using_subcommand list\" -l sort"));
+        let powershell = generate(Shell::PowerShell, Cli::command());
+        assert!(powershell.contains("'pngsecret;decode' {"));
+        let elvish = generate(Shell::Elvish, Cli::command());
+        assert!(elvish.contains("&'pngsecret;remove'= {"));
+    }
+}
@@ -0,0 +1,90 @@
+//! the crc-32 png chunks use, the same as `crc::CRC_32_ISO_HDLC`, for builds with
+//! the no-crc-dep feature. it follows the sample code in the png spec: a table of
+//! the crcs of every byte, built the first time it is needed
+
+use std::sync::OnceLock;
+
+/// the reversed polynomial of crc-32
+const POLYNOMIAL: u32 = 0xedb88320;
+
+fn table() -> &'static [u32; 256] {
+    static TABLE: OnceLock<[u32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = match c & 1 {
+                    1 => POLYNOMIAL ^ (c >> 1),
+                    _ => c >> 1,
+                };
+            }
+            *entry = c;
+        }
+        table
+    })
+}
+
+/// a crc being computed over bytes that come in parts, like `crc::Digest`
+#[derive(Clone)]
+pub struct Digest {
+    crc: u32,
+}
+impl Digest {
+    pub fn update(&mut self, bytes: &[u8]) {
+        let table = table();
+        for &byte in bytes {
+            self.crc = table[((self.crc ^ u32::from(byte)) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+    pub fn finalize(self) -> u32 {
+        self.crc ^ 0xffffffff
+    }
+}
+pub fn digest() -> Digest {
+    Digest { crc: 0xffffffff }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksum(bytes: &[u8]) -> u32 {
+        let mut digest = digest();
+        digest.update(bytes);
+        digest.finalize()
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(b""), 0);
+        // the check value of crc-32
+        assert_eq!(checksum(b"123456789"), 0xcbf43926);
+        assert_eq!(
+            checksum(b"RuStThis is where your secret message will be!"),
+            2882656334
+        );
+        let mut digest = digest();
+        digest.update(b"RuSt");
+        digest.update(b"This is where your secret message will be!");
+        assert_eq!(digest.finalize(), 2882656334);
+    }
+
+    #[test]
+    fn test_same_as_crc_crate() {
+        let crc = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+        // an lcg instead of the crypto module's randomness, which only the cli has
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for size in [1, 3, 64, 1000, 65_537] {
+            let bytes: Vec<u8> = (0..size)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    (state >> 56) as u8
+                })
+                .collect();
+            assert_eq!(checksum(&bytes), crc.checksum(&bytes));
+        }
+    }
+}
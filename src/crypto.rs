@@ -0,0 +1,580 @@
+//! encryption of chunk payloads, with a password or a raw key
+//!
+//! an encrypted payload is a header followed by the AES-256-GCM ciphertext and tag.
+//! the header is `PSEC`, a version byte, the key derivation parameters of that
+//! version, a 16 byte salt and a 12 byte nonce, and is authenticated along with
+//! the ciphertext. version 1 derives the key with PBKDF2-SHA-256 and stores the
+//! iteration count, version 2 uses Argon2id and stores memory, passes and lanes,
+//! all as big endian u32. version 3 is keyed by a raw key from a key file, its
+//! header is only `PSEC`, the version and the nonce. version 4 is encrypted to
+//! public keys, after the version come the number of recipients, a stanza with
+//! the wrapped key for each, and the nonce. version 5 holds a message and a
+//! decoy under two passwords, see the decoy module, and is what every payload
+//! encrypted with a password is written as, so a decoy doesn't show. versions 1
+//! and 2 are still read, and sealed stealth parts are version 2 without the
+//! header. new versions can be added without breaking old payloads.
+//!
+//! a signed payload keeps the message readable and only proves it unchanged,
+//! see the integrity module. an Ed25519 signature proves who a payload came
+//! from, see the signing module.
+
+mod bech32;
+mod decoy;
+mod deterministic;
+mod ed25519;
+mod gcm;
+mod integrity;
+mod kdf;
+mod recipient;
+mod sha256;
+mod signing;
+mod x25519;
+
+use std::{
+    io::Read,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+pub use decoy::encrypt as encrypt_with_password;
+pub use deterministic::{deterministic, mix};
+pub use integrity::{is_signed, sign, verify};
+pub use kdf::Argon2Params;
+pub use recipient::{Identity, Recipient};
+pub use sha256::sha256;
+pub use signing::{hex, read_verifying_key, verify as verify_signature, SigningKey};
+
+use crate::{error::PngSecretError, Result};
+use gcm::{AesGcm, NONCE_SIZE, TAG_SIZE};
+
+const MAGIC: &[u8; 4] = b"PSEC";
+const SALT_SIZE: usize = 16;
+const KEY_FILE_VERSION: u8 = 3;
+const RECIPIENT_VERSION: u8 = 4;
+const PASSWORD_VERSION: u8 = 5;
+pub const KEY_SIZE: usize = 32;
+
+/// the bytes of memory Argon2 may take for a payload from a header, u64::MAX for
+/// only the Argon2Params maximum
+static MAX_MEMORY: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// --max-memory, so an untrusted header can't ask for more than the user allows
+pub fn limit_memory(bytes: u64) {
+    MAX_MEMORY.store(bytes, Ordering::Relaxed);
+}
+/// `params` from a header, checked before anything is allocated for them
+fn read_params(params: Argon2Params) -> Result<Argon2Params> {
+    check_params(params, MAX_MEMORY.load(Ordering::Relaxed))
+}
+fn check_params(params: Argon2Params, max_memory: u64) -> Result<Argon2Params> {
+    params.validate().map_err(PngSecretError::InvalidData)?;
+    if u64::from(params.memory) * 1024 > max_memory {
+        return Err(PngSecretError::InvalidData(format!(
+            "The payload asks Argon2 for {} KiB of memory, more than --max-memory allows",
+            params.memory
+        )));
+    }
+    Ok(params)
+}
+
+/// what a payload is decrypted with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Secret {
+    Password(String),
+    Key([u8; KEY_SIZE]),
+    Identities(Vec<Identity>),
+}
+
+/// how the key is derived from the password, recorded in the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    Pbkdf2 { iterations: u32 },
+    Argon2id(Argon2Params),
+}
+impl Kdf {
+    fn version(&self) -> u8 {
+        match self {
+            Kdf::Pbkdf2 { .. } => 1,
+            Kdf::Argon2id(_) => 2,
+        }
+    }
+    fn params(&self) -> Vec<u32> {
+        match *self {
+            Kdf::Pbkdf2 { iterations } => vec![iterations],
+            Kdf::Argon2id(params) => vec![params.memory, params.passes, params.lanes],
+        }
+    }
+    /// how many u32 parameters `version` has, 0 for an unknown one
+    fn count(version: u8) -> usize {
+        match version {
+            1 => 1,
+            2 => 3,
+            _ => 0,
+        }
+    }
+    /// the parameters of `version` at the start of `fields`
+    fn read(version: u8, fields: &mut &[u8]) -> Result<Kdf> {
+        let count = match Kdf::count(version) {
+            0 => {
+                return Err(PngSecretError::InvalidData(format!(
+                    "Unsupported encryption version {}",
+                    version
+                )))
+            }
+            count => count,
+        };
+        if fields.len() < 4 * count {
+            return Err(PngSecretError::InvalidData(
+                "The encrypted payload is truncated".into(),
+            ));
+        }
+        let mut values = (0..count).map(|_| u32::from_be_bytes(take(fields)));
+        let mut next = || values.next().unwrap();
+        let kdf = match version {
+            1 => match next() {
+                0 => {
+                    return Err(PngSecretError::InvalidData(
+                        "The payload asks for 0 PBKDF2 iterations".into(),
+                    ))
+                }
+                iterations if iterations > kdf::PBKDF2_MAX_ITERATIONS => {
+                    return Err(PngSecretError::InvalidData(format!(
+                        "The payload asks for {} PBKDF2 iterations, at most {} are allowed",
+                        iterations,
+                        kdf::PBKDF2_MAX_ITERATIONS
+                    )))
+                }
+                iterations => Kdf::Pbkdf2 { iterations },
+            },
+            _ => Kdf::Argon2id(read_params(Argon2Params {
+                memory: next(),
+                passes: next(),
+                lanes: next(),
+            })?),
+        };
+        Ok(kdf)
+    }
+    fn derive(&self, password: &str, salt: &[u8]) -> [u8; 32] {
+        match *self {
+            Kdf::Pbkdf2 { iterations } => kdf::pbkdf2_sha256(password.as_bytes(), salt, iterations),
+            Kdf::Argon2id(params) => kdf::argon2id(password.as_bytes(), salt, params),
+        }
+    }
+}
+
+/// whether `data` starts with the header of an encrypted payload
+pub fn is_encrypted(data: &[u8]) -> bool {
+    // versions are small, so a text message that happens to start with PSEC is left alone
+    data.starts_with(MAGIC) && data.get(MAGIC.len()).is_some_and(|&version| version < 0x20)
+}
+/// whether `data` was encrypted or signed with a key file rather than a password
+pub fn uses_key_file(data: &[u8]) -> bool {
+    (is_encrypted(data) || is_signed(data)) && data[MAGIC.len()] == KEY_FILE_VERSION
+}
+/// whether `data` was encrypted to public keys rather than with a password
+pub fn uses_recipients(data: &[u8]) -> bool {
+    is_encrypted(data) && data[MAGIC.len()] == RECIPIENT_VERSION
+}
+
+/// the key in `path`, which has to be exactly 32 bytes
+pub fn read_key_file(path: &Path) -> Result<[u8; KEY_SIZE]> {
+    let key = std::fs::read(path).map_err(|e| {
+        PngSecretError::Failed(format!("Could not read key file {}: {}", path.display(), e))
+    })?;
+    key.as_slice().try_into().map_err(|_| {
+        PngSecretError::InvalidData(format!(
+            "Key file {} has {} bytes, a key is exactly {} bytes",
+            path.display(),
+            key.len(),
+            KEY_SIZE
+        ))
+    })
+}
+
+/// a single version 1 or 2 payload, for stealth parts. a fresh random salt and nonce
+/// every time, so equal messages don't look equal
+pub fn encrypt_with(plaintext: &[u8], password: &str, kdf: Kdf) -> Result<Vec<u8>> {
+    let salt: [u8; SALT_SIZE] = random_bytes()?;
+    let nonce: [u8; NONCE_SIZE] = random_bytes()?;
+    let mut payload = MAGIC.to_vec();
+    payload.push(kdf.version());
+    for value in kdf.params() {
+        payload.extend_from_slice(&value.to_be_bytes());
+    }
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    let key = kdf.derive(password, &salt);
+    let sealed = AesGcm::new(&key).encrypt(&nonce, &payload, plaintext);
+    payload.extend_from_slice(&sealed);
+    Ok(payload)
+}
+/// `payload` of encrypt_with, encrypt_with_key or encrypt_to without `PSEC`, the
+/// version and the key derivation parameters, for what has to look like random bytes.
+/// what is left is the salt or the stanzas, the nonce and the ciphertext, and for
+/// recipients the number of them in front
+pub fn strip_header(payload: &[u8]) -> &[u8] {
+    let size = match payload[MAGIC.len()] {
+        KEY_FILE_VERSION | RECIPIENT_VERSION => MAGIC.len() + 1,
+        version => MAGIC.len() + 1 + 4 * Kdf::count(version),
+    };
+    &payload[size..]
+}
+/// decrypts what strip_header left of a payload, with the header `secret` and `params`
+/// say it had put back in front
+pub fn decrypt_stripped(stripped: &[u8], secret: &Secret, params: Argon2Params) -> Result<Vec<u8>> {
+    let mut payload = MAGIC.to_vec();
+    match secret {
+        Secret::Key(_) => payload.push(KEY_FILE_VERSION),
+        Secret::Identities(_) => payload.push(RECIPIENT_VERSION),
+        Secret::Password(_) => {
+            let kdf = Kdf::Argon2id(params);
+            payload.push(kdf.version());
+            for value in kdf.params() {
+                payload.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+    payload.extend_from_slice(stripped);
+    decrypt(&payload, secret)
+}
+/// the identities in the identity file at `path`
+pub fn read_identity_file(path: &Path) -> Result<Vec<Identity>> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        PngSecretError::Failed(format!(
+            "Could not read identity file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    recipient::parse_identities(&text).map_err(|e| {
+        PngSecretError::InvalidData(format!("Identity file {}: {}", path.display(), e))
+    })
+}
+
+/// encrypts so that the identity of any of `recipients` can decrypt
+pub fn encrypt_to(plaintext: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
+    let count = u8::try_from(recipients.len())
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| {
+            PngSecretError::Usage("Encrypting needs between 1 and 255 recipients".into())
+        })?;
+    let file_key: [u8; KEY_SIZE] = random_bytes()?;
+    let nonce: [u8; NONCE_SIZE] = random_bytes()?;
+    let mut payload = MAGIC.to_vec();
+    payload.extend([RECIPIENT_VERSION, count]);
+    for recipient in recipients {
+        payload.extend(recipient::wrap(&file_key, recipient)?);
+    }
+    payload.extend_from_slice(&nonce);
+    let sealed = AesGcm::new(&file_key).encrypt(&nonce, &payload, plaintext);
+    payload.extend_from_slice(&sealed);
+    Ok(payload)
+}
+
+/// encrypts with `key` as is, no key derivation needed
+pub fn encrypt_with_key(plaintext: &[u8], key: &[u8; KEY_SIZE]) -> Result<Vec<u8>> {
+    let nonce: [u8; NONCE_SIZE] = random_bytes()?;
+    let mut payload = MAGIC.to_vec();
+    payload.push(KEY_FILE_VERSION);
+    payload.extend_from_slice(&nonce);
+    let sealed = AesGcm::new(key).encrypt(&nonce, &payload, plaintext);
+    payload.extend_from_slice(&sealed);
+    Ok(payload)
+}
+
+pub fn decrypt(payload: &[u8], secret: &Secret) -> Result<Vec<u8>> {
+    if !is_encrypted(payload) {
+        return Err(PngSecretError::InvalidData(
+            "The chunk is not encrypted".into(),
+        ));
+    }
+    let version = payload[MAGIC.len()];
+    let mut fields = &payload[MAGIC.len() + 1..];
+    let key = match (version, secret) {
+        (KEY_FILE_VERSION, Secret::Key(key)) => *key,
+        (KEY_FILE_VERSION, _) => return Err(wrong_mode("encrypted with a key file", "--key-file")),
+        (RECIPIENT_VERSION, Secret::Identities(identities)) => {
+            unwrap_file_key(&mut fields, identities)?
+        }
+        (RECIPIENT_VERSION, _) => {
+            return Err(wrong_mode("encrypted to a public key", "--identity"))
+        }
+        (PASSWORD_VERSION, Secret::Password(password)) => return decoy::decrypt(payload, password),
+        (PASSWORD_VERSION, _) => return Err(wrong_mode("encrypted with a password", "--password")),
+        (_, Secret::Password(password)) => {
+            let kdf = Kdf::read(version, &mut fields)?;
+            if fields.len() < SALT_SIZE {
+                return Err(PngSecretError::InvalidData(
+                    "The encrypted payload is truncated".into(),
+                ));
+            }
+            let salt: [u8; SALT_SIZE] = take(&mut fields);
+            kdf.derive(password, &salt)
+        }
+        _ => {
+            // an unknown version is reported as such
+            Kdf::read(version, &mut fields)?;
+            return Err(wrong_mode("encrypted with a password", "--password"));
+        }
+    };
+    if fields.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(PngSecretError::InvalidData(
+            "The encrypted payload is truncated".into(),
+        ));
+    }
+    let nonce: [u8; NONCE_SIZE] = take(&mut fields);
+    let (header, sealed) = payload.split_at(payload.len() - fields.len());
+    AesGcm::new(&key)
+        .decrypt(&nonce, header, sealed)
+        .ok_or(PngSecretError::DecryptionFailed)
+}
+fn wrong_mode(how: &str, flag: &str) -> PngSecretError {
+    PngSecretError::Usage(format!("The chunk was {}, use {}", how, flag))
+}
+/// the file key from the first stanza one of `identities` can unwrap
+fn unwrap_file_key(fields: &mut &[u8], identities: &[Identity]) -> Result<[u8; KEY_SIZE]> {
+    let count = *fields
+        .first()
+        .ok_or_else(|| PngSecretError::InvalidData("The encrypted payload is truncated".into()))?
+        as usize;
+    if fields.len() < 1 + count * recipient::STANZA_SIZE {
+        return Err(PngSecretError::InvalidData(
+            "The encrypted payload is truncated".into(),
+        ));
+    }
+    let (stanzas, rest) = fields[1..].split_at(count * recipient::STANZA_SIZE);
+    *fields = rest;
+    stanzas
+        .chunks(recipient::STANZA_SIZE)
+        .find_map(|stanza| {
+            identities
+                .iter()
+                .find_map(|identity| recipient::unwrap(stanza, identity))
+        })
+        .ok_or(PngSecretError::DecryptionFailed)
+}
+/// splits the next N bytes off `fields`, which has to hold them
+fn take<const N: usize>(fields: &mut &[u8]) -> [u8; N] {
+    let (taken, rest) = fields.split_at(N);
+    *fields = rest;
+    taken.try_into().unwrap()
+}
+
+/// from the system, or from the seed of `deterministic` while it is in effect
+pub fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    if deterministic::fill(&mut bytes) {
+        return Ok(bytes);
+    }
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .map_err(|e| PngSecretError::Failed(format!("Could not read random bytes: {}", e)))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // cheap parameters, they are in the header anyway
+    const CHEAP: Kdf = Kdf::Argon2id(Argon2Params {
+        memory: 8,
+        passes: 1,
+        lanes: 1,
+    });
+    const HEADER_SIZE: usize = MAGIC.len() + 1 + 12 + SALT_SIZE + NONCE_SIZE;
+
+    fn encrypt(plaintext: &[u8], password: &str) -> Vec<u8> {
+        encrypt_with(plaintext, password, CHEAP).unwrap()
+    }
+    fn decrypt(payload: &[u8], password: &str) -> Result<Vec<u8>> {
+        super::decrypt(payload, &Secret::Password(password.to_string()))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let payload = encrypt(b"secret message", "hunter2");
+        assert!(is_encrypted(&payload));
+        assert_eq!(payload.len(), HEADER_SIZE + 14 + TAG_SIZE);
+        assert_eq!(decrypt(&payload, "hunter2").unwrap(), b"secret message");
+        assert_eq!(decrypt(&encrypt(b"", "pw"), "pw").unwrap(), b"");
+    }
+
+    #[test]
+    fn test_fresh_salt_and_nonce() {
+        let first = encrypt(b"secret message", "hunter2");
+        let second = encrypt(b"secret message", "hunter2");
+        assert_ne!(first[HEADER_SIZE..], second[HEADER_SIZE..]);
+        assert_ne!(first[..HEADER_SIZE], second[..HEADER_SIZE]);
+        assert_eq!(decrypt(&first, "hunter2").unwrap(), b"secret message");
+        assert_eq!(decrypt(&second, "hunter2").unwrap(), b"secret message");
+    }
+
+    #[test]
+    fn test_versions() {
+        let pbkdf2 = encrypt_with(b"old", "pw", Kdf::Pbkdf2 { iterations: 10 }).unwrap();
+        assert_eq!(pbkdf2[MAGIC.len()], 1);
+        assert_eq!(decrypt(&pbkdf2, "pw").unwrap(), b"old");
+        let argon2 = encrypt(b"new", "pw");
+        assert_eq!(argon2[MAGIC.len()], 2);
+        assert_eq!(&argon2[5..17], &[0, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0, 1]);
+        let mut unknown = argon2.clone();
+        unknown[MAGIC.len()] = 9;
+        let error = decrypt(&unknown, "pw").unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported encryption version 9");
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let payload = encrypt(b"secret message", "hunter2");
+        let error = decrypt(&payload, "hunter3").unwrap_err();
+        assert!(matches!(error, PngSecretError::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let payload = encrypt(b"secret message", "hunter2");
+        // the ciphertext, the tag and the salt
+        for index in [
+            HEADER_SIZE + 3,
+            payload.len() - 1,
+            HEADER_SIZE - NONCE_SIZE - 1,
+        ] {
+            let mut tampered = payload.clone();
+            tampered[index] ^= 1;
+            assert!(matches!(
+                decrypt(&tampered, "hunter2").unwrap_err(),
+                PngSecretError::DecryptionFailed
+            ));
+        }
+        assert!(decrypt(&payload[..HEADER_SIZE + 4], "hunter2").is_err());
+        assert!(decrypt(&payload[..7], "hunter2").is_err());
+        assert!(decrypt(b"plain text", "hunter2").is_err());
+        assert!(!is_encrypted(b"PSECret message"));
+    }
+
+    #[test]
+    fn test_key_file() {
+        let key = [7; KEY_SIZE];
+        let payload = encrypt_with_key(b"automated", &key).unwrap();
+        assert!(uses_key_file(&payload));
+        assert_eq!(payload.len(), MAGIC.len() + 1 + NONCE_SIZE + 9 + TAG_SIZE);
+        assert_eq!(
+            super::decrypt(&payload, &Secret::Key(key)).unwrap(),
+            b"automated"
+        );
+        let wrong = super::decrypt(&payload, &Secret::Key([8; KEY_SIZE])).unwrap_err();
+        assert!(matches!(wrong, PngSecretError::DecryptionFailed));
+        let error = decrypt(&payload, "pw").unwrap_err();
+        assert!(error.to_string().contains("--key-file"));
+
+        let password = encrypt(b"typed", "pw");
+        assert!(!uses_key_file(&password));
+        let error = super::decrypt(&password, &Secret::Key(key)).unwrap_err();
+        assert!(error.to_string().contains("--password"));
+    }
+
+    #[test]
+    fn test_recipients() {
+        let alice = Identity::generate().unwrap();
+        let bob = Identity::generate().unwrap();
+        let payload = encrypt_to(b"for both", &[alice.recipient(), bob.recipient()]).unwrap();
+        assert!(uses_recipients(&payload));
+        for identity in [&alice, &bob] {
+            let secret = Secret::Identities(vec![identity.clone()]);
+            assert_eq!(super::decrypt(&payload, &secret).unwrap(), b"for both");
+        }
+        let eve = Secret::Identities(vec![Identity::generate().unwrap()]);
+        assert!(matches!(
+            super::decrypt(&payload, &eve).unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+        assert!(decrypt(&payload, "pw")
+            .unwrap_err()
+            .to_string()
+            .contains("--identity"));
+
+        // the stanzas are authenticated with the rest of the header
+        let mut tampered = payload.clone();
+        tampered[MAGIC.len() + 3] ^= 1;
+        assert!(super::decrypt(&tampered, &Secret::Identities(vec![bob])).is_err());
+        assert!(encrypt_to(b"nobody", &[]).is_err());
+    }
+
+    #[test]
+    fn test_strip_header() {
+        let payload = encrypt(b"secret message", "hunter2");
+        let stripped = strip_header(&payload);
+        assert_eq!(
+            stripped.len(),
+            payload.len() - HEADER_SIZE + SALT_SIZE + NONCE_SIZE
+        );
+        assert_eq!(stripped, &payload[MAGIC.len() + 13..]);
+        let password = Secret::Password("hunter2".to_string());
+        let params = Argon2Params {
+            memory: 8,
+            passes: 1,
+            lanes: 1,
+        };
+        assert_eq!(
+            decrypt_stripped(stripped, &password, params).unwrap(),
+            b"secret message"
+        );
+        // the parameters aren't in what is left, the wrong ones make another key
+        let other = Argon2Params {
+            passes: 2,
+            ..params
+        };
+        assert!(matches!(
+            decrypt_stripped(stripped, &password, other).unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+
+        let key = [7; KEY_SIZE];
+        let payload = encrypt_with_key(b"automated", &key).unwrap();
+        let stripped = strip_header(&payload);
+        assert_eq!(stripped.len(), payload.len() - MAGIC.len() - 1);
+        assert_eq!(
+            decrypt_stripped(stripped, &Secret::Key(key), params).unwrap(),
+            b"automated"
+        );
+
+        let alice = Identity::generate().unwrap();
+        let payload = encrypt_to(b"for alice", &[alice.recipient()]).unwrap();
+        let stripped = strip_header(&payload);
+        assert_eq!(stripped[0], 1);
+        let identities = Secret::Identities(vec![alice]);
+        assert_eq!(
+            decrypt_stripped(stripped, &identities, params).unwrap(),
+            b"for alice"
+        );
+    }
+
+    #[test]
+    fn test_hostile_header() {
+        // memory and passes a crafted header asks for are refused before anything is allocated
+        let payload = encrypt(b"secret message", "hunter2");
+        let mut memory = payload.clone();
+        memory[5..9].copy_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+        let error = decrypt(&memory, "hunter2").unwrap_err();
+        assert!(error.to_string().contains("at most 1048576 KiB"));
+        let mut passes = payload.clone();
+        passes[9..13].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = decrypt(&passes, "hunter2").unwrap_err();
+        assert!(error.to_string().contains("at most 32 passes"));
+
+        let params = Argon2Params {
+            memory: 1024,
+            ..Argon2Params::DEFAULT
+        };
+        assert!(check_params(params, 1 << 20).is_ok());
+        let error = check_params(params, (1 << 20) - 1).unwrap_err();
+        assert!(error.to_string().contains("--max-memory"));
+
+        let mut iterations = encrypt_with(b"old", "pw", Kdf::Pbkdf2 { iterations: 10 }).unwrap();
+        iterations[5..9].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = decrypt(&iterations, "pw").unwrap_err();
+        assert!(error.to_string().contains("at most 10000000 are allowed"));
+    }
+}
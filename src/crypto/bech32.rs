@@ -0,0 +1,110 @@
+//! bech32 from BIP 173, the encoding of age keys. unlike BIP 173 there is no
+//! length limit, like in age
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ff_ffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+fn expand_hrp(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    let high = hrp.bytes().map(|b| b >> 5);
+    let low = hrp.bytes().map(|b| b & 31);
+    high.chain([0]).chain(low)
+}
+
+/// regroups bits, from `from` bits per value to `to`, padding the last group
+fn convert(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let (mut acc, mut bits, mut out) = (0u32, 0, Vec::new());
+    for &value in data {
+        acc = acc << from | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push((acc >> bits & ((1 << to) - 1)) as u8);
+        }
+    }
+    if pad && bits > 0 {
+        out.push((acc << (to - bits) & ((1 << to) - 1)) as u8);
+    } else if !pad && (bits >= from || acc & ((1 << bits) - 1) != 0) {
+        return None;
+    }
+    Some(out)
+}
+
+/// `hrp` with `data`, in lowercase
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    let values = convert(data, 8, 5, true).unwrap();
+    let checksum = polymod(expand_hrp(hrp).chain(values.iter().copied()).chain([0; 6])) ^ 1;
+    let mut encoded = format!("{}1", hrp);
+    for value in values {
+        encoded.push(CHARSET[value as usize] as char);
+    }
+    for i in (0..6).rev() {
+        encoded.push(CHARSET[(checksum >> (5 * i) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// the human readable part, lowercase, and the data of `encoded`
+pub fn decode(encoded: &str) -> Result<(String, Vec<u8>), String> {
+    let lower = encoded.to_ascii_lowercase();
+    if lower != encoded && encoded.to_ascii_uppercase() != encoded {
+        return Err("Mixed case in bech32 string".to_string());
+    }
+    let (hrp, data) = lower
+        .rsplit_once('1')
+        .filter(|(hrp, data)| !hrp.is_empty() && data.len() >= 6)
+        .ok_or("Not a bech32 string")?;
+    let values = data
+        .bytes()
+        .map(|b| {
+            CHARSET
+                .iter()
+                .position(|&c| c == b)
+                .map(|value| value as u8)
+        })
+        .collect::<Option<Vec<u8>>>()
+        .ok_or("Invalid character in bech32 string")?;
+    if polymod(expand_hrp(hrp).chain(values.iter().copied())) != 1 {
+        return Err("Invalid bech32 checksum".to_string());
+    }
+    let data = convert(&values[..values.len() - 6], 5, 8, false).ok_or("Invalid bech32 padding")?;
+    Ok((hrp.to_string(), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bip173_vectors() {
+        assert_eq!(decode("A12UEL5L").unwrap(), ("a".to_string(), vec![]));
+        assert!(decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").is_ok());
+        assert!(decode("a12uel5m").is_err());
+        assert!(decode("A12uEL5L").is_err());
+        assert!(decode("pzry9x0s0muk").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data: Vec<u8> = (0..32).collect();
+        let encoded = encode("age", &data);
+        assert!(encoded.starts_with("age1"));
+        assert_eq!(decode(&encoded).unwrap(), ("age".to_string(), data.clone()));
+        assert_eq!(decode(&encoded.to_uppercase()).unwrap().1, data);
+        let mut typo = encoded.into_bytes();
+        typo[10] = if typo[10] == b'q' { b'p' } else { b'q' };
+        assert!(decode(std::str::from_utf8(&typo).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! two messages under two passwords in one payload, for plausible deniability.
+//!
+//! after `PSEC` and version 5 come the Argon2id parameters, the padded message
+//! size as a big endian u32 and two slots of salt, nonce and sealed message.
+//! each message is prefixed with its length and padded with zeros to the same
+//! size, and the slots are in random order, so nothing tells the real one from
+//! the decoy. a password opens the one slot it was used for.
+//!
+//! every payload encrypted with a password is written this way, without a decoy
+//! the other slot is random bytes no password opens, so neither the version nor
+//! the size gives a decoy away.
+
+use super::gcm::{AesGcm, NONCE_SIZE, TAG_SIZE};
+use super::{
+    random_bytes, read_params, take, Argon2Params, Kdf, MAGIC, PASSWORD_VERSION, SALT_SIZE,
+};
+use crate::{error::PngSecretError, Result};
+
+/// `message` under `password`, and the decoy under its own password when there is one
+pub fn encrypt(
+    message: &[u8],
+    password: &str,
+    decoy: Option<(&[u8], &str)>,
+    params: Argon2Params,
+) -> Result<Vec<u8>> {
+    if decoy.is_some_and(|(_, decoy_password)| decoy_password == password) {
+        return Err(PngSecretError::Usage(
+            "The decoy password has to differ from the password".into(),
+        ));
+    }
+    let kdf = Kdf::Argon2id(params);
+    let decoy_size = decoy.map_or(0, |(decoy, _)| decoy.len());
+    let padded_size = (4 + message.len().max(decoy_size)).next_multiple_of(16);
+    let padded_size_u32 = u32::try_from(padded_size)
+        .map_err(|_| PngSecretError::Usage("The message is too big".into()))?;
+    let mut payload = MAGIC.to_vec();
+    payload.push(PASSWORD_VERSION);
+    for value in kdf.params() {
+        payload.extend_from_slice(&value.to_be_bytes());
+    }
+    payload.extend_from_slice(&padded_size_u32.to_be_bytes());
+    let header = payload.clone();
+
+    let mut slots = [Some((message, password)), decoy];
+    if random_bytes::<1>()?[0] & 1 == 1 {
+        slots.swap(0, 1);
+    }
+    for slot in slots {
+        let salt: [u8; SALT_SIZE] = random_bytes()?;
+        let nonce: [u8; NONCE_SIZE] = random_bytes()?;
+        let Some((message, password)) = slot else {
+            // looks like a sealed message, as salts, nonces and ciphertexts look random
+            payload.extend_from_slice(&salt);
+            payload.extend_from_slice(&nonce);
+            payload.extend(random(padded_size + TAG_SIZE)?);
+            continue;
+        };
+        let mut padded = (message.len() as u32).to_be_bytes().to_vec();
+        padded.extend_from_slice(message);
+        padded.resize(padded_size, 0);
+        let key = kdf.derive(password, &salt);
+        payload.extend_from_slice(&salt);
+        payload.extend_from_slice(&nonce);
+        payload.extend(AesGcm::new(&key).encrypt(&nonce, &header, &padded));
+    }
+    Ok(payload)
+}
+
+/// the message of whichever slot `password` opens
+pub fn decrypt(payload: &[u8], password: &str) -> Result<Vec<u8>> {
+    let mut fields = &payload[MAGIC.len() + 1..];
+    if fields.len() < 16 {
+        return Err(PngSecretError::InvalidData(
+            "The encrypted payload is truncated".into(),
+        ));
+    }
+    let mut next = || u32::from_be_bytes(take(&mut fields));
+    let params = read_params(Argon2Params {
+        memory: next(),
+        passes: next(),
+        lanes: next(),
+    })?;
+    let padded_size = next() as usize;
+    let slot_size = SALT_SIZE + NONCE_SIZE + padded_size + TAG_SIZE;
+    if fields.len() != 2 * slot_size {
+        return Err(PngSecretError::InvalidData(
+            "The encrypted payload is truncated".into(),
+        ));
+    }
+    let header = &payload[..payload.len() - fields.len()];
+    let kdf = Kdf::Argon2id(params);
+    for mut slot in fields.chunks(slot_size) {
+        let salt: [u8; SALT_SIZE] = take(&mut slot);
+        let nonce: [u8; NONCE_SIZE] = take(&mut slot);
+        let key = kdf.derive(password, &salt);
+        if let Some(padded) = AesGcm::new(&key).decrypt(&nonce, header, slot) {
+            // a slot too short for its length only comes from a crafted payload
+            let Some((length, rest)) = padded.split_first_chunk::<4>() else {
+                return Err(PngSecretError::DecryptionFailed);
+            };
+            let message = rest
+                .get(..u32::from_be_bytes(*length) as usize)
+                .ok_or_else(|| {
+                    PngSecretError::InvalidData("The encrypted message length is wrong".into())
+                })?;
+            return Ok(message.to_vec());
+        }
+    }
+    Err(PngSecretError::DecryptionFailed)
+}
+/// `size` random bytes, in blocks of what random_bytes gives at once
+fn random(size: usize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(size.next_multiple_of(64));
+    while bytes.len() < size {
+        bytes.extend(random_bytes::<64>()?);
+    }
+    bytes.truncate(size);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHEAP: Argon2Params = Argon2Params {
+        memory: 8,
+        passes: 1,
+        lanes: 1,
+    };
+
+    #[test]
+    fn test_both_passwords() {
+        let decoy = Some((&b"shopping list"[..], "decoy"));
+        let payload = encrypt(b"the real plans", "real", decoy, CHEAP).unwrap();
+        assert_eq!(decrypt(&payload, "real").unwrap(), b"the real plans");
+        assert_eq!(decrypt(&payload, "decoy").unwrap(), b"shopping list");
+        assert!(matches!(
+            decrypt(&payload, "third").unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+        assert!(encrypt(b"a", "same", Some((b"b", "same")), CHEAP).is_err());
+    }
+
+    #[test]
+    fn test_without_decoy() {
+        // as big as with a decoy, and the random slot opens with no password
+        let payload = encrypt(b"the real plans", "real", None, CHEAP).unwrap();
+        let decoy = Some((&b"shopping list"[..], "decoy"));
+        let with_decoy = encrypt(b"the real plans", "real", decoy, CHEAP).unwrap();
+        assert_eq!(payload.len(), with_decoy.len());
+        assert_eq!(payload[..21], with_decoy[..21]);
+        assert_eq!(decrypt(&payload, "real").unwrap(), b"the real plans");
+        assert!(matches!(
+            decrypt(&payload, "").unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+    }
+
+    #[test]
+    fn test_same_size_slots() {
+        // a long decoy and a short message look like a short decoy and a long message
+        let first = encrypt(b"short", "a", Some((&[b'x'; 40], "b")), CHEAP).unwrap();
+        let second = encrypt(&[b'x'; 40], "a", Some((b"short", "b")), CHEAP).unwrap();
+        assert_eq!(first.len(), second.len());
+        assert_eq!(first[..21], second[..21]);
+        let mut tampered = first.clone();
+        tampered[10] ^= 1;
+        assert!(decrypt(&tampered, "a").is_err());
+    }
+
+    #[test]
+    fn test_hostile_header() {
+        let mut payload = encrypt(b"message", "a", Some((b"decoy", "b")), CHEAP).unwrap();
+        payload[5..9].copy_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+        assert!(decrypt(&payload, "a").is_err());
+
+        // a slot sealed too short to hold its length fails like a wrong password
+        let mut header = MAGIC.to_vec();
+        header.push(PASSWORD_VERSION);
+        for value in [8, 1, 1, 0] {
+            header.extend_from_slice(&u32::to_be_bytes(value));
+        }
+        let mut crafted = header.clone();
+        for _ in 0..2 {
+            let salt = [1; SALT_SIZE];
+            let nonce = [2; NONCE_SIZE];
+            let key = Kdf::Argon2id(CHEAP).derive("a", &salt);
+            crafted.extend_from_slice(&salt);
+            crafted.extend_from_slice(&nonce);
+            crafted.extend(AesGcm::new(&key).encrypt(&nonce, &header, b""));
+        }
+        assert!(matches!(
+            decrypt(&crafted, "a").unwrap_err(),
+            PngSecretError::DecryptionFailed
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+//! `encode --deterministic`: the random bytes of the current thread drawn from a
+//! stream keyed by a seed instead of the system, so the same inputs give the same
+//! file. the stream is HMAC-SHA-256 of a counter under a key derived from the seed,
+//! and the message is mixed into the key before anything is encrypted, so two
+//! messages under one seed don't share their nonces.
+//!
+//! this gives up what fresh randomness is for: anyone with the seed knows every salt,
+//! nonce and key that isn't derived from the password, and the same seed, password
+//! and message always encrypt to the same bytes, which tells that a message repeats
+
+use std::cell::RefCell;
+
+use super::sha256::hmac_sha256;
+
+thread_local! {
+    static STREAM: RefCell<Option<Stream>> = const { RefCell::new(None) };
+}
+
+struct Stream {
+    key: [u8; 32],
+    counter: u64,
+}
+
+/// the random bytes of this thread come from `seed` until it is dropped
+pub struct Deterministic(());
+impl Drop for Deterministic {
+    fn drop(&mut self) {
+        STREAM.with(|stream| stream.borrow_mut().take());
+    }
+}
+
+pub fn deterministic(seed: &[u8]) -> Deterministic {
+    let key = hmac_sha256(b"pngsecret deterministic", seed);
+    STREAM.with(|stream| *stream.borrow_mut() = Some(Stream { key, counter: 0 }));
+    Deterministic(())
+}
+
+/// mixes `input` into the key of the stream, if there is one
+pub fn mix(input: &[u8]) {
+    STREAM.with(|stream| {
+        if let Some(stream) = stream.borrow_mut().as_mut() {
+            stream.key = hmac_sha256(&stream.key, input);
+            stream.counter = 0;
+        }
+    });
+}
+
+/// fills `bytes` from the stream, false without one
+pub(super) fn fill(bytes: &mut [u8]) -> bool {
+    STREAM.with(|stream| {
+        let mut stream = stream.borrow_mut();
+        let Some(stream) = stream.as_mut() else {
+            return false;
+        };
+        for chunk in bytes.chunks_mut(32) {
+            let block = hmac_sha256(&stream.key, &stream.counter.to_be_bytes());
+            stream.counter += 1;
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        true
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::random_bytes;
+
+    #[test]
+    fn test_deterministic() {
+        let draw = |seed: &[u8], message: &[u8]| {
+            let _deterministic = deterministic(seed);
+            let before: [u8; 40] = random_bytes().unwrap();
+            mix(message);
+            (before, random_bytes::<40>().unwrap())
+        };
+        assert_eq!(draw(b"seed", b"message"), draw(b"seed", b"message"));
+        assert_ne!(draw(b"seed", b"message").0, draw(b"other", b"message").0);
+        let (before, after) = draw(b"seed", b"message");
+        assert_eq!(before, draw(b"seed", b"another").0);
+        assert_ne!(after, draw(b"seed", b"another").1);
+        assert_ne!(after, before);
+        // the system again once the guard is gone
+        assert_ne!(random_bytes::<32>().unwrap(), random_bytes::<32>().unwrap());
+        mix(b"nothing to mix into");
+        assert_ne!(random_bytes::<32>().unwrap(), random_bytes::<32>().unwrap());
+    }
+}
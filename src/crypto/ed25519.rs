@@ -0,0 +1,108 @@
+//! Ed25519 signatures from RFC 8032
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+pub const SEED_SIZE: usize = 32;
+pub const PUBLIC_KEY_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// the public key of the secret `seed`
+pub fn public_key(seed: &[u8; SEED_SIZE]) -> [u8; PUBLIC_KEY_SIZE] {
+    SigningKey::from_bytes(seed).verifying_key().to_bytes()
+}
+
+/// the signature of `message` by the secret `seed`
+pub fn sign(seed: &[u8; SEED_SIZE], message: &[u8]) -> [u8; SIGNATURE_SIZE] {
+    SigningKey::from_bytes(seed).sign(message).to_bytes()
+}
+
+/// whether `signature` is a signature of `message` by `public_key`
+pub fn verify(
+    public_key: &[u8; PUBLIC_KEY_SIZE],
+    message: &[u8],
+    signature: &[u8; SIGNATURE_SIZE],
+) -> bool {
+    // strict also refuses small order keys, and the crate refuses an s that isn't
+    // reduced, or the signature could be changed without the key
+    VerifyingKey::from_bytes(public_key).is_ok_and(|key| {
+        key.verify_strict(message, &Signature::from_bytes(signature))
+            .is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        bytes.try_into().unwrap()
+    }
+
+    // RFC 8032 section 7.1, tests 1 to 3
+    #[test]
+    fn test_rfc_8032() {
+        let cases = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                "af82",
+                "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+            ),
+        ];
+        for (seed, public, message, signature) in cases {
+            let seed = bytes::<32>(seed);
+            let message: Vec<u8> = (0..message.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&message[i..i + 2], 16).unwrap())
+                .collect();
+            let signature = bytes::<64>(signature);
+            assert_eq!(public_key(&seed), bytes::<32>(public));
+            assert_eq!(sign(&seed, &message), signature);
+            assert!(verify(&bytes(public), &message, &signature));
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects() {
+        let seed = [7; 32];
+        let public = public_key(&seed);
+        let signature = sign(&seed, b"message");
+        assert!(verify(&public, b"message", &signature));
+        assert!(!verify(&public, b"massage", &signature));
+        assert!(!verify(&public_key(&[8; 32]), b"message", &signature));
+        let mut changed = signature;
+        changed[40] ^= 1;
+        assert!(!verify(&public, b"message", &changed));
+        // s + L is the same scalar, but not a valid encoding of it
+        const L: [u64; 4] = [0x5812631a5cf5d3ed, 0x14def9dea2f79cd6, 0, 1 << 60];
+        let mut s = [0u64; 4];
+        let mut carry = 0u128;
+        for i in 0..4 {
+            let limb = u64::from_le_bytes(signature[32 + i * 8..40 + i * 8].try_into().unwrap());
+            let sum = limb as u128 + L[i] as u128 + carry;
+            s[i] = sum as u64;
+            carry = sum >> 64;
+        }
+        let mut malleated = signature;
+        for (chunk, limb) in malleated[32..].chunks_mut(8).zip(s) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        assert!(!verify(&public, b"message", &malleated));
+    }
+}
@@ -0,0 +1,76 @@
+//! AES-256-GCM with 96 bit nonces and 128 bit tags
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm,
+};
+
+pub const NONCE_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+
+pub struct AesGcm {
+    cipher: Aes256Gcm,
+}
+impl AesGcm {
+    pub fn new(key: &[u8; 32]) -> Self {
+        AesGcm {
+            cipher: Aes256Gcm::new(key.into()),
+        }
+    }
+    /// the ciphertext with the tag appended
+    pub fn encrypt(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+        self.cipher
+            .encrypt(nonce.into(), payload)
+            .expect("the message fits in a gcm stream")
+    }
+    /// None when the tag doesn't match, nothing is decrypted then
+    pub fn decrypt(&self, nonce: &[u8; NONCE_SIZE], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+        let payload = Payload { msg: sealed, aad };
+        self.cipher.decrypt(nonce.into(), payload).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // test cases 13 and 14 of the original GCM paper
+    #[test]
+    fn test_zero_key_vectors() {
+        let gcm = AesGcm::new(&[0; 32]);
+        let nonce = [0; NONCE_SIZE];
+        assert_eq!(
+            gcm.encrypt(&nonce, &[], &[]),
+            hex("530f8afbc74536b9a963b4f1c4cb738b")
+        );
+        assert_eq!(
+            gcm.encrypt(&nonce, &[], &[0; 16]),
+            hex("cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let gcm = AesGcm::new(&[7; 32]);
+        let nonce = [1; NONCE_SIZE];
+        let message = b"a message longer than a single block of sixteen bytes";
+        let sealed = gcm.encrypt(&nonce, b"header", message);
+        assert_eq!(gcm.decrypt(&nonce, b"header", &sealed).unwrap(), message);
+        assert!(gcm.decrypt(&nonce, b"other", &sealed).is_none());
+        let mut flipped = sealed.clone();
+        flipped[3] ^= 1;
+        assert!(gcm.decrypt(&nonce, b"header", &flipped).is_none());
+        assert!(gcm.decrypt(&nonce, b"header", &sealed[..10]).is_none());
+    }
+}
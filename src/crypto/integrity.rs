@@ -0,0 +1,166 @@
+//! signed payloads, the message stays readable and an HMAC-SHA-256 tag over the
+//! chunk type, the header and the message shows whether it was changed.
+//!
+//! the header is `PSIG` and a version numbered like the encryption versions,
+//! so 2 has Argon2id parameters and a salt and 3 is keyed by a key file. the
+//! 32 byte tag follows the header, then the message.
+
+use super::{
+    random_bytes, sha256::hmac_sha256, take, wrong_mode, Argon2Params, Kdf, Secret,
+    KEY_FILE_VERSION, SALT_SIZE,
+};
+use crate::{error::PngSecretError, Result};
+
+const MAGIC: &[u8; 4] = b"PSIG";
+const TAG_SIZE: usize = 32;
+
+/// whether `data` starts with the header of a signed payload
+pub fn is_signed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC) && data.get(MAGIC.len()).is_some_and(|&version| version < 0x20)
+}
+
+/// `message` with a tag keyed by `secret`, a password is stretched with `params`
+pub fn sign(
+    chunk_type: [u8; 4],
+    message: &[u8],
+    secret: &Secret,
+    params: Argon2Params,
+) -> Result<Vec<u8>> {
+    let mut payload = MAGIC.to_vec();
+    let key = match secret {
+        Secret::Password(password) => {
+            let kdf = Kdf::Argon2id(params);
+            let salt: [u8; SALT_SIZE] = random_bytes()?;
+            payload.push(kdf.version());
+            for value in kdf.params() {
+                payload.extend_from_slice(&value.to_be_bytes());
+            }
+            payload.extend_from_slice(&salt);
+            kdf.derive(password, &salt)
+        }
+        Secret::Key(key) => {
+            payload.push(KEY_FILE_VERSION);
+            *key
+        }
+        Secret::Identities(_) => {
+            return Err(PngSecretError::Usage(
+                "A message can't be signed with an identity".into(),
+            ))
+        }
+    };
+    let tag = tag(&key, chunk_type, &payload, message);
+    payload.extend_from_slice(&tag);
+    payload.extend_from_slice(message);
+    Ok(payload)
+}
+
+/// the message of a signed payload, if its tag matches
+pub fn verify(chunk_type: [u8; 4], payload: &[u8], secret: &Secret) -> Result<Vec<u8>> {
+    if !is_signed(payload) {
+        return Err(PngSecretError::InvalidData(
+            "The chunk is not signed".into(),
+        ));
+    }
+    let version = payload[MAGIC.len()];
+    let mut fields = &payload[MAGIC.len() + 1..];
+    let key = match (version, secret) {
+        (KEY_FILE_VERSION, Secret::Key(key)) => *key,
+        (KEY_FILE_VERSION, _) => return Err(wrong_mode("signed with a key file", "--key-file")),
+        (_, Secret::Password(password)) => {
+            let kdf = Kdf::read(version, &mut fields)?;
+            if fields.len() < SALT_SIZE {
+                return Err(PngSecretError::InvalidData(
+                    "The signed payload is truncated".into(),
+                ));
+            }
+            let salt: [u8; SALT_SIZE] = take(&mut fields);
+            kdf.derive(password, &salt)
+        }
+        _ => {
+            Kdf::read(version, &mut fields)?;
+            return Err(wrong_mode("signed with a password", "--password"));
+        }
+    };
+    if fields.len() < TAG_SIZE {
+        return Err(PngSecretError::InvalidData(
+            "The signed payload is truncated".into(),
+        ));
+    }
+    let header = &payload[..payload.len() - fields.len()];
+    let stored: [u8; TAG_SIZE] = take(&mut fields);
+    if !constant_time_eq(&stored, &tag(&key, chunk_type, header, fields)) {
+        return Err(PngSecretError::IntegrityFailed);
+    }
+    Ok(fields.to_vec())
+}
+
+fn tag(key: &[u8; 32], chunk_type: [u8; 4], header: &[u8], message: &[u8]) -> [u8; TAG_SIZE] {
+    let mut data = chunk_type.to_vec();
+    data.extend_from_slice(header);
+    data.extend_from_slice(message);
+    hmac_sha256(key, &data)
+}
+/// looks at every byte whatever they hold, so the time taken tells nothing
+fn constant_time_eq(a: &[u8; TAG_SIZE], b: &[u8; TAG_SIZE]) -> bool {
+    a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHEAP: Argon2Params = Argon2Params {
+        memory: 8,
+        passes: 1,
+        lanes: 1,
+    };
+
+    fn password() -> Secret {
+        Secret::Password("hunter2".to_string())
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let payload = sign(*b"ruSt", b"build 1234", &password(), CHEAP).unwrap();
+        assert!(is_signed(&payload));
+        assert!(payload.ends_with(b"build 1234"));
+        assert_eq!(
+            verify(*b"ruSt", &payload, &password()).unwrap(),
+            b"build 1234"
+        );
+
+        let key = Secret::Key([3; 32]);
+        let keyed = sign(*b"ruSt", b"build 1234", &key, CHEAP).unwrap();
+        assert_eq!(verify(*b"ruSt", &keyed, &key).unwrap(), b"build 1234");
+        let error = verify(*b"ruSt", &keyed, &password()).unwrap_err();
+        assert!(error.to_string().contains("--key-file"));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let payload = sign(*b"ruSt", b"build 1234", &password(), CHEAP).unwrap();
+        let mut tampered = payload.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            verify(*b"ruSt", &tampered, &password()).unwrap_err(),
+            PngSecretError::IntegrityFailed
+        ));
+        // the tag covers the chunk type too
+        let moved = verify(*b"teSt", &payload, &password()).unwrap_err();
+        assert!(matches!(moved, PngSecretError::IntegrityFailed));
+        let wrong = Secret::Password("hunter3".to_string());
+        assert!(matches!(
+            verify(*b"ruSt", &payload, &wrong).unwrap_err(),
+            PngSecretError::IntegrityFailed
+        ));
+        assert!(verify(*b"ruSt", &payload[..30], &password()).is_err());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(&[1; TAG_SIZE], &[1; TAG_SIZE]));
+        let mut other = [1; TAG_SIZE];
+        other[TAG_SIZE - 1] = 2;
+        assert!(!constant_time_eq(&[1; TAG_SIZE], &other));
+    }
+}
@@ -0,0 +1,199 @@
+//! turning a password or a shared secret into a key
+
+use std::{fmt::Display, str::FromStr};
+
+use argon2::{Algorithm, Argon2, AssociatedData, ParamsBuilder, Version};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// the most iterations a header may ask for, well above the 600000 OWASP
+/// recommends, so a crafted payload can't keep the key derivation going for hours
+pub const PBKDF2_MAX_ITERATIONS: u32 = 10_000_000;
+
+/// PBKDF2 with HMAC-SHA-256, for a 32 byte key
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password, salt, iterations)
+}
+
+/// HKDF with SHA-256 from RFC 5869, for a 32 byte key
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut key = [0; 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF length");
+    key
+}
+
+/// the Argon2id cost, memory in KiB, passes over it and lanes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    pub memory: u32,
+    pub passes: u32,
+    pub lanes: u32,
+}
+impl Argon2Params {
+    /// what OWASP recommends for Argon2id, 19 MiB and two passes
+    pub const DEFAULT: Argon2Params = Argon2Params {
+        memory: 19 * 1024,
+        passes: 2,
+        lanes: 1,
+    };
+    /// the most a header may ask for, 1 GiB and 32 passes, so a crafted payload
+    /// can't exhaust memory or keep the key derivation going for hours
+    pub const MAX_MEMORY: u32 = 1024 * 1024;
+    pub const MAX_PASSES: u32 = 32;
+    pub fn validate(&self) -> Result<(), String> {
+        if self.passes == 0 || self.lanes == 0 || self.lanes >= 1 << 24 {
+            return Err("Argon2 needs at least one pass and between 1 and 2^24 lanes".to_string());
+        }
+        if self.passes > Self::MAX_PASSES {
+            return Err(format!(
+                "Argon2 takes at most {} passes, not {}",
+                Self::MAX_PASSES,
+                self.passes
+            ));
+        }
+        if self.memory > Self::MAX_MEMORY {
+            return Err(format!(
+                "Argon2 takes at most {} KiB of memory, not {}",
+                Self::MAX_MEMORY,
+                self.memory
+            ));
+        }
+        if self.memory < 8 * self.lanes {
+            return Err(format!(
+                "Argon2 needs at least {} KiB of memory for {} lanes",
+                8 * self.lanes,
+                self.lanes
+            ));
+        }
+        Ok(())
+    }
+}
+impl FromStr for Argon2Params {
+    type Err = String;
+    /// `memory,passes,lanes`, like 19456,2,1
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<u32> = s
+            .split(',')
+            .map(|value| value.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid kdf params {}, expected m,t,p", s))?;
+        let [memory, passes, lanes] = values[..] else {
+            return Err(format!("invalid kdf params {}, expected m,t,p", s));
+        };
+        let params = Argon2Params {
+            memory,
+            passes,
+            lanes,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
+impl Display for Argon2Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.memory, self.passes, self.lanes)
+    }
+}
+
+/// Argon2id version 1.3 as in RFC 9106, for a 32 byte key
+pub fn argon2id(password: &[u8], salt: &[u8], params: Argon2Params) -> [u8; 32] {
+    argon2id_full(password, salt, &[], &[], params, 32)
+        .try_into()
+        .unwrap()
+}
+/// with the secret and associated data the RFC test vectors need
+fn argon2id_full(
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    associated: &[u8],
+    params: Argon2Params,
+    tag_size: usize,
+) -> Vec<u8> {
+    // validate checked the same bounds the crate does
+    let params = ParamsBuilder::new()
+        .m_cost(params.memory)
+        .t_cost(params.passes)
+        .p_cost(params.lanes)
+        .output_len(tag_size)
+        .data(AssociatedData::new(associated).expect("the associated data is short"))
+        .build()
+        .expect("the Argon2 params are validated");
+    let argon2 = Argon2::new_with_secret(secret, Algorithm::Argon2id, Version::V0x13, params)
+        .expect("the secret is short");
+    let mut tag = vec![0; tag_size];
+    argon2
+        .hash_password_into(password, salt, &mut tag)
+        .expect("the salt is long enough");
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // RFC 5869 test case 1, the first 32 bytes
+    #[test]
+    fn test_hkdf_sha256() {
+        let salt: Vec<u8> = (0..13).collect();
+        let info: Vec<u8> = (0xf0..0xfa).collect();
+        assert_eq!(
+            hex(&hkdf_sha256(&salt, &[0x0b; 22], &info)),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_sha256() {
+        assert_eq!(
+            hex(&pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex(&pbkdf2_sha256(b"password", b"salt", 2)),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+    }
+
+    // RFC 9106 section 5.3
+    #[test]
+    fn test_argon2id_vector() {
+        let params = Argon2Params {
+            memory: 32,
+            passes: 3,
+            lanes: 4,
+        };
+        let tag = argon2id_full(&[1; 32], &[2; 16], &[3; 8], &[4; 12], params, 32);
+        assert_eq!(
+            hex(&tag),
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"
+        );
+    }
+
+    #[test]
+    fn test_argon2_params() {
+        let params: Argon2Params = "64,3,2".parse().unwrap();
+        assert_eq!(
+            params,
+            Argon2Params {
+                memory: 64,
+                passes: 3,
+                lanes: 2
+            }
+        );
+        assert_eq!(params.to_string(), "64,3,2");
+        assert!("64,3".parse::<Argon2Params>().is_err());
+        assert!("8,0,1".parse::<Argon2Params>().is_err());
+        assert!("8,1,2".parse::<Argon2Params>().is_err());
+        assert!("a,b,c".parse::<Argon2Params>().is_err());
+        assert!("1048576,32,1".parse::<Argon2Params>().is_ok());
+        assert!("1048577,1,1".parse::<Argon2Params>().is_err());
+        assert!("8,33,1".parse::<Argon2Params>().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! age style public keys. a payload gets a random file key, wrapped once for
+//! every recipient with a key agreed between a fresh ephemeral key and theirs
+
+use std::{fmt::Display, str::FromStr};
+
+use super::{
+    bech32,
+    gcm::{AesGcm, NONCE_SIZE, TAG_SIZE},
+    kdf::hkdf_sha256,
+    random_bytes,
+    x25519::{public_key, x25519},
+    KEY_SIZE,
+};
+use crate::{error::PngSecretError, Result};
+
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+const INFO: &[u8] = b"pngsecret X25519";
+/// an ephemeral public key and the file key wrapped for one recipient
+pub const STANZA_SIZE: usize = 32 + KEY_SIZE + TAG_SIZE;
+
+/// a public key, `age1...`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recipient([u8; 32]);
+impl FromStr for Recipient {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match bech32::decode(s)? {
+            (hrp, key) if hrp == RECIPIENT_HRP => key
+                .try_into()
+                .map(Recipient)
+                .map_err(|_| "A recipient is a 32 byte key".to_string()),
+            _ => Err("A recipient starts with age1".to_string()),
+        }
+    }
+}
+impl Display for Recipient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", bech32::encode(RECIPIENT_HRP, &self.0))
+    }
+}
+
+/// a private key, `AGE-SECRET-KEY-1...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity([u8; 32]);
+impl Identity {
+    pub fn generate() -> Result<Identity> {
+        Ok(Identity(random_bytes()?))
+    }
+    pub fn recipient(&self) -> Recipient {
+        Recipient(public_key(&self.0))
+    }
+}
+impl FromStr for Identity {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match bech32::decode(s)? {
+            (hrp, key) if hrp == IDENTITY_HRP => key
+                .try_into()
+                .map(Identity)
+                .map_err(|_| "An identity is a 32 byte key".to_string()),
+            _ => Err("An identity starts with AGE-SECRET-KEY-1".to_string()),
+        }
+    }
+}
+impl Display for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encoded = bech32::encode(IDENTITY_HRP, &self.0);
+        write!(f, "{}", encoded.to_uppercase())
+    }
+}
+
+/// the identities in an identity file, one per line, `#` starts a comment
+pub fn parse_identities(text: &str) -> Result<Vec<Identity>> {
+    let identities = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Identity::from_str)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(PngSecretError::InvalidData)?;
+    if identities.is_empty() {
+        return Err(PngSecretError::InvalidData("No identity found".into()));
+    }
+    Ok(identities)
+}
+
+/// the key that wraps the file key for `recipient`, none for a low order point
+fn wrapping_key(shared: [u8; 32], ephemeral: &[u8; 32], recipient: &Recipient) -> Option<AesGcm> {
+    if shared == [0; 32] {
+        return None;
+    }
+    let mut salt = ephemeral.to_vec();
+    salt.extend_from_slice(&recipient.0);
+    Some(AesGcm::new(&hkdf_sha256(&salt, &shared, INFO)))
+}
+
+/// the stanza of `file_key` for `recipient`
+pub fn wrap(file_key: &[u8; KEY_SIZE], recipient: &Recipient) -> Result<Vec<u8>> {
+    let ephemeral = Identity::generate()?;
+    let ephemeral_public = ephemeral.recipient().0;
+    let key = wrapping_key(
+        x25519(&ephemeral.0, &recipient.0),
+        &ephemeral_public,
+        recipient,
+    )
+    .ok_or_else(|| {
+        PngSecretError::InvalidData("The recipient is not a usable public key".into())
+    })?;
+    let mut stanza = ephemeral_public.to_vec();
+    // every wrapping key is used only once, so a fixed nonce is fine
+    stanza.extend(key.encrypt(&[0; NONCE_SIZE], &[], file_key));
+    Ok(stanza)
+}
+/// the file key, if the stanza was made for `identity`
+pub fn unwrap(stanza: &[u8], identity: &Identity) -> Option<[u8; KEY_SIZE]> {
+    let (ephemeral, wrapped) = stanza.split_at(32);
+    let ephemeral: [u8; 32] = ephemeral.try_into().ok()?;
+    let key = wrapping_key(
+        x25519(&identity.0, &ephemeral),
+        &ephemeral,
+        &identity.recipient(),
+    )?;
+    key.decrypt(&[0; NONCE_SIZE], &[], wrapped)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let identity = Identity::generate().unwrap();
+        let text = identity.to_string();
+        assert!(text.starts_with("AGE-SECRET-KEY-1"));
+        assert_eq!(text.parse::<Identity>().unwrap(), identity);
+        let recipient = identity.recipient();
+        assert!(recipient.to_string().starts_with("age1"));
+        assert_eq!(
+            recipient.to_string().parse::<Recipient>().unwrap(),
+            recipient
+        );
+        assert!(text.parse::<Recipient>().is_err());
+        assert!(recipient.to_string().parse::<Identity>().is_err());
+    }
+
+    #[test]
+    fn test_parse_identities() {
+        let identity = Identity::generate().unwrap();
+        let file = format!("# public key: {}\n\n{}\n", identity.recipient(), identity);
+        assert_eq!(parse_identities(&file).unwrap(), vec![identity]);
+        assert!(parse_identities("# nothing here\n").is_err());
+        assert!(parse_identities("not a key\n").is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let identity = Identity::generate().unwrap();
+        let file_key = [5; KEY_SIZE];
+        let stanza = wrap(&file_key, &identity.recipient()).unwrap();
+        assert_eq!(stanza.len(), STANZA_SIZE);
+        assert_eq!(unwrap(&stanza, &identity), Some(file_key));
+        assert_eq!(unwrap(&stanza, &Identity::generate().unwrap()), None);
+    }
+}
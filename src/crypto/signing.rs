@@ -0,0 +1,147 @@
+//! Ed25519 keypairs for signing payloads, kept as JSON with the keys in hex
+
+use std::{fs, path::Path};
+
+use super::{
+    ed25519::{self, PUBLIC_KEY_SIZE, SEED_SIZE, SIGNATURE_SIZE},
+    random_bytes,
+};
+use crate::{error::PngSecretError, json::Value, Result};
+
+const KEY_TYPE: &str = "ed25519";
+
+/// a secret signing key and its public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    seed: [u8; SEED_SIZE],
+    public_key: [u8; PUBLIC_KEY_SIZE],
+}
+impl SigningKey {
+    pub fn generate() -> Result<SigningKey> {
+        let seed = random_bytes()?;
+        Ok(SigningKey {
+            seed,
+            public_key: ed25519::public_key(&seed),
+        })
+    }
+    /// the keypair in a file keygen --type ed25519 wrote
+    pub fn read(path: &Path) -> Result<SigningKey> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            PngSecretError::Failed(format!("Could not read {}: {}", path.display(), e))
+        })?;
+        let not_a_keypair =
+            || PngSecretError::InvalidData(format!("{} is not an Ed25519 keypair", path.display()));
+        let keypair = crate::json::parse(&text).map_err(|_| not_a_keypair())?;
+        let field = |name: &str| keypair.get(name).and_then(Value::as_str);
+        if field("type") != Some(KEY_TYPE) {
+            return Err(not_a_keypair());
+        }
+        let seed = field("secret_key")
+            .and_then(from_hex)
+            .ok_or_else(not_a_keypair)?;
+        let key = SigningKey {
+            seed,
+            public_key: ed25519::public_key(&seed),
+        };
+        if field("public_key").and_then(from_hex) != Some(key.public_key) {
+            return Err(PngSecretError::InvalidData(format!(
+                "The public key in {} doesn't belong to its secret key",
+                path.display()
+            )));
+        }
+        Ok(key)
+    }
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        self.public_key
+    }
+    pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_SIZE] {
+        ed25519::sign(&self.seed, message)
+    }
+    /// the keypair as keygen writes it
+    pub fn to_json(&self) -> Value {
+        Value::object([
+            ("type", KEY_TYPE.into()),
+            ("public_key", hex(&self.public_key).into()),
+            ("secret_key", hex(&self.seed).into()),
+        ])
+    }
+}
+
+/// the public key `given` in hex, or the one in the keypair or public key file it names
+pub fn read_verifying_key(given: &str) -> Result<[u8; PUBLIC_KEY_SIZE]> {
+    if let Some(key) = from_hex(given) {
+        return Ok(key);
+    }
+    let text = fs::read_to_string(given).map_err(|e| {
+        PngSecretError::Failed(format!(
+            "{} is neither a hex public key nor a readable file: {}",
+            given, e
+        ))
+    })?;
+    let key = match crate::json::parse(&text) {
+        Ok(keypair) => keypair
+            .get("public_key")
+            .and_then(Value::as_str)
+            .and_then(from_hex),
+        Err(_) => from_hex(text.trim()),
+    };
+    key.ok_or_else(|| PngSecretError::InvalidData(format!("{} holds no Ed25519 public key", given)))
+}
+
+/// whether `signature` of `message` was made with the secret key of `public_key`
+pub fn verify(
+    public_key: &[u8; PUBLIC_KEY_SIZE],
+    message: &[u8],
+    signature: &[u8; SIGNATURE_SIZE],
+) -> bool {
+    ed25519::verify(public_key, message, signature)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+fn from_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != N * 2 || !text.is_ascii() {
+        return None;
+    }
+    let bytes: Option<Vec<u8>> = (0..N)
+        .map(|i| u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok())
+        .collect();
+    bytes?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypair_file() {
+        let key = SigningKey::generate().unwrap();
+        let path =
+            std::env::temp_dir().join(format!("pngsecret-keypair-{}.json", std::process::id()));
+        fs::write(&path, key.to_json().to_string()).unwrap();
+        assert_eq!(SigningKey::read(&path).unwrap(), key);
+        assert_eq!(
+            read_verifying_key(path.to_str().unwrap()).unwrap(),
+            key.public_key()
+        );
+        assert_eq!(
+            read_verifying_key(&hex(&key.public_key())).unwrap(),
+            key.public_key()
+        );
+        let signature = key.sign(b"message");
+        assert!(verify(&key.public_key(), b"message", &signature));
+
+        // a public key that isn't the secret key's
+        let mut keypair = key.to_json();
+        if let Value::Object(fields) = &mut keypair {
+            fields[1].1 = hex(&[1; 32]).into();
+        }
+        fs::write(&path, keypair.to_string()).unwrap();
+        assert!(SigningKey::read(&path).is_err());
+        fs::write(&path, "{}").unwrap();
+        assert!(SigningKey::read(&path).is_err());
+        fs::remove_file(path).unwrap();
+        assert!(read_verifying_key("abcd").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! X25519 key agreement from RFC 7748
+
+/// the scalar `scalar` times the point with u coordinate `u`
+pub fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    x25519_dalek::x25519(*scalar, *u)
+}
+
+/// the public key of `secret`
+pub fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    x25519(secret, &x25519_dalek::X25519_BASEPOINT_BYTES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> [u8; 32] {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        bytes.try_into().unwrap()
+    }
+
+    // RFC 7748 section 5.2
+    #[test]
+    fn test_x25519() {
+        let scalar = bytes("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4");
+        let u = bytes("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c");
+        assert_eq!(
+            x25519(&scalar, &u),
+            bytes("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552")
+        );
+        let mut base = [0; 32];
+        base[0] = 9;
+        assert_eq!(
+            x25519(&base, &base),
+            bytes("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079")
+        );
+    }
+
+    // RFC 7748 section 6.1
+    #[test]
+    fn test_key_agreement() {
+        let alice = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = bytes("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_public = public_key(&alice);
+        let bob_public = public_key(&bob);
+        assert_eq!(
+            alice_public,
+            bytes("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob_public,
+            bytes("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+        assert_eq!(x25519(&alice, &bob_public), x25519(&bob, &alice_public));
+    }
+}
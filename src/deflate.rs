@@ -0,0 +1,105 @@
+//! zlib streams (RFC 1950) holding deflate data (RFC 1951), with flate2
+
+use flate2::{write::ZlibEncoder, Compression, Decompress, FlushDecompress, Status};
+use std::io::Write;
+
+use crate::{error::PngSecretError, Result};
+
+/// `data` as a zlib stream, `level` 0 stores it, 1 to 9 search harder for repeats
+pub fn compress(data: &[u8], level: u8) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(level.min(9) as u32));
+    encoder
+        .write_all(data)
+        .expect("writing to a Vec doesn't fail");
+    encoder.finish().expect("writing to a Vec doesn't fail")
+}
+
+/// the data of a zlib stream, at most `limit` bytes of it
+pub fn decompress(stream: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut inflater = Decompress::new(true);
+    let mut out = Vec::new();
+    loop {
+        if out.len() > limit {
+            return Err(too_big(limit));
+        }
+        out.reserve(32 * 1024);
+        let progress = (inflater.total_in(), inflater.total_out());
+        let input = &stream[inflater.total_in() as usize..];
+        let status = inflater
+            .decompress_vec(input, &mut out, FlushDecompress::None)
+            .map_err(|e| PngSecretError::InvalidData(format!("Corrupt zlib stream: {}", e)))?;
+        if status == Status::StreamEnd {
+            break;
+        }
+        if progress == (inflater.total_in(), inflater.total_out()) {
+            return Err(PngSecretError::InvalidData("Truncated zlib stream".into()));
+        }
+    }
+    if out.len() > limit {
+        return Err(too_big(limit));
+    }
+    Ok(out)
+}
+fn too_big(limit: usize) -> PngSecretError {
+    PngSecretError::InvalidData(format!("Decompressed data is bigger than {} bytes", limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text() -> Vec<u8> {
+        let mut text = Vec::new();
+        for i in 0..2000 {
+            text.extend_from_slice(
+                format!("line {} of a log that repeats a lot\n", i % 37).as_bytes(),
+            );
+        }
+        text
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = text();
+        for level in 0..=9 {
+            let compressed = compress(&text, level);
+            assert_eq!(decompress(&compressed, usize::MAX).unwrap(), text);
+            if level > 0 {
+                assert!(compressed.len() * 10 < text.len());
+            }
+        }
+        for data in [&b""[..], b"a", b"ab", b"abcabcabcabcabc"] {
+            assert_eq!(decompress(&compress(data, 6), usize::MAX).unwrap(), data);
+            assert_eq!(decompress(&compress(data, 0), usize::MAX).unwrap(), data);
+        }
+    }
+
+    // made by zlib, which uses dynamic codes for this one
+    #[test]
+    fn test_decompress_zlib() {
+        let stream = [
+            120, 218, 181, 203, 201, 17, 128, 32, 16, 68, 209, 84, 58, 0, 203, 156, 64, 81, 70,
+            133, 65, 100, 17, 162, 119, 202, 8, 188, 120, 236, 250, 175, 147, 53, 56, 51, 77, 59,
+            116, 228, 234, 177, 240, 141, 45, 187, 112, 129, 139, 137, 72, 146, 15, 213, 27, 102,
+            94, 135, 119, 253, 131, 131, 18, 231, 26, 180, 160, 74, 201, 98, 161, 98, 36, 117, 227,
+            113, 208, 153, 57, 202, 119, 189, 198, 239, 240, 1, 147, 106, 78, 126,
+        ];
+        let text = [
+            "the quick brown fox jumps over the lazy dog, ".repeat(3),
+            "pack my box with five dozen liquor jugs. ".repeat(2),
+        ]
+        .concat();
+        assert_eq!(decompress(&stream, usize::MAX).unwrap(), text.as_bytes());
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        let compressed = compress(&text(), 6);
+        assert!(decompress(&compressed, 1000).is_err());
+        assert!(decompress(&compressed[..compressed.len() / 2], usize::MAX).is_err());
+        let mut wrong_checksum = compressed.clone();
+        *wrong_checksum.last_mut().unwrap() ^= 1;
+        assert!(decompress(&wrong_checksum, usize::MAX).is_err());
+        assert!(decompress(b"not zlib at all", usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, png::Png};
+
+/// a chunk is identified by its type and which occurrence of that type it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkKey {
+    pub chunk_type: String,
+    pub occurrence: usize,
+}
+impl Display for ChunkKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}#{}", self.chunk_type, self.occurrence)
+    }
+}
+
+#[derive(Debug)]
+pub enum ChunkDiff<'a> {
+    Added {
+        key: ChunkKey,
+        chunk: &'a Chunk,
+    },
+    Removed {
+        key: ChunkKey,
+        chunk: &'a Chunk,
+    },
+    Changed {
+        key: ChunkKey,
+        old: &'a Chunk,
+        new: &'a Chunk,
+    },
+    Moved {
+        key: ChunkKey,
+        old_index: usize,
+        new_index: usize,
+    },
+}
+
+fn keyed_chunks(png: &Png) -> Vec<(ChunkKey, usize, &Chunk)> {
+    let mut keyed: Vec<(ChunkKey, usize, &Chunk)> = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type().to_string();
+        let occurrence = keyed
+            .iter()
+            .filter(|(key, _, _)| key.chunk_type == chunk_type)
+            .count();
+        keyed.push((
+            ChunkKey {
+                chunk_type,
+                occurrence,
+            },
+            index,
+            chunk,
+        ));
+    }
+    keyed
+}
+
+/// compares the chunks of two files, reordering only counts with `strict_order`
+pub fn diff<'a>(old: &'a Png, new: &'a Png, strict_order: bool) -> Vec<ChunkDiff<'a>> {
+    let old = keyed_chunks(old);
+    let new = keyed_chunks(new);
+    let find = |chunks: &[(ChunkKey, usize, &'a Chunk)], key: &ChunkKey| {
+        chunks
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|&(_, index, chunk)| (index, chunk))
+    };
+    let mut diffs = Vec::new();
+
+    for (key, _, chunk) in &old {
+        match find(&new, key) {
+            None => diffs.push(ChunkDiff::Removed {
+                key: key.clone(),
+                chunk,
+            }),
+            Some((_, new_chunk)) if new_chunk != *chunk => diffs.push(ChunkDiff::Changed {
+                key: key.clone(),
+                old: chunk,
+                new: new_chunk,
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, _, chunk) in &new {
+        if find(&old, key).is_none() {
+            diffs.push(ChunkDiff::Added {
+                key: key.clone(),
+                chunk,
+            });
+        }
+    }
+    if strict_order {
+        // compare the order of the chunks both files have
+        let common_old = old.iter().filter(|(key, _, _)| find(&new, key).is_some());
+        let common_new = new.iter().filter(|(key, _, _)| find(&old, key).is_some());
+        for ((old_key, old_index, _), (new_key, _, _)) in common_old.zip(common_new) {
+            if old_key != new_key {
+                let (new_index, _) = find(&new, old_key).unwrap();
+                diffs.push(ChunkDiff::Moved {
+                    key: old_key.clone(),
+                    old_index: *old_index,
+                    new_index,
+                });
+            }
+        }
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &str) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.as_bytes())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("tEXt", "first"),
+            chunk("tEXt", "second"),
+            chunk("IDAT", "pixels"),
+            chunk("IEND", ""),
+        ])
+    }
+
+    #[test]
+    fn test_identical() {
+        assert!(diff(&testing_png(), &testing_png(), true).is_empty());
+    }
+
+    #[test]
+    fn test_added_removed_changed() {
+        let new = Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("tEXt", "first"),
+            chunk("IDAT", "other pixels"),
+            chunk("ruSt", "secret"),
+            chunk("IEND", ""),
+        ]);
+        let old = testing_png();
+        let diffs = diff(&old, &new, false);
+        assert_eq!(diffs.len(), 3);
+        assert!(
+            matches!(&diffs[0], ChunkDiff::Removed { key, chunk } if key.to_string() == "tEXt#1" && chunk.length() == 6)
+        );
+        assert!(
+            matches!(&diffs[1], ChunkDiff::Changed { key, old, new } if key.to_string() == "IDAT#0" && old.length() == 6 && new.length() == 12)
+        );
+        assert!(matches!(&diffs[2], ChunkDiff::Added { key, .. } if key.to_string() == "ruSt#0"));
+    }
+
+    #[test]
+    fn test_reordering() {
+        let new = Png::from_chunks(vec![
+            chunk("IHDR", "header"),
+            chunk("IDAT", "pixels"),
+            chunk("tEXt", "first"),
+            chunk("tEXt", "second"),
+            chunk("IEND", ""),
+        ]);
+        let old = testing_png();
+        assert!(diff(&old, &new, false).is_empty());
+        let diffs = diff(&old, &new, true);
+        assert!(!diffs.is_empty());
+        assert!(diffs
+            .iter()
+            .all(|diff| matches!(diff, ChunkDiff::Moved { .. })));
+    }
+}
@@ -0,0 +1,62 @@
+//! `--encoding` of decode and print: how data is turned into text to show it. bytes
+//! that don't fit the encoding are shown as U+FFFD
+
+use clap::ValueEnum;
+
+use crate::{base64, hex, text};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    Utf8,
+    /// ISO 8859-1, what tEXt and zTXt chunks hold and legacy Windows tools write
+    Latin1,
+    Utf16le,
+    Utf16be,
+    Hex,
+    Base64,
+}
+impl Encoding {
+    /// `data` as text of this encoding
+    pub fn text(self, data: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(data).into_owned(),
+            Encoding::Latin1 => text::from_latin1(data),
+            Encoding::Utf16le => utf16(data, u16::from_le_bytes),
+            Encoding::Utf16be => utf16(data, u16::from_be_bytes),
+            Encoding::Hex => hex::encode(data),
+            Encoding::Base64 => base64::encode(data),
+        }
+    }
+}
+
+/// an odd byte at the end is half a unit, shown as U+FFFD too
+fn utf16(data: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = data.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if data.len() % 2 == 1 {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        let latin1 = b"caf\xe9";
+        assert_eq!(Encoding::Latin1.text(latin1), "café");
+        assert_eq!(Encoding::Utf8.text(latin1), "caf\u{fffd}");
+        assert_eq!(Encoding::Utf8.text("café".as_bytes()), "café");
+        assert_eq!(Encoding::Utf16le.text(b"c\0a\0f\0\xe9\0"), "café");
+        assert_eq!(Encoding::Utf16be.text(b"\0c\0a\0f\0\xe9"), "café");
+        assert_eq!(Encoding::Utf16le.text(b"c\0a"), "c\u{fffd}");
+        // an unpaired surrogate
+        assert_eq!(Encoding::Utf16be.text(b"\xd8\x00\0a"), "\u{fffd}a");
+        assert_eq!(Encoding::Hex.text(latin1), "636166e9");
+        assert_eq!(Encoding::Base64.text(latin1), "Y2Fm6Q==");
+    }
+}
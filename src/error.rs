@@ -0,0 +1,344 @@
+//! the ways reading and changing a png can fail, and the ways the commands of the
+//! cli can, as one enum callers can match on instead of comparing messages. new
+//! variants are added as the commands grow, so a match needs a wildcard arm
+
+use std::{
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+    str::Utf8Error,
+};
+
+use crate::{chunk_type::ChunkTypeError, ihdr::IhdrError};
+
+/// where the chunk an error is about sits, as far as the code that failed knows it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    /// the index of the chunk in the file
+    pub index: Option<usize>,
+    /// the type of the chunk, as far as it could be read
+    pub chunk_type: Option<String>,
+    /// where the chunk starts in the file
+    pub offset: Option<usize>,
+    /// the file the chunk is in
+    pub path: Option<PathBuf>,
+}
+impl Display for Location {
+    /// like chunk #7 (tEXt) at offset 0x1A2B0 in ./photos/x.png
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chunk")?;
+        if let Some(index) = self.index {
+            write!(f, " #{}", index)?;
+        }
+        if let Some(chunk_type) = &self.chunk_type {
+            write!(f, " ({})", chunk_type)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {:#X}", offset)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, " in {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// which of `png::Limits` a file went past
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// `max_chunks`
+    Chunks,
+    /// `max_chunk_size`
+    ChunkSize,
+    /// `max_total`
+    Total,
+}
+impl Limit {
+    /// what the chunk did, with the limit at `max`
+    fn exceeded(self, max: usize) -> String {
+        match self {
+            Limit::Chunks => format!("is past the limit of {} chunks", max),
+            Limit::ChunkSize => format!("is bigger than the limit of {} bytes per chunk", max),
+            Limit::Total => format!("takes the chunk data past the limit of {} bytes", max),
+        }
+    }
+}
+
+/// why a png couldn't be read or changed, or a command failed
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum PngSecretError {
+    /// reading or writing failed, `path` is none for stdin and in memory readers
+    #[error("{}", io_message(.path.as_deref(), .source))]
+    Io {
+        /// the file that failed
+        path: Option<PathBuf>,
+        /// what failed
+        source: io::Error,
+    },
+    /// the input doesn't start with the png signature
+    #[error("{}", with_path(.path.as_deref(), "Invalid header, not a png file"))]
+    NotAPng {
+        /// the file that isn't a png, none for stdin and in memory readers
+        path: Option<PathBuf>,
+    },
+    /// the 4 bytes of a chunk's type aren't a valid type
+    #[error("invalid chunk type in {at}: {error}")]
+    InvalidChunkType {
+        /// what is wrong with the type
+        #[source]
+        error: ChunkTypeError,
+        /// the chunk with the bad type
+        at: Location,
+    },
+    /// `expected` is the crc of the chunk type and data, `found` the stored one
+    #[error("invalid CRC in {at}: expected {expected:#010X}, found {found:#010X}")]
+    CrcMismatch {
+        /// the crc of the chunk's type and data
+        expected: u32,
+        /// the crc stored after the data
+        found: u32,
+        /// the chunk with the bad crc
+        at: Location,
+    },
+    /// no chunk of this type is in the png
+    #[error("{}", not_found_message(.chunk_type, .keyword.as_deref(), .matching.as_deref(), .path.as_deref()))]
+    ChunkNotFound {
+        /// the type looked for
+        chunk_type: String,
+        /// the keyword of the text chunk that was looked for
+        keyword: Option<String>,
+        /// the regex of remove --matching, when chunks of the type were there
+        matching: Option<String>,
+        /// the file looked in, none for in memory pngs
+        path: Option<PathBuf>,
+    },
+    /// the input ends inside the chunk `at`
+    #[error("the file ends inside {at}")]
+    Truncated {
+        /// the chunk the input ends in
+        at: Location,
+    },
+    /// a chunk index past the end of the png
+    #[error("Chunk index {index} is past the end, the file has {len} chunks")]
+    IndexOutOfRange {
+        /// the index asked for
+        index: usize,
+        /// the number of chunks the png has
+        len: usize,
+    },
+    /// data of this many bytes doesn't fit in a chunk, see `Chunk::MAX_LENGTH`
+    #[error("A chunk holds at most {max} bytes, not {0}", max = crate::chunk::Chunk::MAX_LENGTH)]
+    ChunkTooLong(usize),
+    /// the chunk `at` goes past `limit`, whose value is `max`
+    #[error("{at} {}", .limit.exceeded(*.max))]
+    LimitExceeded {
+        /// which limit
+        limit: Limit,
+        /// the value of the limit
+        max: usize,
+        /// the first chunk past the limit
+        at: Location,
+    },
+    /// the chunk data isn't utf-8 text
+    #[error("The chunk data is not utf-8: {0}")]
+    NotUtf8(#[from] Utf8Error),
+    /// the IHDR chunk isn't a valid image header
+    #[error("{0}")]
+    InvalidIhdr(#[from] IhdrError),
+    /// the arguments ask for what can't be done, like options that don't go together
+    #[error("{0}")]
+    Usage(String),
+    /// a payload, key or chunk doesn't hold what it should, like a truncated header or
+    /// an unsupported version
+    #[error("{0}")]
+    InvalidData(String),
+    /// something a command relies on failed, like the editor or a key file
+    #[error("{0}")]
+    Failed(String),
+    /// `remove --nth` asked for an occurrence past the last matching chunk
+    #[error("Chunk {chunk_type} number {nth} requested but only {count} found")]
+    NthOutOfRange {
+        /// the type looked for
+        chunk_type: String,
+        /// the occurrence asked for
+        nth: usize,
+        /// how many chunks of the type there are
+        count: usize,
+    },
+    /// a critical chunk type was given without --allow-critical
+    #[error("{chunk_type} is a critical chunk type, use --allow-critical to {verb} it anyway")]
+    CriticalChunk {
+        /// the critical type
+        chunk_type: String,
+        /// what was going to be done to it
+        verb: &'static str,
+    },
+    /// a command left a file alone instead of overwriting or breaking it
+    #[error("{reason}")]
+    Refused {
+        /// why
+        reason: String,
+    },
+    /// verify found `count` problems in the file, `crc` when a crc is among them
+    #[error("{count} problem(s) found")]
+    ProblemsFound {
+        /// how many problems
+        count: usize,
+        /// whether a bad crc is one of them
+        crc: bool,
+    },
+    /// the failure was reported already, or must not be, only its exit code is left
+    #[error("exit code {code}")]
+    Silent {
+        /// the exit code
+        code: i32,
+    },
+    /// the password or key is wrong, or the payload was changed after it was encrypted
+    #[error("Could not decrypt, wrong password or key, or tampered data")]
+    DecryptionFailed,
+    /// the integrity tag of a payload doesn't match, the message or its header changed
+    #[error("The integrity tag doesn't match, the message was tampered with")]
+    IntegrityFailed,
+    /// the signature of a payload is bad, or there is none to check
+    #[error("{0}")]
+    BadSignature(String),
+    /// a payload doesn't hash to the checksum in its header
+    #[error(
+        "Checksum mismatch, the payload is not what was encoded, use --ignore-checksum to decode it anyway"
+    )]
+    ChecksumMismatch,
+    /// --verify-render found the written png doesn't decode or shows other pixels
+    #[error("Render verification failed: {reason}")]
+    RenderChanged {
+        /// what differs
+        reason: String,
+    },
+    /// the pixels are too few for the payload of `encode --method lsb`
+    #[error(
+        "The pixels hold {capacity} bytes, the payload needs {needed}: {} bytes short",
+        .needed - .capacity
+    )]
+    LsbTooSmall {
+        /// the payload bytes the pixels hold
+        capacity: usize,
+        /// the size of the payload
+        needed: usize,
+    },
+    /// the pixels don't start with what `encode --method lsb` writes
+    #[error(
+        "No payload in the pixels, their lowest bits don't hold what encode --method lsb writes"
+    )]
+    LsbNotFound,
+    /// no chunk opens with the password as a part of `encode --stealth`
+    #[error("No chunk opens with this password or key as a part of what encode --stealth writes")]
+    StealthNotFound,
+}
+fn io_message(path: Option<&Path>, source: &io::Error) -> String {
+    with_path(path, source)
+}
+fn with_path(path: Option<&Path>, message: impl Display) -> String {
+    match path {
+        Some(path) => format!("{}: {}", path.display(), message),
+        None => message.to_string(),
+    }
+}
+fn not_found_message(
+    chunk_type: &str,
+    keyword: Option<&str>,
+    matching: Option<&str>,
+    path: Option<&Path>,
+) -> String {
+    let place = path.map_or(String::new(), |path| format!(" in {}", path.display()));
+    match (matching, keyword) {
+        (Some(matching), _) => format!("no {} chunk{} matches {}", chunk_type, place, matching),
+        (None, Some(keyword)) => format!(
+            "no {} chunk with keyword '{}' found{}",
+            chunk_type, keyword, place
+        ),
+        (None, None) => format!("no chunk of type '{}' found{}", chunk_type, place),
+    }
+}
+impl PngSecretError {
+    /// that no chunk of `chunk_type` is in the png
+    pub fn chunk_not_found(chunk_type: impl Into<String>) -> Self {
+        PngSecretError::ChunkNotFound {
+            chunk_type: chunk_type.into(),
+            keyword: None,
+            matching: None,
+            path: None,
+        }
+    }
+    /// an io error about the file at `path`
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        PngSecretError::Io {
+            path: Some(path.into()),
+            source,
+        }
+    }
+    /// where the chunk the error is about sits, if it is about one
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            PngSecretError::InvalidChunkType { at, .. }
+            | PngSecretError::CrcMismatch { at, .. }
+            | PngSecretError::LimitExceeded { at, .. }
+            | PngSecretError::Truncated { at } => Some(at),
+            _ => None,
+        }
+    }
+    fn location_mut(&mut self) -> Option<&mut Location> {
+        match self {
+            PngSecretError::InvalidChunkType { at, .. }
+            | PngSecretError::CrcMismatch { at, .. }
+            | PngSecretError::LimitExceeded { at, .. }
+            | PngSecretError::Truncated { at } => Some(at),
+            _ => None,
+        }
+    }
+    /// the same error for the chunk at `index` of a png, which starts at `offset`
+    pub fn at(mut self, index: usize, offset: usize) -> Self {
+        if let Some(at) = self.location_mut() {
+            at.index = Some(index);
+            at.offset = Some(offset);
+        }
+        self
+    }
+    /// the same error for the file at `path`
+    pub fn in_file(mut self, path: &Path) -> Self {
+        match &mut self {
+            PngSecretError::Io { path: file, .. }
+            | PngSecretError::NotAPng { path: file }
+            | PngSecretError::ChunkNotFound { path: file, .. } => {
+                file.get_or_insert_with(|| path.to_path_buf());
+            }
+            error => {
+                if let Some(at) = error.location_mut() {
+                    at.path = Some(path.to_path_buf());
+                }
+            }
+        }
+        self
+    }
+    /// the file the error names, if its message has one
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PngSecretError::Io { path, .. }
+            | PngSecretError::NotAPng { path }
+            | PngSecretError::ChunkNotFound { path, .. } => path.as_deref(),
+            error => error.location()?.path.as_deref(),
+        }
+    }
+}
+impl From<io::Error> for PngSecretError {
+    fn from(source: io::Error) -> Self {
+        PngSecretError::Io { path: None, source }
+    }
+}
+impl From<ChunkTypeError> for PngSecretError {
+    fn from(error: ChunkTypeError) -> Self {
+        PngSecretError::InvalidChunkType {
+            error,
+            at: Location::default(),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! the eXIf chunk of the png spec, an EXIF blob as it would follow the APP1
+//! marker of a jpeg: a TIFF header, II*\0 or MM\0*, then the offset of IFD0
+
+use crate::{error::PngSecretError, Result};
+
+pub const EXIF: &str = "eXIf";
+
+/// what the TIFF header and IFD0 of an EXIF blob say
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exif {
+    pub big_endian: bool,
+    /// the number of entries in IFD0
+    pub entries: u16,
+}
+impl Exif {
+    /// the byte order and IFD0 of an EXIF blob, an error unless it starts with a TIFF header
+    pub fn parse(data: &[u8]) -> Result<Exif> {
+        let big_endian = match data.get(..4) {
+            Some(b"II*\0") => false,
+            Some(b"MM\0*") => true,
+            _ if data.starts_with(b"Exif\0\0") => {
+                return Err(PngSecretError::InvalidData(
+                    "The eXIf chunk starts with the Exif\\0\\0 of a jpeg, not a TIFF header".into(),
+                ))
+            }
+            _ => {
+                return Err(PngSecretError::InvalidData(
+                    "The eXIf chunk doesn't start with a TIFF header".into(),
+                ))
+            }
+        };
+        let read = |at: usize, size: usize| -> Option<u32> {
+            let bytes = data.get(at..at + size)?;
+            let fold = |value, &byte| value << 8 | u32::from(byte);
+            Some(match big_endian {
+                true => bytes.iter().fold(0, fold),
+                false => bytes.iter().rev().fold(0, fold),
+            })
+        };
+        let ifd0 = read(4, 4).ok_or_else(|| {
+            PngSecretError::InvalidData("The eXIf chunk ends in its TIFF header".into())
+        })? as usize;
+        let entries = read(ifd0, 2).ok_or_else(|| {
+            PngSecretError::InvalidData(format!(
+                "IFD0 of the eXIf chunk is at {}, past the end of its {} bytes",
+                ifd0,
+                data.len()
+            ))
+        })? as u16;
+        Ok(Exif {
+            big_endian,
+            entries,
+        })
+    }
+    pub fn byte_order(&self) -> &'static str {
+        match self.big_endian {
+            true => "big endian (MM)",
+            false => "little endian (II)",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let little = [b'I', b'I', 42, 0, 8, 0, 0, 0, 2, 0];
+        assert_eq!(
+            Exif::parse(&little).unwrap(),
+            Exif {
+                big_endian: false,
+                entries: 2
+            }
+        );
+        let big = [b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 3];
+        assert_eq!(Exif::parse(&big).unwrap().entries, 3);
+        assert_eq!(Exif::parse(&big).unwrap().byte_order(), "big endian (MM)");
+        assert!(Exif::parse(b"Exif\0\0MM\0*").is_err());
+        assert!(Exif::parse(&big[..6]).is_err());
+        assert!(Exif::parse(&big[..9]).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! how main turns the error a command failed with into an exit code and a json report
+
+use crate::{error::PngSecretError, json, output};
+
+/// the exit codes args::EXIT_CODES lists for --help
+pub fn code(error: &PngSecretError) -> i32 {
+    match error {
+        PngSecretError::Silent { code } => *code,
+        // 4 like any other integrity failure when a crc is bad
+        PngSecretError::ProblemsFound { crc: true, .. } => 4,
+        PngSecretError::NthOutOfRange { .. } => 7,
+        PngSecretError::Io { .. }
+        | PngSecretError::NotAPng { .. }
+        | PngSecretError::InvalidChunkType { .. }
+        | PngSecretError::InvalidIhdr(_)
+        | PngSecretError::LimitExceeded { .. }
+        | PngSecretError::Truncated { .. } => 2,
+        PngSecretError::ChunkNotFound { .. }
+        | PngSecretError::LsbNotFound
+        | PngSecretError::StealthNotFound => 3,
+        PngSecretError::CrcMismatch { .. }
+        | PngSecretError::BadSignature(_)
+        | PngSecretError::IntegrityFailed
+        | PngSecretError::ChecksumMismatch
+        | PngSecretError::RenderChanged { .. } => 4,
+        PngSecretError::DecryptionFailed => 5,
+        PngSecretError::Refused { .. } | PngSecretError::CriticalChunk { .. } => 6,
+        PngSecretError::ProblemsFound { crc: false, .. }
+        | PngSecretError::IndexOutOfRange { .. }
+        | PngSecretError::ChunkTooLong(_)
+        | PngSecretError::NotUtf8(_)
+        | PngSecretError::Usage(_)
+        | PngSecretError::InvalidData(_)
+        | PngSecretError::Failed(_)
+        | PngSecretError::LsbTooSmall { .. } => 1,
+    }
+}
+
+/// the error as --format json reports it, the kind matches the exit code: all that
+/// exit 2 are unreadable pngs but io errors and limits, all that exit 3 are not found
+pub fn error_json(error: &PngSecretError) -> json::Value {
+    let kind = match error {
+        PngSecretError::Io { .. } => "io",
+        PngSecretError::LimitExceeded { .. } => "limit_exceeded",
+        PngSecretError::NotAPng { .. }
+        | PngSecretError::InvalidChunkType { .. }
+        | PngSecretError::InvalidIhdr(_)
+        | PngSecretError::Truncated { .. } => "unreadable_png",
+        PngSecretError::ChunkNotFound { .. } => "chunk_not_found",
+        PngSecretError::LsbNotFound => "lsb_not_found",
+        PngSecretError::StealthNotFound => "stealth_not_found",
+        PngSecretError::CrcMismatch { .. } | PngSecretError::ProblemsFound { crc: true, .. } => {
+            "crc_mismatch"
+        }
+        PngSecretError::IntegrityFailed => "integrity_failed",
+        PngSecretError::ChecksumMismatch => "checksum_mismatch",
+        PngSecretError::BadSignature(_) => "bad_signature",
+        PngSecretError::RenderChanged { .. } => "render_changed",
+        PngSecretError::DecryptionFailed => "decryption_failed",
+        PngSecretError::CriticalChunk { .. } => "critical_chunk",
+        PngSecretError::Refused { .. } => "refused",
+        PngSecretError::NthOutOfRange { .. } => "nth_out_of_range",
+        PngSecretError::ProblemsFound { crc: false, .. } => "problems_found",
+        _ => "other",
+    };
+    let path = error.path().map(|path| path.display().to_string());
+    output::error(kind, error.to_string(), path)
+}
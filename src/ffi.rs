@@ -0,0 +1,286 @@
+//! a C abi over [`crate::encode`], [`crate::decode`] and removing a chunk, for
+//! programs that embed the library instead of running the binary. every function
+//! returns 0 or the exit code the cli would fail with, and
+//! `pngsecret_last_error_message` says what went wrong. include/pngsecret.h is
+//! generated from this file with `cbindgen --output include/pngsecret.h`
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+    str::FromStr,
+};
+
+use crate::{chunk_type::ChunkType, error::PngSecretError, exit, png::Png, Result};
+
+/// the call succeeded
+pub const PNGSECRET_OK: c_int = 0;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// runs `call`, keeping its error or panic for `pngsecret_last_error_message`
+fn guarded(call: impl FnOnce() -> Result<()>) -> c_int {
+    let (code, message) = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => (PNGSECRET_OK, None),
+        Ok(Err(error)) => (exit::code(&error), Some(error.to_string())),
+        Err(_) => (1, Some("pngsecret panicked".to_string())),
+    };
+    // a message with a NUL in it is cut there
+    let message = message.map(|message| {
+        let end = message.find('\0').unwrap_or(message.len());
+        CString::new(&message[..end]).unwrap()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    code
+}
+
+/// the `len` bytes at `buf`, an empty slice for a null `buf` with no length
+unsafe fn bytes<'a>(buf: *const u8, len: usize) -> Result<&'a [u8]> {
+    match (buf.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(PngSecretError::Usage("The buffer is null".into())),
+        (false, _) => Ok(slice::from_raw_parts(buf, len)),
+    }
+}
+unsafe fn chunk_type<'a>(chunk_type: *const c_char) -> Result<&'a str> {
+    if chunk_type.is_null() {
+        return Err(PngSecretError::Usage("The chunk type is null".into()));
+    }
+    Ok(CStr::from_ptr(chunk_type).to_str()?)
+}
+unsafe fn read_png(buf: *const u8, len: usize) -> Result<Png> {
+    Png::try_from(bytes(buf, len)?)
+}
+/// hands `bytes` to the caller, who gives them back to `pngsecret_free`
+unsafe fn give(bytes: Vec<u8>, out_buf: *mut *mut u8, out_len: *mut usize) -> Result<()> {
+    if out_buf.is_null() || out_len.is_null() {
+        return Err(PngSecretError::Usage("The output pointers are null".into()));
+    }
+    let bytes = bytes.into_boxed_slice();
+    *out_len = bytes.len();
+    *out_buf = Box::into_raw(bytes).cast();
+    Ok(())
+}
+
+/// writes the png in `in_buf` with `data` in a new chunk of `chunk_type` before its
+/// IEND to a new buffer, which `pngsecret_free` frees
+///
+/// # Safety
+/// `in_buf` and `data` point to `in_len` and `data_len` readable bytes, `chunk_type` to
+/// a NUL terminated string and `out_buf` and `out_len` to writable pointers
+#[no_mangle]
+pub unsafe extern "C" fn pngsecret_encode(
+    in_buf: *const u8,
+    in_len: usize,
+    chunk_type: *const c_char,
+    data: *const u8,
+    data_len: usize,
+    out_buf: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    guarded(|| {
+        let mut png = read_png(in_buf, in_len)?;
+        let chunk_type = ChunkType::from_str(self::chunk_type(chunk_type)?)?;
+        crate::encode(&mut png, chunk_type, bytes(data, data_len)?)?;
+        give(png.as_bytes(), out_buf, out_len)
+    })
+}
+
+/// copies the data of the first chunk of `chunk_type` in the png in `in_buf` to a new
+/// buffer, which `pngsecret_free` frees
+///
+/// # Safety
+/// as for `pngsecret_encode`
+#[no_mangle]
+pub unsafe extern "C" fn pngsecret_decode(
+    in_buf: *const u8,
+    in_len: usize,
+    chunk_type: *const c_char,
+    out_buf: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    guarded(|| {
+        let png = read_png(in_buf, in_len)?;
+        let data = crate::decode(&png, self::chunk_type(chunk_type)?)?;
+        give(data.to_vec(), out_buf, out_len)
+    })
+}
+
+/// writes the png in `in_buf` without its first chunk of `chunk_type` to a new buffer,
+/// which `pngsecret_free` frees
+///
+/// # Safety
+/// as for `pngsecret_encode`
+#[no_mangle]
+pub unsafe extern "C" fn pngsecret_remove(
+    in_buf: *const u8,
+    in_len: usize,
+    chunk_type: *const c_char,
+    out_buf: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    guarded(|| {
+        let mut png = read_png(in_buf, in_len)?;
+        png.remove_chunk(self::chunk_type(chunk_type)?)?;
+        give(png.as_bytes(), out_buf, out_len)
+    })
+}
+
+/// frees a buffer one of the functions above returned, null does nothing
+///
+/// # Safety
+/// `buf` and `len` are what a call returned in `out_buf` and `out_len`, and `buf`
+/// isn't freed twice
+#[no_mangle]
+pub unsafe extern "C" fn pngsecret_free(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+/// what the last failed call on this thread failed with, null after a call that
+/// succeeded. the string stays valid until the next call on the thread
+#[no_mangle]
+pub extern "C" fn pngsecret_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |last| last.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+
+    fn testing_png() -> Vec<u8> {
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk("IEND", &[]),
+        ])
+        .as_bytes()
+    }
+
+    /// the bytes a call returned, freed again
+    fn taken(code: c_int, buf: *mut u8, len: usize) -> Vec<u8> {
+        assert_eq!(code, PNGSECRET_OK);
+        assert!(pngsecret_last_error_message().is_null());
+        let bytes = unsafe { slice::from_raw_parts(buf, len) }.to_vec();
+        unsafe { pngsecret_free(buf, len) };
+        bytes
+    }
+    fn last_error() -> String {
+        let message = pngsecret_last_error_message();
+        assert!(!message.is_null());
+        unsafe { CStr::from_ptr(message) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = testing_png();
+        let (mut buf, mut len) = (ptr::null_mut(), 0);
+        let code = unsafe {
+            pngsecret_encode(
+                input.as_ptr(),
+                input.len(),
+                c"ruSt".as_ptr(),
+                b"hidden".as_ptr(),
+                6,
+                &mut buf,
+                &mut len,
+            )
+        };
+        let encoded = taken(code, buf, len);
+        assert_eq!(
+            crate::decode(&Png::try_from(&encoded[..]).unwrap(), "ruSt").unwrap(),
+            b"hidden"
+        );
+
+        let code = unsafe {
+            pngsecret_decode(
+                encoded.as_ptr(),
+                encoded.len(),
+                c"ruSt".as_ptr(),
+                &mut buf,
+                &mut len,
+            )
+        };
+        assert_eq!(taken(code, buf, len), b"hidden");
+
+        let code = unsafe {
+            pngsecret_remove(
+                encoded.as_ptr(),
+                encoded.len(),
+                c"ruSt".as_ptr(),
+                &mut buf,
+                &mut len,
+            )
+        };
+        assert_eq!(taken(code, buf, len), input);
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = testing_png();
+        let (mut buf, mut len) = (ptr::null_mut(), 0);
+        let decode = |input: &[u8], chunk_type: &CStr, buf: &mut *mut u8, len: &mut usize| unsafe {
+            pngsecret_decode(input.as_ptr(), input.len(), chunk_type.as_ptr(), buf, len)
+        };
+        assert_eq!(decode(b"not a png", c"ruSt", &mut buf, &mut len), 2);
+        assert_eq!(last_error(), "Invalid header, not a png file");
+        assert_eq!(decode(&input, c"ruSt", &mut buf, &mut len), 3);
+        assert_eq!(last_error(), "no chunk of type 'ruSt' found");
+        let mut bad_crc = input.clone();
+        bad_crc[20] ^= 0xff;
+        assert_eq!(decode(&bad_crc, c"IHDR", &mut buf, &mut len), 4);
+        assert!(buf.is_null());
+
+        let code = unsafe {
+            pngsecret_encode(
+                input.as_ptr(),
+                input.len(),
+                c"ru5t".as_ptr(),
+                ptr::null(),
+                0,
+                &mut buf,
+                &mut len,
+            )
+        };
+        assert_eq!(code, 1);
+        let code = unsafe {
+            pngsecret_encode(
+                input.as_ptr(),
+                input.len(),
+                ptr::null(),
+                ptr::null(),
+                0,
+                &mut buf,
+                &mut len,
+            )
+        };
+        assert_eq!(code, 1);
+        assert_eq!(last_error(), "The chunk type is null");
+        let code = unsafe {
+            pngsecret_remove(
+                input.as_ptr(),
+                input.len(),
+                c"IHDR".as_ptr(),
+                ptr::null_mut(),
+                &mut len,
+            )
+        };
+        assert_eq!(code, 1);
+        assert_eq!(last_error(), "The output pointers are null");
+        unsafe { pngsecret_free(ptr::null_mut(), 0) };
+    }
+}
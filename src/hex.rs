@@ -0,0 +1,56 @@
+//! hex for bytes on the command line, `encode --message-hex` and `decode --hex`
+
+/// lowercase hex, two digits a byte
+pub fn encode(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// hex in either case, whitespace is skipped and each word may start with 0x
+pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text
+        .split_whitespace()
+        .map(|word| {
+            word.strip_prefix("0x")
+                .or_else(|| word.strip_prefix("0X"))
+                .unwrap_or(word)
+        })
+        .collect();
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex character {:?}", c));
+    }
+    if digits.len() % 2 == 1 {
+        return Err(format!("Odd number of hex digits: {}", digits.len()));
+    }
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+        .collect())
+}
+
+/// bytes given as hex, for arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexBytes(pub Vec<u8>);
+impl std::str::FromStr for HexBytes {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        decode(text).map(HexBytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        let bytes = [0xde, 0xad, 0xbe, 0xef, 0x00, 0xff];
+        assert_eq!(encode(&bytes), "deadbeef00ff");
+        assert_eq!(decode("deadbeef00ff").unwrap(), bytes);
+        assert_eq!(decode("0xDEAD beef\n0x00ff").unwrap(), bytes);
+        assert_eq!(decode("").unwrap(), b"");
+        assert_eq!(decode("abc").unwrap_err(), "Odd number of hex digits: 3");
+        assert_eq!(decode("zz").unwrap_err(), "Invalid hex character 'z'");
+        assert!(decode("0x0x00").is_err());
+    }
+}
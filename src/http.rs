@@ -0,0 +1,69 @@
+//! an http or https url instead of a file, for the commands that only read it. the
+//! response streams into the parser like stdin does, so a huge one stops at the limit.
+//! only builds with the http feature fetch anything
+
+use std::{io, path::Path};
+
+#[cfg(not(feature = "http"))]
+pub const UNAVAILABLE: &str = "Reading a URL needs a pngsecret built with the http feature";
+
+/// the most a response may hold without --max-memory, 256 MiB
+pub const MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+/// what a server may call a png. octet-stream is what a lot of them send for any file,
+/// the signature is checked anyway
+#[cfg(feature = "http")]
+const CONTENT_TYPES: [&str; 3] = ["image/png", "image/apng", "application/octet-stream"];
+
+/// whether `path` is an http or https url rather than a file
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("https://") || path.starts_with("http://"))
+}
+
+/// the body of a 200 response to a GET of `url`, an error past `max_size` bytes
+#[cfg(feature = "http")]
+pub fn open(url: &str, max_size: u64) -> io::Result<Box<dyn io::Read>> {
+    use crate::log;
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = agent.get(url).call().map_err(io::Error::other)?;
+    if response.status() != 200 {
+        return Err(io::Error::other(format!(
+            "the server answered {}",
+            response.status()
+        )));
+    }
+    let body = response.into_body();
+    if let Some(mime_type) = body.mime_type() {
+        if !CONTENT_TYPES.contains(&mime_type) {
+            return Err(io::Error::other(format!(
+                "the server sent {}, not a png",
+                mime_type
+            )));
+        }
+    }
+    log::debug!(url, length = ?body.content_length(), "fetched url");
+    Ok(Box::new(body.into_with_config().limit(max_size).reader()))
+}
+#[cfg(not(feature = "http"))]
+pub fn open(_url: &str, _max_size: u64) -> io::Result<Box<dyn io::Read>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, UNAVAILABLE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(Path::new("https://example.com/image.png")));
+        assert!(is_url(Path::new("http://localhost:8080/a.png")));
+        assert!(!is_url(Path::new("image.png")));
+        assert!(!is_url(Path::new("./https://example.com")));
+        assert!(!is_url(Path::new("-")));
+    }
+}
@@ -0,0 +1,217 @@
+//! the IHDR chunk every png starts with: the size of the image and how its
+//! pixels are stored
+
+/// the type of the chunk
+pub const IHDR: &str = "IHDR";
+
+/// what each pixel holds, the color type byte of IHDR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    /// 0, a gray level
+    Grayscale,
+    /// 2, red, green and blue
+    Rgb,
+    /// 3, an index into the PLTE palette
+    Indexed,
+    /// 4, a gray level and an alpha
+    GrayscaleAlpha,
+    /// 6, red, green, blue and alpha
+    Rgba,
+}
+impl ColorType {
+    fn from_byte(byte: u8) -> Option<ColorType> {
+        match byte {
+            0 => Some(ColorType::Grayscale),
+            2 => Some(ColorType::Rgb),
+            3 => Some(ColorType::Indexed),
+            4 => Some(ColorType::GrayscaleAlpha),
+            6 => Some(ColorType::Rgba),
+            _ => None,
+        }
+    }
+    /// the byte IHDR stores it as
+    pub fn byte(&self) -> u8 {
+        match self {
+            ColorType::Grayscale => 0,
+            ColorType::Rgb => 2,
+            ColorType::Indexed => 3,
+            ColorType::GrayscaleAlpha => 4,
+            ColorType::Rgba => 6,
+        }
+    }
+    /// like RGBA or grayscale with alpha
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorType::Grayscale => "grayscale",
+            ColorType::Rgb => "RGB",
+            ColorType::Indexed => "indexed",
+            ColorType::GrayscaleAlpha => "grayscale with alpha",
+            ColorType::Rgba => "RGBA",
+        }
+    }
+    /// the bit depths the spec allows with the color type
+    fn bit_depths(&self) -> &'static [u8] {
+        match self {
+            ColorType::Grayscale => &[1, 2, 4, 8, 16],
+            ColorType::Indexed => &[1, 2, 4, 8],
+            ColorType::Rgb | ColorType::GrayscaleAlpha | ColorType::Rgba => &[8, 16],
+        }
+    }
+}
+
+/// why the data of an IHDR chunk isn't a valid header
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IhdrError {
+    /// the data is this many bytes long, not 13
+    #[error("The IHDR chunk has {0} bytes, not 13")]
+    Length(usize),
+    /// the width or height is 0
+    #[error("The IHDR chunk has a size of {width} x {height}, an image can't be empty")]
+    ZeroSize {
+        /// the width stored
+        width: u32,
+        /// the height stored
+        height: u32,
+    },
+    /// the color type byte isn't 0, 2, 3, 4 or 6
+    #[error("The IHDR chunk has color type {0}, not 0, 2, 3, 4 or 6")]
+    ColorType(u8),
+    /// the color type can't have this bit depth
+    #[error(
+        "The IHDR chunk has bit depth {bit_depth}, which {} images can't have",
+        .color_type.name()
+    )]
+    BitDepth {
+        /// the bit depth stored
+        bit_depth: u8,
+        /// the color type stored
+        color_type: ColorType,
+    },
+}
+
+/// the fields of an IHDR chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ihdr {
+    /// in pixels, never 0
+    pub width: u32,
+    /// in pixels, never 0
+    pub height: u32,
+    /// bits per sample, or per palette index for indexed images
+    pub bit_depth: u8,
+    /// what each pixel holds
+    pub color_type: ColorType,
+    /// 0 is deflate, the only method there is
+    pub compression: u8,
+    /// 0 is adaptive filtering, the only method there is
+    pub filter: u8,
+    /// 0 for none, 1 for Adam7
+    pub interlace: u8,
+}
+impl Ihdr {
+    /// the 13 bytes of IHDR data, an error for another length, an empty image, an
+    /// unknown color type or a bit depth the color type can't have
+    pub fn parse(data: &[u8]) -> Result<Ihdr, IhdrError> {
+        let data: &[u8; 13] = data.try_into().map_err(|_| IhdrError::Length(data.len()))?;
+        let u32_at = |at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+        let (width, height) = (u32_at(0), u32_at(4));
+        if width == 0 || height == 0 {
+            return Err(IhdrError::ZeroSize { width, height });
+        }
+        let color_type = ColorType::from_byte(data[9]).ok_or(IhdrError::ColorType(data[9]))?;
+        let bit_depth = data[8];
+        if !color_type.bit_depths().contains(&bit_depth) {
+            return Err(IhdrError::BitDepth {
+                bit_depth,
+                color_type,
+            });
+        }
+        Ok(Ihdr {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            compression: data[10],
+            filter: data[11],
+            interlace: data[12],
+        })
+    }
+    /// none, Adam7, or unknown for a method the spec doesn't have
+    pub fn interlace_name(&self) -> &'static str {
+        match self.interlace {
+            0 => "none",
+            1 => "Adam7",
+            _ => "unknown",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let ihdr = Ihdr::parse(&[0, 0, 2, 128, 0, 0, 1, 224, 8, 6, 0, 0, 1]).unwrap();
+        assert_eq!(
+            ihdr,
+            Ihdr {
+                width: 640,
+                height: 480,
+                bit_depth: 8,
+                color_type: ColorType::Rgba,
+                compression: 0,
+                filter: 0,
+                interlace: 1,
+            }
+        );
+        assert_eq!(ihdr.color_type.name(), "RGBA");
+        assert_eq!(ihdr.interlace_name(), "Adam7");
+
+        let indexed = Ihdr::parse(&[0, 0, 0, 1, 0, 0, 0, 1, 1, 3, 0, 0, 0]).unwrap();
+        assert_eq!(indexed.color_type, ColorType::Indexed);
+        assert_eq!(indexed.bit_depth, 1);
+        let gray = Ihdr::parse(&[0, 0, 0, 1, 0, 0, 0, 1, 16, 0, 0, 0, 0]).unwrap();
+        assert_eq!(gray.color_type.byte(), 0);
+        assert_eq!(gray.interlace_name(), "none");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |data: &[u8]| Ihdr::parse(data).unwrap_err();
+        assert_eq!(error(&[0; 12]), IhdrError::Length(12));
+        assert_eq!(error(&[0; 14]), IhdrError::Length(14));
+        assert_eq!(
+            error(&[0, 0, 0, 0, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            IhdrError::ZeroSize {
+                width: 0,
+                height: 1
+            }
+        );
+        assert_eq!(
+            error(&[0, 0, 0, 1, 0, 0, 0, 0, 8, 6, 0, 0, 0]),
+            IhdrError::ZeroSize {
+                width: 1,
+                height: 0
+            }
+        );
+        assert_eq!(
+            error(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 5, 0, 0, 0]),
+            IhdrError::ColorType(5)
+        );
+        assert_eq!(
+            error(&[0, 0, 0, 1, 0, 0, 0, 1, 16, 3, 0, 0, 0]),
+            IhdrError::BitDepth {
+                bit_depth: 16,
+                color_type: ColorType::Indexed
+            }
+        );
+        assert_eq!(
+            error(&[0, 0, 0, 1, 0, 0, 0, 1, 4, 2, 0, 0, 0]).to_string(),
+            "The IHDR chunk has bit depth 4, which RGB images can't have"
+        );
+        assert_eq!(
+            error(&[0, 0, 0, 0, 0, 0, 0, 0, 8, 6, 0, 0, 0]).to_string(),
+            "The IHDR chunk has a size of 0 x 0, an image can't be empty"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! where every chunk of a file is, found by seeking over the chunk data instead of
+//! reading it. listing a file this way takes about as long for 1 GB as for 1 KB
+
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::{Location, PngSecretError},
+    png::Png,
+};
+
+/// a chunk without its data, `crc` is the stored one and isn't checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkInfo {
+    pub index: usize,
+    /// where the chunk starts in the file
+    pub offset: usize,
+    pub chunk_type: ChunkType,
+    pub length: u32,
+    pub crc: u32,
+}
+impl ChunkInfo {
+    pub fn new(index: usize, offset: usize, chunk: &Chunk) -> Self {
+        ChunkInfo {
+            index,
+            offset,
+            chunk_type: chunk.chunk_type().clone(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+        }
+    }
+    /// where the data of the chunk starts in the file
+    pub fn data_offset(&self) -> usize {
+        self.offset + 8
+    }
+    /// where the next chunk starts, or the trailing data after the last one
+    pub fn end(&self) -> usize {
+        self.offset + self.length as usize + Chunk::OVERHEAD
+    }
+}
+
+pub struct PngIndex;
+impl PngIndex {
+    /// the chunks of the png in `reader`. like parsing the whole file it checks the
+    /// signature, the chunk types and that the last chunk isn't cut off, but no crcs
+    pub fn scan<R: Read + Seek>(reader: R) -> Result<Vec<ChunkInfo>, PngSecretError> {
+        let mut reader = BufReader::new(reader);
+        let len = reader.seek(SeekFrom::End(0))? as usize;
+        reader.rewind()?;
+        let mut signature = [0; 8];
+        if reader.read_exact(&mut signature).is_err() || signature != Png::STANDARD_HEADER {
+            return Err(PngSecretError::NotAPng { path: None });
+        }
+        let mut chunks: Vec<ChunkInfo> = Vec::new();
+        let mut offset = signature.len();
+        // past IEND what isn't a chunk is trailing data, which ends the index
+        let mut after_iend = false;
+        while offset < len {
+            let index = chunks.len();
+            let truncated = || PngSecretError::Truncated {
+                at: Location {
+                    index: Some(index),
+                    offset: Some(offset),
+                    ..Location::default()
+                },
+            };
+            let mut header = [0; 8];
+            if offset + header.len() > len {
+                if after_iend {
+                    break;
+                }
+                return Err(truncated());
+            }
+            reader.read_exact(&mut header)?;
+            let length = u32::from_be_bytes(header[..4].try_into().unwrap());
+            let chunk_type = match ChunkType::try_from(<[u8; 4]>::try_from(&header[4..]).unwrap()) {
+                Ok(chunk_type) => chunk_type,
+                Err(_) if after_iend => break,
+                Err(error) => return Err(PngSecretError::from(error).at(index, offset)),
+            };
+            let end = offset + length as usize + Chunk::OVERHEAD;
+            if end > len {
+                if after_iend {
+                    break;
+                }
+                return Err(truncated());
+            }
+            after_iend |= chunk_type.to_string() == "IEND";
+            reader.seek_relative(i64::from(length))?;
+            let mut crc = [0; 4];
+            reader.read_exact(&mut crc)?;
+            chunks.push(ChunkInfo {
+                index,
+                offset,
+                chunk_type,
+                length,
+                crc: u32::from_be_bytes(crc),
+            });
+            offset = end;
+        }
+        Ok(chunks)
+    }
+    /// the same for a png already in memory
+    pub fn of(png: &Png) -> Vec<ChunkInfo> {
+        png.chunks()
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| ChunkInfo::new(index, png.chunk_offset(index), chunk))
+            .collect()
+    }
+    /// the data of `chunk`, read from the file `scan` indexed
+    pub fn read_data<R: Read + Seek>(mut reader: R, chunk: &ChunkInfo) -> io::Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(chunk.data_offset() as u64))?;
+        let mut data = vec![0; chunk.length as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, str::FromStr};
+
+    fn testing_png() -> Png {
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk("tEXt", b"Comment\0an index"),
+            chunk("IDAT", &vec![7; 100_000]),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_scan() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        let chunks = PngIndex::scan(Cursor::new(&bytes)).unwrap();
+        assert_eq!(chunks, PngIndex::of(&png));
+
+        // the offsets point at the chunks
+        let mut reader = Cursor::new(&bytes);
+        for chunk in &chunks {
+            reader
+                .seek(SeekFrom::Start(chunk.offset as u64 + 4))
+                .unwrap();
+            let mut chunk_type = [0; 4];
+            reader.read_exact(&mut chunk_type).unwrap();
+            assert_eq!(chunk_type, chunk.chunk_type.bytes());
+        }
+        assert_eq!(
+            PngIndex::read_data(&mut reader, &chunks[1]).unwrap(),
+            b"Comment\0an index"
+        );
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let bytes = testing_png().as_bytes();
+        let scan = |bytes: &[u8]| PngIndex::scan(Cursor::new(bytes.to_vec()));
+        assert!(matches!(scan(b""), Err(PngSecretError::NotAPng { .. })));
+        assert!(matches!(
+            scan(&bytes[..bytes.len() - 14]),
+            Err(PngSecretError::Truncated { at }) if at.index == Some(2)
+        ));
+        assert!(matches!(
+            scan(&bytes[..bytes.len() - 6]),
+            Err(PngSecretError::Truncated { at }) if at.index == Some(3)
+        ));
+        let mut bad_type = bytes.clone();
+        bad_type[12] = b'1';
+        assert!(matches!(
+            scan(&bad_type),
+            Err(PngSecretError::InvalidChunkType { at, .. }) if at.index == Some(0)
+        ));
+        // crcs are left to verify
+        let mut bad_crc = bytes.clone();
+        bad_crc[32] ^= 0xff;
+        assert_eq!(scan(&bad_crc).unwrap().len(), 4);
+        // past IEND the index only stops
+        let mut trailing = bytes.clone();
+        trailing.extend_from_slice(b"not a chunk");
+        let chunks = scan(&trailing).unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].end(), bytes.len());
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt::{Display, Write};
+
+/// A minimal json value, objects keep their insertion order so output is stable
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn object<const N: usize>(fields: [(&str, Value); N]) -> Self {
+        Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Number(value as f64)
+    }
+}
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::Array(value)
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            // integers are printed without a fraction so offsets and lengths look like integers
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Value::Number(_) => f.write_str("null"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_char(']')
+            }
+            Value::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        bytes: input.as_bytes(),
+        position: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position != parser.bytes.len() {
+        return Err("Trailing characters after json value".to_string());
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.bytes.get(self.position) {
+            self.position += 1;
+        }
+    }
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.position).copied()
+    }
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() != Some(byte) {
+            return Err(format!("Expected '{}' at {}", byte as char, self.position));
+        }
+        self.position += 1;
+        Ok(())
+    }
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value, String> {
+        if !self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            return Err(format!("Invalid json literal at {}", self.position));
+        }
+        self.position += literal.len();
+        Ok(value)
+    }
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                if self.peek() == Some(b']') {
+                    self.position += 1;
+                    return Ok(Value::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b']')?;
+                Ok(Value::Array(values))
+            }
+            Some(b'{') => {
+                self.position += 1;
+                let mut fields = Vec::new();
+                if self.peek() == Some(b'}') {
+                    self.position += 1;
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.value()?));
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b'}')?;
+                Ok(Value::Object(fields))
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(format!("Unexpected character at {}", self.position)),
+        }
+    }
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+            self.bytes.get(self.position)
+        {
+            self.position += 1;
+        }
+        let text =
+            std::str::from_utf8(&self.bytes[start..self.position]).map_err(|e| e.to_string())?;
+        Ok(Value::Number(text.parse().map_err(|_| {
+            format!("Invalid json number at {}", start)
+        })?))
+    }
+    fn string(&mut self) -> Result<String, String> {
+        if self.bytes.get(self.position) != Some(&b'"') {
+            return Err(format!("Expected string at {}", self.position));
+        }
+        self.position += 1;
+        let mut string = String::new();
+        loop {
+            let start = self.position;
+            while let Some(&b) = self.bytes.get(self.position) {
+                if b == b'"' || b == b'\\' {
+                    break;
+                }
+                self.position += 1;
+            }
+            string.push_str(
+                std::str::from_utf8(&self.bytes[start..self.position])
+                    .map_err(|e| e.to_string())?,
+            );
+            match self.bytes.get(self.position) {
+                Some(b'"') => {
+                    self.position += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') => {
+                    let escape = self.bytes.get(self.position + 1).copied();
+                    self.position += 2;
+                    match escape {
+                        Some(b'"') => string.push('"'),
+                        Some(b'\\') => string.push('\\'),
+                        Some(b'/') => string.push('/'),
+                        Some(b'b') => string.push('\u{8}'),
+                        Some(b'f') => string.push('\u{c}'),
+                        Some(b'n') => string.push('\n'),
+                        Some(b'r') => string.push('\r'),
+                        Some(b't') => string.push('\t'),
+                        Some(b'u') => string.push(self.unicode_escape()?),
+                        _ => return Err(format!("Invalid escape at {}", self.position)),
+                    }
+                }
+                _ => return Err("Unterminated json string".to_string()),
+            }
+        }
+    }
+    fn hex4(&mut self) -> Result<u32, String> {
+        let hex = self
+            .bytes
+            .get(self.position..self.position + 4)
+            .ok_or("Truncated unicode escape")?;
+        self.position += 4;
+        let hex = std::str::from_utf8(hex).map_err(|e| e.to_string())?;
+        u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid unicode escape {:?}", hex))
+    }
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let mut code = self.hex4()?;
+        // surrogate pairs come as two escapes
+        if (0xd800..0xdc00).contains(&code) && self.bytes[self.position..].starts_with(b"\\u") {
+            self.position += 2;
+            let low = self.hex4()?;
+            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+        }
+        char::from_u32(code).ok_or_else(|| "Invalid unicode escape".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let value = Value::object([
+            ("name", "a \"quoted\"\nline".into()),
+            ("count", 3u32.into()),
+            ("ok", true.into()),
+            ("items", vec![Value::Null, Value::Number(1.5)].into()),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"name":"a \"quoted\"\nline","count":3,"ok":true,"items":[null,1.5]}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let text = r#" { "a" : [1, -2.5e1, "xé😀"], "b": {"c": null, "d": false} } "#;
+        let value = parse(text).unwrap();
+        assert_eq!(
+            value.get("a").unwrap().as_array().unwrap()[1].as_f64(),
+            Some(-25.0)
+        );
+        assert_eq!(
+            value.get("a").unwrap().as_array().unwrap()[2].as_str(),
+            Some("xé😀")
+        );
+        assert_eq!(parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("{\"a\": }").is_err());
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("1 2").is_err());
+        assert!(parse("\"unterminated").is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! reading and changing png files chunk by chunk, and hiding messages in chunks
+//! of their own. [`encode`] and [`decode`] do what `pngsecret encode` and
+//! `pngsecret decode` do for a message without options, [`png::Png`] and
+//! [`chunk::Chunk`] are there for everything else
+//!
+//! ```
+//! use pngsecret::{chunk_type::ChunkType, png::Png};
+//! use std::str::FromStr;
+//!
+//! # let png = Png::from_chunks(Vec::new()).as_bytes();
+//! let mut png = Png::try_from(png.as_slice())?;
+//! pngsecret::encode(&mut png, ChunkType::from_str("ruSt")?, b"hidden")?;
+//! assert_eq!(pngsecret::decode(&png, "ruSt")?, b"hidden");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+#![warn(missing_docs)]
+
+pub mod chunk;
+pub mod chunk_type;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod ihdr;
+pub mod png;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// the cli, public for src/main.rs and not part of the api
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod args;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod color;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod commands;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod exit;
+#[doc(hidden)]
+pub mod json;
+#[doc(hidden)]
+pub mod log;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod output;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod progress;
+
+#[cfg(feature = "cli")]
+mod base64;
+#[cfg(feature = "cli")]
+mod completions;
+#[cfg(any(test, not(feature = "crc"), feature = "no-crc-dep"))]
+mod crc32;
+#[cfg(feature = "cli")]
+mod crypto;
+#[cfg(feature = "cli")]
+mod deflate;
+#[cfg(feature = "cli")]
+mod diff;
+#[cfg(feature = "cli")]
+mod encoding;
+#[cfg(feature = "cli")]
+mod exif;
+#[cfg(feature = "cli")]
+mod hex;
+#[cfg(feature = "cli")]
+mod http;
+#[cfg(feature = "cli")]
+mod index;
+#[cfg(feature = "cli")]
+mod lsb;
+#[cfg(feature = "cli")]
+mod metadata;
+#[cfg(feature = "cli")]
+mod mmap;
+#[cfg(feature = "cli")]
+mod parallel;
+#[cfg(feature = "cli")]
+mod password;
+#[cfg(feature = "cli")]
+mod payload;
+#[cfg(feature = "cli")]
+mod render;
+#[cfg(feature = "cli")]
+mod scan;
+#[cfg(feature = "cli")]
+mod stealth;
+#[cfg(feature = "cli")]
+mod stream;
+#[cfg(feature = "cli")]
+mod text;
+#[cfg(feature = "cli")]
+mod time;
+
+use chunk::Chunk;
+use chunk_type::ChunkType;
+use error::PngSecretError;
+use png::Png;
+
+/// a result with [`PngSecretError`], what every command fails with
+pub type Result<T> = std::result::Result<T, PngSecretError>;
+
+/// hides `message` in a new chunk of `chunk_type` before the IEND, or at the end
+/// without one, and returns its index. nothing stops a critical chunk type here,
+/// it is up to the caller
+pub fn encode(
+    png: &mut Png,
+    chunk_type: ChunkType,
+    message: &[u8],
+) -> std::result::Result<usize, PngSecretError> {
+    if message.len() > Chunk::MAX_LENGTH {
+        return Err(PngSecretError::ChunkTooLong(message.len()));
+    }
+    let index = png.iend_index().unwrap_or(png.chunks().len());
+    png.insert_chunk_at(index, Chunk::new(chunk_type, message))?;
+    Ok(index)
+}
+/// the message in the first chunk of `chunk_type`, as [`encode`] stored it
+pub fn decode<'a>(png: &'a Png, chunk_type: &str) -> std::result::Result<&'a [u8], PngSecretError> {
+    png.chunk_by_type(chunk_type)
+        .map(Chunk::data)
+        .ok_or_else(|| PngSecretError::chunk_not_found(chunk_type))
+}
@@ -0,0 +1,278 @@
+//! how chatty the cli is, and the tracing subscriber it prints events with
+
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// how chatty the cli is, messages above the current level are dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+impl Level {
+    /// the level for `-q` and the number of `-v` flags
+    pub fn from_flags(verbose: u8, quiet: bool) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Level::Error,
+            (false, 0) => Level::Info,
+            (false, 1) => Level::Debug,
+            (false, _) => Level::Trace,
+        }
+    }
+    fn from_u8(level: u8) -> Self {
+        [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ][level.min(4) as usize]
+    }
+}
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Level::Error => "error",
+            Level::Warn => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<tracing::Level> for Level {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::ERROR => Level::Error,
+            tracing::Level::WARN => Level::Warn,
+            tracing::Level::INFO => Level::Info,
+            tracing::Level::DEBUG => Level::Debug,
+            tracing::Level::TRACE => Level::Trace,
+        }
+    }
+}
+impl From<Level> for tracing::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Error => tracing::Level::ERROR,
+            Level::Warn => tracing::Level::WARN,
+            Level::Info => tracing::Level::INFO,
+            Level::Debug => tracing::Level::DEBUG,
+            Level::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// the level the cli prints its own output at, `-q` drops everything but errors
+pub fn set_max_level(level: Level) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+pub fn max_level() -> Level {
+    #[cfg(test)]
+    if let Some(level) = capture::level() {
+        return level;
+    }
+    Level::from_u8(MAX_LEVEL.load(Ordering::Relaxed))
+}
+pub fn enabled(level: Level) -> bool {
+    level <= max_level()
+}
+
+// everything is logged through tracing, so a program using the library decides
+// where it goes. only the cli installs a subscriber, see `init`
+#[cfg_attr(not(feature = "cli"), allow(unused_imports))]
+pub(crate) use tracing::{debug, trace, warn as warning};
+
+/// the name tracing knows the environment variable by that overrides the -v flags,
+/// with filter directives like `debug` or `pngsecret::png=trace`
+#[cfg(feature = "cli")]
+pub const ENV_FILTER: &str = "PNGSECRET_LOG";
+
+/// sets `level` for the cli's output and prints tracing events of it to stderr, or
+/// the ones $PNGSECRET_LOG asks for
+#[cfg(feature = "cli")]
+pub fn init(level: Level) {
+    use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+
+    set_max_level(level);
+    let overridden = std::env::var_os(ENV_FILTER).is_some();
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::from_level(level.into()).into())
+        .with_env_var(ENV_FILTER)
+        .from_env_lossy();
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .event_format(Format {
+            spans: overridden || level >= Level::Debug,
+        })
+        .init();
+}
+
+/// `debug: message field=value`, like the cli's errors are `error: message`. the
+/// command and the file it works on come first with -v
+#[cfg(feature = "cli")]
+struct Format {
+    spans: bool,
+}
+#[cfg(feature = "cli")]
+impl<S, N> tracing_subscriber::fmt::FormatEvent<S, N> for Format
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    N: for<'a> tracing_subscriber::fmt::FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &tracing_subscriber::fmt::FmtContext<'_, S, N>,
+        mut writer: tracing_subscriber::fmt::format::Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> std::fmt::Result {
+        write!(writer, "{}: ", Level::from(*event.metadata().level()))?;
+        if let Some(scope) = ctx.event_scope().filter(|_| self.spans) {
+            for span in scope.from_root() {
+                write!(writer, "{}", span.name())?;
+                let extensions = span.extensions();
+                let fields = extensions.get::<tracing_subscriber::fmt::FormattedFields<N>>();
+                if let Some(fields) = fields.filter(|fields| !fields.is_empty()) {
+                    write!(writer, "{{{}}}", fields)?;
+                }
+                write!(writer, ": ")?;
+            }
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// lets a test run at its own level and collect what it logged as `level: message
+/// field=value` lines, and its spans as `span: name field=value`, without touching
+/// other tests
+#[cfg(test)]
+pub mod capture {
+    use std::{
+        cell::RefCell,
+        fmt::{Debug, Write},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    use super::Level;
+
+    thread_local! {
+        static LEVEL: RefCell<Option<Level>> = const { RefCell::new(None) };
+    }
+
+    pub(super) fn level() -> Option<Level> {
+        LEVEL.with(|level| *level.borrow())
+    }
+
+    /// the message first, then the other fields
+    #[derive(Default)]
+    struct Line {
+        message: String,
+        fields: String,
+    }
+    impl Visit for Line {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            match field.name() {
+                "message" => write!(self.message, "{:?}", value),
+                name => write!(self.fields, " {}={:?}", name, value),
+            }
+            .unwrap()
+        }
+        fn record_str(&mut self, field: &Field, value: &str) {
+            match field.name() {
+                "message" => self.message.push_str(value),
+                name => write!(self.fields, " {}={}", name, value).unwrap(),
+            }
+        }
+    }
+
+    struct Capture {
+        level: Level,
+        lines: Arc<Mutex<Vec<String>>>,
+        next_id: AtomicU64,
+    }
+    impl Subscriber for Capture {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            Level::from(*metadata.level()) <= self.level
+        }
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut line = Line::default();
+            span.record(&mut line);
+            let name = span.metadata().name();
+            self.lines
+                .lock()
+                .unwrap()
+                .push(format!("span: {}{}", name, line.fields));
+            span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed))
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line::default();
+            event.record(&mut line);
+            let level = Level::from(*event.metadata().level());
+            let line = format!("{}: {}{}", level, line.message, line.fields);
+            self.lines.lock().unwrap().push(line.trim_end().to_string());
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    /// runs `f` on this thread at `level` and returns what it logged
+    pub fn capture<R>(level: Level, f: impl FnOnce() -> R) -> (R, Vec<String>) {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let capture = Capture {
+            level,
+            lines: lines.clone(),
+            next_id: AtomicU64::new(1),
+        };
+        LEVEL.with(|current| *current.borrow_mut() = Some(level));
+        let result = tracing::subscriber::with_default(capture, f);
+        LEVEL.with(|current| *current.borrow_mut() = None);
+        let lines = std::mem::take(&mut *lines.lock().unwrap());
+        (result, lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_flags() {
+        assert_eq!(Level::from_flags(0, false), Level::Info);
+        assert_eq!(Level::from_flags(1, false), Level::Debug);
+        assert_eq!(Level::from_flags(3, false), Level::Trace);
+        assert_eq!(Level::from_flags(2, true), Level::Error);
+    }
+
+    #[test]
+    fn test_capture() {
+        let ((), lines) = capture::capture(Level::Debug, || {
+            debug!("chunk {} parsed", "IHDR");
+            trace!("too detailed");
+            warning!("careful");
+        });
+        assert_eq!(lines, ["debug: chunk IHDR parsed", "warning: careful"]);
+        assert!(enabled(Level::Info));
+        assert!(!enabled(Level::Debug));
+    }
+}
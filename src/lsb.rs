@@ -0,0 +1,285 @@
+//! `encode --method lsb`: hiding a payload in the least significant bits of the
+//! pixels instead of in a chunk, where a chunk listing doesn't show it. the pixels
+//! are decoded to 8 bit RGBA and read left to right and top to bottom, each channel
+//! carrying one bit: first [`MAGIC`], then the length of the payload as 4 big endian
+//! bytes, then the payload, most significant bit first. alpha only carries bits when
+//! asked to, and only builds with the image feature decode and encode pixels
+
+use crate::{
+    error::PngSecretError,
+    ihdr::{ColorType, Ihdr},
+    png::Png,
+};
+
+/// what the bits start with, so pixels without a payload aren't read as one
+const MAGIC: &[u8; 4] = b"PSlb";
+/// the bytes in front of the payload, the magic and its length
+pub const PREFIX_SIZE: usize = MAGIC.len() + 4;
+/// the bits of each channel encode writes, `capacity --bits` shows what more would hold
+pub const BITS: u8 = 1;
+/// chunks that describe the pixel format of the original, which RGBA pixels replace
+const FORMAT_CHUNKS: [&str; 5] = ["PLTE", "tRNS", "sBIT", "bKGD", "hIST"];
+
+/// the indexes of the bytes of `rgba` that carry a bit, in order
+#[cfg_attr(not(feature = "image"), allow(dead_code))]
+fn carriers(length: usize, alpha: bool) -> impl Iterator<Item = usize> {
+    (0..length).filter(move |index| alpha || index % 4 != 3)
+}
+
+/// the payload bytes `pixels` RGBA pixels hold with `bits` bits of each channel, after
+/// the magic and length
+pub fn capacity(pixels: usize, alpha: bool, bits: u8) -> usize {
+    let channels = if alpha { 4 } else { 3 };
+    (pixels * channels * usize::from(bits) / 8).saturating_sub(PREFIX_SIZE)
+}
+/// what writing the pixels back as 8 bit RGBA does to an image of `ihdr`'s format,
+/// none when it loses nothing
+pub fn conversion(ihdr: &Ihdr) -> Option<&'static str> {
+    match ihdr.color_type {
+        ColorType::Indexed => {
+            Some("the palette is expanded, each pixel is written as 8 bit RGBA and the file grows")
+        }
+        ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+            Some("the gray pixels are written as 8 bit RGBA, their changed low bits can tint them")
+        }
+        _ if ihdr.bit_depth == 16 => Some("the 16 bit samples are cut to 8 bits"),
+        _ => None,
+    }
+}
+
+/// writes the magic, the length of `payload` and `payload` into the low bits of the
+/// RGBA pixels in `rgba`, leaving every other bit as it was
+#[cfg_attr(not(feature = "image"), allow(dead_code))]
+pub fn embed(rgba: &mut [u8], payload: &[u8], alpha: bool) -> Result<(), PngSecretError> {
+    let capacity = capacity(rgba.len() / 4, alpha, BITS);
+    if payload.len() > capacity || u32::try_from(payload.len()).is_err() {
+        return Err(PngSecretError::LsbTooSmall {
+            capacity,
+            needed: payload.len(),
+        });
+    }
+    let length = (payload.len() as u32).to_be_bytes();
+    let bits = MAGIC
+        .iter()
+        .chain(&length)
+        .chain(payload)
+        .flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1));
+    for (index, bit) in carriers(rgba.len(), alpha).zip(bits) {
+        rgba[index] = rgba[index] & !1 | bit;
+    }
+    Ok(())
+}
+/// the payload `embed` wrote into `rgba`
+#[cfg_attr(not(feature = "image"), allow(dead_code))]
+pub fn extract(rgba: &[u8], alpha: bool) -> Result<Vec<u8>, PngSecretError> {
+    let mut bits = carriers(rgba.len(), alpha).map(|index| rgba[index] & 1);
+    let mut byte = || (0..8).fold(0, |byte, _| byte << 1 | bits.next().unwrap_or(0));
+    let mut word = || [byte(), byte(), byte(), byte()];
+    if word() != *MAGIC {
+        return Err(PngSecretError::LsbNotFound);
+    }
+    let length = u32::from_be_bytes(word()) as usize;
+    if length > capacity(rgba.len() / 4, alpha, BITS) {
+        return Err(PngSecretError::LsbNotFound);
+    }
+    Ok((0..length).map(|_| byte()).collect())
+}
+
+/// `png` with `payload` in the low bits of its pixels, written as 8 bit RGBA. its
+/// other chunks stay, except the ones about the pixel format it had
+#[cfg(feature = "image")]
+pub fn hide(png: &Png, payload: &[u8], alpha: bool) -> crate::Result<Png> {
+    use image::ImageEncoder;
+
+    let mut rgba = rgba(png)?;
+    embed(&mut rgba, payload, alpha)?;
+    let mut encoded = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut encoded)
+        .write_image(
+            &rgba,
+            rgba.width(),
+            rgba.height(),
+            image::ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| PngSecretError::Failed(format!("The pixels don't encode: {}", e)))?;
+    Ok(with_pixels(png, &Png::try_from(encoded.as_slice())?))
+}
+/// the payload `hide` wrote into the pixels of `png`
+#[cfg(feature = "image")]
+pub fn reveal(png: &Png, alpha: bool) -> crate::Result<Vec<u8>> {
+    extract(&rgba(png)?, alpha)
+}
+#[cfg(feature = "image")]
+fn rgba(png: &Png) -> crate::Result<image::RgbaImage> {
+    let image = image::load_from_memory_with_format(&png.as_bytes(), image::ImageFormat::Png)
+        .map_err(|e| PngSecretError::InvalidData(format!("The pixels don't decode: {}", e)))?;
+    Ok(image.to_rgba8())
+}
+#[cfg(not(feature = "image"))]
+pub fn hide(_png: &Png, _payload: &[u8], _alpha: bool) -> crate::Result<Png> {
+    Err(PngSecretError::Usage(UNAVAILABLE.into()))
+}
+#[cfg(not(feature = "image"))]
+pub fn reveal(_png: &Png, _alpha: bool) -> crate::Result<Vec<u8>> {
+    Err(PngSecretError::Usage(UNAVAILABLE.into()))
+}
+/// whether this build can decode and encode pixels
+pub const ENABLED: bool = cfg!(feature = "image");
+pub const UNAVAILABLE: &str = "--method lsb needs a pngsecret built with the image feature";
+
+/// the chunks of `png` with the IHDR and IDATs of `pixels` in place of its own
+#[cfg_attr(not(feature = "image"), allow(dead_code))]
+fn with_pixels(png: &Png, pixels: &Png) -> Png {
+    let replaced = |chunk_type: &str| {
+        chunk_type == "IHDR" || chunk_type == "IDAT" || FORMAT_CHUNKS.contains(&chunk_type)
+    };
+    let new = |chunk_type: &str| {
+        pixels
+            .chunks_by_type(chunk_type)
+            .into_iter()
+            .map(|(_, chunk)| chunk.clone())
+    };
+    let mut chunks = Vec::new();
+    let mut idat_done = false;
+    for chunk in png.chunks() {
+        let chunk_type = chunk.chunk_type().to_string();
+        match chunk_type.as_str() {
+            "IHDR" => chunks.extend(new("IHDR")),
+            "IDAT" if !idat_done => {
+                chunks.extend(new("IDAT"));
+                idat_done = true;
+            }
+            chunk_type if replaced(chunk_type) => {}
+            _ => chunks.push(chunk.clone()),
+        }
+    }
+    Png::from_chunks(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rgba = crypto::random_bytes::<1024>().unwrap();
+        let original = rgba;
+        let payload = crypto::random_bytes::<88>().unwrap();
+        assert_eq!(capacity(256, false, 1), 88);
+        embed(&mut rgba, &payload, false).unwrap();
+        assert_eq!(extract(&rgba, false).unwrap(), payload);
+        for (index, (before, after)) in original.iter().zip(&rgba).enumerate() {
+            assert!(before ^ after <= 1);
+            if index % 4 == 3 {
+                assert_eq!(before, after);
+            }
+        }
+
+        let mut rgba = original;
+        let payload = crypto::random_bytes::<120>().unwrap();
+        embed(&mut rgba, &payload, true).unwrap();
+        assert_eq!(extract(&rgba, true).unwrap(), payload);
+        assert!(original.iter().zip(&rgba).all(|(a, b)| a ^ b <= 1));
+
+        let mut rgba = original;
+        embed(&mut rgba, b"", false).unwrap();
+        assert_eq!(extract(&rgba, false).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_capacity() {
+        // 100 x 100 RGB: 30000 channels, 3750 bytes at 1 bit, less the 8 byte prefix
+        assert_eq!(capacity(100 * 100, false, 1), 3742);
+        assert_eq!(capacity(100 * 100, true, 1), 4992);
+        assert_eq!(capacity(100 * 100, false, 2), 7492);
+        assert_eq!(capacity(100 * 100, false, 3), 11242);
+        assert_eq!(capacity(100 * 100, false, 4), 14992);
+        assert_eq!(capacity(2, false, 1), 0);
+
+        let ihdr = |bit_depth, color_type| {
+            Ihdr::parse(&[0, 0, 0, 100, 0, 0, 0, 100, bit_depth, color_type, 0, 0, 0]).unwrap()
+        };
+        assert_eq!(conversion(&ihdr(8, 2)), None);
+        assert_eq!(conversion(&ihdr(8, 6)), None);
+        assert!(conversion(&ihdr(16, 6)).unwrap().contains("16 bit"));
+        assert!(conversion(&ihdr(4, 3)).unwrap().contains("palette"));
+        assert!(conversion(&ihdr(8, 0)).unwrap().contains("gray"));
+        assert!(conversion(&ihdr(8, 4)).unwrap().contains("gray"));
+    }
+
+    #[test]
+    fn test_too_small() {
+        let mut rgba = [0; 128];
+        let error = embed(&mut rgba, &[0; 7], false).unwrap_err();
+        assert!(matches!(
+            error,
+            PngSecretError::LsbTooSmall {
+                capacity: 4,
+                needed: 7
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "The pixels hold 4 bytes, the payload needs 7: 3 bytes short"
+        );
+        assert_eq!(rgba, [0; 128]);
+        assert!(embed(&mut rgba, &[0; 7], true).is_ok());
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert!(extract(&[0; 128], false).is_err());
+        assert!(extract(&crypto::random_bytes::<128>().unwrap(), true).is_err());
+        // the magic, then a length past what the pixels hold
+        let mut rgba = [0; 128];
+        embed(&mut rgba, &[0; 8], true).unwrap();
+        assert!(extract(&rgba, false).is_err());
+        assert_eq!(extract(&rgba, true).unwrap(), [0; 8]);
+        assert!(extract(&rgba[..64], true).is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_hide() {
+        use crate::chunk::Chunk;
+
+        let pixels: Vec<u8> = (0..16 * 16 * 3).map(|i| (i * 7) as u8).collect();
+        let mut encoded = Vec::new();
+        image::ImageEncoder::write_image(
+            image::codecs::png::PngEncoder::new(&mut encoded),
+            &pixels,
+            16,
+            16,
+            image::ExtendedColorType::Rgb8,
+        )
+        .unwrap();
+        let mut png = Png::try_from(encoded.as_slice()).unwrap();
+        png.insert_chunk_at(1, Chunk::new("bKGD".parse().unwrap(), &[0, 1, 0, 2, 0, 3]))
+            .unwrap();
+        png.append_chunk(Chunk::new("tEXt".parse().unwrap(), b"Comment\0kept"));
+        let payload = crypto::random_bytes::<80>().unwrap();
+
+        let hidden = hide(&png, &payload, false).unwrap();
+        let types: Vec<_> = hidden
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "tEXt", "IEND"]);
+        let decoded = image::load_from_memory(&hidden.as_bytes())
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(extract(&decoded, false).unwrap(), payload);
+        // the same picture, give or take the lowest bit of a channel
+        let before = image::load_from_memory(&encoded).unwrap().to_rgba8();
+        assert_eq!(before.dimensions(), decoded.dimensions());
+        assert!(before
+            .iter()
+            .zip(decoded.iter())
+            .all(|(before, after)| before ^ after <= 1));
+
+        let error = hide(&png, &[0; 89], false).unwrap_err();
+        assert!(matches!(error, PngSecretError::LsbTooSmall { .. }));
+    }
+}
@@ -1,21 +1,28 @@
-mod args;
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
-use args::{Cli, Commands};
-use clap::{Parser, ValueEnum};
-use commands::{decode, encode, print, remove};
-
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
+use clap::Parser;
+use pngsecret::{args::Cli, color, commands, error::PngSecretError, exit, log, output, progress};
 
 fn main() {
-    let cli = Cli::parse();
-    match &cli.commands {
-        Commands::Encode(args) => encode(args),
-        Commands::Decode(args) => decode(args),
-        Commands::Remove(args) => remove(args),
-        Commands::Print(args) => print(args),
+    let cli = Cli::try_parse().unwrap_or_else(|error| {
+        // clap exits with 2 for bad arguments, which is taken by unreadable pngs here
+        let _ = error.print();
+        std::process::exit(if error.use_stderr() { 1 } else { 0 });
+    });
+    log::init(log::Level::from_flags(cli.verbose, cli.quiet));
+    color::init(cli.color);
+    progress::init(cli.quiet, cli.progress_threshold);
+    output::init(cli.format);
+    commands::init(cli.max_memory);
+    match commands::run(&cli.commands) {
+        Ok(report) => output::emit(&report),
+        Err(e) => {
+            if !matches!(e, PngSecretError::Silent { .. }) {
+                if output::json() {
+                    eprintln!("{}", exit::error_json(&e));
+                } else {
+                    eprintln!("error: {e}");
+                }
+            }
+            std::process::exit(exit::code(&e));
+        }
     }
 }
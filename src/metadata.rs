@@ -0,0 +1,226 @@
+//! what the standard ancillary chunks say about an image: its gamma, pixel
+//! size, colour space, background and profile
+
+use crate::{error::PngSecretError, exif::Exif, json::Value, time::Time, Result};
+
+/// the chunk types `describe` knows, in the order the metadata command shows them
+pub const KNOWN: [&str; 8] = [
+    "gAMA", "cHRM", "sRGB", "iCCP", "pHYs", "bKGD", "tIME", "eXIf",
+];
+
+const RENDERING_INTENTS: [&str; 4] = [
+    "perceptual",
+    "relative colorimetric",
+    "saturation",
+    "absolute colorimetric",
+];
+
+/// a line saying what a known chunk holds and the same fields for json, none for
+/// other chunk types
+pub fn describe(chunk_type: &str, data: &[u8]) -> Option<Result<(String, Value)>> {
+    let described = match chunk_type {
+        "gAMA" => gamma(data),
+        "cHRM" => chromaticities(data),
+        "sRGB" => rendering_intent(data),
+        "iCCP" => profile(data),
+        "pHYs" => pixel_size(data),
+        "bKGD" => background(data),
+        "tIME" => Time::parse(data).map(|time| {
+            let text = time.to_string();
+            (text.clone(), Value::object([("time", text.into())]))
+        }),
+        "eXIf" => Exif::parse(data).map(|exif| {
+            (
+                format!("{}, {} entries in IFD0", exif.byte_order(), exif.entries),
+                Value::object([
+                    ("big_endian", exif.big_endian.into()),
+                    ("entries", u32::from(exif.entries).into()),
+                ]),
+            )
+        }),
+        _ => return None,
+    };
+    Some(described)
+}
+
+fn u32_at(data: &[u8], index: usize) -> u32 {
+    u32::from_be_bytes(data[index * 4..index * 4 + 4].try_into().unwrap())
+}
+fn sized<'a>(chunk_type: &str, data: &'a [u8], size: usize) -> Result<&'a [u8]> {
+    match data.len() == size {
+        true => Ok(data),
+        false => Err(PngSecretError::InvalidData(format!(
+            "The {} chunk has {} bytes, not {}",
+            chunk_type,
+            data.len(),
+            size
+        ))),
+    }
+}
+
+/// the gamma times 100000
+fn gamma(data: &[u8]) -> Result<(String, Value)> {
+    let gamma = f64::from(u32_at(sized("gAMA", data, 4)?, 0)) / 100000.0;
+    Ok((
+        format!("gamma {}", gamma),
+        Value::object([("gamma", Value::Number(gamma))]),
+    ))
+}
+/// the white point and the red, green and blue primaries, x and y times 100000
+fn chromaticities(data: &[u8]) -> Result<(String, Value)> {
+    let data = sized("cHRM", data, 32)?;
+    let names = ["white", "red", "green", "blue"];
+    let points: Vec<(f64, f64)> = (0..4)
+        .map(|point| {
+            let coordinate = |n: usize| f64::from(u32_at(data, point * 2 + n)) / 100000.0;
+            (coordinate(0), coordinate(1))
+        })
+        .collect();
+    let text = names
+        .iter()
+        .zip(&points)
+        .map(|(name, (x, y))| format!("{} {}, {}", name, x, y))
+        .collect::<Vec<_>>()
+        .join("  ");
+    let fields = names.iter().zip(&points).map(|(name, &(x, y))| {
+        let point = Value::object([("x", Value::Number(x)), ("y", Value::Number(y))]);
+        (name.to_string(), point)
+    });
+    Ok((text, Value::Object(fields.collect())))
+}
+fn rendering_intent(data: &[u8]) -> Result<(String, Value)> {
+    let intent = sized("sRGB", data, 1)?[0];
+    let name = RENDERING_INTENTS.get(usize::from(intent)).ok_or_else(|| {
+        PngSecretError::InvalidData(format!(
+            "The sRGB chunk has rendering intent {}, not 0 to 3",
+            intent
+        ))
+    })?;
+    Ok((
+        format!("rendering intent {}", name),
+        Value::object([("rendering_intent", (*name).into())]),
+    ))
+}
+/// the profile name, a NUL, the compression method and the deflated ICC profile
+fn profile(data: &[u8]) -> Result<(String, Value)> {
+    let separator = data.iter().position(|&byte| byte == 0).ok_or_else(|| {
+        PngSecretError::InvalidData("The iCCP chunk has no NUL after its profile name".into())
+    })?;
+    let name: String = data[..separator].iter().map(|&byte| byte as char).collect();
+    let compressed = data.len().saturating_sub(separator + 2);
+    Ok((
+        format!("profile '{}', {} bytes compressed", name, compressed),
+        Value::object([
+            ("profile", name.into()),
+            ("compressed_size", compressed.into()),
+        ]),
+    ))
+}
+/// pixels per unit on x and y, and the unit: 0 for none, just the aspect ratio, 1 for the metre
+fn pixel_size(data: &[u8]) -> Result<(String, Value)> {
+    let data = sized("pHYs", data, 9)?;
+    let (x, y) = (u32_at(data, 0), u32_at(data, 1));
+    let (unit, text) = match data[8] {
+        0 => (
+            "unknown",
+            format!("{} x {} pixels, aspect ratio only", x, y),
+        ),
+        // the dpi most tools show for it, rounded
+        1 => (
+            "metre",
+            format!(
+                "{} x {} pixels per metre ({:.0} x {:.0} dpi)",
+                x,
+                y,
+                f64::from(x) * 0.0254,
+                f64::from(y) * 0.0254
+            ),
+        ),
+        unit => {
+            return Err(PngSecretError::InvalidData(format!(
+                "The pHYs chunk has unit {}, not 0 or 1",
+                unit
+            )))
+        }
+    };
+    Ok((
+        text,
+        Value::object([("x", x.into()), ("y", y.into()), ("unit", unit.into())]),
+    ))
+}
+/// a palette index, a gray level or an rgb colour, whichever the color type has
+fn background(data: &[u8]) -> Result<(String, Value)> {
+    let samples = |count: usize| -> Vec<u32> {
+        (0..count)
+            .map(|i| u32::from(u16::from_be_bytes([data[i * 2], data[i * 2 + 1]])))
+            .collect()
+    };
+    match data.len() {
+        1 => Ok((
+            format!("palette index {}", data[0]),
+            Value::object([("palette_index", u32::from(data[0]).into())]),
+        )),
+        2 => {
+            let gray = samples(1)[0];
+            Ok((
+                format!("gray {}", gray),
+                Value::object([("gray", gray.into())]),
+            ))
+        }
+        6 => {
+            let rgb = samples(3);
+            Ok((
+                format!("rgb {}, {}, {}", rgb[0], rgb[1], rgb[2]),
+                Value::object([(
+                    "rgb",
+                    rgb.into_iter().map(Value::from).collect::<Vec<_>>().into(),
+                )]),
+            ))
+        }
+        length => Err(PngSecretError::InvalidData(format!(
+            "The bKGD chunk has {} bytes, not 1, 2 or 6",
+            length
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(chunk_type: &str, data: &[u8]) -> String {
+        describe(chunk_type, data).unwrap().unwrap().0
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(text("gAMA", &45455_u32.to_be_bytes()), "gamma 0.45455");
+        assert_eq!(text("sRGB", &[0]), "rendering intent perceptual");
+        assert_eq!(
+            text("pHYs", &[0, 0, 11, 19, 0, 0, 11, 19, 1]),
+            "2835 x 2835 pixels per metre (72 x 72 dpi)"
+        );
+        assert_eq!(text("bKGD", &[0, 255, 0, 255, 0, 255]), "rgb 255, 255, 255");
+        assert_eq!(text("bKGD", &[3]), "palette index 3");
+        let chrm: Vec<u8> = [31270_u32, 32900, 64000, 33000, 30000, 60000, 15000, 6000]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        assert_eq!(
+            text("cHRM", &chrm),
+            "white 0.3127, 0.329  red 0.64, 0.33  green 0.3, 0.6  blue 0.15, 0.06"
+        );
+        assert_eq!(
+            text("iCCP", b"sRGB built-in\0\0\x78\x9c\x01\x02"),
+            "profile 'sRGB built-in', 4 bytes compressed"
+        );
+        assert!(describe("ruSt", b"").is_none());
+
+        // a malformed chunk is an error, not a panic
+        assert!(describe("gAMA", &[1, 2]).unwrap().is_err());
+        assert!(describe("sRGB", &[4]).unwrap().is_err());
+        assert!(describe("pHYs", &[0; 9][..8]).unwrap().is_err());
+        assert!(describe("bKGD", &[0; 4]).unwrap().is_err());
+        assert!(describe("iCCP", b"no separator").unwrap().is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! read only memory maps of whole files with memmap2, so a big png can be parsed
+//! where the page cache has it instead of being copied into a Vec first. where a
+//! file can't be mapped `open` fails and callers read the file instead
+
+use std::{fs::File, io, path::Path};
+
+pub use memmap2::Mmap;
+
+/// the file at `path` mapped read only, its bytes are the file's until the map is
+/// dropped. another process shrinking the file meanwhile makes reading past its new
+/// end fail with SIGBUS, like with any map
+pub fn open(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // a map of nothing fails on some platforms and is of no use on the others
+    if file.metadata()?.len() == 0 {
+        return Err(io::Error::other("An empty file can't be mapped"));
+    }
+    // SAFETY: the map is only read, a file changed underneath it is the risk the
+    // doc comment above spells out
+    unsafe { Mmap::map(&file) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open() {
+        let path = std::env::temp_dir().join(format!("pngsecret-mmap-{}", std::process::id()));
+        let bytes: Vec<u8> = (0..10_000_u32).map(|i| i as u8).collect();
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(&open(&path).unwrap()[..], &bytes[..]);
+        std::fs::write(&path, b"").unwrap();
+        assert!(open(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
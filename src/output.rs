@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ValueEnum;
+
+use crate::json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Text meant to be read by people
+    Human,
+    /// One JSON document on stdout, errors as JSON objects on stderr
+    Json,
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn init(format: Format) {
+    JSON.store(format == Format::Json, Ordering::Relaxed);
+}
+/// with --format json the human text is left out and only the report is printed
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+/// prints the report of a command, only with --format json
+pub fn emit(report: &Value) {
+    if json() {
+        println!("{}", report);
+    }
+}
+/// the document a command reports, `command` comes first and then `fields`
+pub fn report<const N: usize>(command: &str, fields: [(&str, Value); N]) -> Value {
+    let mut report = Value::object(fields);
+    if let Value::Object(fields) = &mut report {
+        fields.insert(0, ("command".to_string(), command.into()));
+    }
+    report
+}
+/// `{"error": {"kind": ..., "message": ..., "path": ...}}`, path is null when unknown
+pub fn error(kind: &str, message: String, path: Option<String>) -> Value {
+    Value::object([(
+        "error",
+        Value::object([
+            ("kind", kind.into()),
+            ("message", message.into()),
+            ("path", path.map_or(Value::Null, Value::from)),
+        ]),
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let report = report("list", [("file", "a.png".into())]);
+        assert_eq!(report.to_string(), r#"{"command":"list","file":"a.png"}"#);
+    }
+
+    #[test]
+    fn test_error() {
+        let error = error("io", "No such file".to_string(), None);
+        assert_eq!(
+            error.to_string(),
+            r#"{"error":{"kind":"io","message":"No such file","path":null}}"#
+        );
+    }
+}
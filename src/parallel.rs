@@ -0,0 +1,88 @@
+//! running the same work over many files on a few threads, for the commands that
+//! take a directory. results come back in the order of the input, whichever
+//! thread finishes first
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+/// the threads to use without --threads, one per core
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// `work` applied to every item on up to `threads` threads. `done` is called on
+/// the calling thread as each item finishes, in no particular order, with the
+/// index of the item, to move a progress bar
+pub fn map<T: Sync, R: Send>(
+    items: &[T],
+    threads: usize,
+    work: impl Fn(&T) -> R + Sync,
+    mut done: impl FnMut(usize),
+) -> Vec<R> {
+    let threads = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let result = work(item);
+                done(index);
+                result
+            })
+            .collect();
+    }
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                // the receiver lives until every result is in
+                let _ = sender.send((index, work(item)));
+            });
+        }
+        drop(sender);
+        for (index, result) in receiver {
+            done(index);
+            results[index] = Some(result);
+        }
+    });
+    // a thread that panicked took the scope down with it, so every slot is filled
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let items: Vec<u64> = (0..200).collect();
+        let square = |n: &u64| {
+            // finishing out of order
+            thread::sleep(std::time::Duration::from_micros(200 - n));
+            n * n
+        };
+        let serial = map(&items, 1, square, |_| {});
+        for threads in [2, 8, 500] {
+            let mut finished = Vec::new();
+            let parallel = map(&items, threads, square, |index| finished.push(index));
+            assert_eq!(parallel, serial);
+            finished.sort();
+            assert_eq!(finished, (0..200).collect::<Vec<_>>());
+        }
+        assert!(map(&[] as &[u64], 4, square, |_| {}).is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use std::io::{self, IsTerminal};
+
+use crate::{error::PngSecretError, Result};
+
+/// read when no --password is given, so the password stays out of shell history
+pub const ENV_VAR: &str = "PNGSECRET_PASSWORD";
+
+/// the password from --password, $PNGSECRET_PASSWORD or the terminal, in that order.
+/// with `confirm` a typed password has to be typed twice
+pub fn resolve(given: Option<&str>, confirm: bool) -> Result<String> {
+    let env = std::env::var(ENV_VAR)
+        .ok()
+        .filter(|value| !value.is_empty());
+    from_sources(given, env, terminal(), confirm, prompt)
+}
+
+fn from_sources(
+    given: Option<&str>,
+    env: Option<String>,
+    terminal: bool,
+    confirm: bool,
+    mut read: impl FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    if let Some(password) = given {
+        return Ok(password.to_string());
+    }
+    if let Some(password) = env {
+        return Ok(password);
+    }
+    if !terminal {
+        return Err(PngSecretError::Usage(format!(
+            "A password is needed, pass --password or set {} when not on a terminal",
+            ENV_VAR
+        )));
+    }
+    let password = read("Password: ")?;
+    if password.is_empty() {
+        return Err(PngSecretError::Usage("Empty password".into()));
+    }
+    if confirm && read("Repeat password: ")? != password {
+        return Err(PngSecretError::Usage("The passwords don't match".into()));
+    }
+    Ok(password)
+}
+
+fn terminal() -> bool {
+    // a test must never wait for someone to type
+    if cfg!(test) {
+        return false;
+    }
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// reads a line from the terminal without echoing it
+fn prompt(prompt: &str) -> Result<String> {
+    Ok(rpassword::prompt_password(prompt)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(answers: &[&str]) -> impl FnMut(&str) -> Result<String> {
+        let mut answers: Vec<String> = answers.iter().rev().map(|a| a.to_string()).collect();
+        move |_| Ok(answers.pop().expect("asked too often"))
+    }
+
+    #[test]
+    fn test_given_first() {
+        let password = from_sources(Some("flag"), Some("env".into()), true, true, answers(&[]));
+        assert_eq!(password.unwrap(), "flag");
+    }
+
+    #[test]
+    fn test_env_var() {
+        let password = from_sources(None, Some("env".into()), false, true, answers(&[]));
+        assert_eq!(password.unwrap(), "env");
+    }
+
+    #[test]
+    fn test_not_a_terminal() {
+        let error = from_sources(None, None, false, false, answers(&[])).unwrap_err();
+        assert!(error.to_string().contains(ENV_VAR));
+    }
+
+    #[test]
+    fn test_prompt() {
+        let password = from_sources(None, None, true, false, answers(&["typed"]));
+        assert_eq!(password.unwrap(), "typed");
+        let password = from_sources(None, None, true, true, answers(&["typed", "typed"]));
+        assert_eq!(password.unwrap(), "typed");
+        let error = from_sources(None, None, true, true, answers(&["typed", "typo"])).unwrap_err();
+        assert_eq!(error.to_string(), "The passwords don't match");
+        assert!(from_sources(None, None, true, false, answers(&[""])).is_err());
+    }
+}
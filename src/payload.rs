@@ -0,0 +1,682 @@
+//! the header in front of a payload: `pSCv`, a version byte and a byte of
+//! flags, then the fields of the flags that have any. version 2 has these
+//! flags, then a big endian u32 part count when it is split, the checksum, the
+//! file metadata and the signature when there are any:
+//!
+//! - compressed: the message was deflated to a zlib stream
+//! - encrypted: the body is an encrypted payload, see the crypto module
+//! - multi-part: the payload was split into parts to fit the chunks
+//! - signed: the body is a signed payload with an integrity tag
+//! - checksum: a SHA-256 of the message, before it was compressed
+//! - metadata: the file the message was read from, a u16 length and the
+//!   name, then the u64 size, u32 unix mode and i64 modification time in
+//!   seconds since the epoch
+//! - ed25519: the public key of the signer and an Ed25519 signature of the
+//!   body, or of what was encrypted for an encrypted body
+//! - padded: what is encrypted is the big endian u32 length of the message,
+//!   the message and then padding, which hides how long the message is
+//!
+//! data without the header is a raw payload, as written before the header
+//! existed, and is left as it is. version 1 only knew the compressed flag.
+//!
+//! a payload too big for one chunk is split into parts, each behind a part
+//! header: `pSCp`, a version byte, the big endian u32 part index and part
+//! count, and an 8 byte id shared by the parts of one payload. parts are split
+//! off the finished payload, so they are as opaque as it is.
+
+use std::{fs, path::Path, time::SystemTime};
+
+use crate::{crypto, deflate, error::PngSecretError, json::Value, Result};
+
+const MAGIC: &[u8; 4] = b"pSCv";
+const VERSION: u8 = 2;
+const COMPRESSED: u8 = 1;
+const ENCRYPTED: u8 = 2;
+const MULTI_PART: u8 = 4;
+const SIGNED: u8 = 8;
+const CHECKSUM: u8 = 16;
+const METADATA: u8 = 32;
+const ED25519: u8 = 64;
+const PADDED: u8 = 128;
+const PART_MAGIC: &[u8; 4] = b"pSCp";
+const PART_VERSION: u8 = 1;
+pub const PART_HEADER_SIZE: usize = PART_MAGIC.len() + 1 + 4 + 4 + 8;
+/// no payload decompresses to more than this, whatever its header says
+pub const MAX_DECOMPRESSED: usize = 1 << 30;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub compressed: bool,
+    pub encrypted: bool,
+    pub signed: bool,
+    /// the message is behind its length and followed by padding
+    pub padded: bool,
+    /// how many parts the payload was split into
+    pub parts: Option<u32>,
+    /// the SHA-256 of the message
+    pub checksum: Option<[u8; 32]>,
+    /// the file the message was read from
+    pub metadata: Option<Metadata>,
+    pub signature: Option<Signature>,
+}
+impl Header {
+    /// a header of the current version
+    pub fn new() -> Header {
+        Header {
+            version: VERSION,
+            ..Header::default()
+        }
+    }
+    fn flags(&self) -> u8 {
+        [
+            (self.compressed, COMPRESSED),
+            (self.encrypted, ENCRYPTED),
+            (self.parts.is_some(), MULTI_PART),
+            (self.signed, SIGNED),
+            (self.checksum.is_some(), CHECKSUM),
+            (self.metadata.is_some(), METADATA),
+            (self.signature.is_some(), ED25519),
+            (self.padded, PADDED),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+    pub fn to_json(&self) -> Value {
+        Value::object([
+            ("version", (self.version as usize).into()),
+            ("compressed", self.compressed.into()),
+            ("encrypted", self.encrypted.into()),
+            ("signed", self.signed.into()),
+            ("padded", self.padded.into()),
+            (
+                "parts",
+                self.parts
+                    .map_or(Value::Null, |parts| (parts as usize).into()),
+            ),
+            (
+                "checksum",
+                self.checksum
+                    .map_or(Value::Null, |checksum| crypto::hex(&checksum).into()),
+            ),
+            (
+                "metadata",
+                self.metadata
+                    .as_ref()
+                    .map_or(Value::Null, Metadata::to_json),
+            ),
+            (
+                "signature",
+                self.signature
+                    .as_ref()
+                    .map_or(Value::Null, Signature::to_json),
+            ),
+        ])
+    }
+}
+
+/// an Ed25519 signature and the key that made it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub public_key: [u8; 32],
+    pub signature: [u8; 64],
+}
+impl Signature {
+    fn to_json(&self) -> Value {
+        Value::object([
+            ("public_key", crypto::hex(&self.public_key).into()),
+            ("signature", crypto::hex(&self.signature).into()),
+        ])
+    }
+}
+
+/// what a header keeps of the file a message was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// the file name, without any directory
+    pub name: String,
+    pub size: u64,
+    /// the unix permission bits
+    pub mode: u32,
+    /// the modification time in seconds since the epoch
+    pub mtime: i64,
+}
+impl Metadata {
+    /// the metadata of the file at `path`, a platform without unix modes gets 644
+    pub fn of(path: &Path) -> Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                PngSecretError::Usage(format!("{} has no file name to record", path.display()))
+            })?;
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777;
+        #[cfg(not(unix))]
+        let mode = 0o644;
+        let mtime = match metadata.modified()?.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        Ok(Metadata {
+            name: name.to_string(),
+            size: metadata.len(),
+            mode,
+            mtime,
+        })
+    }
+    /// the modification time as a point in time
+    pub fn modified(&self) -> SystemTime {
+        let seconds = std::time::Duration::from_secs(self.mtime.unsigned_abs());
+        match self.mtime >= 0 {
+            true => SystemTime::UNIX_EPOCH + seconds,
+            false => SystemTime::UNIX_EPOCH - seconds,
+        }
+    }
+    fn write(&self, payload: &mut Vec<u8>) {
+        payload.extend_from_slice(&(self.name.len() as u16).to_be_bytes());
+        payload.extend_from_slice(self.name.as_bytes());
+        payload.extend_from_slice(&self.size.to_be_bytes());
+        payload.extend_from_slice(&self.mode.to_be_bytes());
+        payload.extend_from_slice(&self.mtime.to_be_bytes());
+    }
+    fn read(fields: &mut &[u8]) -> Result<Metadata> {
+        let mut take = |size: usize| -> Result<&[u8]> {
+            if fields.len() < size {
+                return Err(PngSecretError::InvalidData(
+                    "The payload header is truncated".into(),
+                ));
+            }
+            let (field, rest) = fields.split_at(size);
+            *fields = rest;
+            Ok(field)
+        };
+        let length = u16::from_be_bytes(take(2)?.try_into().unwrap());
+        let name = String::from_utf8(take(length as usize)?.to_vec()).map_err(|_| {
+            PngSecretError::InvalidData("The file name in the payload header is not UTF-8".into())
+        })?;
+        // decode writes the file under this name, it must not lead anywhere else
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+            return Err(PngSecretError::InvalidData(format!(
+                "The payload header names the file {:?}, not a plain file name",
+                name
+            )));
+        }
+        Ok(Metadata {
+            name,
+            size: u64::from_be_bytes(take(8)?.try_into().unwrap()),
+            mode: u32::from_be_bytes(take(4)?.try_into().unwrap()),
+            mtime: i64::from_be_bytes(take(8)?.try_into().unwrap()),
+        })
+    }
+    fn to_json(&self) -> Value {
+        Value::object([
+            ("name", self.name.as_str().into()),
+            ("size", (self.size as usize).into()),
+            ("mode", format!("{:o}", self.mode).into()),
+            ("mtime", Value::Number(self.mtime as f64)),
+        ])
+    }
+}
+
+/// the checksum a header keeps of `message`
+pub fn checksum(message: &[u8]) -> [u8; 32] {
+    crypto::sha256(message)
+}
+/// whether `message` is what the header's checksum was taken of, none
+/// without a checksum
+pub fn verify_checksum(header: &Header, message: &[u8]) -> Option<bool> {
+    header
+        .checksum
+        .map(|checksum| checksum == crypto::sha256(message))
+}
+
+/// `body` behind `header`
+pub fn pack(header: &Header, body: &[u8]) -> Vec<u8> {
+    let mut payload = MAGIC.to_vec();
+    payload.extend([VERSION, header.flags()]);
+    if let Some(parts) = header.parts {
+        payload.extend_from_slice(&parts.to_be_bytes());
+    }
+    if let Some(checksum) = header.checksum {
+        payload.extend_from_slice(&checksum);
+    }
+    if let Some(metadata) = &header.metadata {
+        metadata.write(&mut payload);
+    }
+    if let Some(signature) = &header.signature {
+        payload.extend_from_slice(&signature.public_key);
+        payload.extend_from_slice(&signature.signature);
+    }
+    payload.extend_from_slice(body);
+    payload
+}
+/// `body` behind `header`, with the part count set when it won't fit in
+/// chunks of `max_size`
+pub fn pack_for(mut header: Header, body: &[u8], max_size: usize) -> Vec<u8> {
+    let whole = pack(&header, body);
+    if whole.len() <= max_size {
+        return whole;
+    }
+    let size = whole.len() + 4;
+    header.parts = Some(size.div_ceil(max_size) as u32);
+    pack(&header, body)
+}
+
+/// the header and the body of `data`, none for a raw payload
+pub fn unpack(data: &[u8]) -> Result<Option<(Header, &[u8])>> {
+    // versions are small, so a raw message that happens to start with the magic is left alone
+    if !data.starts_with(MAGIC) || data.get(MAGIC.len()).is_none_or(|&version| version >= 0x20) {
+        return Ok(None);
+    }
+    let version = data[MAGIC.len()];
+    let known = match version {
+        1 => COMPRESSED,
+        VERSION => {
+            COMPRESSED | ENCRYPTED | MULTI_PART | SIGNED | CHECKSUM | METADATA | ED25519 | PADDED
+        }
+        _ => {
+            return Err(PngSecretError::InvalidData(format!(
+                "Unsupported payload version {}, this pngsecret reads up to version {}",
+                version, VERSION
+            )))
+        }
+    };
+    let flags = *data
+        .get(MAGIC.len() + 1)
+        .ok_or_else(|| PngSecretError::InvalidData("The payload header is truncated".into()))?;
+    if flags & !known != 0 {
+        return Err(PngSecretError::InvalidData(format!(
+            "Unknown payload flags {:#04x}",
+            flags
+        )));
+    }
+    let mut body = &data[MAGIC.len() + 2..];
+    let mut header = Header {
+        version,
+        compressed: flags & COMPRESSED != 0,
+        encrypted: flags & ENCRYPTED != 0,
+        signed: flags & SIGNED != 0,
+        padded: flags & PADDED != 0,
+        parts: None,
+        checksum: None,
+        metadata: None,
+        signature: None,
+    };
+    if flags & MULTI_PART != 0 {
+        let parts = body
+            .get(..4)
+            .ok_or_else(|| PngSecretError::InvalidData("The payload header is truncated".into()))?;
+        header.parts = Some(u32::from_be_bytes(parts.try_into().unwrap()));
+        body = &body[4..];
+    }
+    if flags & CHECKSUM != 0 {
+        let checksum = body
+            .get(..32)
+            .ok_or_else(|| PngSecretError::InvalidData("The payload header is truncated".into()))?;
+        header.checksum = Some(checksum.try_into().unwrap());
+        body = &body[32..];
+    }
+    if flags & METADATA != 0 {
+        header.metadata = Some(Metadata::read(&mut body)?);
+    }
+    if flags & ED25519 != 0 {
+        let signature = body
+            .get(..96)
+            .ok_or_else(|| PngSecretError::InvalidData("The payload header is truncated".into()))?;
+        header.signature = Some(Signature {
+            public_key: signature[..32].try_into().unwrap(),
+            signature: signature[32..].try_into().unwrap(),
+        });
+        body = &body[96..];
+    }
+    Ok(Some((header, body)))
+}
+
+/// `message` behind its length and followed by `padding` zeros, which are as opaque
+/// as the message once encrypted
+pub fn pad(message: &[u8], padding: usize) -> Result<Vec<u8>> {
+    let length = u32::try_from(message.len())
+        .map_err(|_| PngSecretError::Usage("The message is too big to pad".into()))?;
+    let mut padded = length.to_be_bytes().to_vec();
+    padded.extend_from_slice(message);
+    padded.resize(padded.len() + padding, 0);
+    Ok(padded)
+}
+/// the message `pad` padded, without its length and padding
+pub fn unpad(padded: &[u8]) -> Result<Vec<u8>> {
+    let length = padded
+        .get(..4)
+        .ok_or_else(|| PngSecretError::InvalidData("The padded message is truncated".into()))?;
+    let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+    let message = padded.get(4..4 + length).ok_or_else(|| {
+        PngSecretError::InvalidData(format!(
+            "The padded message claims {} bytes, it only has {}",
+            length,
+            padded.len() - 4
+        ))
+    })?;
+    Ok(message.to_vec())
+}
+
+/// `body` inflated if it is compressed
+pub fn decompress(header: &Header, body: Vec<u8>) -> Result<Vec<u8>> {
+    match header.compressed {
+        true => deflate::decompress(&body, MAX_DECOMPRESSED),
+        false => Ok(body),
+    }
+}
+/// the message in a decrypted payload written before the header went in front
+/// of the encryption, compressed ones had a version 1 header inside it
+pub fn open_legacy(data: Vec<u8>) -> Result<Vec<u8>> {
+    match unpack(&data)? {
+        Some((header, body)) if header.version == 1 => decompress(&header, body.to_vec()),
+        _ => Ok(data),
+    }
+}
+
+/// one part of a split payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Part<'a> {
+    pub index: u32,
+    pub total: u32,
+    pub id: [u8; 8],
+    pub body: &'a [u8],
+}
+
+/// `payload` in parts of at most `max_size` bytes, not counting the part
+/// headers. a payload that fits is left whole, without a header
+pub fn split(payload: &[u8], max_size: usize, id: [u8; 8]) -> Result<Vec<Vec<u8>>> {
+    if payload.len() <= max_size {
+        return Ok(vec![payload.to_vec()]);
+    }
+    let total = u32::try_from(payload.len().div_ceil(max_size))
+        .map_err(|_| PngSecretError::Usage("The payload needs too many parts".into()))?;
+    let parts = payload
+        .chunks(max_size)
+        .enumerate()
+        .map(|(index, body)| {
+            let mut part = PART_MAGIC.to_vec();
+            part.push(PART_VERSION);
+            part.extend_from_slice(&(index as u32).to_be_bytes());
+            part.extend_from_slice(&total.to_be_bytes());
+            part.extend_from_slice(&id);
+            part.extend_from_slice(body);
+            part
+        })
+        .collect();
+    Ok(parts)
+}
+
+/// the part in `data`, none when it is a whole payload
+pub fn part(data: &[u8]) -> Result<Option<Part<'_>>> {
+    if !data.starts_with(PART_MAGIC)
+        || data
+            .get(PART_MAGIC.len())
+            .is_none_or(|&version| version >= 0x20)
+    {
+        return Ok(None);
+    }
+    let version = data[PART_MAGIC.len()];
+    if version != PART_VERSION {
+        return Err(PngSecretError::InvalidData(format!(
+            "Unsupported part version {}",
+            version
+        )));
+    }
+    if data.len() < PART_HEADER_SIZE {
+        return Err(PngSecretError::InvalidData(
+            "The part header is truncated".into(),
+        ));
+    }
+    let field = |at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+    let part = Part {
+        index: field(5),
+        total: field(9),
+        id: data[13..21].try_into().unwrap(),
+        body: &data[PART_HEADER_SIZE..],
+    };
+    if part.index >= part.total {
+        return Err(PngSecretError::InvalidData(format!(
+            "Part {} of only {}",
+            part.index, part.total
+        )));
+    }
+    Ok(Some(part))
+}
+
+/// the payload `parts` were split from, in any order. every part has to be
+/// there exactly once
+pub fn join(parts: &[Part]) -> Result<Vec<u8>> {
+    let total = parts
+        .first()
+        .ok_or_else(|| PngSecretError::InvalidData("No parts to join".into()))?
+        .total;
+    if parts.iter().any(|part| part.total != total) {
+        return Err(PngSecretError::InvalidData(
+            "The parts of the split payload disagree on how many there are".into(),
+        ));
+    }
+    // total comes from the untrusted header, so nothing is allocated for it
+    let mut ordered: Vec<&Part> = parts.iter().collect();
+    ordered.sort_by_key(|part| part.index);
+    if let Some(pair) = ordered
+        .windows(2)
+        .find(|pair| pair[0].index == pair[1].index)
+    {
+        return Err(PngSecretError::InvalidData(format!(
+            "Part {} of the split payload is there twice",
+            pair[0].index
+        )));
+    }
+    if let Some(part) = ordered.last().filter(|part| part.index >= total) {
+        return Err(PngSecretError::InvalidData(format!(
+            "Part {} of only {}",
+            part.index, total
+        )));
+    }
+    if ordered.len() as u64 != u64::from(total) {
+        let missing_count = u64::from(total) - ordered.len() as u64;
+        let mut missing: Vec<String> = (0..total)
+            .filter(|index| {
+                ordered
+                    .binary_search_by_key(index, |part| part.index)
+                    .is_err()
+            })
+            .take(MISSING_SHOWN)
+            .map(|index| index.to_string())
+            .collect();
+        if missing_count > MISSING_SHOWN as u64 {
+            missing.push(format!("and {} more", missing_count - MISSING_SHOWN as u64));
+        }
+        return Err(PngSecretError::InvalidData(format!(
+            "The split payload is missing part(s) {} of {}",
+            missing.join(", "),
+            total
+        )));
+    }
+    Ok(ordered.iter().flat_map(|part| part.body).copied().collect())
+}
+/// how many missing parts an error lists by number
+const MISSING_SHOWN: usize = 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        // every combination of the flags
+        for flags in 0..=255u8 {
+            let header = Header {
+                compressed: flags & COMPRESSED != 0,
+                encrypted: flags & ENCRYPTED != 0,
+                signed: flags & SIGNED != 0,
+                padded: flags & PADDED != 0,
+                parts: (flags & MULTI_PART != 0).then_some(7),
+                checksum: (flags & CHECKSUM != 0).then(|| checksum(b"body")),
+                metadata: (flags & METADATA != 0).then(|| Metadata {
+                    name: "notes.txt".to_string(),
+                    size: 4,
+                    mode: 0o640,
+                    mtime: -1,
+                }),
+                signature: (flags & ED25519 != 0).then_some(Signature {
+                    public_key: [1; 32],
+                    signature: [2; 64],
+                }),
+                ..Header::new()
+            };
+            let payload = pack(&header, b"body");
+            assert_eq!(&payload[..6], &[b'p', b'S', b'C', b'v', 2, flags]);
+            assert_eq!(unpack(&payload).unwrap(), Some((header, &b"body"[..])));
+        }
+    }
+
+    #[test]
+    fn test_legacy() {
+        assert_eq!(unpack(b"raw message").unwrap(), None);
+        assert_eq!(unpack(b"pSCvery raw").unwrap(), None);
+        let (header, body) = unpack(b"pSCv\x01\x01body").unwrap().unwrap();
+        assert_eq!(
+            (header.version, header.compressed, body),
+            (1, true, &b"body"[..])
+        );
+        assert!(unpack(b"pSCv\x01\x02body").is_err());
+        let compressed = [&b"pSCv\x01\x01"[..], &deflate::compress(b"old", 6)].concat();
+        assert_eq!(open_legacy(compressed).unwrap(), b"old");
+        assert_eq!(open_legacy(b"plain".to_vec()).unwrap(), b"plain");
+    }
+
+    #[test]
+    fn test_unknown() {
+        let error = unpack(b"pSCv\x09\x00body").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Unsupported payload version 9"));
+        // version 2 knows every flag there is room for, version 1 only one
+        assert!(unpack(b"pSCv\x01\x80body").is_err());
+        assert!(unpack(b"pSCv\x02").is_err());
+        assert!(unpack(b"pSCv\x02\x04ab").is_err());
+        assert!(unpack(b"pSCv\x02\x10short").is_err());
+    }
+
+    #[test]
+    fn test_pad() {
+        let padded = pad(b"secret", 10).unwrap();
+        assert_eq!(padded.len(), 4 + 6 + 10);
+        assert_eq!(&padded[..4], &[0, 0, 0, 6]);
+        assert_eq!(unpad(&padded).unwrap(), b"secret");
+        assert_eq!(unpad(&pad(b"", 0).unwrap()).unwrap(), b"");
+        assert!(unpad(&[0, 0]).is_err());
+        let error = unpad(&[0, 0, 0, 9, 1, 2]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The padded message claims 9 bytes, it only has 2"
+        );
+    }
+
+    #[test]
+    fn test_metadata_names() {
+        for name in ["", "..", "../secret", "dir/file", "c:\\file"] {
+            let header = Header {
+                metadata: Some(Metadata {
+                    name: name.to_string(),
+                    size: 0,
+                    mode: 0o644,
+                    mtime: 0,
+                }),
+                ..Header::new()
+            };
+            assert!(unpack(&pack(&header, b"")).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        let header = Header {
+            checksum: Some(checksum(b"message")),
+            ..Header::new()
+        };
+        assert_eq!(verify_checksum(&header, b"message"), Some(true));
+        assert_eq!(verify_checksum(&header, b"massage"), Some(false));
+        assert_eq!(verify_checksum(&Header::new(), b"message"), None);
+        assert_eq!(
+            header.to_json().get("checksum").unwrap().as_str().unwrap()[..8],
+            *"ab530a13"
+        );
+    }
+
+    #[test]
+    fn test_pack_for() {
+        let body = [0; 100];
+        assert_eq!(
+            unpack(&pack_for(Header::new(), &body, 106))
+                .unwrap()
+                .unwrap()
+                .0
+                .parts,
+            None
+        );
+        let packed = pack_for(Header::new(), &body, 50);
+        assert_eq!(unpack(&packed).unwrap().unwrap().0.parts, Some(3));
+        assert_eq!(split(&packed, 50, [0; 8]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_split() {
+        let payload: Vec<u8> = (0..250).collect();
+        assert_eq!(split(&payload, 250, [1; 8]).unwrap(), vec![payload.clone()]);
+        let parts = split(&payload, 100, [1; 8]).unwrap();
+        assert_eq!(parts.len(), 3);
+        for (index, part_data) in parts.iter().enumerate() {
+            let part = part(part_data).unwrap().unwrap();
+            assert_eq!((part.index, part.total, part.id), (index as u32, 3, [1; 8]));
+            assert_eq!(
+                part.body,
+                &payload[index * 100..(index * 100 + 100).min(250)]
+            );
+        }
+        assert_eq!(part(&payload).unwrap(), None);
+        let mut bad = parts[2].clone();
+        bad[8] = 3;
+        assert!(part(&bad).is_err());
+        assert!(part(&parts[0][..10]).is_err());
+    }
+
+    #[test]
+    fn test_join() {
+        let payload: Vec<u8> = (0..250).collect();
+        let data = split(&payload, 60, [2; 8]).unwrap();
+        let mut parts: Vec<Part> = data
+            .iter()
+            .map(|part_data| part(part_data).unwrap().unwrap())
+            .collect();
+        parts.reverse();
+        assert_eq!(join(&parts).unwrap(), payload);
+
+        let error = join(&[parts[0], parts[2], parts[4]]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The split payload is missing part(s) 1, 3 of 5"
+        );
+        let twice = [&parts[..], &parts[1..2]].concat();
+        assert!(join(&twice).unwrap_err().to_string().contains("twice"));
+
+        // a header claiming billions of parts is an error, not an allocation
+        let mut huge = data[0].clone();
+        huge[9..13].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = join(&[part(&huge).unwrap().unwrap()]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "The split payload is missing part(s) 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, \
+                 13, 14, 15, 16, and {} more of {}",
+                u32::MAX as u64 - 17,
+                u32::MAX
+            )
+        );
+    }
+}
@@ -1,114 +1,906 @@
+//! a whole png file as its chunks, and the checks `verify` runs on them
+
 use std::{
     fmt::Display,
-    io::{BufReader, Read},
+    io::{BufRead, BufReader, Read, Write},
+};
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::{Limit, Location, PngSecretError},
+    ihdr::{self, Ihdr},
+    log,
 };
 
-use crate::{chunk::Chunk, Error};
+/// a structural problem found by `Png::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// the IHDR isn't the first chunk
+    IhdrNotFirst {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// the png has no IDAT
+    MissingIdat,
+    /// the png has no IEND
+    MissingIend,
+    /// chunks follow the IEND
+    IendNotLast {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// a chunk before the IHDR, in a file that has one
+    ChunkBeforeIhdr {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// a second IHDR
+    DuplicateIhdr {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// a second IEND
+    DuplicateIend {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// an IDAT after other chunks that follow an IDAT, the spec wants them in a row
+    IdatNotContiguous {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// a PLTE after the image data
+    PlteAfterIdat {
+        /// the index of the chunk
+        index: usize,
+    },
+    /// an ancillary chunk where the spec doesn't allow it, `rule` says where it goes
+    Misplaced {
+        /// the index of the chunk
+        index: usize,
+        /// the type of the misplaced chunk
+        chunk_type: String,
+        /// where the spec wants it
+        rule: String,
+    },
+    /// the stored crc of a chunk doesn't match its type and data
+    CrcMismatch {
+        /// the index of the chunk
+        index: usize,
+        /// the crc in the file
+        stored: u32,
+        /// the crc of the chunk's type and data
+        computed: u32,
+    },
+}
+impl ValidationIssue {
+    /// index of the chunk the issue is about, if it is about a single chunk
+    pub fn chunk_index(&self) -> Option<usize> {
+        match self {
+            ValidationIssue::IhdrNotFirst { index }
+            | ValidationIssue::IendNotLast { index }
+            | ValidationIssue::ChunkBeforeIhdr { index }
+            | ValidationIssue::DuplicateIhdr { index }
+            | ValidationIssue::DuplicateIend { index }
+            | ValidationIssue::IdatNotContiguous { index }
+            | ValidationIssue::PlteAfterIdat { index }
+            | ValidationIssue::Misplaced { index, .. }
+            | ValidationIssue::CrcMismatch { index, .. } => Some(*index),
+            ValidationIssue::MissingIdat | ValidationIssue::MissingIend => None,
+        }
+    }
+}
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::IhdrNotFirst { .. } => write!(f, "first chunk is not IHDR"),
+            ValidationIssue::MissingIdat => write!(f, "no IDAT chunk"),
+            ValidationIssue::MissingIend => write!(f, "no IEND chunk"),
+            ValidationIssue::IendNotLast { .. } => write!(f, "IEND is not the last chunk"),
+            ValidationIssue::ChunkBeforeIhdr { .. } => write!(f, "chunk before IHDR"),
+            ValidationIssue::DuplicateIhdr { .. } => write!(f, "second IHDR chunk"),
+            ValidationIssue::DuplicateIend { .. } => write!(f, "second IEND chunk"),
+            ValidationIssue::IdatNotContiguous { .. } => {
+                write!(f, "IDAT chunk apart from the IDAT chunks before it")
+            }
+            ValidationIssue::PlteAfterIdat { .. } => write!(f, "PLTE chunk after the image data"),
+            ValidationIssue::Misplaced {
+                chunk_type, rule, ..
+            } => write!(f, "{} should come {}", chunk_type, rule),
+            ValidationIssue::CrcMismatch {
+                stored, computed, ..
+            } => write!(
+                f,
+                "crc mismatch, stored {:#010x}, computed {:#010x}",
+                stored, computed
+            ),
+        }
+    }
+}
 
+/// what `Png::parse_salvage` left out of a damaged file
+#[derive(Debug)]
+pub enum SalvageIssue {
+    /// the chunk at `offset` is cut short or broken, it and the `remaining` bytes from
+    /// there to the end were dropped
+    Dropped {
+        /// where the dropped bytes start
+        offset: usize,
+        /// how many bytes were dropped
+        remaining: usize,
+        /// what is wrong with the chunk there
+        error: PngSecretError,
+    },
+    /// the chunks that were kept end without an IEND
+    MissingIend,
+}
+impl Display for SalvageIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SalvageIssue::Dropped {
+                offset,
+                remaining,
+                error,
+            } => write!(
+                f,
+                "dropped {} bytes from offset {:#x}: {}",
+                remaining, offset, error
+            ),
+            SalvageIssue::MissingIend => write!(f, "no IEND chunk, the file was cut short"),
+        }
+    }
+}
+
+/// where the spec puts ancillary chunks: the chunks each type has to come before
+/// and the ones it has to come after, when the file has them. the others go anywhere
+const ORDERING: [(&str, &[&str], &[&str]); 13] = [
+    ("cHRM", &["PLTE", "IDAT"], &[]),
+    ("gAMA", &["PLTE", "IDAT"], &[]),
+    ("iCCP", &["PLTE", "IDAT"], &[]),
+    ("sBIT", &["PLTE", "IDAT"], &[]),
+    ("sRGB", &["PLTE", "IDAT"], &[]),
+    ("cICP", &["PLTE", "IDAT"], &[]),
+    ("bKGD", &["IDAT"], &["PLTE"]),
+    ("hIST", &["IDAT"], &["PLTE"]),
+    ("tRNS", &["IDAT"], &["PLTE"]),
+    ("pHYs", &["IDAT"], &[]),
+    ("sPLT", &["IDAT"], &[]),
+    ("oFFs", &["IDAT"], &[]),
+    ("acTL", &["IDAT"], &[]),
+];
+
+/// how much of an untrusted file the parser takes before it gives up with
+/// `PngSecretError::LimitExceeded`. sizes are in bytes of chunk data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// chunks in the file
+    pub max_chunks: usize,
+    /// bytes of data in one chunk
+    pub max_chunk_size: usize,
+    /// bytes of data in all chunks together
+    pub max_total: usize,
+}
+impl Limits {
+    /// what `try_from` and `from_reader` use, only the input bounds them
+    pub const NONE: Limits = Limits {
+        max_chunks: usize::MAX,
+        max_chunk_size: usize::MAX,
+        max_total: usize::MAX,
+    };
+    /// the defaults with chunk data capped at `bytes`
+    pub fn memory(bytes: usize) -> Limits {
+        Limits {
+            max_chunk_size: bytes,
+            max_total: bytes,
+            ..Limits::default()
+        }
+    }
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_chunks: 10_000,
+            max_chunk_size: Chunk::MAX_LENGTH,
+            max_total: usize::MAX,
+        }
+    }
+}
+
+/// sizes in bytes, `data_size` only counts chunk data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PngStats {
+    /// the whole file, signature included
+    pub file_size: usize,
+    /// the number of chunks
+    pub chunk_count: usize,
+    /// the data of all chunks together
+    pub data_size: usize,
+}
+
+/// a png as its signature and chunks, in file order, and the bytes after IEND that
+/// aren't a chunk
 #[derive(Debug)]
 pub struct Png {
     header: [u8; 8],
     chunks: Vec<Chunk>,
+    trailing_data: Vec<u8>,
 }
 impl Png {
+    /// the 8 bytes every png starts with
     pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
 
+    /// a png of `chunks` after the signature
     pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
         Png {
             header: Png::STANDARD_HEADER,
             chunks,
+            trailing_data: Vec::new(),
         }
     }
+    /// the chunks in file order
     pub fn chunks(&self) -> &Vec<Chunk> {
         &self.chunks
     }
+    /// what follows IEND without being a chunk, garbage or hidden data. it is written
+    /// back with the chunks
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing_data
+    }
+    /// removes the trailing data and returns it
+    pub fn strip_trailing_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.trailing_data)
+    }
+    /// adds `chunk` right before IEND, decoders ignore what comes after it and
+    /// optimizers drop it. a file without IEND gets it at the end
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk);
+        let index = self.iend_index().unwrap_or(self.chunks.len());
+        log::debug!(
+            chunk_type = %chunk.chunk_type(),
+            index,
+            length = chunk.length(),
+            "appended chunk"
+        );
+        self.chunks.insert(index, chunk);
+    }
+    /// inserts `chunk` so it ends up at `index`, shifting the chunks after it
+    pub fn insert_chunk_at(&mut self, index: usize, chunk: Chunk) -> Result<(), PngSecretError> {
+        if index > self.chunks.len() {
+            return Err(PngSecretError::IndexOutOfRange {
+                index,
+                len: self.chunks.len(),
+            });
+        }
+        log::debug!(
+            chunk_type = %chunk.chunk_type(),
+            index,
+            length = chunk.length(),
+            "inserted chunk"
+        );
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+    /// puts `chunk` in the place of the one at `index` and returns that one
+    pub fn replace_chunk(&mut self, index: usize, chunk: Chunk) -> Result<Chunk, PngSecretError> {
+        let len = self.chunks.len();
+        let Some(place) = self.chunks.get_mut(index) else {
+            return Err(PngSecretError::IndexOutOfRange { index, len });
+        };
+        log::debug!(
+            chunk_type = %chunk.chunk_type(),
+            index,
+            length = chunk.length(),
+            "replaced chunk"
+        );
+        Ok(std::mem::replace(place, chunk))
+    }
+    fn position_of(&self, chunk_type: &str) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type().to_string() == chunk_type)
+    }
+    /// the index of the first IHDR
+    pub fn ihdr_index(&self) -> Option<usize> {
+        self.position_of("IHDR")
+    }
+    /// the fields of the first IHDR, parsed each call
+    pub fn ihdr(&self) -> Result<Ihdr, PngSecretError> {
+        let chunk = self
+            .chunk_by_type(ihdr::IHDR)
+            .ok_or_else(|| PngSecretError::chunk_not_found(ihdr::IHDR))?;
+        Ok(Ihdr::parse(chunk.data())?)
+    }
+    /// the width and height of the image in pixels
+    pub fn dimensions(&self) -> Result<(u32, u32), PngSecretError> {
+        self.ihdr().map(|ihdr| (ihdr.width, ihdr.height))
+    }
+    /// the index of the first IDAT
+    pub fn first_idat_index(&self) -> Option<usize> {
+        self.position_of("IDAT")
     }
+    /// the index of the first IEND
+    pub fn iend_index(&self) -> Option<usize> {
+        self.position_of("IEND")
+    }
+    /// whether the file is an APNG, which has an acTL chunk
+    pub fn is_animated(&self) -> bool {
+        self.position_of("acTL").is_some()
+    }
+    /// the frames of an APNG, from each fcTL chunk to the last IDAT or fdAT chunk
+    /// before the next fcTL, what a chunk mustn't be put inside
+    pub fn frames(&self) -> Vec<std::ops::RangeInclusive<usize>> {
+        let types: Vec<String> = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        let starts: Vec<usize> = (0..types.len()).filter(|&i| types[i] == "fcTL").collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(n, &start)| {
+                let next = starts.get(n + 1).copied().unwrap_or(types.len());
+                let end = (start..next)
+                    .rev()
+                    .find(|&i| types[i] == "IDAT" || types[i] == "fdAT")
+                    .unwrap_or(start);
+                start..=end
+            })
+            .collect()
+    }
+    /// the frame an insertion at `index` would land inside, between its fcTL and
+    /// its last data chunk
+    pub fn frame_at(&self, index: usize) -> Option<std::ops::RangeInclusive<usize>> {
+        self.frames()
+            .into_iter()
+            .find(|frame| *frame.start() < index && index <= *frame.end())
+    }
+    /// the first chunk of `chunk_type`
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         self.chunks
             .iter()
             .find(|&chunk| chunk.chunk_type().to_string() == chunk_type)
     }
-    pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk, Error> {
+    /// every chunk of `chunk_type` with its index
+    pub fn chunks_by_type(&self, chunk_type: &str) -> Vec<(usize, &Chunk)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.chunk_type().to_string() == chunk_type)
+            .collect()
+    }
+    /// byte offset of the chunk at `index` in the serialized file
+    pub fn chunk_offset(&self, index: usize) -> usize {
+        self.header.len()
+            + self.chunks[..index]
+                .iter()
+                .map(|chunk| chunk.length() as usize + Chunk::OVERHEAD)
+                .sum::<usize>()
+    }
+    /// how big the file and its chunks are
+    pub fn stats(&self) -> PngStats {
+        PngStats {
+            file_size: self.chunk_offset(self.chunks.len()) + self.trailing_data.len(),
+            chunk_count: self.chunks.len(),
+            data_size: self
+                .chunks
+                .iter()
+                .map(|chunk| chunk.length() as usize)
+                .sum(),
+        }
+    }
+    /// removes the first chunk of `chunk_type` and returns it
+    pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk, PngSecretError> {
         let chunk = self
             .chunks
             .iter()
             .position(|chunk| chunk.chunk_type().to_string() == *chunk_type);
-        if let None = chunk {
-            return Err(Error::from("Chunk not found"));
+        if chunk.is_none() {
+            return Err(PngSecretError::chunk_not_found(chunk_type));
         };
-        let chunk = self.chunks.remove(chunk.unwrap());
-        Ok(chunk)
+        Ok(self.remove_at(chunk.unwrap()))
     }
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let chunk_bytes: Vec<u8> = self
-            .chunks
-            .clone()
-            .iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
-
-        self.header
-            .iter()
-            .chain(chunk_bytes.iter())
-            .cloned()
+    /// removes the chunk of `chunk_type` after `n` others of it, counting from 0
+    pub fn remove_nth_chunk_by_type(&mut self, chunk_type: &str, n: usize) -> Option<Chunk> {
+        let (index, _) = *self.chunks_by_type(chunk_type).get(n)?;
+        Some(self.remove_at(index))
+    }
+    /// removes every chunk of `chunk_type` and returns them in file order
+    pub fn remove_all_chunks_by_type(&mut self, chunk_type: &str) -> Vec<Chunk> {
+        let (removed, kept): (Vec<Chunk>, Vec<Chunk>) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| chunk.chunk_type().to_string() == chunk_type);
+        self.chunks = kept;
+        log::debug!(chunk_type, count = removed.len(), "removed chunks");
+        removed
+    }
+    /// removes the chunk at `index` and returns it
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk, PngSecretError> {
+        if index >= self.chunks.len() {
+            return Err(PngSecretError::IndexOutOfRange {
+                index,
+                len: self.chunks.len(),
+            });
+        }
+        Ok(self.remove_at(index))
+    }
+    fn remove_at(&mut self, index: usize) -> Chunk {
+        let chunk = self.chunks.remove(index);
+        log::debug!(
+            chunk_type = %chunk.chunk_type(),
+            index,
+            length = chunk.length(),
+            "removed chunk"
+        );
+        chunk
+    }
+    /// removes every ancillary chunk whose type is not in `keep`, returns the removed chunks
+    pub fn strip_ancillary(&mut self, keep: &[ChunkType]) -> Vec<Chunk> {
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| {
+                !chunk.chunk_type().is_critical() && !keep.contains(chunk.chunk_type())
+            });
+        self.chunks = kept;
+        removed
+    }
+    /// fixes every stored crc, returns the indices of the chunks that changed
+    pub fn recompute_crcs(&mut self) -> Vec<usize> {
+        self.chunks
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, chunk)| chunk.recompute_crc().then_some(index))
             .collect()
     }
+    /// checks chunk ordering, required chunks and every crc
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let is_type =
+            |chunk: &Chunk, chunk_type: &str| chunk.chunk_type().to_string() == chunk_type;
+        match self.ihdr_index() {
+            Some(0) => {}
+            Some(ihdr) => {
+                issues.extend((0..ihdr).map(|index| ValidationIssue::ChunkBeforeIhdr { index }))
+            }
+            None => issues.push(ValidationIssue::IhdrNotFirst { index: 0 }),
+        }
+        if !self.chunks.iter().any(|chunk| is_type(chunk, "IDAT")) {
+            issues.push(ValidationIssue::MissingIdat);
+        }
+        match self.chunks.iter().position(|chunk| is_type(chunk, "IEND")) {
+            None => issues.push(ValidationIssue::MissingIend),
+            Some(index) if index != self.chunks.len() - 1 => {
+                issues.push(ValidationIssue::IendNotLast { index })
+            }
+            Some(_) => {}
+        }
+        let (mut ihdr_seen, mut iend_seen) = (false, false);
+        // where the first run of IDAT chunks started and whether it ended
+        let (mut idat_seen, mut idat_ended) = (false, false);
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            match chunk.chunk_type().to_string().as_str() {
+                "IHDR" if ihdr_seen => issues.push(ValidationIssue::DuplicateIhdr { index }),
+                "IHDR" => ihdr_seen = true,
+                "IEND" if iend_seen => issues.push(ValidationIssue::DuplicateIend { index }),
+                "IEND" => iend_seen = true,
+                "PLTE" if idat_seen => issues.push(ValidationIssue::PlteAfterIdat { index }),
+                "IDAT" if idat_ended => {
+                    issues.push(ValidationIssue::IdatNotContiguous { index });
+                    // one issue for each run apart, not for every chunk in it
+                    idat_ended = false;
+                }
+                "IDAT" => idat_seen = true,
+                _ => {}
+            }
+            if idat_seen && !is_type(chunk, "IDAT") {
+                idat_ended = true;
+            }
+        }
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if !chunk.is_crc_valid() {
+                issues.push(ValidationIssue::CrcMismatch {
+                    index,
+                    stored: chunk.crc(),
+                    computed: u32::from_be_bytes(Chunk::calculate_crc(
+                        &chunk.chunk_type().bytes(),
+                        chunk.data(),
+                    )),
+                });
+            }
+        }
+        issues
+    }
+    /// the chunks that break the ordering rules of the spec, which `validate` doesn't check
+    pub fn validate_ordering(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let chunk_type = chunk.chunk_type().to_string();
+            let Some((_, before, after)) = ORDERING.iter().find(|(t, ..)| *t == chunk_type) else {
+                continue;
+            };
+            let misplaced = |rule: String| ValidationIssue::Misplaced {
+                index,
+                chunk_type: chunk_type.clone(),
+                rule,
+            };
+            // against the first PLTE and IDAT, validate flags any others
+            if let Some(other) = before
+                .iter()
+                .find(|other| self.position_of(other).is_some_and(|at| at < index))
+            {
+                issues.push(misplaced(format!("before {}", other)));
+            }
+            if let Some(other) = after
+                .iter()
+                .find(|other| self.position_of(other).is_some_and(|at| at > index))
+            {
+                issues.push(misplaced(format!("after {}", other)));
+            }
+        }
+        issues
+    }
+    /// the png as it is in a file
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let size = self.header.len()
+            + self
+                .chunks
+                .iter()
+                .map(|chunk| Chunk::OVERHEAD + chunk.data().len())
+                .sum::<usize>()
+            + self.trailing_data.len();
+        let mut bytes = Vec::with_capacity(size);
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec doesn't fail");
+        bytes
+    }
 }
 impl TryFrom<&[u8]> for Png {
-    type Error = Error;
+    type Error = PngSecretError;
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let mut reader = BufReader::new(value);
+        Png::read(value, true)
+    }
+}
+impl Png {
+    /// reads a whole file from `reader`, like stdin
+    pub fn from_reader(reader: impl Read) -> Result<Self, PngSecretError> {
+        Png::from_reader_with_limits(reader, Limits::NONE)
+    }
+    /// like `from_reader`, but an error as soon as the file goes past `limits`
+    pub fn from_reader_with_limits(
+        reader: impl Read,
+        limits: Limits,
+    ) -> Result<Self, PngSecretError> {
+        Png::parse(BufReader::new(reader), true, limits)
+    }
+    /// writes the png as it is in a file to `writer`
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.header)?;
+        for chunk in &self.chunks {
+            writer.write_all(&chunk.as_bytes())?;
+        }
+        writer.write_all(&self.trailing_data)?;
+        writer.flush()
+    }
+    /// like `from_reader_with_limits` but keeps chunks whose stored crc doesn't match
+    /// their contents
+    pub fn from_reader_lenient(reader: impl Read, limits: Limits) -> Result<Self, PngSecretError> {
+        Png::parse(BufReader::new(reader), false, limits)
+    }
+    /// every whole and valid chunk up to the first damaged one, like the end of a file
+    /// that was cut short, and what was dropped. only a file that doesn't start with the
+    /// png signature is an error
+    pub fn parse_salvage(bytes: &[u8]) -> Result<(Self, Vec<SalvageIssue>), PngSecretError> {
+        if !bytes.starts_with(&Png::STANDARD_HEADER) {
+            return Err(PngSecretError::NotAPng { path: None });
+        }
+        let mut chunks: Vec<Chunk> = Vec::default();
+        let mut issues = Vec::new();
+        let mut offset = Png::STANDARD_HEADER.len();
+        let mut after_iend = false;
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+            match Png::chunk_at(rest, true) {
+                Ok(chunk) => {
+                    offset += chunk.length() as usize + Chunk::OVERHEAD;
+                    after_iend |= chunk.chunk_type().to_string() == "IEND";
+                    chunks.push(chunk);
+                }
+                // past IEND it is trailing data, nothing was lost
+                Err(_) if after_iend => break,
+                Err(error) => {
+                    log::debug!(offset, remaining = rest.len(), "salvage stopped");
+                    issues.push(SalvageIssue::Dropped {
+                        offset,
+                        remaining: rest.len(),
+                        error: error.at(chunks.len(), offset),
+                    });
+                    offset = bytes.len();
+                }
+            }
+        }
+        let mut png = Png::from_chunks(chunks);
+        if png.iend_index().is_none() {
+            issues.push(SalvageIssue::MissingIend);
+        }
+        png.trailing_data = bytes[offset..].to_vec();
+        Ok((png, issues))
+    }
+    /// the chunk `rest` starts with, as far as its length field says it goes
+    fn chunk_at(rest: &[u8], check_crc: bool) -> Result<Chunk, PngSecretError> {
+        let end = rest
+            .get(..4)
+            .map(|length| u32::from_be_bytes(length.try_into().unwrap()) as usize)
+            .and_then(|length| length.checked_add(Chunk::OVERHEAD))
+            .filter(|&end| end <= rest.len());
+        match (end, check_crc) {
+            (Some(end), true) => Chunk::try_from(&rest[..end]),
+            (Some(end), false) => Chunk::try_from_lenient(&rest[..end]),
+            (None, _) => Err(PngSecretError::Truncated {
+                at: Location {
+                    chunk_type: rest
+                        .get(4..8)
+                        .map(|bytes| String::from_utf8_lossy(bytes).into_owned()),
+                    ..Location::default()
+                },
+            }),
+        }
+    }
+    /// like `try_from` but keeps chunks whose stored crc doesn't match their contents
+    pub fn try_from_lenient(value: &[u8]) -> Result<Self, PngSecretError> {
+        Png::read(value, false)
+    }
+    fn read(value: &[u8], check_crc: bool) -> Result<Self, PngSecretError> {
+        Png::parse(value, check_crc, Limits::NONE)
+    }
+    fn parse(
+        mut reader: impl BufRead,
+        check_crc: bool,
+        limits: Limits,
+    ) -> Result<Self, PngSecretError> {
         let mut buffer: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 0];
 
-        // reading the header
-        reader.read_exact(&mut buffer)?;
+        // reading the header, shorter input can't be a png either
+        if reader.read_exact(&mut buffer).is_err() {
+            return Err(PngSecretError::NotAPng { path: None });
+        }
         let header = buffer;
         if header != Png::STANDARD_HEADER {
-            return Err(Error::from("Invalid header"));
+            return Err(PngSecretError::NotAPng { path: None });
         }
+        log::debug!("png signature ok");
 
         let mut chunks: Vec<Chunk> = Vec::default();
-        let mut data_length: u32;
-        // chunk bytes should be data bytes + 4 + 4 + 4
-        let chunk_length: u32 = 4 + 4;
-        // try to read chunks
-        let try_chunks: Result<(), Error> = loop {
-            // reading data length
-            let mut buffer: [u8; 4] = [0, 0, 0, 0];
-            if let Err(_) = reader.read_exact(&mut buffer) {
-                break Ok(());
+        let mut offset = header.len();
+        let mut total = 0_usize;
+        let mut trailing_data = Vec::new();
+        // try to read chunks until the input ends on a chunk boundary
+        while !reader.fill_buf()?.is_empty() {
+            let location = |chunk_type: Option<&[u8]>| Location {
+                index: Some(chunks.len()),
+                chunk_type: chunk_type.map(|bytes| String::from_utf8_lossy(bytes).into_owned()),
+                offset: Some(offset),
+                path: None,
+            };
+            // past IEND the chunks run up to the first bytes that aren't one, those
+            // and the rest are trailing data. they count towards the total
+            if chunks
+                .last()
+                .is_some_and(|chunk| chunk.chunk_type().to_string() == "IEND")
+            {
+                let left = limits.max_total.saturating_sub(total);
+                let mut rest = Vec::new();
+                (&mut reader)
+                    .take((left as u64).saturating_add(1))
+                    .read_to_end(&mut rest)?;
+                if rest.len() > left {
+                    return Err(PngSecretError::LimitExceeded {
+                        limit: Limit::Total,
+                        max: limits.max_total,
+                        at: location(None),
+                    });
+                }
+                let mut at = 0;
+                while let Ok(chunk) = Png::chunk_at(&rest[at..], check_crc) {
+                    // the same limits hold for the chunks past IEND
+                    let exceeded = |limit, max| PngSecretError::LimitExceeded {
+                        limit,
+                        max,
+                        at: Location {
+                            index: Some(chunks.len()),
+                            chunk_type: Some(chunk.chunk_type().to_string()),
+                            offset: Some(offset + at),
+                            path: None,
+                        },
+                    };
+                    if chunks.len() == limits.max_chunks {
+                        return Err(exceeded(Limit::Chunks, limits.max_chunks));
+                    }
+                    if chunk.length() as usize > limits.max_chunk_size {
+                        return Err(exceeded(Limit::ChunkSize, limits.max_chunk_size));
+                    }
+                    at += chunk.length() as usize + Chunk::OVERHEAD;
+                    chunks.push(chunk);
+                }
+                trailing_data = rest.split_off(at);
+                if !trailing_data.is_empty() {
+                    log::debug!(
+                        length = trailing_data.len(),
+                        offset = offset + at,
+                        "trailing data"
+                    );
+                }
+                break;
             }
-            data_length = u32::from_be_bytes(buffer);
+            let exceeded = |limit, max, chunk_type| PngSecretError::LimitExceeded {
+                limit,
+                max,
+                at: location(Some(chunk_type)),
+            };
 
-            // reading chunk type, data and crc
-            let chunk_length = chunk_length + data_length;
-            let mut buffer: Vec<u8> = vec![0; chunk_length as usize];
-            if let Err(_) = reader.read_exact(&mut buffer) {
-                break Ok(());
+            // reading data length and chunk type
+            let mut buffer: [u8; 8] = [0; 8];
+            if reader.read_exact(&mut buffer).is_err() {
+                return Err(PngSecretError::Truncated { at: location(None) });
+            }
+            let data_length = u32::from_be_bytes(buffer[..4].try_into().unwrap()) as usize;
+            let chunk_type = &buffer[4..];
+            if chunks.len() == limits.max_chunks {
+                return Err(exceeded(Limit::Chunks, limits.max_chunks, chunk_type));
+            }
+            if data_length > limits.max_chunk_size {
+                return Err(exceeded(
+                    Limit::ChunkSize,
+                    limits.max_chunk_size,
+                    chunk_type,
+                ));
+            }
+            total = total.saturating_add(data_length);
+            if total > limits.max_total {
+                return Err(exceeded(Limit::Total, limits.max_total, chunk_type));
             }
 
-            // preparing chunk bytes
-            let bytes: Vec<u8> = data_length
-                .to_be_bytes() // data length goes first
-                .iter()
-                .chain(buffer.iter())
-                .cloned()
-                .collect();
+            // reading data and crc, the buffer only grows as far as the input goes
+            // whatever length the chunk claims
+            let chunk_length = Chunk::OVERHEAD + data_length;
+            let mut bytes = buffer.to_vec();
+            (&mut reader)
+                .take((chunk_length - bytes.len()) as u64)
+                .read_to_end(&mut bytes)?;
+            if bytes.len() < chunk_length {
+                return Err(PngSecretError::Truncated { at: location(None) });
+            }
 
             // trying to get the chunk
-            let chunk = Chunk::try_from(bytes.as_ref());
-            if let Err(e) = chunk {
-                break Err(e);
+            let chunk = if check_crc {
+                Chunk::try_from(bytes.as_ref())
+            } else {
+                Chunk::try_from_lenient(bytes.as_ref())
+            };
+            let chunk = chunk.map_err(|error| {
+                if let PngSecretError::CrcMismatch {
+                    expected, found, ..
+                } = error
+                {
+                    log::debug!(offset, expected, found, "crc mismatch");
+                }
+                error.at(chunks.len(), offset)
+            })?;
+            // checking costs a crc of the data, only worth it when someone listens
+            if !check_crc && tracing::enabled!(tracing::Level::DEBUG) && !chunk.is_crc_valid() {
+                log::debug!(chunk_type = %chunk.chunk_type(), offset, "crc mismatch, kept");
             }
+            log::debug!(
+                chunk_type = %chunk.chunk_type(),
+                length = data_length,
+                offset,
+                "parsed chunk"
+            );
+            log::trace!(chunk_type = %chunk.chunk_type(), crc = %format_args!("{:#010x}", chunk.crc()), "chunk crc");
+            offset += bytes.len();
             // pushin to chunks
-            chunks.push(chunk.unwrap());
-        };
-        if let Err(e) = try_chunks {
-            return Err(e);
-        };
-        Ok(Png { header, chunks })
+            chunks.push(chunk);
+        }
+        Ok(Png {
+            header,
+            chunks,
+            trailing_data,
+        })
+    }
+}
+/// a chunk read in place, its data borrowed from the bytes of the whole file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRef<'a> {
+    /// the index of the chunk in the file
+    pub index: usize,
+    /// where the chunk starts in the file
+    pub offset: usize,
+    chunk_type: ChunkType,
+    data: &'a [u8],
+    crc: u32,
+}
+impl ChunkRef<'_> {
+    /// the type of the chunk
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+    /// the data of the chunk
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+    /// the stored crc, `to_chunk` checks it
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+    /// an owned copy of the chunk, an error if its crc doesn't match, like `Png::try_from`
+    pub fn to_chunk(&self) -> Result<Chunk, PngSecretError> {
+        let chunk = Chunk::new_unchecked(self.chunk_type.clone(), self.data, self.crc);
+        if !chunk.is_crc_valid() {
+            return Err(PngSecretError::CrcMismatch {
+                expected: u32::from_be_bytes(Chunk::calculate_crc(
+                    &self.chunk_type.bytes(),
+                    self.data,
+                )),
+                found: self.crc,
+                at: Location {
+                    index: Some(self.index),
+                    chunk_type: Some(self.chunk_type.to_string()),
+                    offset: Some(self.offset),
+                    path: None,
+                },
+            });
+        }
+        Ok(chunk)
+    }
+}
+impl Png {
+    /// the chunks of a whole file without copying their data. only the signature,
+    /// the chunk lengths and types are checked, crcs once a chunk is copied
+    pub fn chunk_refs(bytes: &[u8]) -> Result<Vec<ChunkRef<'_>>, PngSecretError> {
+        if !bytes.starts_with(&Png::STANDARD_HEADER) {
+            return Err(PngSecretError::NotAPng { path: None });
+        }
+        let mut chunks = Vec::new();
+        let mut offset = Png::STANDARD_HEADER.len();
+        // past IEND what isn't a chunk is trailing data, where the chunks end
+        let mut after_iend = false;
+        while offset < bytes.len() {
+            let index = chunks.len();
+            let truncated = || PngSecretError::Truncated {
+                at: Location {
+                    index: Some(index),
+                    offset: Some(offset),
+                    ..Location::default()
+                },
+            };
+            let chunk = bytes
+                .get(offset..offset + 8)
+                .ok_or_else(truncated)
+                .and_then(|header| {
+                    let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+                    let chunk_type =
+                        ChunkType::try_from(<[u8; 4]>::try_from(&header[4..]).unwrap())
+                            .map_err(|error| PngSecretError::from(error).at(index, offset))?;
+                    let end = offset + length + Chunk::OVERHEAD;
+                    let rest = bytes.get(offset + 8..end).ok_or_else(truncated)?;
+                    Ok((length, chunk_type, end, rest))
+                });
+            let (length, chunk_type, end, rest) = match chunk {
+                Ok(chunk) => chunk,
+                Err(_) if after_iend => break,
+                Err(error) => return Err(error),
+            };
+            let (data, crc) = rest.split_at(length);
+            after_iend |= chunk_type.to_string() == "IEND";
+            chunks.push(ChunkRef {
+                index,
+                offset,
+                chunk_type,
+                data,
+                crc: u32::from_be_bytes(crc.try_into().unwrap()),
+            });
+            offset = end;
+        }
+        Ok(chunks)
     }
 }
 impl Display for Png {
@@ -126,8 +918,7 @@ impl Display for Png {
 mod tests {
     use super::*;
     use crate::chunk::Chunk;
-    use crate::chunk_type::ChunkType;
-    use crate::Error;
+    use crate::chunk_type::{ChunkType, ChunkTypeError};
     use std::convert::TryFrom;
     use std::str::FromStr;
 
@@ -144,9 +935,7 @@ mod tests {
         Png::from_chunks(chunks)
     }
 
-    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk, Error> {
-        use std::str::FromStr;
-
+    fn chunk_from_strings(chunk_type: &str, data: &str) -> crate::Result<Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type)?;
         let data: Vec<u8> = data.bytes().collect();
 
@@ -194,7 +983,11 @@ mod tests {
 
         let png = Png::try_from(bytes.as_ref());
         println!("{:?}", png);
-        assert!(png.is_err());
+        assert!(matches!(png, Err(PngSecretError::NotAPng { .. })));
+        assert!(matches!(
+            Png::try_from(&Png::STANDARD_HEADER[..6]),
+            Err(PngSecretError::NotAPng { .. })
+        ));
     }
 
     #[test]
@@ -221,7 +1014,571 @@ mod tests {
 
         let png = Png::try_from(chunk_bytes.as_ref());
         println!("{:?}", png);
-        assert!(png.is_err());
+        let index = testing_chunks().len();
+        assert!(matches!(
+            png,
+            Err(PngSecretError::InvalidChunkType {
+                error: ChunkTypeError::NotLetters,
+                at: Location { index: Some(at), offset: Some(offset), .. }
+            }) if at == index && offset == 8 + chunks.len()
+        ));
+    }
+
+    #[test]
+    fn test_truncated_png() {
+        let bytes = Png::try_from(&PNG_FILE[..]).unwrap().as_bytes();
+        // cut inside the length, the data and the crc of the last chunk
+        let offset = bytes.len() - Chunk::OVERHEAD;
+        for cut in [2, 6, 10] {
+            let png = Png::try_from(&bytes[..bytes.len() - cut]);
+            assert!(
+                matches!(&png, Err(PngSecretError::Truncated { at }) if at.offset == Some(offset)),
+                "{:?}",
+                png
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunk_refs() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        let refs = Png::chunk_refs(&bytes).unwrap();
+        assert_eq!(refs.len(), png.chunks().len());
+        for (chunk_ref, chunk) in refs.iter().zip(png.chunks()) {
+            assert_eq!(chunk_ref.offset, png.chunk_offset(chunk_ref.index));
+            assert_eq!(chunk_ref.chunk_type(), chunk.chunk_type());
+            assert_eq!(chunk_ref.data(), chunk.data());
+            assert_eq!(chunk_ref.to_chunk().unwrap().as_bytes(), chunk.as_bytes());
+        }
+
+        assert!(matches!(
+            Png::chunk_refs(b"not a png"),
+            Err(PngSecretError::NotAPng { .. })
+        ));
+        assert!(matches!(
+            Png::chunk_refs(&bytes[..bytes.len() - 2]),
+            Err(PngSecretError::Truncated { at }) if at.index == Some(refs.len() - 1)
+        ));
+        let mut bad_crc = bytes.clone();
+        let last = bad_crc.len() - 1;
+        bad_crc[last] ^= 0xff;
+        let refs = Png::chunk_refs(&bad_crc).unwrap();
+        assert!(matches!(
+            refs.last().unwrap().to_chunk(),
+            Err(PngSecretError::CrcMismatch { at, .. }) if at.index == Some(refs.len() - 1)
+        ));
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let bytes = testing_png().as_bytes();
+        let (png, lines) =
+            log::capture::capture(log::Level::Debug, || Png::try_from(bytes.as_slice()));
+        let parsed: Vec<&String> = lines
+            .iter()
+            .filter(|line| line.starts_with("debug: parsed chunk"))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                "debug: parsed chunk chunk_type=FrSt length=20 offset=8",
+                "debug: parsed chunk chunk_type=miDl length=18 offset=40",
+                "debug: parsed chunk chunk_type=LASt length=19 offset=70",
+            ]
+        );
+
+        let mut png = png.unwrap();
+        let ((), lines) = log::capture::capture(log::Level::Debug, || {
+            png.append_chunk(chunk_from_strings("ruSt", "new").unwrap());
+            png.remove_chunk("FrSt").unwrap();
+        });
+        assert_eq!(
+            lines,
+            [
+                "debug: appended chunk chunk_type=ruSt index=3 length=3",
+                "debug: removed chunk chunk_type=FrSt index=0 length=20",
+            ]
+        );
+
+        let mut bad_crc = bytes.clone();
+        bad_crc[40 - 1] ^= 0xff;
+        let (_, lines) =
+            log::capture::capture(log::Level::Debug, || Png::try_from(bad_crc.as_slice()));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("debug: crc mismatch offset=8 ")));
+        let (_, lines) = log::capture::capture(log::Level::Debug, || {
+            Png::try_from_lenient(bad_crc.as_slice())
+        });
+        assert!(lines.contains(&"debug: crc mismatch, kept chunk_type=FrSt offset=8".to_string()));
+        // nothing at the default level
+        let (_, lines) =
+            log::capture::capture(log::Level::Info, || Png::try_from(bytes.as_slice()));
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_limits() {
+        let chunk = |data: &[u8]| Chunk::new(ChunkType::from_str("ruSt").unwrap(), data);
+        let png = |chunks: Vec<Chunk>| Png::from_chunks(chunks).as_bytes();
+        let read = |bytes: &[u8], limits| Png::from_reader_with_limits(bytes, limits);
+        let limits = Limits {
+            max_chunks: 3,
+            max_chunk_size: 100,
+            max_total: 250,
+        };
+
+        let fits = png(vec![chunk(&[1; 100]), chunk(&[2; 100]), chunk(&[3; 50])]);
+        assert_eq!(read(&fits, limits).unwrap().chunks().len(), 3);
+        assert_eq!(
+            read(&fits, Limits::NONE).unwrap().as_bytes(),
+            Png::try_from(fits.as_ref()).unwrap().as_bytes()
+        );
+
+        let too_many = png(vec![chunk(b""); 4]);
+        assert!(matches!(
+            read(&too_many, limits),
+            Err(PngSecretError::LimitExceeded { limit: Limit::Chunks, max: 3, at })
+                if at.index == Some(3)
+        ));
+        let too_big = png(vec![chunk(&[0; 101])]);
+        let error = read(&too_big, limits).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "chunk #0 (ruSt) at offset 0x8 is bigger than the limit of 100 bytes per chunk"
+        );
+        let too_much = png(vec![chunk(&[0; 100]), chunk(&[0; 100]), chunk(&[0; 51])]);
+        assert!(matches!(
+            read(&too_much, limits),
+            Err(PngSecretError::LimitExceeded { limit: Limit::Total, max: 250, at })
+                if at.index == Some(2) && at.offset == Some(8 + 2 * 112)
+        ));
+
+        // chunks past IEND count the same
+        let iend = Chunk::new(ChunkType::from_str("IEND").unwrap(), b"");
+        let after_iend = png(vec![iend.clone(), chunk(b""), chunk(b""), chunk(b"")]);
+        assert!(matches!(
+            read(&after_iend, limits),
+            Err(PngSecretError::LimitExceeded { limit: Limit::Chunks, max: 3, at })
+                if at.index == Some(3) && at.offset == Some(8 + 3 * 12)
+        ));
+        let big_after_iend = png(vec![iend, chunk(&[0; 101])]);
+        assert!(matches!(
+            read(&big_after_iend, limits),
+            Err(PngSecretError::LimitExceeded { limit: Limit::ChunkSize, max: 100, at })
+                if at.index == Some(1) && at.chunk_type.as_deref() == Some("ruSt")
+        ));
+
+        // thousands of chunks claiming the biggest length, the first one ends the file
+        let mut claims = Png::STANDARD_HEADER.to_vec();
+        for _ in 0..5000 {
+            claims.extend_from_slice(&(Chunk::MAX_LENGTH as u32).to_be_bytes());
+            claims.extend_from_slice(b"IDAT");
+        }
+        assert!(matches!(
+            read(&claims, Limits::default()),
+            Err(PngSecretError::Truncated { at }) if at.index == Some(0)
+        ));
+        assert!(matches!(
+            read(&claims, Limits::memory(1 << 20)),
+            Err(PngSecretError::LimitExceeded {
+                limit: Limit::ChunkSize,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_error_location() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"a\0b"));
+        let index = png
+            .chunks()
+            .iter()
+            .position(|chunk| chunk.chunk_type().to_string() == "tEXt")
+            .unwrap();
+        let offset = png.chunk_offset(index);
+        let mut bytes = png.as_bytes();
+        // zero the stored crc of the tEXt chunk
+        let crc = offset + 8 + 3;
+        bytes[crc..crc + 4].fill(0);
+        let error = Png::try_from(bytes.as_ref()).unwrap_err();
+        let expected = u32::from_be_bytes(Chunk::calculate_crc(b"tEXt", b"a\0b"));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "invalid CRC in chunk #{} (tEXt) at offset {:#X}: expected {:#010X}, found 0x00000000",
+                index, offset, expected
+            )
+        );
+        let error = error.in_file(std::path::Path::new("./photos/x.png"));
+        assert!(error.to_string().contains(&format!(
+            "at offset {:#X} in ./photos/x.png: expected",
+            offset
+        )));
+
+        let error = Png::try_from(&bytes[..offset + 6]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "the file ends inside chunk #{} at offset {:#X}",
+                index, offset
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_salvage() {
+        let chunk = |chunk_type: &str, data: &[u8]| Chunk::new(chunk_type.parse().unwrap(), data);
+        let bytes = Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk("ruSt", b"0123456789"),
+            chunk("IDAT", &[0; 16]),
+            chunk("IEND", &[]),
+        ])
+        .as_bytes();
+        // the IDAT starts after the signature, the 25 bytes of IHDR and the 22 of ruSt
+        let idat = 8 + 25 + 22;
+        let salvage = |cut: usize| {
+            let (png, issues) = Png::parse_salvage(&bytes[..cut]).unwrap();
+            (png.chunks().len(), issues)
+        };
+        for (cut, remaining) in [
+            // in the length, the data and the crc of the IDAT
+            (idat + 2, 2),
+            (idat + 8 + 5, 13),
+            (idat + 8 + 16 + 2, 26),
+        ] {
+            let (kept, issues) = salvage(cut);
+            assert_eq!(kept, 2, "cut at {}", cut);
+            assert!(matches!(
+                &issues[..],
+                [
+                    SalvageIssue::Dropped { offset, remaining: r, error: PngSecretError::Truncated { at } },
+                    SalvageIssue::MissingIend,
+                ] if *offset == idat && *r == remaining && at.index == Some(2)
+            ));
+        }
+        let (kept, issues) = salvage(bytes.len() - 12);
+        assert_eq!(kept, 3);
+        assert!(matches!(&issues[..], [SalvageIssue::MissingIend]));
+        let (kept, issues) = salvage(bytes.len());
+        assert_eq!(kept, 4);
+        assert!(issues.is_empty());
+        assert_eq!(
+            salvage(idat + 2)
+                .1
+                .iter()
+                .map(SalvageIssue::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "dropped 2 bytes from offset 0x37: the file ends inside chunk #2 at offset 0x37",
+                "no IEND chunk, the file was cut short"
+            ]
+        );
+
+        // a bad crc is damage too, what follows it is dropped
+        let mut broken = bytes.clone();
+        broken[idat - 1] ^= 0xff;
+        let (png, issues) = Png::parse_salvage(&broken).unwrap();
+        assert_eq!(png.chunks().len(), 1);
+        assert!(matches!(
+            &issues[0],
+            SalvageIssue::Dropped {
+                offset: 33,
+                error: PngSecretError::CrcMismatch { .. },
+                ..
+            }
+        ));
+        assert!(matches!(
+            Png::parse_salvage(&bytes[..4]),
+            Err(PngSecretError::NotAPng { .. })
+        ));
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        let mut bytes = png.as_bytes();
+        let late = chunk_from_strings("LaTe", "after IEND").unwrap();
+        bytes.extend_from_slice(&late.as_bytes());
+        bytes.extend_from_slice(&[0xab; 1024]);
+        let mut png = Png::try_from(bytes.as_slice()).unwrap();
+        // a chunk after IEND is still one, what follows it isn't
+        assert_eq!(png.chunks().len(), testing_png().chunks().len() + 2);
+        assert_eq!(png.trailing_data(), [0xab; 1024]);
+        assert_eq!(png.as_bytes(), bytes);
+        assert_eq!(png.stats().file_size, bytes.len());
+        let mut written = Vec::new();
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, bytes);
+        let (salvaged, issues) = Png::parse_salvage(&bytes).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(salvaged.trailing_data().len(), 1024);
+        let refs = Png::chunk_refs(&bytes).unwrap();
+        assert_eq!(refs.len(), png.chunks().len());
+
+        assert_eq!(png.strip_trailing_data().len(), 1024);
+        assert!(png.trailing_data().is_empty());
+        assert_eq!(png.as_bytes(), &bytes[..bytes.len() - 1024]);
+        // the total limit counts them
+        let limits = Limits {
+            max_total: 512,
+            ..Limits::default()
+        };
+        assert!(matches!(
+            Png::from_reader_with_limits(bytes.as_slice(), limits),
+            Err(PngSecretError::LimitExceeded {
+                limit: Limit::Total,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_lenient_from_bytes() {
+        let mut bytes = PNG_FILE.to_vec();
+        // the last byte of the IEND crc
+        *bytes.last_mut().unwrap() ^= 0xff;
+        let chunks = Png::try_from(&PNG_FILE[..]).unwrap().chunks().len();
+        assert!(matches!(
+            Png::try_from(bytes.as_ref()),
+            Err(PngSecretError::CrcMismatch { at, .. }) if at.index == Some(chunks - 1)
+        ));
+        let png = Png::try_from_lenient(bytes.as_ref()).unwrap();
+        assert_eq!(png.as_bytes(), bytes);
+        let read = Png::from_reader_lenient(bytes.as_slice(), Limits::NONE).unwrap();
+        assert_eq!(read.as_bytes(), bytes);
+        assert!(!read.chunks().last().unwrap().is_crc_valid());
+    }
+
+    #[test]
+    fn test_validate_clean() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let issues = testing_png().validate();
+        assert_eq!(
+            issues,
+            [
+                ValidationIssue::IhdrNotFirst { index: 0 },
+                ValidationIssue::MissingIdat,
+                ValidationIssue::MissingIend
+            ]
+        );
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let end = png.chunks().len();
+        png.insert_chunk_at(end, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        let last = png.chunks().len() - 2;
+        assert_eq!(
+            png.validate(),
+            [ValidationIssue::IendNotLast { index: last }]
+        );
+    }
+
+    #[test]
+    fn test_validate_ordering() {
+        let png = |types: &[&str]| {
+            let chunks = types
+                .iter()
+                .map(|chunk_type| chunk_from_strings(chunk_type, "").unwrap())
+                .collect();
+            Png::from_chunks(chunks).validate()
+        };
+        assert!(png(&["IHDR", "PLTE", "IDAT", "IDAT", "tEXt", "IEND"]).is_empty());
+        assert_eq!(
+            png(&["tEXt", "IHDR", "IDAT", "IEND"]),
+            [ValidationIssue::ChunkBeforeIhdr { index: 0 }]
+        );
+        assert_eq!(
+            png(&["IHDR", "IHDR", "IDAT", "IEND"]),
+            [ValidationIssue::DuplicateIhdr { index: 1 }]
+        );
+        assert_eq!(
+            png(&["IHDR", "IDAT", "IEND", "IEND"]),
+            [
+                ValidationIssue::IendNotLast { index: 2 },
+                ValidationIssue::DuplicateIend { index: 3 }
+            ]
+        );
+        assert_eq!(
+            png(&["IHDR", "IDAT", "tEXt", "IDAT", "IDAT", "zTXt", "IDAT", "IEND"]),
+            [
+                ValidationIssue::IdatNotContiguous { index: 3 },
+                ValidationIssue::IdatNotContiguous { index: 6 }
+            ]
+        );
+        assert_eq!(
+            png(&["IHDR", "IDAT", "PLTE", "IEND"]),
+            [ValidationIssue::PlteAfterIdat { index: 2 }]
+        );
+        let issue = ValidationIssue::IdatNotContiguous { index: 3 };
+        assert_eq!(issue.chunk_index(), Some(3));
+    }
+
+    #[test]
+    fn test_validate_ordering_rules() {
+        let png = |types: &[&str]| {
+            let chunks = types
+                .iter()
+                .map(|chunk_type| chunk_from_strings(chunk_type, "").unwrap())
+                .collect();
+            Png::from_chunks(chunks).validate_ordering()
+        };
+        let misplaced = |index, chunk_type: &str, rule: &str| ValidationIssue::Misplaced {
+            index,
+            chunk_type: chunk_type.to_string(),
+            rule: rule.to_string(),
+        };
+        let clean = [
+            "IHDR", "gAMA", "cHRM", "sRGB", "iCCP", "sBIT", "pHYs", "sPLT", "PLTE", "bKGD", "tRNS",
+            "hIST", "tIME", "IDAT", "tEXt", "tIME", "IEND",
+        ];
+        assert!(png(&clean).is_empty());
+        // bKGD needs no PLTE in a file without one
+        assert!(png(&["IHDR", "bKGD", "IDAT", "IEND"]).is_empty());
+        for (types, issue) in [
+            (
+                &["IHDR", "PLTE", "gAMA", "IDAT", "IEND"][..],
+                misplaced(2, "gAMA", "before PLTE"),
+            ),
+            (
+                &["IHDR", "IDAT", "cHRM", "IEND"],
+                misplaced(2, "cHRM", "before IDAT"),
+            ),
+            (
+                &["IHDR", "PLTE", "sRGB", "IDAT", "IEND"],
+                misplaced(2, "sRGB", "before PLTE"),
+            ),
+            (
+                &["IHDR", "PLTE", "iCCP", "IDAT", "IEND"],
+                misplaced(2, "iCCP", "before PLTE"),
+            ),
+            (
+                &["IHDR", "tRNS", "PLTE", "IDAT", "IEND"],
+                misplaced(1, "tRNS", "after PLTE"),
+            ),
+            (
+                &["IHDR", "PLTE", "IDAT", "bKGD", "IEND"],
+                misplaced(3, "bKGD", "before IDAT"),
+            ),
+            (
+                &["IHDR", "hIST", "PLTE", "IDAT", "IEND"],
+                misplaced(1, "hIST", "after PLTE"),
+            ),
+            (
+                &["IHDR", "IDAT", "pHYs", "IEND"],
+                misplaced(2, "pHYs", "before IDAT"),
+            ),
+            (
+                &["IHDR", "IDAT", "sPLT", "IEND"],
+                misplaced(2, "sPLT", "before IDAT"),
+            ),
+        ] {
+            assert_eq!(png(types), [issue]);
+        }
+        assert_eq!(
+            misplaced(2, "pHYs", "before IDAT").to_string(),
+            "pHYs should come before IDAT"
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let chunks = [
+            "IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "ruSt", "fdAT", "IEND",
+        ]
+        .iter()
+        .map(|chunk_type| chunk_from_strings(chunk_type, "").unwrap())
+        .collect();
+        let png = Png::from_chunks(chunks);
+        assert!(png.is_animated());
+        assert!(!testing_png().is_animated());
+        assert_eq!(png.frames(), [2..=3, 4..=7]);
+        assert_eq!(png.frame_at(3), Some(2..=3));
+        assert_eq!(png.frame_at(4), None);
+        assert_eq!(png.frame_at(6), Some(4..=7));
+        assert_eq!(png.frame_at(8), None);
+    }
+
+    #[test]
+    fn test_validate_crc() {
+        let mut bytes = PNG_FILE.to_vec();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        let png = Png::try_from_lenient(bytes.as_ref()).unwrap();
+        let index = png.chunks().len() - 1;
+        let issues = png.validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].chunk_index(), Some(index));
+        assert!(matches!(issues[0], ValidationIssue::CrcMismatch { .. }));
+    }
+
+    #[test]
+    fn test_recompute_crcs() {
+        let mut bytes = PNG_FILE.to_vec();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        let mut png = Png::try_from_lenient(bytes.as_ref()).unwrap();
+        assert_eq!(png.recompute_crcs(), [png.chunks().len() - 1]);
+        assert_eq!(png.as_bytes(), PNG_FILE);
+        assert!(png.recompute_crcs().is_empty());
+    }
+
+    #[test]
+    fn test_strip_ancillary() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let removed = png.strip_ancillary(&[ChunkType::from_str("gAMA").unwrap()]);
+        let removed: Vec<String> = removed
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(removed, ["sRGB", "pHYs"]);
+        let kept: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        // RuSt is critical going by its first letter
+        assert_eq!(kept, ["IHDR", "gAMA", "IDAT", "RuSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_insert_chunk_at() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let len = png.chunks().len();
+        let iend = png.iend_index().unwrap();
+        assert_eq!(iend, len - 1);
+        assert_eq!(png.ihdr_index(), Some(0));
+        assert_eq!(png.first_idat_index(), Some(4));
+        png.insert_chunk_at(iend, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(png.iend_index(), Some(len));
+        assert_eq!(&png.chunks()[len - 1].chunk_type().to_string(), "TeSt");
+        assert!(matches!(
+            png.insert_chunk_at(len + 2, chunk_from_strings("TeSt", "Message").unwrap()),
+            Err(PngSecretError::IndexOutOfRange { index, len: chunks }) if index == len + 2 && chunks == len + 1
+        ));
+    }
+
+    #[test]
+    fn test_replace_chunk() {
+        let mut png = testing_png();
+        let len = png.chunks().len();
+        let old = png
+            .replace_chunk(1, chunk_from_strings("RuSt", "newer").unwrap())
+            .unwrap();
+        assert_eq!(old.data_as_string().unwrap(), "I am another chunk");
+        assert_eq!(png.chunks().len(), len);
+        assert_eq!(png.chunks()[1].data(), b"newer");
+        assert!(matches!(
+            png.replace_chunk(len, chunk_from_strings("RuSt", "newer").unwrap()),
+            Err(PngSecretError::IndexOutOfRange { index, len: chunks }) if index == len && chunks == len
+        ));
     }
 
     #[test]
@@ -239,6 +1596,82 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
     }
 
+    #[test]
+    fn test_ihdr() {
+        let ihdr = |data: &[u8]| {
+            let mut png = testing_png();
+            png.insert_chunk_at(0, Chunk::new(ChunkType::from_str("IHDR").unwrap(), data))
+                .unwrap();
+            png
+        };
+        let png = ihdr(&[0, 0, 1, 0, 0, 0, 0, 64, 8, 2, 0, 0, 0]);
+        assert_eq!(png.dimensions().unwrap(), (256, 64));
+        assert_eq!(png.ihdr().unwrap().color_type, ihdr::ColorType::Rgb);
+
+        assert!(matches!(
+            testing_png().ihdr(),
+            Err(PngSecretError::ChunkNotFound { chunk_type, .. }) if chunk_type == "IHDR"
+        ));
+        assert!(matches!(
+            ihdr(&[0, 0, 1, 0, 0, 0, 0, 64, 8, 2, 0, 0]).dimensions(),
+            Err(PngSecretError::InvalidIhdr(ihdr::IhdrError::Length(12)))
+        ));
+        assert!(matches!(
+            ihdr(&[0, 0, 1, 0, 0, 0, 0, 0, 8, 2, 0, 0, 0]).dimensions(),
+            Err(PngSecretError::InvalidIhdr(
+                ihdr::IhdrError::ZeroSize { .. }
+            ))
+        ));
+        assert!(matches!(
+            ihdr(&[0, 0, 1, 0, 0, 0, 0, 64, 2, 6, 0, 0, 0]).ihdr(),
+            Err(PngSecretError::InvalidIhdr(
+                ihdr::IhdrError::BitDepth { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am a second first chunk").unwrap());
+        let chunks = png.chunks_by_type("FrSt");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0, 0);
+        assert_eq!(chunks[1].0, 3);
+        assert_eq!(
+            &chunks[1].1.data_as_string().unwrap(),
+            "I am a second first chunk"
+        );
+        assert!(png.chunks_by_type("NoNe").is_empty());
+    }
+
+    #[test]
+    fn test_chunk_offset() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        assert_eq!(png.chunk_offset(0), 8);
+        let offset = png.chunk_offset(2);
+        assert_eq!(&bytes[offset + 4..offset + 8], b"LASt");
+    }
+
+    #[test]
+    fn test_reader_and_writer() {
+        let png = Png::from_reader(std::io::Cursor::new(PNG_FILE)).unwrap();
+        let mut written = Vec::new();
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, PNG_FILE);
+        assert!(Png::from_reader(&PNG_FILE[..20]).is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let stats = png.stats();
+        assert_eq!(stats.file_size, PNG_FILE.len());
+        assert_eq!(stats.chunk_count, 7);
+        assert_eq!(stats.data_size, PNG_FILE.len() - 8 - 7 * Chunk::OVERHEAD);
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -248,6 +1681,26 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_append_chunk_before_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let len = png.chunks().len();
+        assert_eq!(png.chunks_by_type("TeSt")[0].0, len - 2);
+        assert_eq!(png.iend_index(), Some(len - 1));
+        assert!(png.validate().is_empty());
+
+        // a chunk already trailing IEND stays where it is
+        png.insert_chunk_at(len, chunk_from_strings("LaTe", "after IEND").unwrap())
+            .unwrap();
+        png.append_chunk(chunk_from_strings("TeSt", "Again").unwrap());
+        let types: Vec<_> = png.chunks()[len - 1..]
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["TeSt", "IEND", "LaTe"]);
+    }
+
     #[test]
     fn test_remove_chunk() {
         let mut png = testing_png();
@@ -255,6 +1708,33 @@ mod tests {
         png.remove_chunk("TeSt").unwrap();
         let chunk = png.chunk_by_type("TeSt");
         assert!(chunk.is_none());
+        assert!(matches!(
+            png.remove_chunk("TeSt"),
+            Err(PngSecretError::ChunkNotFound { chunk_type, .. }) if chunk_type == "TeSt"
+        ));
+    }
+
+    #[test]
+    fn test_remove_nth_chunk_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am a second first chunk").unwrap());
+        let removed = png.remove_nth_chunk_by_type("FrSt", 1).unwrap();
+        assert_eq!(
+            &removed.data_as_string().unwrap(),
+            "I am a second first chunk"
+        );
+        assert_eq!(png.chunks_by_type("FrSt").len(), 1);
+        assert!(png.remove_nth_chunk_by_type("FrSt", 1).is_none());
+    }
+
+    #[test]
+    fn test_remove_all_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am a second first chunk").unwrap());
+        let removed = png.remove_all_chunks_by_type("FrSt");
+        assert_eq!(removed.len(), 2);
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.chunk_by_type("FrSt").is_none());
     }
 
     #[test]
@@ -0,0 +1,194 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressStyle};
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+/// files smaller than this are read and written without a progress bar
+pub const DEFAULT_THRESHOLD: u64 = 16 * 1024 * 1024;
+const WIDTH: u64 = 30;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static THRESHOLD: AtomicU64 = AtomicU64::new(DEFAULT_THRESHOLD);
+
+/// bars are only drawn on a terminal, and never with --quiet
+pub fn init(quiet: bool, threshold: u64) {
+    ENABLED.store(!quiet && io::stderr().is_terminal(), Ordering::Relaxed);
+    THRESHOLD.store(threshold, Ordering::Relaxed);
+}
+
+/// a single line indicatif bar on stderr, cleared again once it is dropped
+pub struct Bar(ProgressBar);
+impl Bar {
+    /// a bar over `total` bytes, hidden for files under the threshold
+    pub fn bytes(total: u64, label: &str) -> Self {
+        let shown = ENABLED.load(Ordering::Relaxed) && total >= THRESHOLD.load(Ordering::Relaxed);
+        Bar::new(
+            total,
+            label,
+            "bytes",
+            shown.then(ProgressDrawTarget::stderr),
+        )
+    }
+    /// a bar over the files of a batch, hidden for a single file
+    pub fn files(total: u64) -> Self {
+        let shown = ENABLED.load(Ordering::Relaxed) && total > 1;
+        Bar::new(total, "", "files", shown.then(ProgressDrawTarget::stderr))
+    }
+    pub fn new(
+        total: u64,
+        label: &str,
+        unit: &'static str,
+        target: Option<ProgressDrawTarget>,
+    ) -> Self {
+        let template = format!(
+            "[{{bar:{}}}] {{percent:>3}}% {{pos}}/{{len}} {} {{msg}}",
+            WIDTH, unit
+        );
+        let style = ProgressStyle::with_template(&template)
+            .expect("the template is valid")
+            .progress_chars("# ");
+        let bar = ProgressBar::with_draw_target(
+            Some(total),
+            target.unwrap_or_else(ProgressDrawTarget::hidden),
+        )
+        .with_style(style)
+        .with_message(label.to_string())
+        .with_finish(ProgressFinish::AndClear);
+        Bar(bar)
+    }
+    pub fn set_label(&self, label: &str) {
+        self.0.set_message(label.to_string());
+    }
+    pub fn advance(&self, by: u64) {
+        let total = self.0.length().unwrap_or(u64::MAX);
+        self.0.set_position((self.0.position() + by).min(total));
+    }
+}
+
+/// passes reads through and moves the bar along with them
+pub struct ProgressReader<R> {
+    inner: R,
+    bar: Bar,
+}
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, bar: Bar) -> Self {
+        ProgressReader { inner, bar }
+    }
+}
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bar.advance(read as u64);
+        Ok(read)
+    }
+}
+
+/// passes writes through and moves the bar along with them
+pub struct ProgressWriter<W> {
+    inner: W,
+    bar: Bar,
+}
+impl<W: Write> ProgressWriter<W> {
+    pub fn new(inner: W, bar: Bar) -> Self {
+        ProgressWriter { inner, bar }
+    }
+}
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bar.advance(written as u64);
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indicatif::TermLike;
+    use std::sync::{Arc, Mutex};
+
+    /// a terminal the test can look into after the bar has taken it
+    #[derive(Clone, Debug, Default)]
+    struct Screen(Arc<Mutex<String>>);
+    impl TermLike for Screen {
+        fn width(&self) -> u16 {
+            80
+        }
+        fn move_cursor_up(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_down(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_right(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_left(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn write_line(&self, s: &str) -> io::Result<()> {
+            self.write_str(&format!("{}\n", s))
+        }
+        fn write_str(&self, s: &str) -> io::Result<()> {
+            self.0.lock().unwrap().push_str(s);
+            Ok(())
+        }
+        fn clear_line(&self) -> io::Result<()> {
+            Ok(())
+        }
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reader_passes_bytes_through() {
+        let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let screen = Screen::default();
+        let target = ProgressDrawTarget::term_like(Box::new(screen.clone()));
+        let bar = Bar::new(10_000, "big.png", "bytes", Some(target));
+        let mut reader = ProgressReader::new(&data[..], bar);
+        // small reads so the bar moves many times
+        let mut read = Vec::new();
+        let mut buffer = [0; 7];
+        loop {
+            match reader.read(&mut buffer).unwrap() {
+                0 => break,
+                n => read.extend_from_slice(&buffer[..n]),
+            }
+        }
+        assert_eq!(read, data);
+        assert_eq!(reader.bar.0.position(), 10_000);
+        // redraws are rate limited, finishing draws the last state for certain
+        reader.bar.0.finish();
+        let screen = screen.0.lock().unwrap();
+        assert!(screen.contains(&format!(
+            "[{}] 100% 10000/10000 bytes big.png",
+            "#".repeat(30)
+        )));
+    }
+
+    #[test]
+    fn test_writer_passes_bytes_through() {
+        let data = b"some png bytes".repeat(100);
+        let mut written = Vec::new();
+        let mut writer = ProgressWriter::new(&mut written, Bar::new(1000, "", "bytes", None));
+        writer.write_all(&data).unwrap();
+        writer.flush().unwrap();
+        // never past the end, even when there was more to write than expected
+        assert_eq!(writer.bar.0.position(), 1000);
+        drop(writer);
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn test_hidden_by_default() {
+        // init is only called by main, so the tests never draw
+        assert!(Bar::bytes(u64::MAX, "huge").0.is_hidden());
+        assert!(Bar::files(1000).0.is_hidden());
+    }
+}
@@ -0,0 +1,175 @@
+//! a python module of the same name, built with `maturin develop` from
+//! pyproject.toml. chunk types are `str`, data and whole files are `bytes`, and
+//! every `PngSecretError` variant is an exception under `pngsecret.PngSecretError`,
+//! except io errors which are python's own `OSError`
+
+use std::path::PathBuf;
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyOSError},
+    prelude::*,
+    types::PyBytes,
+};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, error, png::Png};
+
+create_exception!(
+    pngsecret,
+    PngSecretError,
+    PyException,
+    "why a png couldn't be read or changed"
+);
+create_exception!(pngsecret, NotAPng, PngSecretError);
+create_exception!(pngsecret, InvalidChunkType, PngSecretError);
+create_exception!(pngsecret, CrcMismatch, PngSecretError);
+create_exception!(pngsecret, ChunkNotFound, PngSecretError);
+create_exception!(pngsecret, Truncated, PngSecretError);
+create_exception!(pngsecret, IndexOutOfRange, PngSecretError);
+create_exception!(pngsecret, ChunkTooLong, PngSecretError);
+create_exception!(pngsecret, LimitExceeded, PngSecretError);
+create_exception!(pngsecret, NotUtf8, PngSecretError);
+create_exception!(pngsecret, InvalidIhdr, PngSecretError);
+
+fn py_error(error: error::PngSecretError) -> PyErr {
+    let message = error.to_string();
+    match error {
+        error::PngSecretError::Io { .. } => PyOSError::new_err(message),
+        error::PngSecretError::NotAPng { .. } => NotAPng::new_err(message),
+        error::PngSecretError::InvalidChunkType { .. } => InvalidChunkType::new_err(message),
+        error::PngSecretError::CrcMismatch { .. } => CrcMismatch::new_err(message),
+        error::PngSecretError::ChunkNotFound { .. } => ChunkNotFound::new_err(message),
+        error::PngSecretError::Truncated { .. } => Truncated::new_err(message),
+        error::PngSecretError::IndexOutOfRange { .. } => IndexOutOfRange::new_err(message),
+        error::PngSecretError::ChunkTooLong(_) => ChunkTooLong::new_err(message),
+        error::PngSecretError::LimitExceeded { .. } => LimitExceeded::new_err(message),
+        error::PngSecretError::NotUtf8(_) => NotUtf8::new_err(message),
+        error::PngSecretError::InvalidIhdr(_) => InvalidIhdr::new_err(message),
+        // the rest are errors of the cli's commands, which the module doesn't run
+        _ => PngSecretError::new_err(message),
+    }
+}
+fn chunk_type(chunk_type: &str) -> PyResult<ChunkType> {
+    chunk_type
+        .parse()
+        .map_err(|error| py_error(error::PngSecretError::from(error)))
+}
+fn read(path: &PathBuf) -> PyResult<Png> {
+    let bytes =
+        std::fs::read(path).map_err(|error| py_error(error::PngSecretError::io(path, error)))?;
+    Png::try_from(bytes.as_slice()).map_err(|error| py_error(error.in_file(path)))
+}
+
+/// one chunk, `Chunk("ruSt", b"data")`
+#[pyclass(name = "Chunk", module = "pngsecret", frozen)]
+struct PyChunk(Chunk);
+
+#[pymethods]
+impl PyChunk {
+    #[new]
+    fn new(chunk_type: &str, data: &[u8]) -> PyResult<Self> {
+        if data.len() > Chunk::MAX_LENGTH {
+            return Err(py_error(error::PngSecretError::ChunkTooLong(data.len())));
+        }
+        Ok(PyChunk(Chunk::new(self::chunk_type(chunk_type)?, data)))
+    }
+    #[getter]
+    fn chunk_type(&self) -> String {
+        self.0.chunk_type().to_string()
+    }
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.0.data())
+    }
+    #[getter]
+    fn crc(&self) -> u32 {
+        self.0.crc()
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "Chunk('{}', {} bytes)",
+            self.0.chunk_type(),
+            self.0.length()
+        )
+    }
+}
+
+/// a whole png, `Png.from_bytes(open("x.png", "rb").read())`
+#[pyclass(name = "Png", module = "pngsecret")]
+struct PyPng(Png);
+
+#[pymethods]
+impl PyPng {
+    /// parses a png, checking every crc
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Png::try_from(bytes).map(PyPng).map_err(py_error)
+    }
+    #[getter]
+    fn chunks(&self) -> Vec<PyChunk> {
+        self.0.chunks().iter().cloned().map(PyChunk).collect()
+    }
+    /// adds a chunk right before IEND, or at the end without one
+    fn append(&mut self, chunk_type: &str, data: &[u8]) -> PyResult<()> {
+        let chunk = PyChunk::new(chunk_type, data)?.0;
+        self.0.append_chunk(chunk);
+        Ok(())
+    }
+    /// removes the first chunk of `chunk_type` and returns it
+    fn remove(&mut self, chunk_type: &str) -> PyResult<PyChunk> {
+        self.0
+            .remove_chunk(chunk_type)
+            .map(PyChunk)
+            .map_err(py_error)
+    }
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.as_bytes())
+    }
+    fn __len__(&self) -> usize {
+        self.0.chunks().len()
+    }
+}
+
+/// hides `message` in a new chunk of `chunk_type` in the png at `path`, changing it in
+/// place like `pngsecret encode` does, and returns the index of the chunk
+#[pyfunction]
+fn encode_file(path: PathBuf, chunk_type: &str, message: &[u8]) -> PyResult<usize> {
+    let mut png = read(&path)?;
+    let index =
+        crate::encode(&mut png, self::chunk_type(chunk_type)?, message).map_err(py_error)?;
+    std::fs::write(&path, png.as_bytes())
+        .map_err(|error| py_error(error::PngSecretError::io(&path, error)))?;
+    Ok(index)
+}
+/// the data of the first chunk of `chunk_type` in the png at `path`
+#[pyfunction]
+fn decode_file<'py>(
+    py: Python<'py>,
+    path: PathBuf,
+    chunk_type: &str,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let png = read(&path)?;
+    let data = crate::decode(&png, chunk_type).map_err(py_error)?;
+    Ok(PyBytes::new(py, data))
+}
+
+#[pymodule]
+fn pngsecret(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPng>()?;
+    m.add_class::<PyChunk>()?;
+    m.add_function(wrap_pyfunction!(encode_file, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_file, m)?)?;
+    let py = m.py();
+    m.add("PngSecretError", py.get_type::<PngSecretError>())?;
+    m.add("NotAPng", py.get_type::<NotAPng>())?;
+    m.add("InvalidChunkType", py.get_type::<InvalidChunkType>())?;
+    m.add("CrcMismatch", py.get_type::<CrcMismatch>())?;
+    m.add("ChunkNotFound", py.get_type::<ChunkNotFound>())?;
+    m.add("Truncated", py.get_type::<Truncated>())?;
+    m.add("IndexOutOfRange", py.get_type::<IndexOutOfRange>())?;
+    m.add("ChunkTooLong", py.get_type::<ChunkTooLong>())?;
+    m.add("LimitExceeded", py.get_type::<LimitExceeded>())?;
+    m.add("NotUtf8", py.get_type::<NotUtf8>())?;
+    m.add("InvalidIhdr", py.get_type::<InvalidIhdr>())?;
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! --verify-render: decoding a changed png to pixels with the image crate and
+//! comparing them to the pixels of the original, for proof that a decoder still
+//! shows the same image. only builds with the image feature do the decoding
+
+use crate::{error::PngSecretError, Result};
+
+/// whether this build can decode pixels at all
+pub const ENABLED: bool = cfg!(feature = "image");
+
+/// the size of an image both decodes agreed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rendered {
+    pub width: u32,
+    pub height: u32,
+}
+impl Rendered {
+    /// decoded they fit in memory, so in a usize too
+    pub fn pixels(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+/// decodes both files and compares their size, pixel format and pixels
+#[cfg(feature = "image")]
+pub fn verify(original: &[u8], written: &[u8]) -> Result<Rendered> {
+    let decode = |bytes: &[u8], which: &str| {
+        image::load_from_memory_with_format(bytes, image::ImageFormat::Png).map_err(|e| {
+            PngSecretError::RenderChanged {
+                reason: format!("the {} doesn't decode: {}", which, e),
+            }
+        })
+    };
+    let before = decode(original, "original")?;
+    let after = decode(written, "output")?;
+    let size = |image: &image::DynamicImage| (image.width(), image.height());
+    if size(&before) != size(&after) {
+        return Err(PngSecretError::RenderChanged {
+            reason: format!(
+                "the size changed from {:?} to {:?}",
+                size(&before),
+                size(&after)
+            ),
+        });
+    }
+    if before.color() != after.color() {
+        return Err(PngSecretError::RenderChanged {
+            reason: format!(
+                "the pixel format changed from {:?} to {:?}",
+                before.color(),
+                after.color()
+            ),
+        });
+    }
+    if before.as_bytes() != after.as_bytes() {
+        let pixel = usize::from(before.color().bytes_per_pixel());
+        let differ = before
+            .as_bytes()
+            .chunks(pixel)
+            .zip(after.as_bytes().chunks(pixel))
+            .filter(|(before, after)| before != after)
+            .count();
+        return Err(PngSecretError::RenderChanged {
+            reason: format!("{} pixels differ", differ),
+        });
+    }
+    Ok(Rendered {
+        width: before.width(),
+        height: before.height(),
+    })
+}
+#[cfg(not(feature = "image"))]
+pub fn verify(_original: &[u8], _written: &[u8]) -> Result<Rendered> {
+    Err(PngSecretError::Usage(UNAVAILABLE.into()))
+}
+pub const UNAVAILABLE: &str = "--verify-render needs a pngsecret built with the image feature";
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use super::*;
+    use crate::{chunk::Chunk, png::Png};
+
+    fn png(idat: &[u8]) -> Vec<u8> {
+        let chunk = |chunk_type: &str, data: &[u8]| Chunk::new(chunk_type.parse().unwrap(), data);
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk("IDAT", idat),
+            chunk("IEND", &[]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_verify() {
+        // one transparent pixel, then one opaque white pixel
+        let clear = png(&[120, 1, 1, 5, 0, 250, 255, 0, 0, 0, 0, 0, 0, 5, 0, 1]);
+        let white = png(&[
+            120, 1, 1, 5, 0, 250, 255, 0, 255, 255, 255, 255, 9, 251, 3, 253,
+        ]);
+        assert_eq!(
+            verify(&clear, &clear).unwrap(),
+            Rendered {
+                width: 1,
+                height: 1
+            }
+        );
+        let error = verify(&clear, &white).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Render verification failed: 1 pixels differ"
+        );
+        let broken = png(&[120, 1, 1, 5, 0]);
+        let error = verify(&clear, &broken).unwrap_err();
+        assert!(matches!(error, PngSecretError::RenderChanged { .. }));
+        assert!(error.to_string().contains("the output doesn't decode"));
+    }
+}
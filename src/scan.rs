@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::png::Png;
+
+/// ancillary chunks bigger than this are reported
+pub const LARGE_ANCILLARY_CHUNK: u32 = 64 * 1024;
+
+// magic numbers of formats that commonly end up embedded in chunks
+const KNOWN_BINARY_MAGIC: [&[u8]; 9] = [
+    &[0x78],       // zlib stream
+    &[0x1f, 0x8b], // gzip
+    &[0x89, b'P', b'N', b'G'],
+    &[0xff, 0xd8, 0xff], // jpeg
+    b"PK\x03\x04",       // zip
+    b"%PDF",
+    b"GIF8",
+    b"II*\x00", // tiff / exif, little endian
+    b"MM\x00*", // tiff / exif, big endian
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub chunk_index: usize,
+    pub reason: String,
+}
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] chunk #{}: {}",
+            self.severity, self.chunk_index, self.reason
+        )
+    }
+}
+
+pub(crate) fn is_known_binary(data: &[u8]) -> bool {
+    KNOWN_BINARY_MAGIC
+        .iter()
+        .any(|magic| data.starts_with(magic))
+}
+
+/// looks through the chunks for anything that might be hiding a payload
+pub fn scan(png: &Png) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let iend = png
+        .chunks()
+        .iter()
+        .position(|chunk| chunk.chunk_type().to_string() == "IEND");
+    // private type -> (first index, count)
+    let mut private_types: Vec<(String, usize, usize)> = Vec::new();
+
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        let name = chunk_type.to_string();
+        let mut report = |severity, reason: String| {
+            findings.push(Finding {
+                severity,
+                chunk_index: index,
+                reason,
+            })
+        };
+
+        if iend.is_some_and(|iend| index > iend) {
+            report(Severity::High, format!("{} chunk after IEND", name));
+        }
+        if !chunk_type.is_registered() {
+            if chunk_type.is_public() {
+                report(Severity::Low, format!("unknown public chunk type {}", name));
+            } else {
+                report(
+                    Severity::Medium,
+                    format!("unregistered private chunk type {}", name),
+                );
+            }
+            if std::str::from_utf8(chunk.data()).is_err() && !is_known_binary(chunk.data()) {
+                report(
+                    Severity::High,
+                    format!(
+                        "{} holds {} bytes that are neither UTF-8 text nor a known binary format",
+                        name,
+                        chunk.length()
+                    ),
+                );
+            }
+        }
+        if !chunk_type.is_critical() && chunk.length() > LARGE_ANCILLARY_CHUNK {
+            let severity = if chunk_type.is_registered() {
+                Severity::Low
+            } else {
+                Severity::Medium
+            };
+            report(
+                severity,
+                format!("{} byte ancillary chunk {}", chunk.length(), name),
+            );
+        }
+        if !chunk_type.is_public() {
+            match private_types.iter_mut().find(|(t, _, _)| *t == name) {
+                Some((_, _, count)) => *count += 1,
+                None => private_types.push((name, index, 1)),
+            }
+        }
+    }
+
+    for (name, index, count) in private_types {
+        if count > 1 {
+            findings.push(Finding {
+                severity: Severity::High,
+                chunk_index: index,
+                reason: format!("{} chunks share the private type {}", count, name),
+            });
+        }
+    }
+    // bytes that aren't a chunk after IEND are under the last chunk
+    if !png.trailing_data().is_empty() {
+        findings.push(Finding {
+            severity: Severity::High,
+            chunk_index: png.chunks().len().saturating_sub(1),
+            reason: format!("{} trailing bytes after IEND", png.trailing_data().len()),
+        });
+    }
+    findings.sort_by_key(|finding| finding.chunk_index);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    fn clean_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk("tEXt", b"Comment\0made with care"),
+            chunk(
+                "IDAT",
+                &[120, 1, 1, 5, 0, 250, 255, 0, 0, 0, 0, 0, 0, 5, 0, 1],
+            ),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    fn png_with(extra: Vec<Chunk>) -> Png {
+        let mut chunks = clean_png().chunks().clone();
+        let iend = chunks.pop().unwrap();
+        chunks.extend(extra);
+        chunks.push(iend);
+        Png::from_chunks(chunks)
+    }
+
+    fn reasons(png: &Png, severity: Severity) -> Vec<String> {
+        scan(png)
+            .into_iter()
+            .filter(|finding| finding.severity == severity)
+            .map(|finding| finding.reason)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_file() {
+        assert!(scan(&clean_png()).is_empty());
+    }
+
+    #[test]
+    fn test_private_chunk() {
+        let png = png_with(vec![chunk("ruSt", b"hello")]);
+        assert_eq!(
+            reasons(&png, Severity::Medium),
+            ["unregistered private chunk type ruSt"]
+        );
+        assert!(reasons(&png, Severity::High).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_public_chunk() {
+        let png = png_with(vec![chunk("zZZz", b"hello")]);
+        assert_eq!(
+            reasons(&png, Severity::Low),
+            ["unknown public chunk type zZZz"]
+        );
+    }
+
+    #[test]
+    fn test_large_ancillary_chunk() {
+        let png = png_with(vec![chunk("zTXt", &[b'a'; 70_000])]);
+        assert_eq!(
+            reasons(&png, Severity::Low),
+            ["70000 byte ancillary chunk zTXt"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_private_chunks() {
+        let png = png_with(vec![chunk("ruSt", b"one"), chunk("ruSt", b"two")]);
+        assert_eq!(
+            reasons(&png, Severity::High),
+            ["2 chunks share the private type ruSt"]
+        );
+    }
+
+    #[test]
+    fn test_chunk_after_iend() {
+        let mut png = clean_png();
+        let end = png.chunks().len();
+        png.insert_chunk_at(end, chunk("tEXt", b"Comment\0late"))
+            .unwrap();
+        assert_eq!(reasons(&png, Severity::High), ["tEXt chunk after IEND"]);
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut bytes = clean_png().as_bytes();
+        bytes.extend_from_slice(&[0xab; 1024]);
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+        let findings = scan(&png);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].chunk_index, 3);
+        assert_eq!(
+            reasons(&png, Severity::High),
+            ["1024 trailing bytes after IEND"]
+        );
+    }
+
+    #[test]
+    fn test_opaque_binary_data() {
+        let png = png_with(vec![chunk("ruSt", &[0x13, 0xff, 0xfe, 0x80])]);
+        assert_eq!(
+            reasons(&png, Severity::High),
+            ["ruSt holds 4 bytes that are neither UTF-8 text nor a known binary format"]
+        );
+        let png = png_with(vec![chunk("ruSt", &[0x1f, 0x8b, 0xff, 0xfe])]);
+        assert!(reasons(&png, Severity::High).is_empty());
+    }
+}
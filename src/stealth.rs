@@ -0,0 +1,294 @@
+//! `encode --stealth`: a payload in a few small chunks of types other programs write,
+//! instead of one chunk of the type asked for. each part is encrypted on its own, with
+//! a salt and nonce of its own, and [`MAGIC`], the id of the payload, the index of the
+//! part and how many there are only sit inside the ciphertext. the `PSEC` header with
+//! the version and the Argon2 parameters is left off, so a part is the salt, the nonce
+//! and the ciphertext and nothing outside of it ties the parts together. decode needs
+//! --kdf-params again where encode was given them. parts are of random sizes, at random
+//! places between IHDR and IEND, and of a type each, as two chunks of one private type
+//! are what scan notices. decode tries every unknown ancillary chunk with the password.
+//!
+//! `encode --noise` adds chunks of random bytes the same way, of these types and at
+//! random places, and after the cleartext a sealed part starts with, so a listing
+//! doesn't tell them from the real ones
+
+use std::ops::RangeInclusive;
+
+use crate::{
+    chunk::Chunk, chunk_type::ChunkType, crypto, error::PngSecretError, payload::Part, png::Png,
+    Result,
+};
+
+/// private types Fireworks, ImageMagick and Microsoft Office write, ancillary and
+/// unregistered, so decoders skip them
+pub const TYPES: [&str; 9] = [
+    "mkBF", "mkBS", "mkBT", "mkTS", "prVW", "vpAg", "caNv", "cmOD", "msOG",
+];
+/// what the plaintext of a part starts with, a chunk that opens with the password but
+/// holds some other payload isn't taken for a part
+const MAGIC: &[u8; 4] = b"pSCs";
+/// the magic, the id, the index and the total in front of the body of a part
+const HEADER_SIZE: usize = MAGIC.len() + 8 + 4 + 4;
+/// the size parts are aimed at, a bigger payload gets more of them while types are free
+const PART_SIZE: usize = 4096;
+/// a payload is spread over at least this many chunks, if it has the bytes
+const MIN_PARTS: usize = 3;
+/// the sizes of noise chunks, about those of the parts
+pub const NOISE_SIZES: RangeInclusive<usize> = 256..=PART_SIZE;
+
+/// the types of [`TYPES`] that aren't in `png` yet
+pub fn free_types(png: &Png) -> Vec<ChunkType> {
+    TYPES
+        .iter()
+        .filter(|name| png.chunk_by_type(name).is_none())
+        .map(|name| name.parse().unwrap())
+        .collect()
+}
+
+/// how many parts `length` bytes are spread over with `free` types left
+pub fn part_count(length: usize, free: usize) -> Result<usize> {
+    if free == 0 {
+        return Err(PngSecretError::Usage(format!(
+            "The file has a chunk of every stealth type already: {}",
+            TYPES.join(", ")
+        )));
+    }
+    Ok(length
+        .div_ceil(PART_SIZE)
+        .max(MIN_PARTS)
+        .min(free)
+        .min(length.max(1)))
+}
+
+/// the plaintexts of `count` parts of `payload`, of random sizes, each with the header
+/// that links it to the others
+pub fn parts(payload: &[u8], count: usize) -> Result<Vec<Vec<u8>>> {
+    let id: [u8; 8] = crypto::random_bytes()?;
+    let weights: Vec<usize> = crypto::random_bytes::<{ TYPES.len() }>()?
+        .iter()
+        .take(count)
+        .map(|&weight| 64 + usize::from(weight))
+        .collect();
+    let total: usize = weights.iter().sum();
+    let mut start = 0;
+    let mut sum = 0;
+    let parts = weights
+        .iter()
+        .enumerate()
+        .map(|(index, weight)| {
+            sum += weight;
+            let end = payload.len() * sum / total;
+            let mut part = MAGIC.to_vec();
+            part.extend_from_slice(&id);
+            part.extend_from_slice(&(index as u32).to_be_bytes());
+            part.extend_from_slice(&(count as u32).to_be_bytes());
+            part.extend_from_slice(&payload[start..end]);
+            start = end;
+            part
+        })
+        .collect();
+    Ok(parts)
+}
+
+/// the part in the plaintext of a chunk, none when it isn't one
+pub fn part(plaintext: &[u8]) -> Option<Part<'_>> {
+    if !plaintext.starts_with(MAGIC) || plaintext.len() < HEADER_SIZE {
+        return None;
+    }
+    let field = |at: usize| u32::from_be_bytes(plaintext[at..at + 4].try_into().unwrap());
+    let part = Part {
+        id: plaintext[4..12].try_into().unwrap(),
+        index: field(12),
+        total: field(16),
+        body: &plaintext[HEADER_SIZE..],
+    };
+    (part.index < part.total).then_some(part)
+}
+
+/// where a chunk can go: after IHDR, before IEND, and neither between two IDAT chunks
+/// nor inside an animation frame
+fn places(png: &Png) -> Vec<usize> {
+    let chunks = png.chunks();
+    let end = png.iend_index().unwrap_or(chunks.len());
+    let idat = |index: usize| chunks[index].chunk_type().to_string() == "IDAT";
+    (png.ihdr_index().map_or(0, |ihdr| ihdr + 1)..=end)
+        .filter(|&index| index == 0 || index == chunks.len() || !(idat(index - 1) && idat(index)))
+        .filter(|&index| png.frame_at(index).is_none())
+        .collect()
+}
+
+/// adds a chunk for each of `data` to `png`, of a free type each, at random places,
+/// and pushes their indexes to `added`, whose indexes keep pointing at the same chunks
+pub fn spread(
+    png: &mut Png,
+    data: Vec<Vec<u8>>,
+    random: &mut Random,
+    added: &mut Vec<usize>,
+) -> Result<()> {
+    let mut free = free_types(png);
+    if free.len() < data.len() {
+        return Err(PngSecretError::Usage(format!(
+            "{} chunks need as many stealth chunk types, only {} are free: {}",
+            data.len(),
+            free.len(),
+            TYPES.join(", ")
+        )));
+    }
+    for data in data {
+        let chunk_type = free.remove(random.below(free.len()));
+        let places = places(png);
+        let index = places[random.below(places.len())];
+        png.insert_chunk_at(index, Chunk::new(chunk_type, &data))?;
+        for added in added.iter_mut().filter(|added| **added >= index) {
+            *added += 1;
+        }
+        added.push(index);
+    }
+    Ok(())
+}
+
+/// the data of `count` noise chunks of a size in [`NOISE_SIZES`] each, laid out like a
+/// sealed part: `header`, the cleartext every part starts with, then random bytes where
+/// a part has its salt, nonce and ciphertext
+pub fn noise(count: usize, random: &mut Random, header: &[u8]) -> Vec<Vec<u8>> {
+    let sizes = NOISE_SIZES.end() - NOISE_SIZES.start() + 1;
+    (0..count)
+        .map(|_| {
+            let size = NOISE_SIZES.start() + random.below(sizes);
+            let mut data = header.to_vec();
+            data.extend(random.bytes(size.saturating_sub(header.len())));
+            data
+        })
+        .collect()
+}
+
+/// where the sizes, types and places are picked from. splitmix64, seeded from the
+/// system or from `--seed` for the same noise every time. what is encrypted takes its
+/// salts and nonces from the system still, unless --deterministic is given too
+pub struct Random(u64);
+impl Random {
+    pub fn new(seed: Option<&str>) -> Result<Random> {
+        let seed = match seed {
+            Some(seed) => crypto::sha256(seed.as_bytes())[..8].try_into().unwrap(),
+            None => crypto::random_bytes()?,
+        };
+        Ok(Random(u64::from_be_bytes(seed)))
+    }
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    /// a number below `bound`, which isn't 0
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+    pub fn bytes(&mut self, length: usize) -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..length.div_ceil(8))
+            .flat_map(|_| self.next().to_be_bytes())
+            .collect();
+        bytes.truncate(length);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload;
+
+    fn png() -> Png {
+        let chunk = |chunk_type: &str| Chunk::new(chunk_type.parse().unwrap(), b"");
+        Png::from_chunks(vec![
+            chunk("IHDR"),
+            chunk("IDAT"),
+            chunk("IDAT"),
+            chunk("tEXt"),
+            chunk("IEND"),
+        ])
+    }
+
+    #[test]
+    fn test_parts() {
+        let payload: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        assert_eq!(part_count(payload.len(), TYPES.len()).unwrap(), 3);
+        assert_eq!(part_count(100_000, TYPES.len()).unwrap(), TYPES.len());
+        assert_eq!(part_count(100_000, 2).unwrap(), 2);
+        assert_eq!(part_count(2, TYPES.len()).unwrap(), 2);
+        assert_eq!(part_count(0, TYPES.len()).unwrap(), 1);
+        assert!(part_count(10, 0).is_err());
+
+        let plaintexts = parts(&payload, 5).unwrap();
+        assert_eq!(plaintexts.len(), 5);
+        let mut parsed: Vec<Part> = plaintexts.iter().map(|p| part(p).unwrap()).collect();
+        assert!(parsed
+            .iter()
+            .all(|part| part.id == parsed[0].id && part.total == 5));
+        assert!(parsed.iter().all(|part| !part.body.is_empty()));
+        parsed.reverse();
+        assert_eq!(payload::join(&parsed).unwrap(), payload);
+        // another payload has another id
+        let other = parts(&payload, 5).unwrap();
+        assert_ne!(part(&other[0]).unwrap().id, parsed[0].id);
+
+        assert_eq!(part(b"pSCs"), None);
+        assert_eq!(part(&payload), None);
+        let mut past_the_end = plaintexts[0].clone();
+        past_the_end[12..16].copy_from_slice(&5_u32.to_be_bytes());
+        assert_eq!(part(&past_the_end), None);
+    }
+
+    #[test]
+    fn test_spread() {
+        assert_eq!(places(&png()), vec![1, 3, 4]);
+        let mut random = Random::new(None).unwrap();
+        for _ in 0..10 {
+            let mut png = png();
+            let sealed: Vec<Vec<u8>> = (0..4).map(|part| vec![part; 10]).collect();
+            // the tEXt chunk is followed as the others go in
+            let mut added = vec![3];
+            spread(&mut png, sealed, &mut random, &mut added).unwrap();
+            assert_eq!(added.len(), 5);
+            assert_eq!(png.chunks()[added[0]].chunk_type().to_string(), "tEXt");
+            for (part, &index) in added[1..].iter().enumerate() {
+                let chunk = &png.chunks()[index];
+                assert!(TYPES.contains(&&*chunk.chunk_type().to_string()));
+                assert_eq!(chunk.data(), [part as u8; 10]);
+            }
+            let names: Vec<String> = png
+                .chunks()
+                .iter()
+                .map(|chunk| chunk.chunk_type().to_string())
+                .collect();
+            assert_eq!(names.first().unwrap(), "IHDR");
+            assert_eq!(names.last().unwrap(), "IEND");
+            let idat = names.iter().position(|name| name == "IDAT").unwrap();
+            assert_eq!(names[idat + 1], "IDAT");
+            assert_eq!(free_types(&png).len(), TYPES.len() - 4);
+        }
+        let mut png = png();
+        let mut added = Vec::new();
+        spread(&mut png, vec![vec![]; TYPES.len()], &mut random, &mut added).unwrap();
+        assert!(free_types(&png).is_empty());
+        assert!(spread(&mut png, vec![vec![]], &mut random, &mut added).is_err());
+    }
+
+    #[test]
+    fn test_noise() {
+        let noise = |seed| noise(4, &mut Random::new(seed).unwrap(), &[]);
+        assert_eq!(noise(Some("7")), noise(Some("7")));
+        assert_ne!(noise(Some("7")), noise(Some("8")));
+        assert_ne!(noise(None), noise(None));
+        let chunks = noise(Some("7"));
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|data| NOISE_SIZES.contains(&data.len())));
+        assert!(chunks[1..].iter().any(|data| data.len() != chunks[0].len()));
+        let shaped = super::noise(4, &mut Random::new(Some("7")).unwrap(), &[2]);
+        assert!(shaped.iter().all(|data| data[0] == 2));
+        assert!(shaped.iter().all(|data| NOISE_SIZES.contains(&data.len())));
+        // splitmix64 seeded with 0, as its reference implementation gives it
+        assert_eq!(Random(0).next(), 0xe220_a839_7b1d_cdaf);
+    }
+}
@@ -0,0 +1,230 @@
+//! copying a png chunk by chunk from a reader to a writer, for files too big to
+//! hold in memory. only the length and type of a chunk are looked at, its data
+//! goes through a small buffer
+
+use std::io::{self, BufWriter, Read, Write};
+
+use crate::{
+    chunk::{Chunk, CrcWriter},
+    chunk_type::ChunkType,
+    error::{Location, PngSecretError},
+    png::Png,
+};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// where `insert_before_iend` put the chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inserted {
+    pub index: usize,
+    pub offset: usize,
+}
+
+/// copies the png in `reader` to `writer` with `chunks` before its IEND, or at
+/// the end without one. crcs are checked on the way, like reading the whole file does
+pub fn insert_before_iend(
+    mut reader: impl Read,
+    writer: impl Write,
+    chunks: &[Chunk],
+) -> Result<Inserted, PngSecretError> {
+    let mut writer = BufWriter::new(writer);
+    let mut signature = [0; 8];
+    if reader.read_exact(&mut signature).is_err() || signature != Png::STANDARD_HEADER {
+        return Err(PngSecretError::NotAPng { path: None });
+    }
+    writer.write_all(&signature)?;
+    let (mut index, mut offset) = (0, signature.len());
+    let mut inserted = None;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let mut header = [0; 8];
+        let read = read_up_to(&mut reader, &mut header)?;
+        // the input ends on a chunk boundary
+        if read == 0 {
+            break;
+        }
+        if read < header.len() {
+            return Err(truncated(
+                index,
+                offset,
+                io::ErrorKind::UnexpectedEof.into(),
+            ));
+        }
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&header[4..]).unwrap())
+            .map_err(|error| PngSecretError::from(error).at(index, offset))?;
+        if inserted.is_none() && chunk_type.to_string() == "IEND" {
+            inserted = Some(Inserted { index, offset });
+            offset += write_chunks(&mut writer, chunks)?;
+            index += chunks.len();
+        }
+
+        let truncated = |error| truncated(index, offset, error);
+        writer.write_all(&header[..4])?;
+        let mut data_writer = CrcWriter::new(&mut writer);
+        data_writer.write_all(&header[4..])?;
+        let mut left = length;
+        while left > 0 {
+            let part = &mut buffer[..left.min(BUFFER_SIZE)];
+            reader.read_exact(part).map_err(truncated)?;
+            data_writer.write_all(part)?;
+            left -= part.len();
+        }
+        let mut crc = [0; 4];
+        reader.read_exact(&mut crc).map_err(truncated)?;
+        let expected = data_writer.crc();
+        if u32::from_be_bytes(crc) != expected {
+            return Err(PngSecretError::CrcMismatch {
+                expected,
+                found: u32::from_be_bytes(crc),
+                at: Location {
+                    index: Some(index),
+                    chunk_type: Some(chunk_type.to_string()),
+                    offset: Some(offset),
+                    path: None,
+                },
+            });
+        }
+        writer.write_all(&crc)?;
+        index += 1;
+        offset += length + Chunk::OVERHEAD;
+        // what follows IEND goes through as it is, chunks or trailing data
+        if chunk_type.to_string() == "IEND" {
+            io::copy(&mut reader, &mut writer)?;
+            break;
+        }
+    }
+    // files without IEND get the chunks at the end
+    let inserted = match inserted {
+        Some(inserted) => inserted,
+        None => {
+            write_chunks(&mut writer, chunks)?;
+            Inserted { index, offset }
+        }
+    };
+    writer.flush()?;
+    Ok(inserted)
+}
+
+/// the input ended inside the chunk at `index`, other read errors stay what they are
+fn truncated(index: usize, offset: usize, error: io::Error) -> PngSecretError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => PngSecretError::Truncated {
+            at: Location {
+                index: Some(index),
+                offset: Some(offset),
+                ..Location::default()
+            },
+        },
+        _ => error.into(),
+    }
+}
+fn write_chunks(writer: &mut impl Write, chunks: &[Chunk]) -> io::Result<usize> {
+    let mut written = 0;
+    for chunk in chunks {
+        let bytes = chunk.as_bytes();
+        writer.write_all(&bytes)?;
+        written += bytes.len();
+    }
+    Ok(written)
+}
+
+/// fills `buffer` unless the input ends first, returns how much was read
+fn read_up_to(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        let chunk = |chunk_type: &str, data: &[u8]| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            // bigger than the buffer, so it goes through in parts
+            chunk("IDAT", &vec![7; BUFFER_SIZE * 2 + 5]),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_insert_before_iend() {
+        let png = testing_png();
+        let chunks = [
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"first"),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second"),
+        ];
+        let mut streamed = Vec::new();
+        let inserted = insert_before_iend(&png.as_bytes()[..], &mut streamed, &chunks).unwrap();
+
+        let mut expected = testing_png();
+        for (offset, chunk) in chunks.iter().enumerate() {
+            expected.insert_chunk_at(2 + offset, chunk.clone()).unwrap();
+        }
+        assert_eq!(streamed, expected.as_bytes());
+        assert_eq!(
+            inserted,
+            Inserted {
+                index: 2,
+                offset: expected.chunk_offset(2)
+            }
+        );
+
+        // without an IEND they go at the end
+        let mut chunks_only = testing_png();
+        chunks_only.remove_chunk("IEND").unwrap();
+        let mut streamed = Vec::new();
+        let inserted =
+            insert_before_iend(&chunks_only.as_bytes()[..], &mut streamed, &chunks[..1]).unwrap();
+        assert_eq!(inserted.index, 2);
+        assert!(streamed.ends_with(&chunks[0].as_bytes()));
+
+        // what follows IEND comes through as it was
+        let mut trailing = png.as_bytes();
+        trailing.extend_from_slice(&[0xab; 1024]);
+        let mut streamed = Vec::new();
+        insert_before_iend(&trailing[..], &mut streamed, &chunks[..1]).unwrap();
+        assert_eq!(streamed.len(), trailing.len() + chunks[0].as_bytes().len());
+        assert!(streamed.ends_with(&[0xab; 1024]));
+    }
+
+    #[test]
+    fn test_insert_before_iend_errors() {
+        let bytes = testing_png().as_bytes();
+        let insert = |bytes: &[u8]| insert_before_iend(bytes, io::sink(), &[]);
+        assert!(matches!(
+            insert(b"not a png"),
+            Err(PngSecretError::NotAPng { .. })
+        ));
+        // inside the IDAT data
+        assert!(matches!(
+            insert(&bytes[..bytes.len() - 40]),
+            Err(PngSecretError::Truncated { at }) if at.index == Some(1) && at.offset == Some(33)
+        ));
+        let mut bad_crc = bytes.clone();
+        bad_crc[50] ^= 0xff;
+        assert!(matches!(
+            insert(&bad_crc),
+            Err(PngSecretError::CrcMismatch { at, .. }) if at.index == Some(1)
+        ));
+        let mut bad_type = bytes.clone();
+        bad_type[12] = b'1';
+        assert!(matches!(
+            insert(&bad_type),
+            Err(PngSecretError::InvalidChunkType { at, .. }) if at.index == Some(0)
+        ));
+    }
+}
@@ -0,0 +1,413 @@
+//! the text chunks of the png spec, keyword and text pairs that other tools
+//! read. a tEXt chunk is the Latin-1 keyword, a NUL and the Latin-1 text. a
+//! zTXt chunk is the keyword, a NUL, a compression method byte, always 0, and
+//! the text as a zlib stream. an iTXt chunk is the keyword, a NUL, a
+//! compression flag and method, the language tag, a NUL, the UTF-8 translated
+//! keyword, a NUL and the UTF-8 text, a zlib stream when the flag is 1
+
+use crate::{deflate, error::PngSecretError, payload::MAX_DECOMPRESSED, Result};
+
+pub const TEXT: &str = "tEXt";
+pub const ZTXT: &str = "zTXt";
+pub const ITXT: &str = "iTXt";
+
+/// the keyword and text of a text chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text {
+    pub keyword: String,
+    pub text: String,
+    /// the size of the text compressed, for a compressed one
+    pub compressed_size: Option<usize>,
+    /// the language tag of an iTXt chunk, empty when it doesn't say
+    pub language: Option<String>,
+    /// the keyword of an iTXt chunk in its language
+    pub translated_keyword: Option<String>,
+}
+
+/// whether chunks of `chunk_type` are text chunks
+pub fn is_text(chunk_type: &str) -> bool {
+    [TEXT, ZTXT, ITXT].contains(&chunk_type)
+}
+
+/// `keyword` if the spec allows it: 1 to 79 printable Latin-1 characters,
+/// without leading, trailing or consecutive spaces
+pub fn keyword(keyword: &str) -> std::result::Result<String, String> {
+    let length = keyword.chars().count();
+    if !(1..=79).contains(&length) {
+        return Err(format!("A keyword has 1 to 79 characters, not {}", length));
+    }
+    if let Some(c) = keyword
+        .chars()
+        .find(|&c| !matches!(c as u32, 32..=126 | 161..=255))
+    {
+        return Err(format!(
+            "A keyword has only printable Latin-1 characters, not {:?}",
+            c
+        ));
+    }
+    if keyword.starts_with(' ') || keyword.ends_with(' ') || keyword.contains("  ") {
+        return Err("A keyword has no leading, trailing or consecutive spaces".to_string());
+    }
+    Ok(keyword.to_string())
+}
+
+/// the data of a tEXt chunk with `text` under `keyword`
+pub fn text_data(keyword: &str, text: &str) -> Result<Vec<u8>> {
+    let mut data = latin1(&self::keyword(keyword).map_err(PngSecretError::Usage)?)?;
+    data.push(0);
+    if text.contains('\0') {
+        return Err(PngSecretError::Usage(
+            "The text of a tEXt chunk can't hold a NUL".into(),
+        ));
+    }
+    data.extend(
+        latin1(text).map_err(|e| PngSecretError::Usage(format!("{}, iTXt holds any text", e)))?,
+    );
+    Ok(data)
+}
+
+/// `language` if it is a language tag like en or en-US, hyphen separated parts of 1 to 8
+/// letters and digits, or empty for an unknown language
+pub fn language(language: &str) -> std::result::Result<String, String> {
+    let valid = |part: &str| {
+        (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+    match language.is_empty() || language.split('-').all(valid) {
+        true => Ok(language.to_string()),
+        false => Err(format!(
+            "{:?} is not a language tag like en or en-US",
+            language
+        )),
+    }
+}
+
+/// the data of a zTXt chunk with `text` compressed under `keyword`
+pub fn ztxt_data(keyword: &str, text: &str) -> Result<Vec<u8>> {
+    let mut data = latin1(&self::keyword(keyword).map_err(PngSecretError::Usage)?)?;
+    data.extend([0, 0]);
+    let text =
+        latin1(text).map_err(|e| PngSecretError::Usage(format!("{}, iTXt holds any text", e)))?;
+    data.extend(deflate::compress(&text, 9));
+    Ok(data)
+}
+
+/// the data of an iTXt chunk with the UTF-8 `text` under `keyword`, deflated at the
+/// `compress` level if there is one
+pub fn itxt_data(
+    keyword: &str,
+    language: &str,
+    translated_keyword: &str,
+    text: &str,
+    compress: Option<u8>,
+) -> Result<Vec<u8>> {
+    let mut data = latin1(&self::keyword(keyword).map_err(PngSecretError::Usage)?)?;
+    data.extend([0, compress.is_some() as u8, 0]);
+    data.extend(
+        self::language(language)
+            .map_err(PngSecretError::Usage)?
+            .as_bytes(),
+    );
+    data.push(0);
+    if translated_keyword.contains('\0') {
+        return Err(PngSecretError::Usage(
+            "A translated keyword can't hold a NUL".into(),
+        ));
+    }
+    data.extend(translated_keyword.as_bytes());
+    data.push(0);
+    match compress {
+        Some(level) => data.extend(deflate::compress(text.as_bytes(), level)),
+        None => data.extend(text.as_bytes()),
+    }
+    Ok(data)
+}
+
+/// the keyword and text in the data of a `chunk_type` chunk
+pub fn parse(chunk_type: &str, data: &[u8]) -> Result<Text> {
+    match chunk_type {
+        TEXT => parse_text(data),
+        ZTXT => parse_ztxt(data),
+        ITXT => parse_itxt(data),
+        _ => Err(PngSecretError::Usage(format!(
+            "{} is not a text chunk type",
+            chunk_type
+        ))),
+    }
+}
+/// the keyword in the data of a text chunk, none when it has no valid one
+pub fn keyword_of(data: &[u8]) -> Option<String> {
+    let (keyword, _) = split_nul(data)?;
+    self::keyword(&from_latin1(keyword)).ok()
+}
+
+/// the keyword and text in the data of a tEXt chunk
+pub fn parse_text(data: &[u8]) -> Result<Text> {
+    let (keyword, text) = split_keyword(TEXT, data)?;
+    Ok(Text {
+        keyword,
+        text: from_latin1(text),
+        compressed_size: None,
+        language: None,
+        translated_keyword: None,
+    })
+}
+/// the keyword and the inflated text in the data of a zTXt chunk
+pub fn parse_ztxt(data: &[u8]) -> Result<Text> {
+    let (keyword, rest) = split_keyword(ZTXT, data)?;
+    let (&method, stream) = rest.split_first().ok_or_else(|| {
+        PngSecretError::InvalidData(format!(
+            "The zTXt chunk '{}' has no compression method",
+            keyword
+        ))
+    })?;
+    if method != 0 {
+        return Err(PngSecretError::InvalidData(format!(
+            "The zTXt chunk '{}' uses compression method {}, only 0 exists",
+            keyword, method
+        )));
+    }
+    let text = inflate(ZTXT, &keyword, stream)?;
+    Ok(Text {
+        keyword,
+        text: from_latin1(&text),
+        compressed_size: Some(stream.len()),
+        language: None,
+        translated_keyword: None,
+    })
+}
+/// the fields in the data of an iTXt chunk, with the text inflated if it is compressed
+pub fn parse_itxt(data: &[u8]) -> Result<Text> {
+    let (keyword, rest) = split_keyword(ITXT, data)?;
+    let [flag, method, rest @ ..] = rest else {
+        return Err(PngSecretError::InvalidData(format!(
+            "The iTXt chunk '{}' ends before its compression flag and method",
+            keyword
+        )));
+    };
+    let missing = |field: &str| {
+        PngSecretError::InvalidData(format!(
+            "The iTXt chunk '{}' has no NUL after its {}",
+            keyword, field
+        ))
+    };
+    let (language, rest) = split_nul(rest).ok_or_else(|| missing("language tag"))?;
+    let (translated_keyword, stream) =
+        split_nul(rest).ok_or_else(|| missing("translated keyword"))?;
+    let text = match (flag, method) {
+        (0, _) => stream.to_vec(),
+        (1, 0) => inflate(ITXT, &keyword, stream)?,
+        (1, method) => {
+            return Err(PngSecretError::InvalidData(format!(
+                "The iTXt chunk '{}' uses compression method {}, only 0 exists",
+                keyword, method
+            )))
+        }
+        (flag, _) => {
+            return Err(PngSecretError::InvalidData(format!(
+                "The iTXt chunk '{}' has compression flag {}, not 0 or 1",
+                keyword, flag
+            )))
+        }
+    };
+    let utf8 = |bytes: &[u8], field: &str| {
+        String::from_utf8(bytes.to_vec()).map_err(|_| {
+            PngSecretError::InvalidData(format!(
+                "The {} of the iTXt chunk '{}' is not UTF-8",
+                field, keyword
+            ))
+        })
+    };
+    Ok(Text {
+        text: utf8(&text, "text")?,
+        compressed_size: (*flag == 1).then_some(stream.len()),
+        // a tag is ASCII, so a bad one still shows as it is
+        language: Some(from_latin1(language)),
+        translated_keyword: Some(utf8(translated_keyword, "translated keyword")?),
+        keyword,
+    })
+}
+/// the keyword of a text chunk and the data after its NUL
+fn split_keyword<'a>(chunk_type: &str, data: &'a [u8]) -> Result<(String, &'a [u8])> {
+    let (keyword, rest) = split_nul(data).ok_or_else(|| {
+        PngSecretError::InvalidData(format!(
+            "The {} chunk has no NUL after its keyword",
+            chunk_type
+        ))
+    })?;
+    let keyword = self::keyword(&from_latin1(keyword)).map_err(PngSecretError::InvalidData)?;
+    Ok((keyword, rest))
+}
+/// the bytes before the first NUL and the bytes after it
+fn split_nul(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let separator = data.iter().position(|&byte| byte == 0)?;
+    Some((&data[..separator], &data[separator + 1..]))
+}
+fn inflate(chunk_type: &str, keyword: &str, stream: &[u8]) -> Result<Vec<u8>> {
+    deflate::decompress(stream, MAX_DECOMPRESSED).map_err(|e| {
+        PngSecretError::InvalidData(format!(
+            "The text of the {} chunk '{}' is not a valid zlib stream: {}",
+            chunk_type, keyword, e
+        ))
+    })
+}
+
+fn latin1(text: &str) -> Result<Vec<u8>> {
+    text.chars()
+        .map(|c| {
+            u8::try_from(c)
+                .map_err(|_| PngSecretError::Usage(format!("{:?} is not a Latin-1 character", c)))
+        })
+        .collect()
+}
+pub fn from_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+/// the bytes the text of a `chunk_type` chunk was read from, UTF-8 for iTXt and Latin-1
+/// for the others, whose characters all fit a byte
+pub fn text_bytes(chunk_type: &str, text: &str) -> Vec<u8> {
+    match chunk_type {
+        ITXT => text.as_bytes().to_vec(),
+        _ => text.chars().map(|c| c as u8).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword() {
+        assert!(keyword("Comment").is_ok());
+        assert!(keyword("Creation Time").is_ok());
+        assert!(keyword("Café").is_ok());
+        assert!(keyword("").is_err());
+        assert!(keyword(&"k".repeat(80)).is_err());
+        assert!(keyword(&"k".repeat(79)).is_ok());
+        assert!(keyword(" Comment").is_err());
+        assert!(keyword("Comment ").is_err());
+        assert!(keyword("Two  spaces").is_err());
+        assert!(keyword("tab\there").is_err());
+        assert!(keyword("snow☃").is_err());
+    }
+
+    #[test]
+    fn test_text_data() {
+        assert_eq!(
+            text_data("Comment", "Hello, world").unwrap(),
+            b"Comment\0Hello, world"
+        );
+        // Latin-1, not UTF-8
+        assert_eq!(text_data("Author", "José").unwrap(), b"Author\0Jos\xe9");
+        assert!(text_data("Comment", "snow☃").is_err());
+        assert!(text_data("Comment", "a\0b").is_err());
+        assert_eq!(
+            parse_text(b"Author\0Jos\xe9").unwrap(),
+            Text {
+                keyword: "Author".to_string(),
+                text: "José".to_string(),
+                compressed_size: None,
+                language: None,
+                translated_keyword: None,
+            }
+        );
+        assert!(parse_text(b"no separator").is_err());
+        assert!(parse_text(b" bad\0keyword").is_err());
+    }
+
+    #[test]
+    fn test_ztxt() {
+        let text = "line of text that repeats\n".repeat(4000);
+        let data = ztxt_data("Description", &text).unwrap();
+        assert!(data.len() < text.len() / 20);
+        assert!(data.starts_with(b"Description\0\0"));
+        let parsed = parse(ZTXT, &data).unwrap();
+        assert_eq!(
+            (parsed.keyword.as_str(), parsed.text == text),
+            ("Description", true)
+        );
+        assert_eq!(parsed.compressed_size, Some(data.len() - 13));
+        assert_eq!(keyword_of(&data).as_deref(), Some("Description"));
+        assert!(parse("teXt", &data).is_err());
+    }
+
+    #[test]
+    fn test_ztxt_fixture() {
+        // written by zlib at its default level, as libpng based tools do
+        let fixture = [
+            67, 111, 109, 109, 101, 110, 116, 0, 0, 120, 156, 243, 72, 205, 201, 201, 87, 72, 43,
+            202, 207, 85, 168, 202, 201, 76, 210, 81, 240, 32, 32, 0, 0, 176, 195, 17, 100,
+        ];
+        let parsed = parse_ztxt(&fixture).unwrap();
+        assert_eq!(parsed.keyword, "Comment");
+        assert_eq!(
+            parsed.text,
+            "Hello from zlib, ".repeat(3).trim_end_matches(", ")
+        );
+
+        let mut corrupt = fixture.to_vec();
+        corrupt[14] ^= 0xff;
+        let error = parse_ztxt(&corrupt).unwrap_err().to_string();
+        assert!(
+            error.starts_with("The text of the zTXt chunk 'Comment' is not a valid zlib stream")
+        );
+        assert!(parse_ztxt(&fixture[..fixture.len() - 3]).is_err());
+        let mut method = fixture.to_vec();
+        method[8] = 1;
+        assert!(parse_ztxt(&method).is_err());
+    }
+
+    #[test]
+    fn test_itxt() {
+        let text = "Grüße aus Köln, ☃\n".repeat(500);
+        let data = itxt_data("Title", "de-DE", "Titel", &text, Some(9)).unwrap();
+        assert!(data.len() < text.len() / 10);
+        assert!(data.starts_with(b"Title\0\x01\0de-DE\0Titel\0\x78"));
+        let parsed = parse(ITXT, &data).unwrap();
+        assert_eq!(parsed.text, text);
+        assert_eq!(parsed.language.as_deref(), Some("de-DE"));
+        assert_eq!(parsed.translated_keyword.as_deref(), Some("Titel"));
+        assert!(parsed.compressed_size.is_some());
+
+        // all five fields can be empty but the keyword
+        let data = itxt_data("Comment", "", "", "", None).unwrap();
+        assert_eq!(data, b"Comment\0\0\0\0\0");
+        let parsed = parse_itxt(&data).unwrap();
+        assert_eq!((parsed.text.as_str(), parsed.compressed_size), ("", None));
+        assert_eq!(parsed.language.as_deref(), Some(""));
+
+        assert!(parse_itxt(b"Comment\0\0").is_err());
+        assert!(parse_itxt(b"Comment\0\0\0en").is_err());
+        assert!(parse_itxt(b"Comment\0\x02\0\0\0text").is_err());
+        assert!(parse_itxt(b"Comment\0\x01\0\0\0not zlib").is_err());
+        assert!(parse_itxt(b"Comment\0\0\0\0\0\xff").is_err());
+        assert!(itxt_data("Comment", "en_US", "", "", None).is_err());
+        assert!(language("en").is_ok());
+        assert!(language("x-klingon").is_ok());
+        assert!(language("en-").is_err());
+    }
+
+    #[test]
+    fn test_itxt_xmp() {
+        // the layout of the XMP packet exiftool and Adobe tools write, uncompressed
+        // with neither a language nor a translated keyword
+        let xmp = concat!(
+            "<?xpacket begin='\u{feff}' id='W5M0MpCehiHzreSzNTczkc9d'?>\n",
+            "<x:xmpmeta xmlns:x='adobe:ns:meta/'><rdf:RDF ",
+            "xmlns:rdf='http://www.w3.org/1999/02/22-rdf-syntax-ns#'>",
+            "<rdf:Description xmlns:dc='http://purl.org/dc/elements/1.1/'>",
+            "<dc:creator><rdf:Seq><rdf:li>Ana Müller</rdf:li></rdf:Seq></dc:creator>",
+            "</rdf:Description></rdf:RDF></x:xmpmeta>\n",
+            "<?xpacket end='w'?>",
+        );
+        let mut fixture = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+        fixture.extend(xmp.as_bytes());
+        let parsed = parse(ITXT, &fixture).unwrap();
+        assert_eq!(parsed.keyword, "XML:com.adobe.xmp");
+        assert_eq!(parsed.text, xmp);
+        assert!(parsed.text.contains("Ana Müller"));
+        assert_eq!(
+            itxt_data("XML:com.adobe.xmp", "", "", xmp, None).unwrap(),
+            fixture
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! the tIME chunk of the png spec, the last time the image changed: the year
+//! as a big endian u16, then the month, day, hour, minute and second in UTC
+
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::PngSecretError, Result};
+
+pub const TIME: &str = "tIME";
+/// the time reproducible builds stamp what they make with, in seconds since the epoch
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// a UTC time as a tIME chunk holds it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+impl Time {
+    /// the time in the data of a tIME chunk, an error for a field out of its range
+    pub fn parse(data: &[u8]) -> Result<Time> {
+        let &[high, low, month, day, hour, minute, second] = data else {
+            return Err(PngSecretError::InvalidData(format!(
+                "A tIME chunk has 7 bytes, not {}",
+                data.len()
+            )));
+        };
+        let time = Time {
+            year: u16::from_be_bytes([high, low]),
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        };
+        time.check()?;
+        Ok(time)
+    }
+    pub fn to_data(self) -> [u8; 7] {
+        let [high, low] = self.year.to_be_bytes();
+        [
+            high,
+            low,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        ]
+    }
+    /// now, or the time in $SOURCE_DATE_EPOCH when it is set, for reproducible builds
+    pub fn now() -> Result<Time> {
+        Time::now_or(std::env::var(SOURCE_DATE_EPOCH).ok())
+    }
+    fn now_or(epoch: Option<String>) -> Result<Time> {
+        if let Some(epoch) = epoch.filter(|epoch| !epoch.is_empty()) {
+            let seconds = epoch.parse().map_err(|_| {
+                PngSecretError::Usage(format!(
+                    "${} is {}, not a number of seconds",
+                    SOURCE_DATE_EPOCH, epoch
+                ))
+            })?;
+            return Time::from_unix(seconds);
+        }
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| PngSecretError::Failed("The system clock is before 1970".into()))?
+            .as_secs();
+        Time::from_unix(seconds as i64)
+    }
+    /// the time `seconds` after the epoch
+    pub fn from_unix(seconds: i64) -> Result<Time> {
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let time = seconds.rem_euclid(86400);
+        Ok(Time {
+            year: u16::try_from(year).map_err(|_| {
+                PngSecretError::Usage(format!("The year {} doesn't fit in a tIME chunk", year))
+            })?,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        })
+    }
+    fn check(&self) -> Result<()> {
+        let ranges = [
+            ("month", self.month, 1, 12),
+            ("day", self.day, 1, days_in_month(self.year, self.month)),
+            ("hour", self.hour, 0, 23),
+            ("minute", self.minute, 0, 59),
+            // a leap second
+            ("second", self.second, 0, 60),
+        ];
+        for (field, value, min, max) in ranges {
+            if !(min..=max).contains(&value) {
+                return Err(PngSecretError::InvalidData(format!(
+                    "The tIME chunk has {} {}, not {} to {}",
+                    field, value, min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+impl Display for Time {
+    /// ISO 8601 in UTC, like 2024-03-05T14:30:00Z
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// an RFC 3339 time like 2024-03-05T14:30:00Z or 2024-03-05T16:30:00.5+02:00, in UTC
+pub fn rfc3339(text: &str) -> std::result::Result<Time, String> {
+    let invalid = || {
+        format!(
+            "{:?} is not an RFC 3339 time like 2024-03-05T14:30:00Z",
+            text
+        )
+    };
+    let number = |part: &str| match part.chars().all(|c| c.is_ascii_digit()) {
+        true => part.parse::<i64>().map_err(|_| invalid()),
+        false => Err(invalid()),
+    };
+    let (date, time) = text
+        .split_once(['T', 't', ' '])
+        .filter(|(date, _)| date.len() == 10)
+        .ok_or_else(invalid)?;
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let at = time.rfind(['+', '-']).ok_or_else(invalid)?;
+            let (hours, minutes) = time[at + 1..].split_once(':').ok_or_else(invalid)?;
+            let offset = number(hours)? * 3600 + number(minutes)? * 60;
+            let sign = if time[at..].starts_with('-') { -1 } else { 1 };
+            (&time[..at], sign * offset)
+        }
+    };
+    // fractions of a second don't fit in a tIME chunk
+    let time = time.split_once('.').map_or(time, |(time, _)| time);
+    let date: Vec<i64> = date
+        .split('-')
+        .map(number)
+        .collect::<std::result::Result<_, _>>()?;
+    let time: Vec<i64> = time
+        .split(':')
+        .map(number)
+        .collect::<std::result::Result<_, _>>()?;
+    let (&[year, month, day], &[hour, minute, second]) = (&date[..], &time[..]) else {
+        return Err(invalid());
+    };
+    let parsed = Time {
+        year: u16::try_from(year).map_err(|_| invalid())?,
+        month: u8::try_from(month).map_err(|_| invalid())?,
+        day: u8::try_from(day).map_err(|_| invalid())?,
+        hour: u8::try_from(hour).map_err(|_| invalid())?,
+        minute: u8::try_from(minute).map_err(|_| invalid())?,
+        second: u8::try_from(second).map_err(|_| invalid())?,
+    };
+    parsed.check().map_err(|_| invalid())?;
+    if offset == 0 {
+        return Ok(parsed);
+    }
+    let days = days_from_civil(year, month, day);
+    Time::from_unix(days * 86400 + hour * 3600 + minute * 60 + second - offset)
+        .map_err(|e| e.to_string())
+}
+
+/// the year, month and day `days` after 1970-01-01 in the proleptic gregorian calendar
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+/// the days from 1970-01-01 to the date, the inverse of civil_from_days
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * mp + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+fn days_in_month(year: u16, month: u8) -> u8 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // the tIME data of an image last changed on 2024-03-05 at 14:30:09
+        let fixture = [0x07, 0xe8, 3, 5, 14, 30, 9];
+        let time = Time::parse(&fixture).unwrap();
+        assert_eq!(time.to_string(), "2024-03-05T14:30:09Z");
+        assert_eq!(time.to_data(), fixture);
+        assert!(Time::parse(&fixture[..6]).is_err());
+
+        let error = Time::parse(&[0x07, 0xe8, 13, 5, 14, 30, 9]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The tIME chunk has month 13, not 1 to 12"
+        );
+        assert!(Time::parse(&[0x07, 0xe7, 2, 29, 0, 0, 0]).is_err());
+        assert!(Time::parse(&[0x07, 0xe8, 2, 29, 0, 0, 0]).is_ok());
+        assert!(Time::parse(&[0x07, 0xe8, 1, 1, 24, 0, 0]).is_err());
+        assert!(Time::parse(&[0x07, 0xe8, 6, 30, 23, 59, 60]).is_ok());
+    }
+
+    #[test]
+    fn test_source_date_epoch() {
+        let time = Time::now_or(Some("1700000000".to_string())).unwrap();
+        assert_eq!(time.to_string(), "2023-11-14T22:13:20Z");
+        assert!(Time::now_or(Some("yesterday".to_string())).is_err());
+        assert_ne!(Time::now_or(Some(String::new())).unwrap(), time);
+        assert_ne!(Time::now_or(None).unwrap(), time);
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let time = rfc3339("2024-03-05T14:30:09Z").unwrap();
+        assert_eq!(time.to_data(), [0x07, 0xe8, 3, 5, 14, 30, 9]);
+        assert_eq!(
+            rfc3339("2024-03-05T16:30:09.25+02:00").unwrap(),
+            time,
+            "the offset is taken off"
+        );
+        assert_eq!(
+            rfc3339("2024-12-31T20:00:00-05:00").unwrap().to_string(),
+            "2025-01-01T01:00:00Z"
+        );
+        for invalid in [
+            "2024-03-05",
+            "2024-03-05T14:30Z",
+            "2024-13-05T14:30:09Z",
+            "2024-03-05T14:30:09",
+            "24-03-05T14:30:09Z",
+            "2024-03-05T14:30:+9Z",
+        ] {
+            assert!(rfc3339(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            Time::from_unix(1_700_000_000).unwrap().to_string(),
+            "2023-11-14T22:13:20Z"
+        );
+        assert_eq!(days_from_civil(2023, 11, 14), 1_700_000_000 / 86400);
+    }
+}
@@ -0,0 +1,109 @@
+//! javascript bindings, for a web page that reads and writes messages without the
+//! png leaving the browser. `wasm-pack build -- --no-default-features --features wasm`
+//! builds them without clap and the file handling of the cli. failures are thrown as
+//! js errors with the message of the error, nothing panics on bad input
+
+use wasm_bindgen::prelude::*;
+
+use crate::{chunk_type::ChunkType, error::PngSecretError, json::Value, png::Png, Result};
+
+fn js_error(error: PngSecretError) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+/// the png in `bytes` with `message` in a new chunk of `chunk_type` before its IEND
+#[wasm_bindgen]
+pub fn encode(
+    bytes: &[u8],
+    chunk_type: &str,
+    message: &[u8],
+) -> std::result::Result<Vec<u8>, JsError> {
+    encoded(bytes, chunk_type, message).map_err(js_error)
+}
+/// the data of the first chunk of `chunk_type` in the png in `bytes`, undefined
+/// without one
+#[wasm_bindgen]
+pub fn decode(bytes: &[u8], chunk_type: &str) -> std::result::Result<Option<Vec<u8>>, JsError> {
+    decoded(bytes, chunk_type).map_err(js_error)
+}
+/// the chunks of the png in `bytes` as a json array, with the fields of
+/// `pngsecret print --json --no-data`
+#[wasm_bindgen]
+pub fn list_chunks(bytes: &[u8]) -> std::result::Result<JsValue, JsError> {
+    chunks_json(bytes)
+        .map(|json| JsValue::from_str(&json))
+        .map_err(js_error)
+}
+
+fn encoded(bytes: &[u8], chunk_type: &str, message: &[u8]) -> Result<Vec<u8>> {
+    let mut png = Png::try_from(bytes)?;
+    crate::encode(&mut png, chunk_type.parse::<ChunkType>()?, message)?;
+    Ok(png.as_bytes())
+}
+fn decoded(bytes: &[u8], chunk_type: &str) -> Result<Option<Vec<u8>>> {
+    match crate::decode(&Png::try_from(bytes)?, chunk_type) {
+        Ok(data) => Ok(Some(data.to_vec())),
+        Err(PngSecretError::ChunkNotFound { .. }) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+fn chunks_json(bytes: &[u8]) -> Result<String> {
+    let png = Png::try_from(bytes)?;
+    let chunks = png.chunks().iter().enumerate().map(|(index, chunk)| {
+        let chunk_type = chunk.chunk_type();
+        Value::object([
+            ("index", index.into()),
+            ("type", chunk_type.to_string().into()),
+            ("length", chunk.length().into()),
+            ("crc", format!("{:08x}", chunk.crc()).into()),
+            ("offset", png.chunk_offset(index).into()),
+            ("critical", chunk_type.is_critical().into()),
+            ("public", chunk_type.is_public().into()),
+            ("safe_to_copy", chunk_type.is_safe_to_copy().into()),
+        ])
+    });
+    Ok(Value::Array(chunks.collect()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+
+    fn testing_png() -> Vec<u8> {
+        let chunk = |chunk_type: &str, data: &[u8]| Chunk::new(chunk_type.parse().unwrap(), data);
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+            chunk("IEND", &[]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let encoded = encoded(&testing_png(), "ruSt", b"hidden").unwrap();
+        assert_eq!(decoded(&encoded, "ruSt").unwrap().unwrap(), b"hidden");
+        assert_eq!(decoded(&encoded, "nOpE").unwrap(), None);
+
+        let chunks = crate::json::parse(&chunks_json(&encoded).unwrap()).unwrap();
+        let types: Vec<_> = chunks
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|chunk| chunk.get("type").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["IHDR", "ruSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |result: Result<_>| result.map(|_: Vec<u8>| ()).unwrap_err().to_string();
+        assert_eq!(
+            error(encoded(b"not a png", "ruSt", b"")),
+            "Invalid header, not a png file"
+        );
+        assert!(encoded(&testing_png(), "ru5t", b"").is_err());
+        assert!(decoded(b"", "ruSt").is_err());
+        assert!(chunks_json(&testing_png()[..20]).is_err());
+    }
+}
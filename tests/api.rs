@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+#[cfg(feature = "cli")]
+use pngsecret::args::{DecodeArgs, EncodeArgs, PrintArgs, RemoveArgs};
+use pngsecret::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    error::PngSecretError,
+    png::{Limits, Png},
+};
+
+fn testing_png() -> Png {
+    let chunk =
+        |chunk_type: &str, data: &[u8]| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data);
+    Png::from_chunks(vec![
+        chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+        chunk(
+            "IDAT",
+            &[120, 1, 1, 5, 0, 250, 255, 0, 0, 0, 0, 0, 0, 5, 0, 1],
+        ),
+        chunk("IEND", &[]),
+    ])
+}
+
+#[test]
+fn test_encode_decode() {
+    let mut png = testing_png();
+    let chunk_type = ChunkType::from_str("ruSt").unwrap();
+    assert_eq!(
+        pngsecret::encode(&mut png, chunk_type, b"hidden").unwrap(),
+        2
+    );
+    let bytes = png.as_bytes();
+
+    let png = Png::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(
+        png.chunks().last().unwrap().chunk_type().to_string(),
+        "IEND"
+    );
+    assert_eq!(pngsecret::decode(&png, "ruSt").unwrap(), b"hidden");
+    assert!(matches!(
+        pngsecret::decode(&png, "nOpE"),
+        Err(PngSecretError::ChunkNotFound { .. })
+    ));
+    let limited = Png::from_reader_with_limits(bytes.as_slice(), Limits::memory(64)).unwrap();
+    assert_eq!(limited.chunks().len(), 4);
+}
+
+/// the cli reads what the library wrote
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_decodes_library_output() {
+    let mut png = testing_png();
+    pngsecret::encode(
+        &mut png,
+        ChunkType::from_str("ruSt").unwrap(),
+        b"from the api",
+    )
+    .unwrap();
+    let path = std::env::temp_dir().join(format!("pngsecret-api-{}.png", std::process::id()));
+    std::fs::write(&path, png.as_bytes()).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pngsecret"))
+        .args(["decode", path.to_str().unwrap(), "ruSt", "-o", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"from the api");
+    std::fs::remove_file(path).unwrap();
+}
+
+/// the commands run in process, with arguments built without clap
+#[cfg(feature = "cli")]
+#[test]
+fn test_commands() {
+    let dir = std::env::temp_dir().join(format!("pngsecret-api-commands-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (path, decoded) = (dir.join("in.png"), dir.join("decoded"));
+    std::fs::write(&path, testing_png().as_bytes()).unwrap();
+    let chunk_type = ChunkType::from_str("ruSt").unwrap();
+
+    // a message like an option stays the message
+    pngsecret::commands::encode(&EncodeArgs::new(&path, chunk_type.clone(), "--secret")).unwrap();
+    let decode = DecodeArgs {
+        output: Some(decoded.clone()),
+        ..DecodeArgs::new(&path, chunk_type.clone())
+    };
+    pngsecret::commands::decode(&decode).unwrap();
+    assert_eq!(std::fs::read(&decoded).unwrap(), b"--secret");
+
+    let print = PrintArgs {
+        types: vec![chunk_type.clone()],
+        json: true,
+        ..PrintArgs::new(&path)
+    };
+    assert!(pngsecret::commands::print(&print)
+        .unwrap()
+        .to_string()
+        .contains("ruSt"));
+
+    pngsecret::commands::remove(&RemoveArgs::new(&path, chunk_type.clone())).unwrap();
+    let png = Png::try_from(std::fs::read(&path).unwrap().as_slice()).unwrap();
+    assert_eq!(png.as_bytes(), testing_png().as_bytes());
+    assert!(pngsecret::commands::decode(&decode).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
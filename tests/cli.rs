@@ -0,0 +1,413 @@
+// runs the binary, which needs the cli feature
+#![cfg(feature = "cli")]
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+const CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut bytes = (data.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(chunk_type);
+    bytes.extend_from_slice(data);
+    let crc = CRC.checksum(&bytes[4..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    bytes
+}
+
+fn testing_png() -> Vec<u8> {
+    let mut bytes = vec![137, 80, 78, 71, 13, 10, 26, 10];
+    bytes.extend(chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]));
+    bytes.extend(chunk(
+        b"IDAT",
+        &[120, 1, 1, 5, 0, 250, 255, 0, 0, 0, 0, 0, 0, 5, 0, 1],
+    ));
+    bytes.extend(chunk(b"IEND", &[]));
+    bytes
+}
+
+/// runs the binary with `stdin` piped in
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    run_with_env(args, stdin, &[])
+}
+/// like `run`, with PNGSECRET_PASSWORD taken from `env` only
+fn run_with_env(args: &[&str], stdin: &[u8], env: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pngsecret"))
+        .args(args)
+        .env_remove("PNGSECRET_PASSWORD")
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_stdin_stdout_pipeline() {
+    let encoded = run(
+        &["encode", "-", "ruSt", "piped message", "-"],
+        &testing_png(),
+    );
+    assert!(encoded.status.success());
+    assert!(encoded.stdout.starts_with(&[137, 80, 78, 71]));
+
+    let stripped = run(&["strip", "-", "--keep", "ruSt"], &encoded.stdout);
+    assert!(stripped.status.success());
+    // the status lines must not end up in the file
+    assert!(String::from_utf8_lossy(&stripped.stderr).contains("removed 0 chunk(s)"));
+    assert_eq!(stripped.stdout, encoded.stdout);
+
+    let decoded = run(&["decode", "-", "ruSt"], &stripped.stdout);
+    assert!(decoded.status.success());
+    assert_eq!(decoded.stdout, b"piped message\n");
+
+    let raw = run(&["decode", "-", "ruSt", "-o", "-"], &stripped.stdout);
+    assert_eq!(raw.stdout, b"piped message");
+}
+
+#[test]
+fn test_stdin_not_a_png() {
+    let output = run(&["list", "-"], b"definitely not a png");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid header"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let file = std::env::temp_dir().join(format!(
+        "pngsecret-cli-exit-codes-{}.png",
+        std::process::id()
+    ));
+    std::fs::write(&file, testing_png()).unwrap();
+    let path = file.to_str().unwrap();
+    let code = |args: &[&str], stdin: &[u8]| run(args, stdin).status.code();
+
+    assert_eq!(code(&["list", path], &[]), Some(0));
+    assert_eq!(code(&["--help"], &[]), Some(0));
+    assert_eq!(code(&["list", path, "--no-such-flag"], &[]), Some(1));
+    assert_eq!(code(&["list", "-"], b"definitely not a png"), Some(2));
+    let missing = std::env::temp_dir().join("pngsecret-cli-no-such-file.png");
+    assert_eq!(code(&["list", missing.to_str().unwrap()], &[]), Some(2));
+    assert_eq!(code(&["decode", path, "ruSt"], &[]), Some(3));
+    // every way of removing finds nothing the same way
+    for extra in [&[][..], &["--all"], &["--matching", "."]] {
+        let output = run(&[&["remove", path, "ruSt"][..], extra].concat(), &[]);
+        assert_eq!(output.status.code(), Some(3), "{:?}", extra);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("ruSt") && stderr.contains(path),
+            "{}",
+            stderr
+        );
+    }
+    assert_eq!(
+        code(
+            &["remove", path, "IDAT", "--nth", "1", "--allow-critical"],
+            &[]
+        ),
+        Some(7)
+    );
+    let mut bad_crc = testing_png();
+    // last byte of the IHDR crc
+    bad_crc[32] ^= 0xff;
+    assert_eq!(code(&["list", "-"], &bad_crc), Some(4));
+    assert_eq!(code(&["verify", "-"], &bad_crc), Some(4));
+    assert_eq!(code(&["repair", path, "-o", path], &[]), Some(6));
+    assert_eq!(code(&["remove", path, "IDAT"], &[]), Some(6));
+    assert_eq!(std::fs::read(&file).unwrap(), testing_png());
+    assert_eq!(code(&["list", path, "--max-memory", "1024"], &[]), Some(0));
+    assert_eq!(code(&["print", path, "--max-memory", "15"], &[]), Some(2));
+    assert_eq!(code(&["list", path, "--max-memory", "0"], &[]), Some(1));
+    // the chunk count is limited without --max-memory, unlimited lifts that
+    let mut crowded = testing_png();
+    let iend = crowded.split_off(crowded.len() - 12);
+    for _ in 0..10_000 {
+        crowded.extend(chunk(b"ruSt", b""));
+    }
+    crowded.extend(iend);
+    assert_eq!(code(&["list", "-"], &crowded), Some(2));
+    let unlimited = ["list", "-", "--max-memory", "unlimited"];
+    assert_eq!(code(&unlimited, &crowded), Some(0));
+
+    let help = String::from_utf8_lossy(&run(&["--help"], &[]).stdout).into_owned();
+    for line in ["Exit codes:", "  3  The requested chunk was not found"] {
+        assert!(help.contains(line), "{}", help);
+    }
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_bad_input_is_an_error_not_a_panic() {
+    let dir = std::env::temp_dir().join(format!("pngsecret-cli-dir-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let empty =
+        std::env::temp_dir().join(format!("pngsecret-cli-empty-{}.png", std::process::id()));
+    std::fs::write(&empty, b"").unwrap();
+    for path in [&dir, &empty] {
+        for command in ["list", "print", "decode", "verify", "metadata"] {
+            let mut args = vec![command, path.to_str().unwrap()];
+            if command == "decode" {
+                args.push("ruSt");
+            }
+            let output = run(&args, &[]);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert_eq!(output.status.code(), Some(2), "{:?}: {}", args, stderr);
+            assert!(stderr.starts_with("error: "), "{:?}: {}", args, stderr);
+            assert!(!stderr.contains("panicked"), "{:?}: {}", args, stderr);
+        }
+    }
+
+    let mut binary = testing_png();
+    let iend = binary.len() - 12;
+    binary.splice(iend..iend, chunk(b"ruSt", &[0xff, 0xfe, 0x00, 0x80]));
+    for args in [
+        &["decode", "-", "ruSt"][..],
+        &["print", "-"],
+        &["list", "-"],
+    ] {
+        let output = run(args, &binary);
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+        assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"));
+    }
+    let raw = run(&["decode", "-", "ruSt", "-o", "-"], &binary);
+    assert_eq!(raw.stdout, [0xff, 0xfe, 0x00, 0x80]);
+    std::fs::remove_dir_all(dir).unwrap();
+    std::fs::remove_file(empty).unwrap();
+}
+
+#[test]
+fn test_remove_dry_run() {
+    let encoded = run(&["encode", "-", "ruSt", "keep me", "-"], &testing_png()).stdout;
+    let file =
+        std::env::temp_dir().join(format!("pngsecret-cli-dry-run-{}.png", std::process::id()));
+    std::fs::write(&file, &encoded).unwrap();
+    let output = run(
+        &["remove", file.to_str().unwrap(), "ruSt", "--dry-run"],
+        &[],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!(
+            "would remove ruSt (7 bytes)\n{}: -19 bytes\n",
+            file.display()
+        )
+    );
+    assert_eq!(std::fs::read(&file).unwrap(), encoded);
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_remove_print_removed() {
+    let encoded = run(&["encode", "-", "ruSt", "keep me", "-"], &testing_png()).stdout;
+    let output = run(&["remove", "-", "ruSt", "--print-removed"], &encoded);
+    assert!(output.status.success());
+    // the png goes to stdout, so the removed chunk is shown on stderr
+    assert_eq!(output.stdout, testing_png());
+    let removed = String::from_utf8_lossy(&output.stderr);
+    assert!(removed.contains("Chunk:       ruSt\nData length: 7\n"));
+    assert!(removed.contains("Data:        keep me\n"));
+}
+
+#[test]
+fn test_color_flag() {
+    let always = run(&["list", "-", "--color", "always"], &testing_png());
+    assert!(String::from_utf8_lossy(&always.stdout).contains("\x1b[31mIHDR"));
+    let never = run(&["print", "-", "--color", "never"], &testing_png());
+    assert!(never.status.success());
+    assert!(!never.stdout.contains(&0x1b));
+    // piped output stays plain by default
+    let auto = run(&["list", "-"], &testing_png());
+    assert!(!auto.stdout.contains(&0x1b));
+}
+
+#[test]
+fn test_format_json() {
+    let file = std::env::temp_dir().join(format!("pngsecret-cli-json-{}.png", std::process::id()));
+    std::fs::write(&file, testing_png()).unwrap();
+    let path = file.to_str().unwrap();
+    let parse = |bytes: &[u8]| serde_json::from_slice::<serde_json::Value>(bytes).unwrap();
+
+    let encoded = run(
+        &["encode", path, "ruSt", "keep me", "--format", "json"],
+        &[],
+    );
+    assert!(encoded.status.success());
+    let report = parse(&encoded.stdout);
+    assert_eq!(report["command"], "encode");
+    let chunk = &report["file"]["chunk"];
+    assert_eq!(chunk["type"], "ruSt");
+    assert_eq!(chunk["index"], 2);
+
+    let decoded = run(&["decode", path, "ruSt", "--format", "json"], &[]);
+    let report = parse(&decoded.stdout);
+    assert_eq!(report["command"], "decode");
+    assert_eq!(report["chunks"][0]["payload"], "a2VlcCBtZQ==");
+
+    let missing = run(&["decode", path, "tEXt", "--format", "json"], &[]);
+    assert_eq!(missing.status.code(), Some(3));
+    assert!(missing.stdout.is_empty());
+    let error = &parse(&missing.stderr)["error"];
+    assert_eq!(error["kind"], "chunk_not_found");
+    assert_eq!(error["path"], path);
+    assert!(error["message"].as_str().unwrap().contains("tEXt"));
+
+    // the kind goes with the exit code, a bad crc is no unreadable png
+    let mut bad_crc = testing_png();
+    bad_crc[32] ^= 0xff;
+    let unreadable = run(&["list", "-", "--format", "json"], &bad_crc);
+    assert_eq!(unreadable.status.code(), Some(4));
+    assert_eq!(parse(&unreadable.stderr)["error"]["kind"], "crc_mismatch");
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_format_json_keeps_stdout_for_the_report() {
+    let output = run(
+        &["encode", "-", "ruSt", "keep me", "-", "--format", "json"],
+        &testing_png(),
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let error = String::from_utf8_lossy(&output.stderr);
+    assert!(error.starts_with(r#"{"error":{"kind":"other","#));
+}
+
+#[test]
+fn test_password_from_env() {
+    let env = [("PNGSECRET_PASSWORD", "hunter2")];
+    let encode = [
+        "encode",
+        "-",
+        "ruSt",
+        "secret",
+        "-",
+        "--encrypt",
+        "--kdf-params",
+        "8,1,1",
+    ];
+    let encoded = run_with_env(&encode, &testing_png(), &env);
+    assert!(encoded.status.success());
+    assert!(!encoded.stdout.windows(6).any(|window| window == b"secret"));
+
+    let decoded = run_with_env(&["decode", "-", "ruSt"], &encoded.stdout, &env);
+    assert_eq!(String::from_utf8_lossy(&decoded.stdout), "secret\n");
+    let wrong = [("PNGSECRET_PASSWORD", "hunter3")];
+    assert_eq!(
+        run_with_env(&["decode", "-", "ruSt"], &encoded.stdout, &wrong)
+            .status
+            .code(),
+        Some(5)
+    );
+    // piped, without the env var there is nobody to ask
+    let missing = run(&["decode", "-", "ruSt"], &encoded.stdout);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("PNGSECRET_PASSWORD"));
+}
+
+#[test]
+fn test_bad_signature_exit_code() {
+    let keygen = run(&["keygen", "--type", "ed25519", "--format", "json"], &[]);
+    assert!(keygen.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&keygen.stdout).unwrap();
+    let public_key = report["public_key"].as_str().unwrap();
+    let keypair = report["keypair"].to_string();
+    let file =
+        std::env::temp_dir().join(format!("pngsecret-cli-keypair-{}.json", std::process::id()));
+    std::fs::write(&file, keypair).unwrap();
+
+    let encode = [
+        "encode",
+        "-",
+        "ruSt",
+        "signed",
+        "-",
+        "--sign",
+        file.to_str().unwrap(),
+    ];
+    let encoded = run(&encode, &testing_png()).stdout;
+    let verified = run(
+        &["decode", "-", "ruSt", "--verify-with", public_key],
+        &encoded,
+    );
+    assert!(verified.status.success());
+    assert!(String::from_utf8_lossy(&verified.stderr).contains("signature: valid"));
+    let other = "00".repeat(32);
+    let wrong = run(&["decode", "-", "ruSt", "--verify-with", &other], &encoded);
+    assert_eq!(wrong.status.code(), Some(4));
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_encode_output_option() {
+    let temp = |name: &str| {
+        std::env::temp_dir().join(format!(
+            "pngsecret-cli-output-{}-{}",
+            std::process::id(),
+            name
+        ))
+    };
+    let input = temp("input.png");
+    std::fs::write(&input, testing_png()).unwrap();
+    let message_file = temp("message.txt");
+    std::fs::write(&message_file, "from a file").unwrap();
+    let editor = temp("editor.sh");
+    std::fs::write(&editor, "printf 'from the editor' > \"$1\"\n").unwrap();
+    let editor = format!("sh {}", editor.display());
+    let message_file = message_file.to_str().unwrap();
+    let sources: [(&str, &[&str], &[u8]); 4] = [
+        (
+            "message-file",
+            &["--message-file", message_file],
+            b"from a file",
+        ),
+        ("edit", &["--edit"], b"from the editor"),
+        (
+            "input-file",
+            &["--input-file", message_file],
+            b"from a file",
+        ),
+        (
+            "message-hex",
+            &["--message-hex", "c0ffee"],
+            &[0xc0, 0xff, 0xee],
+        ),
+    ];
+    for (name, source, expected) in sources {
+        let output = temp(&format!("{}.png", name));
+        let output = output.to_str().unwrap();
+        for flag in ["-o", "--output"] {
+            let encode = [
+                &["encode", input.to_str().unwrap(), "ruSt"][..],
+                source,
+                &[flag, output, "--force"],
+            ];
+            let encoded = run_with_env(&encode.concat(), &[], &[("EDITOR", &editor)]);
+            let stderr = String::from_utf8_lossy(&encoded.stderr);
+            assert!(encoded.status.success(), "{} {}: {}", name, flag, stderr);
+            let decoded = run(&["decode", output, "ruSt", "-o", "-"], &[]);
+            assert_eq!(decoded.stdout, expected, "{} {}", name, flag);
+        }
+        std::fs::remove_file(output).unwrap();
+    }
+    // encoded to the output only, the input is as it was
+    assert_eq!(std::fs::read(&input).unwrap(), testing_png());
+    let both = run(
+        &[
+            "encode",
+            input.to_str().unwrap(),
+            "ruSt",
+            "m",
+            "out.png",
+            "-o",
+            "other.png",
+        ],
+        &[],
+    );
+    assert_eq!(both.status.code(), Some(1));
+    std::fs::remove_file(input).unwrap();
+}
@@ -0,0 +1,52 @@
+# run with `maturin develop && pytest tests/python`
+import struct
+import zlib
+
+import pytest
+
+import pngsecret
+
+
+def chunk(chunk_type, data):
+    crc = zlib.crc32(chunk_type + data)
+    return struct.pack(">I", len(data)) + chunk_type + data + struct.pack(">I", crc)
+
+
+def testing_png():
+    return (
+        b"\x89PNG\r\n\x1a\n"
+        + chunk(b"IHDR", bytes([0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]))
+        + chunk(b"IEND", b"")
+    )
+
+
+def test_round_trip():
+    png = pngsecret.Png.from_bytes(testing_png())
+    png.append("ruSt", b"hidden")
+    assert [chunk.chunk_type for chunk in png.chunks] == ["IHDR", "ruSt", "IEND"]
+    assert png.chunks[1].data == b"hidden"
+    assert png.remove("ruSt").data == b"hidden"
+    assert png.to_bytes() == testing_png()
+
+
+def test_files(tmp_path):
+    path = tmp_path / "in.png"
+    path.write_bytes(testing_png())
+    assert pngsecret.encode_file(path, "ruSt", b"in a file") == 1
+    assert pngsecret.decode_file(str(path), "ruSt") == b"in a file"
+    with pytest.raises(pngsecret.ChunkNotFound):
+        pngsecret.decode_file(path, "nOpE")
+    with pytest.raises(OSError):
+        pngsecret.decode_file(tmp_path / "missing.png", "ruSt")
+
+
+def test_errors():
+    with pytest.raises(pngsecret.NotAPng):
+        pngsecret.Png.from_bytes(b"not a png")
+    bad_crc = bytearray(testing_png())
+    bad_crc[20] ^= 0xFF
+    with pytest.raises(pngsecret.CrcMismatch) as error:
+        pngsecret.Png.from_bytes(bytes(bad_crc))
+    assert isinstance(error.value, pngsecret.PngSecretError)
+    with pytest.raises(pngsecret.InvalidChunkType):
+        pngsecret.Chunk("ru5t", b"")
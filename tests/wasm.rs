@@ -0,0 +1,33 @@
+// the bindings as javascript calls them, in node
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use pngsecret::{chunk::Chunk, png::Png, wasm};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn testing_png() -> Vec<u8> {
+    let chunk = |chunk_type: &str, data: &[u8]| Chunk::new(chunk_type.parse().unwrap(), data);
+    Png::from_chunks(vec![
+        chunk("IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]),
+        chunk("IEND", &[]),
+    ])
+    .as_bytes()
+}
+
+#[wasm_bindgen_test]
+fn test_round_trip() {
+    let encoded = wasm::encode(&testing_png(), "ruSt", b"in the browser").unwrap();
+    assert_eq!(
+        wasm::decode(&encoded, "ruSt").unwrap().unwrap(),
+        b"in the browser"
+    );
+    assert_eq!(wasm::decode(&encoded, "nOpE").unwrap(), None);
+    let chunks = wasm::list_chunks(&encoded).unwrap().as_string().unwrap();
+    assert!(chunks.contains(r#""type":"ruSt""#));
+}
+
+#[wasm_bindgen_test]
+fn test_errors() {
+    assert!(wasm::encode(b"not a png", "ruSt", b"").is_err());
+    assert_eq!(wasm::decode(&testing_png(), "ru5t").unwrap(), None);
+    assert!(wasm::list_chunks(b"").is_err());
+}